@@ -0,0 +1,793 @@
+//! Parses twitter archives
+//!
+//! Nothing here talks to twitter, so it can be built on its own, such as for
+//! a browser, see [`ffi`](crate::ffi).
+use std::{collections::HashMap, fs, path::Path};
+
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{from_str, Value};
+use time::{
+    format_description::{well_known::Rfc3339, FormatItem},
+    macros::format_description,
+    OffsetDateTime,
+};
+
+use crate::{id::TweetId, legacy};
+
+/// The format of twitters `created_at` dates
+pub static TWITTER_DATE: &[FormatItem] = format_description!(
+    "[weekday repr:short case_sensitive:false] [month repr:short] [day] [hour]:[minute]:[second] +0000 [year]"
+);
+
+/// Twitter tweet object. Internal, useless.
+#[derive(Debug, Deserialize)]
+struct TweetObj {
+    tweet: Tweet,
+}
+
+/// A Tweet in the twitter archive.
+///
+/// NOTE: This is ***different*** than what would be returned by
+/// the twitter API.
+#[derive(Debug, Deserialize)]
+pub struct Tweet {
+    /// Tweet ID
+    ///
+    /// Currently 19 characters long, a 64-bit number.
+    pub id_str: TweetId,
+
+    /// Number of retweets
+    #[serde(rename = "retweet_count")]
+    pub retweets: String,
+
+    /// Number of likes
+    #[serde(rename = "favorite_count")]
+    pub likes: String,
+
+    /// Time of tweet
+    ///
+    /// See [`TWITTER_DATE`]
+    pub created_at: String,
+
+    /// Text of the tweet
+    #[serde(default)]
+    pub full_text: Option<String>,
+
+    /// Tweet ID this is a reply to
+    #[serde(default)]
+    pub in_reply_to_status_id_str: Option<String>,
+
+    /// Account ID this is a reply to
+    #[serde(default)]
+    pub in_reply_to_user_id_str: Option<String>,
+
+    /// Account handle this is a reply to
+    #[serde(default)]
+    pub in_reply_to_screen_name: Option<String>,
+
+    /// Entities, such as mentions, in the tweet
+    #[serde(default)]
+    pub entities: Entities,
+
+    /// Attached media, only present for tweets that have any
+    #[serde(default)]
+    pub extended_entities: Option<ExtendedEntities>,
+
+    /// Edit history, missing from archives made before tweets could be
+    /// edited
+    #[serde(default)]
+    pub edit_info: Option<EditInfo>,
+}
+
+impl Tweet {
+    /// Whether the tweet has attached photos, videos, or GIFs
+    pub fn has_media(&self) -> bool {
+        let extended = self.extended_entities.as_ref();
+        !self.entities.media.is_empty() || extended.map_or(false, |e| !e.media.is_empty())
+    }
+
+    /// ID of the first version of the tweet, if it was ever edited
+    ///
+    /// Every version of an edited tweet has the same one, including the
+    /// first, so they can be found from each other.
+    pub fn initial_id(&self) -> Option<&TweetId> {
+        let info = self.edit_info.as_ref()?;
+        match (&info.edit, &info.initial) {
+            (Some(edit), _) => Some(&edit.initial_tweet_id),
+            // Tweets that were never edited only list themselves
+            (None, Some(initial)) if initial.edit_tweet_ids.len() > 1 => {
+                initial.edit_tweet_ids.first()
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Edit history of a [`Tweet`] in the twitter archive
+///
+/// Every version of an edited tweet is in the archive as its own tweet. The
+/// first version has [`EditInfo::initial`], later ones [`EditInfo::edit`].
+#[derive(Debug, Default, Deserialize)]
+pub struct EditInfo {
+    /// Set on the first version of a tweet
+    #[serde(default)]
+    pub initial: Option<EditControl>,
+
+    /// Set on every later version of a tweet
+    #[serde(default)]
+    pub edit: Option<Edit>,
+}
+
+/// Edit history of the first version of a [`Tweet`]
+#[derive(Debug, Default, Deserialize)]
+pub struct EditControl {
+    /// ID of every version of the tweet, oldest first
+    #[serde(rename = "editTweetIds", default)]
+    pub edit_tweet_ids: Vec<TweetId>,
+}
+
+/// Edit history of a later version of a [`Tweet`]
+#[derive(Debug, Deserialize)]
+pub struct Edit {
+    /// ID of the first version of the tweet
+    #[serde(rename = "initialTweetId")]
+    pub initial_tweet_id: TweetId,
+}
+
+/// Entities in a [`Tweet`] in the twitter archive.
+#[derive(Debug, Default, Deserialize)]
+pub struct Entities {
+    /// Accounts mentioned in the tweet
+    #[serde(default)]
+    pub user_mentions: Vec<UserMention>,
+
+    /// Links in the tweet
+    #[serde(default)]
+    pub urls: Vec<UrlEntity>,
+
+    /// Attached media, which also get a link in the tweet
+    #[serde(default)]
+    pub media: Vec<UrlEntity>,
+
+    /// Hashtags in the tweet
+    #[serde(default)]
+    pub hashtags: Vec<HashtagEntity>,
+}
+
+/// Extended entities in a [`Tweet`] in the twitter archive, which list every
+/// attached photo, instead of only the first like [`Entities::media`]
+#[derive(Debug, Default, Deserialize)]
+pub struct ExtendedEntities {
+    /// Attached photos, videos, and GIFs
+    #[serde(default)]
+    pub media: Vec<UrlEntity>,
+}
+
+/// A hashtag in a [`Tweet`] in the twitter archive.
+#[derive(Debug, Deserialize)]
+pub struct HashtagEntity {
+    /// Hashtag, without the `#`
+    pub text: String,
+}
+
+/// A t.co link in a [`Tweet`] in the twitter archive.
+#[derive(Debug, Deserialize)]
+pub struct UrlEntity {
+    /// Shortened t.co link
+    pub url: String,
+
+    /// Where the link actually goes
+    pub expanded_url: String,
+}
+
+/// An account mentioned in a [`Tweet`] in the twitter archive.
+#[derive(Debug, Deserialize)]
+pub struct UserMention {
+    /// Account ID
+    pub id_str: String,
+
+    /// Account handle at time of archive
+    pub screen_name: String,
+}
+
+/// Twitter like object. Internal, useless.
+#[derive(Debug, Deserialize)]
+struct LikeObj {
+    like: Like,
+}
+
+/// A Like in the twitter archive.
+#[derive(Debug, Deserialize)]
+pub struct Like {
+    /// ID of the liked tweet
+    #[serde(rename = "tweetId")]
+    pub tweet_id: TweetId,
+
+    /// Text of the liked tweet, missing if it was deleted or private when
+    /// the archive was made
+    #[serde(rename = "fullText", default)]
+    pub full_text: Option<String>,
+}
+
+/// Twitter direct message conversation object. Internal, useless.
+#[derive(Debug, Deserialize)]
+struct ConversationObj {
+    #[serde(rename = "dmConversation")]
+    conversation: Conversation,
+}
+
+/// A direct message conversation in the twitter archive.
+#[derive(Debug, Deserialize)]
+struct Conversation {
+    /// Conversation ID
+    #[serde(rename = "conversationId")]
+    id: String,
+
+    /// Events in the conversation, only some of which are messages
+    messages: Vec<ConversationEvent>,
+}
+
+/// An event in a [`Conversation`]
+#[derive(Debug, Deserialize)]
+struct ConversationEvent {
+    /// Set if this event is a message
+    #[serde(rename = "messageCreate")]
+    #[serde(default)]
+    message: Option<Message>,
+}
+
+/// A direct message in the twitter archive.
+#[derive(Debug, Deserialize)]
+pub struct Message {
+    /// Message ID
+    pub id: String,
+
+    /// ID of the [`Conversation`] it's in
+    #[serde(skip)]
+    pub conversation_id: String,
+
+    /// Account ID of who sent it
+    #[serde(rename = "senderId", default)]
+    pub sender_id: Option<String>,
+
+    /// Text of the message
+    #[serde(default)]
+    pub text: Option<String>,
+
+    /// When the message was sent, in RFC 3339 format
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+/// Twitter account object. Internal, useless.
+#[derive(Debug, Deserialize)]
+struct AccountObj {
+    account: Account,
+}
+
+/// A Account in the twitter archive.
+///
+/// NOTE: This is ***different*** than what would be returned by
+/// the twitter API.
+#[derive(Debug, Deserialize)]
+pub struct Account {
+    /// Tweet ID
+    ///
+    /// Currently 19 characters long, a 64-bit number.
+    #[serde(rename = "accountId")]
+    pub id_str: String,
+
+    /// Account username at time of archive
+    #[serde(rename = "username")]
+    pub user_name: String,
+
+    /// Account display name at time of archive
+    #[serde(rename = "accountDisplayName")]
+    pub display_name: String,
+}
+
+/// Remove the prefix in twitter archive files
+fn remove_tweet_prefix(data: &str) -> &str {
+    // Twitter puts this nonsense in front of the tweet files
+    // Assume there are less than 99 parts.
+    // This will work for both single and double digits
+    // The full line is  `window.YTD.tweets.part4 = [`
+    const PREFIX: &str = "window.YTD.tweets.part99 ";
+    // Anything shorter isn't a tweets file, and fails to parse
+    data.get(PREFIX.len()..).unwrap_or_default()
+}
+
+/// Remove the prefix in twitter archive files
+fn remove_account_prefix(data: &str) -> &str {
+    // Twitter puts this nonsense in front of the account.js file
+    // Assume there is one account
+    // The full line is  `window.YTD.account.part0 = [`
+    const PREFIX: &str = "window.YTD.account.part0 =";
+    &data[PREFIX.len()..]
+}
+
+/// Remove the prefix in other twitter archive files
+///
+/// The full line is `window.YTD.NAME.part0 = [`
+pub fn remove_prefix(data: &str) -> &str {
+    data.split_once('=').map_or(data, |(_, data)| data)
+}
+
+/// Collect every liked tweet from the twitter archive
+///
+/// Likes are expected to exist at `data/like.js`
+pub fn collect_likes(path: &Path) -> Result<Vec<Like>> {
+    let path = path.join("data").join("like.js");
+    let data = fs::read_to_string(path)?;
+    let data = remove_prefix(&data);
+
+    let likes: Vec<LikeObj> = from_str(data)?;
+    Ok(likes.into_iter().map(|l| l.like).collect())
+}
+
+/// Collect every direct message from the twitter archive
+///
+/// Messages are expected to exist at `data/direct-messages.js`
+/// and `data/direct-messages-group.js`, either of which may be missing.
+pub fn collect_dms(path: &Path) -> Result<Vec<Message>> {
+    let path = path.join("data");
+    let mut out = Vec::new();
+    for name in ["direct-messages.js", "direct-messages-group.js"] {
+        let path = path.join(name);
+        if !path.exists() {
+            continue;
+        }
+        let data = fs::read_to_string(path)?;
+        let data = remove_prefix(&data);
+
+        let convos: Vec<ConversationObj> = from_str(data)?;
+        for convo in convos.into_iter().map(|c| c.conversation) {
+            let id = convo.id;
+            out.extend(
+                convo
+                    .messages
+                    .into_iter()
+                    .filter_map(|e| e.message)
+                    .map(|m| Message {
+                        conversation_id: id.clone(),
+                        ..m
+                    }),
+            );
+        }
+    }
+    Ok(out)
+}
+
+/// Get the account ID for this twitter archive
+///
+/// Invalid UTF-8, as in a mangled name, is replaced with U+FFFD.
+///
+/// Old-format archives are detected and handled, see [`legacy`]
+pub fn get_account(path: &Path) -> Result<Account> {
+    if legacy::is_legacy(path) {
+        return legacy::get_account(path);
+    }
+    let path = path.join("data").join("account.js");
+    let data = String::from_utf8_lossy(&fs::read(path)?).into_owned();
+    let data = remove_account_prefix(&data);
+
+    let acc: Vec<AccountObj> = from_str(data)?;
+    let acc = acc
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("Couldn't get twitter account information"))?;
+
+    Ok(acc.account)
+}
+
+/// Tweets collected from the twitter archive, see [`collect_tweets`]
+#[derive(Debug, Default)]
+pub struct Collected {
+    /// ALL found tweets
+    pub tweets: Vec<Tweet>,
+
+    /// How many tweets were found in each file
+    ///
+    /// Files are identified relative to the archive, eg `data/tweets.js`,
+    /// matching the manifest. See [`verify_manifest`]
+    pub counts: HashMap<String, usize>,
+
+    /// Problems that were repaired or skipped while parsing leniently
+    pub repairs: Vec<String>,
+}
+
+/// Read the archive file at `path`, called `name` in `repairs`
+///
+/// If `lenient` is true, invalid UTF-8 is replaced with U+FFFD instead of
+/// failing, and recorded in `repairs`.
+pub(crate) fn read_archive_file(
+    path: &Path,
+    name: &str,
+    lenient: bool,
+    repairs: &mut Vec<String>,
+) -> Result<String> {
+    match String::from_utf8(fs::read(path)?) {
+        Ok(data) => Ok(data),
+        Err(e) if lenient => {
+            repairs.push(format!(
+                "{name}: Replaced invalid UTF-8, starting at byte {}",
+                e.utf8_error().valid_up_to()
+            ));
+            Ok(String::from_utf8_lossy(e.as_bytes()).into_owned())
+        }
+        Err(e) => Err(anyhow!("{name}: {e}")),
+    }
+}
+
+/// Collect tweets from the twitter archive. Returns ALL found tweets.
+///
+/// `path` is the path to the archive, and tweets are expected to exist at
+/// `data/tweets.js` and `data/tweets-partN.js`.
+///
+/// If `lenient` is true, invalid UTF-8 and escapes are repaired and tweets
+/// that fail to parse are skipped, instead of failing the entire file.
+/// See [`Collected::repairs`]
+///
+/// There is a limit of 99 `tweets-partN.js` files
+///
+/// Old-format archives are detected and handled, see
+/// [`legacy::collect_tweets`].
+pub fn collect_tweets(path: &Path, lenient: bool) -> Result<Collected> {
+    if legacy::is_legacy(path) {
+        return legacy::collect_tweets(path, lenient);
+    }
+    let mut files = Vec::with_capacity(99);
+    let path = path.join("data");
+    for file in path.read_dir()? {
+        let file = file?;
+        let ty = file.file_type()?;
+        if !ty.is_file() {
+            continue;
+        }
+        let name = file.file_name();
+        let name = name
+            .to_str()
+            .ok_or_else(|| anyhow!("Invalid UTF-8 in filename {:?}", file.file_name()))?;
+        if !name.starts_with("tweets") {
+            continue;
+        }
+        if files.len() > 99 {
+            return Err(anyhow!("Too many tweet files, can not handle more than 99"));
+        }
+        files.push(file.path());
+    }
+
+    let mut out = Collected::default();
+    for path in files {
+        let name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .expect("BUG: File name was already valid UTF-8");
+        let name = format!("data/{name}");
+        let data = read_archive_file(&path, &name, lenient, &mut out.repairs)?;
+        let tweets = parse_tweets(&name, &data, lenient, &mut out.repairs)?;
+        out.counts.insert(name, tweets.len());
+        out.tweets.extend(tweets);
+    }
+
+    Ok(out)
+}
+
+/// Parse the tweets file `name`, such as `data/tweets.js`, with contents
+/// `data`
+///
+/// If `lenient` is true, problems are repaired or skipped as by
+/// [`collect_tweets`], and recorded in `repairs`.
+pub fn parse_tweets(
+    name: &str,
+    data: &str,
+    lenient: bool,
+    repairs: &mut Vec<String>,
+) -> Result<Vec<Tweet>> {
+    let data = remove_tweet_prefix(data);
+    if lenient {
+        return Ok(parse_lenient(name, data, repairs));
+    }
+    let data: Vec<TweetObj> = from_str(data)?;
+    Ok(data.into_iter().map(|t| t.tweet).collect())
+}
+
+/// Leniently parse the tweets file `name` with contents `data`
+///
+/// Lone UTF-16 surrogate escapes, which are invalid JSON but appear in some
+/// archives, are replaced with U+FFFD.
+/// Multiple concatenated JSON arrays are all parsed.
+/// Tweets that still fail to parse are skipped, and invalid JSON is skipped
+/// up to the start of the next tweet.
+///
+/// Everything repaired or skipped is recorded in `repairs`
+fn parse_lenient(name: &str, data: &str, repairs: &mut Vec<String>) -> Vec<Tweet> {
+    let next_tweet = Regex::new(r#"(?P<tweet>\{)\s*"tweet"\s*:"#).expect("BUG: Invalid regex");
+    parse_lenient_with::<TweetObj>(name, data, &next_tweet, "/tweet/id_str", repairs)
+        .into_iter()
+        .map(|t| t.tweet)
+        .collect()
+}
+
+/// [`parse_lenient`], for tweets of any format `T`, with their ID at the JSON
+/// pointer `id`
+///
+/// After invalid JSON, parsing resumes at the `tweet` group of the next match
+/// of `next_tweet`.
+pub(crate) fn parse_lenient_with<T: DeserializeOwned>(
+    name: &str,
+    data: &str,
+    next_tweet: &Regex,
+    id: &str,
+    repairs: &mut Vec<String>,
+) -> Vec<T> {
+    let (data, replaced) = repair_surrogates(data);
+    if replaced > 0 {
+        repairs.push(format!(
+            "{name}: Replaced {replaced} invalid unicode escapes"
+        ));
+    }
+
+    let mut out = Vec::new();
+    let mut pos = 0;
+    let mut i = 0;
+    loop {
+        // Between tweets are only the brackets and commas of the arrays
+        pos += data[pos..]
+            .find(|c: char| !c.is_whitespace() && !"[,]".contains(c))
+            .unwrap_or(data.len() - pos);
+        let mut values = serde_json::Deserializer::from_str(&data[pos..]).into_iter::<Value>();
+        match values.next() {
+            None => break,
+            Some(Ok(obj)) => {
+                pos += values.byte_offset();
+                let id = obj
+                    .pointer(id)
+                    .and_then(|id| id.as_str())
+                    .unwrap_or("<unknown>")
+                    .to_owned();
+                match serde_json::from_value::<T>(obj) {
+                    Ok(t) => out.push(t),
+                    Err(e) => repairs.push(format!("{name}: Skipped tweet #{i}, ID {id}: {e}")),
+                }
+                i += 1;
+            }
+            Some(Err(e)) => {
+                // Errors know their line from `pos`, not from the start of the file
+                let line = data[..pos].matches('\n').count() + e.line();
+                let why = e.to_string();
+                let why = why
+                    .rsplit_once(" at line ")
+                    .map_or(why.as_str(), |(why, _)| why);
+                repairs.push(format!(
+                    "{name}: Skipped invalid JSON on line {line} up to the next tweet: {why}"
+                ));
+                let after = pos + data[pos..].chars().next().map_or(1, char::len_utf8);
+                match next_tweet.captures_at(&data, after) {
+                    Some(c) => pos = c.name("tweet").expect("BUG: No tweet group").start(),
+                    None => break,
+                }
+            }
+        }
+    }
+
+    out
+}
+
+/// Replace lone UTF-16 surrogate escapes, such as `\ud83d` not followed by a
+/// low surrogate, in the JSON `data` with `\ufffd`.
+///
+/// Returns the repaired JSON and how many escapes were replaced
+fn repair_surrogates(data: &str) -> (String, usize) {
+    /// Parse the `\uXXXX` escape at the start of `s`, if any
+    fn escape(s: &str) -> Option<u16> {
+        let hex = s.strip_prefix("\\u")?.get(..4)?;
+        u16::from_str_radix(hex, 16).ok()
+    }
+    let is_high = |c: u16| (0xD800..=0xDBFF).contains(&c);
+    let is_low = |c: u16| (0xDC00..=0xDFFF).contains(&c);
+
+    let mut out = String::with_capacity(data.len());
+    let mut replaced = 0;
+    let mut rest = data;
+    while let Some(i) = rest.find('\\') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        match escape(rest) {
+            Some(c) if is_high(c) && escape(&rest[6..]).map_or(false, is_low) => {
+                out.push_str(&rest[..12]);
+                rest = &rest[12..];
+            }
+            Some(c) if is_high(c) || is_low(c) => {
+                out.push_str("\\ufffd");
+                replaced += 1;
+                rest = &rest[6..];
+            }
+            // Copy any other escape whole, so `\\` isn't mistaken for the
+            // start of another escape
+            _ => {
+                let len = rest[1..].chars().next().map_or(1, |c| 1 + c.len_utf8());
+                out.push_str(&rest[..len]);
+                rest = &rest[len..];
+            }
+        }
+    }
+    out.push_str(rest);
+
+    (out, replaced)
+}
+
+/// Archive manifest, `data/manifest.js`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    data_types: HashMap<String, ManifestDataType>,
+
+    #[serde(default)]
+    archive_info: Option<ManifestInfo>,
+}
+
+/// Information about the archive in the [`Manifest`]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ManifestInfo {
+    /// When the archive was created, eg `2023-01-28T19:11:24.000Z`
+    generation_date: String,
+}
+
+/// A type of data in the [`Manifest`]
+#[derive(Debug, Deserialize)]
+struct ManifestDataType {
+    #[serde(default)]
+    files: Vec<ManifestFile>,
+}
+
+/// A file in the [`Manifest`]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ManifestFile {
+    /// Relative to the archive, eg `data/tweets.js`
+    file_name: String,
+
+    /// Number of entries in the file
+    count: String,
+}
+
+/// Get when the archive at `path` was created, according to its manifest
+///
+/// Returns [`None`] if the archive has no manifest, such as old-format
+/// archives, or it doesn't say.
+pub fn archive_date(path: &Path) -> Option<OffsetDateTime> {
+    let data = fs::read_to_string(path.join("data").join("manifest.js")).ok()?;
+    // The full line is `window.__THAR_CONFIG = {`
+    let data = data.find('{').map(|i| &data[i..])?;
+    let manifest: Manifest = from_str(data).ok()?;
+    OffsetDateTime::parse(&manifest.archive_info?.generation_date, &Rfc3339).ok()
+}
+
+/// Verify the tweet `counts` from [`collect_tweets`] against the archive
+/// manifest, returning warnings about any missing or truncated files.
+///
+/// The archive at `path` is expected to have a `data/manifest.js`.
+/// If it doesn't, nothing can be verified and a warning is returned.
+pub fn verify_manifest(path: &Path, counts: &HashMap<String, usize>) -> Result<Vec<String>> {
+    if legacy::is_legacy(path) {
+        return legacy::verify_index(path, counts);
+    }
+    let path = path.join("data").join("manifest.js");
+    let data = match fs::read_to_string(&path) {
+        Ok(data) => data,
+        Err(_) => {
+            return Ok(vec![format!(
+                "Couldn't read {}, unable to verify all tweets were imported",
+                path.display()
+            )])
+        }
+    };
+    // Twitter puts this nonsense in front of the manifest
+    // The full line is `window.__THAR_CONFIG = {`
+    let data = data
+        .find('{')
+        .map(|i| &data[i..])
+        .ok_or_else(|| anyhow!("Invalid archive manifest {}", path.display()))?;
+    let manifest: Manifest = from_str(data)?;
+
+    let mut warnings = Vec::new();
+    // Older archives call these `tweet` instead of `tweets`
+    let files = ["tweets", "tweet"]
+        .iter()
+        .filter_map(|k| manifest.data_types.get(*k))
+        .flat_map(|t| t.files.iter());
+    for file in files {
+        let expected: usize = file.count.parse()?;
+        match counts.get(&file.file_name) {
+            Some(&found) if found < expected => warnings.push(format!(
+                "{} appears truncated, found {found} out of {expected} tweets",
+                file.file_name
+            )),
+            Some(_) => (),
+            None => warnings.push(format!(
+                "{} is missing, {expected} tweets were not imported",
+                file.file_name
+            )),
+        }
+    }
+
+    Ok(warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_escaped_backslashes() {
+        let data = r#""\\ud83d \\\ud83d\ude00""#;
+        assert_eq!(repair_surrogates(data), (data.to_owned(), 0));
+    }
+
+    #[test]
+    fn replaces_lone_surrogates() {
+        let data = r#""\ude00 a \ud83d b \ud83d""#;
+        let expected = r#""\ufffd a \ufffd b \ufffd""#;
+        assert_eq!(repair_surrogates(data), (expected.to_owned(), 3));
+    }
+
+    #[test]
+    fn keeps_surrogate_pairs() {
+        let data = r#""\ud83d\ude00\ud83d\ude00""#;
+        assert_eq!(repair_surrogates(data), (data.to_owned(), 0));
+    }
+
+    fn tweet(id: u32) -> String {
+        format!(
+            r#"{{"tweet" : {{"id_str": "{id}", "retweet_count": "0", "favorite_count": "0",
+            "created_at": "Fri Mar 04 12:00:00 +0000 2022"}}}}"#
+        )
+    }
+
+    #[test]
+    fn initial_id_from_edit_info() {
+        let initial = |info: &str| -> Option<String> {
+            let data = format!(
+                r#"{{"id_str": "2", "retweet_count": "0", "favorite_count": "0",
+                "created_at": "Fri Mar 04 12:00:00 +0000 2022"{info}}}"#
+            );
+            let tweet: Tweet = serde_json::from_str(&data).unwrap();
+            tweet.initial_id().map(ToString::to_string)
+        };
+        assert_eq!(initial(""), None);
+        assert_eq!(
+            initial(r#", "edit_info": {"initial": {"editTweetIds": ["2"]}}"#),
+            None
+        );
+        assert_eq!(
+            initial(r#", "edit_info": {"initial": {"editTweetIds": ["2", "3"]}}"#),
+            Some("2".into())
+        );
+        assert_eq!(
+            initial(r#", "edit_info": {"edit": {"initialTweetId": "1"}}"#),
+            Some("1".into())
+        );
+    }
+
+    #[test]
+    fn lenient_skips_to_next_tweet() {
+        let data = format!(
+            "[{}, {{\"tweet\" : {{\"id_str\": \"2\",, }}}},\n{}]\n[{}]",
+            tweet(1),
+            tweet(3),
+            tweet(4)
+        );
+        let mut repairs = Vec::new();
+        let ids: Vec<String> = parse_lenient("data/tweets.js", &data, &mut repairs)
+            .into_iter()
+            .map(|t| t.id_str.to_string())
+            .collect();
+        assert_eq!(ids, ["1", "3", "4"]);
+        assert_eq!(repairs.len(), 1, "{repairs:?}");
+        assert!(repairs[0].contains("on line 2"), "{repairs:?}");
+    }
+}
@@ -0,0 +1,422 @@
+//! Filter expressions, for combining filters in ways the individual flags
+//! can't, such as `age > 2y && likes < 10 && !has_media && text ~ /conference/`
+//!
+//! Expressions are made of comparisons and flags, combined with `&&`, `||`,
+//! `!`, and parentheses.
+//!
+//! - `age`, how long ago the tweet was created, in days, or with a unit of
+//!   `d`ays, `w`eeks, `m`onths of 30 days, or `y`ears of 365 days
+//! - `likes`, `retweets`, `chars`, and `score`, compared with `<`, `<=`, `>`,
+//!   `>=`, `==`, or `!=`
+//! - `text ~ /regex/`, with `/i` for case insensitive
+//! - `has_media`, `is_retweet`, and `is_reply`
+//!
+//! Anything that isn't known for a tweet, such as the text of tweets imported
+//! without it, is neither true nor false, and a tweet only matches if the
+//! whole expression is known to be true.
+use std::{
+    iter::Peekable,
+    str::{CharIndices, FromStr},
+};
+
+use anyhow::{anyhow, Error, Result};
+use regex::{Regex, RegexBuilder};
+
+/// A parsed filter expression
+#[derive(Debug)]
+pub enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Field, Op, f64),
+    Matches(Regex),
+    Flag(Flag),
+}
+
+/// A numeric property of a tweet
+#[derive(Debug, Clone, Copy)]
+pub enum Field {
+    /// Age in seconds
+    Age,
+    Likes,
+    Retweets,
+    Chars,
+    Score,
+}
+
+/// A comparison operator
+#[derive(Debug, Clone, Copy)]
+pub enum Op {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+/// A true or false property of a tweet
+#[derive(Debug, Clone, Copy)]
+pub enum Flag {
+    HasMedia,
+    IsRetweet,
+    IsReply,
+}
+
+/// What is known about a tweet, for evaluating an [`Expr`]
+#[derive(Debug)]
+pub struct Facts<'a> {
+    /// Age in seconds
+    pub age: i64,
+    pub likes: i32,
+    pub retweets: i32,
+    pub score: Option<f64>,
+    pub text: Option<&'a str>,
+    pub has_media: Option<bool>,
+    pub is_retweet: Option<bool>,
+    pub is_reply: bool,
+}
+
+impl Expr {
+    /// Whether `tweet` matches, or [`None`] if that isn't known
+    pub fn eval(&self, tweet: &Facts) -> Option<bool> {
+        match self {
+            // Known false on either side is enough
+            Self::And(a, b) => match (a.eval(tweet), b.eval(tweet)) {
+                (Some(false), _) | (_, Some(false)) => Some(false),
+                (Some(true), Some(true)) => Some(true),
+                _ => None,
+            },
+            Self::Or(a, b) => match (a.eval(tweet), b.eval(tweet)) {
+                (Some(true), _) | (_, Some(true)) => Some(true),
+                (Some(false), Some(false)) => Some(false),
+                _ => None,
+            },
+            Self::Not(a) => a.eval(tweet).map(|a| !a),
+            Self::Compare(field, op, value) => {
+                let actual = match field {
+                    Field::Age => tweet.age as f64,
+                    Field::Likes => tweet.likes.into(),
+                    Field::Retweets => tweet.retweets.into(),
+                    Field::Chars => tweet.text?.chars().count() as f64,
+                    Field::Score => tweet.score?,
+                };
+                Some(match op {
+                    Op::Lt => actual < *value,
+                    Op::Le => actual <= *value,
+                    Op::Gt => actual > *value,
+                    Op::Ge => actual >= *value,
+                    Op::Eq => actual == *value,
+                    Op::Ne => actual != *value,
+                })
+            }
+            Self::Matches(re) => Some(re.is_match(tweet.text?)),
+            Self::Flag(Flag::HasMedia) => tweet.has_media,
+            Self::Flag(Flag::IsRetweet) => tweet.is_retweet,
+            Self::Flag(Flag::IsReply) => Some(tweet.is_reply),
+        }
+    }
+}
+
+impl FromStr for Expr {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parser = Parser {
+            src: s,
+            chars: s.char_indices().peekable(),
+        };
+        let expr = parser.or()?;
+        parser.skip_space();
+        match parser.chars.peek() {
+            None => Ok(expr),
+            Some(&(at, _)) => Err(parser.error(at, "expected `&&`, `||`, or the end")),
+        }
+    }
+}
+
+/// Recursive descent parser for [`Expr`]
+struct Parser<'a> {
+    src: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl Parser<'_> {
+    fn error(&self, at: usize, expected: &str) -> Error {
+        anyhow!(
+            "Invalid filter `{}` at column {}, {expected}",
+            self.src,
+            self.src[..at].chars().count() + 1
+        )
+    }
+
+    /// Error at the next character
+    fn unexpected(&mut self, expected: &str) -> Error {
+        let at = self.pos();
+        self.error(at, expected)
+    }
+
+    /// Position of the next character, or the end
+    fn pos(&mut self) -> usize {
+        self.chars.peek().map_or(self.src.len(), |&(i, _)| i)
+    }
+
+    fn skip_space(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+
+    /// Consume `token` if it's next
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_space();
+        let at = self.pos();
+        if !self.src[at..].starts_with(token) {
+            return false;
+        }
+        for _ in token.chars() {
+            self.chars.next();
+        }
+        true
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        while self.eat("||") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.unary()?;
+        while self.eat("&&") {
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.eat("(") {
+            let expr = self.or()?;
+            if !self.eat(")") {
+                return Err(self.unexpected("expected `)`"));
+            }
+            return Ok(expr);
+        }
+
+        self.skip_space();
+        let at = self.pos();
+        let name = self.word();
+        let field = match name.as_str() {
+            "has_media" => return Ok(Expr::Flag(Flag::HasMedia)),
+            "is_retweet" => return Ok(Expr::Flag(Flag::IsRetweet)),
+            "is_reply" => return Ok(Expr::Flag(Flag::IsReply)),
+            "text" => return self.regex(),
+            "age" => Field::Age,
+            "likes" => Field::Likes,
+            "retweets" => Field::Retweets,
+            "chars" => Field::Chars,
+            "score" => Field::Score,
+            _ => {
+                return Err(self.error(
+                    at,
+                    "expected one of `age`, `likes`, `retweets`, `chars`, `score`, `text`, \
+                     `has_media`, `is_retweet`, or `is_reply`",
+                ))
+            }
+        };
+
+        // Longest first, so `<=` isn't read as `<`
+        let op = [
+            ("<=", Op::Le),
+            (">=", Op::Ge),
+            ("==", Op::Eq),
+            ("!=", Op::Ne),
+            ("<", Op::Lt),
+            (">", Op::Gt),
+        ]
+        .into_iter()
+        .find(|(token, _)| self.eat(token))
+        .map(|(_, op)| op)
+        .ok_or_else(|| self.unexpected("expected `<`, `<=`, `>`, `>=`, `==`, or `!=`"))?;
+
+        self.skip_space();
+        let at = self.pos();
+        let value = self.word();
+        let value = match field {
+            Field::Age => parse_age(&value),
+            _ => value.parse().ok(),
+        }
+        .ok_or_else(|| match field {
+            Field::Age => self.error(at, "expected an age, eg `30`, `2w`, `6m`, or `2y`"),
+            _ => self.error(at, "expected a number"),
+        })?;
+
+        Ok(Expr::Compare(field, op, value))
+    }
+
+    /// A run of letters, digits, `_`, and `.`
+    fn word(&mut self) -> String {
+        self.skip_space();
+        let mut word = String::new();
+        while let Some((_, c)) = self
+            .chars
+            .next_if(|(_, c)| c.is_alphanumeric() || *c == '_' || *c == '.')
+        {
+            word.push(c);
+        }
+        word
+    }
+
+    /// `~ /regex/`, after `text`
+    fn regex(&mut self) -> Result<Expr> {
+        if !self.eat("~") {
+            return Err(self.unexpected("expected `~` after `text`"));
+        }
+        if !self.eat("/") {
+            return Err(self.unexpected("expected a regular expression, eg `/word/`"));
+        }
+        let start = self.pos();
+        let mut pattern = String::new();
+        loop {
+            match self.chars.next() {
+                Some((_, '\\')) if self.chars.next_if(|(_, c)| *c == '/').is_some() => {
+                    pattern.push('/')
+                }
+                Some((_, '/')) => break,
+                Some((_, c)) => pattern.push(c),
+                None => return Err(self.error(start, "missing the closing `/`")),
+            }
+        }
+        let insensitive = self.chars.next_if(|(_, c)| *c == 'i').is_some();
+        let re = RegexBuilder::new(&pattern)
+            .case_insensitive(insensitive)
+            .build()
+            .map_err(|e| anyhow!("Invalid regular expression `{pattern}` in filter: {e}"))?;
+        Ok(Expr::Matches(re))
+    }
+}
+
+/// Parse an age such as `30` or `30d`, `2w`, `6m`, or `2y`, into seconds
+fn parse_age(age: &str) -> Option<f64> {
+    const DAY: f64 = 60.0 * 60.0 * 24.0;
+    let (num, unit) = match age.find(|c: char| c.is_alphabetic()) {
+        Some(i) => age.split_at(i),
+        None => (age, "d"),
+    };
+    let days = match unit {
+        "d" => 1.0,
+        "w" => 7.0,
+        "m" => 30.0,
+        "y" => 365.0,
+        _ => return None,
+    };
+    num.parse::<f64>().ok().map(|n| n * days * DAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 60 * 60 * 24;
+
+    /// A 10 day old tweet with 5 likes, and nothing else
+    fn facts(text: Option<&str>) -> Facts<'_> {
+        Facts {
+            age: 10 * DAY,
+            likes: 5,
+            retweets: 0,
+            score: None,
+            text,
+            has_media: None,
+            is_retweet: Some(false),
+            is_reply: false,
+        }
+    }
+
+    fn eval(expr: &str, tweet: &Facts) -> Option<bool> {
+        expr.parse::<Expr>().unwrap().eval(tweet)
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let tweet = facts(None);
+        // `true || (false && false)`, not `(true || false) && false`
+        let expr = "likes == 5 || likes == 1 && likes == 2";
+        assert_eq!(eval(expr, &tweet), Some(true));
+        let expr = "(likes == 5 || likes == 1) && likes == 2";
+        assert_eq!(eval(expr, &tweet), Some(false));
+    }
+
+    #[test]
+    fn not_and_parentheses() {
+        let tweet = facts(None);
+        assert_eq!(eval("!is_retweet", &tweet), Some(true));
+        assert_eq!(eval("!!is_retweet", &tweet), Some(false));
+        assert_eq!(eval("!(is_retweet || likes > 1)", &tweet), Some(false));
+        assert_eq!(eval("!is_retweet && likes > 1", &tweet), Some(true));
+    }
+
+    #[test]
+    fn le_is_not_lt() {
+        let tweet = facts(None);
+        assert_eq!(eval("likes <= 5", &tweet), Some(true));
+        assert_eq!(eval("likes < 5", &tweet), Some(false));
+        assert_eq!(eval("likes >= 5", &tweet), Some(true));
+        assert_eq!(eval("likes > 5", &tweet), Some(false));
+        assert_eq!(eval("likes != 5", &tweet), Some(false));
+    }
+
+    #[test]
+    fn regex_escaped_slash_and_case() {
+        let tweet = facts(Some("Read the Docs at a/b"));
+        assert_eq!(eval(r"text ~ /a\/b/", &tweet), Some(true));
+        assert_eq!(eval("text ~ /docs/", &tweet), Some(false));
+        assert_eq!(eval("text ~ /docs/i", &tweet), Some(true));
+        assert_eq!(eval("text ~ /docs/i && likes == 5", &tweet), Some(true));
+    }
+
+    #[test]
+    fn age_units() {
+        let tweet = facts(None);
+        assert_eq!(eval("age > 9", &tweet), Some(true));
+        assert_eq!(eval("age > 9d", &tweet), Some(true));
+        assert_eq!(eval("age > 1w", &tweet), Some(true));
+        assert_eq!(eval("age > 2w", &tweet), Some(false));
+        assert_eq!(eval("age < 1m", &tweet), Some(true));
+        assert_eq!(eval("age < 0.1y", &tweet), Some(true));
+        assert!("age > 2x".parse::<Expr>().is_err());
+    }
+
+    #[test]
+    fn unknown_is_neither() {
+        let tweet = facts(None);
+        assert_eq!(eval("text ~ /word/", &tweet), None);
+        assert_eq!(eval("!(text ~ /word/)", &tweet), None);
+        assert_eq!(eval("chars < 10", &tweet), None);
+        assert_eq!(eval("has_media", &tweet), None);
+        assert_eq!(eval("score > 0.5", &tweet), None);
+        // Unless the rest decides it
+        assert_eq!(eval("has_media || likes == 5", &tweet), Some(true));
+        assert_eq!(eval("has_media && likes == 1", &tweet), Some(false));
+        assert_eq!(eval("has_media && likes == 5", &tweet), None);
+    }
+
+    #[test]
+    fn error_columns() {
+        for (expr, column) in [
+            ("likes", 6),
+            ("likes < x", 9),
+            ("age > 1 &&", 11),
+            ("(likes < 1", 11),
+            ("bogus > 1", 1),
+            // Characters, not bytes
+            ("text ~ /é", 9),
+        ] {
+            let error = expr.parse::<Expr>().unwrap_err().to_string();
+            assert!(error.contains(&format!("at column {column},")), "{error}");
+        }
+    }
+}
@@ -0,0 +1,206 @@
+//! Previewing what a filter expression would delete from an archive, from
+//! other languages
+//!
+//! Such as a web page showing what would be deleted, without the archive ever
+//! leaving the browser. Only [filter expressions](crate::expr) are supported,
+//! as the other filters need the database.
+//!
+//! The library built by `cargo build -p twitter_delete_core` can be used from
+//! C, see [`twitter_delete_preview`]. With the `wasm` feature, building for
+//! `wasm32-unknown-unknown`, eg with `wasm-pack build core --features wasm`,
+//! makes a javascript module exporting `preview`, see [`preview_js`].
+use std::ffi::{c_char, CStr, CString};
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use time::PrimitiveDateTime;
+
+use crate::{
+    archive::{parse_tweets, TWITTER_DATE},
+    expr::{Expr, Facts},
+    id::TweetId,
+};
+
+/// A tweet found by [`preview`]
+#[derive(Debug, Serialize)]
+pub struct Previewed {
+    pub id: TweetId,
+
+    /// UTC unix time
+    pub created_at: i64,
+    pub likes: i32,
+    pub retweets: i32,
+    pub text: Option<String>,
+    pub has_media: bool,
+    pub is_reply: bool,
+}
+
+/// What [`preview`] found
+#[derive(Debug, Serialize)]
+pub struct Preview {
+    /// Every matching tweet, in the order they are in the file
+    pub tweets: Vec<Previewed>,
+
+    /// Problems that were repaired or skipped while parsing leniently
+    pub repairs: Vec<String>,
+}
+
+/// Find the tweets in the archive tweets file `data`, such as the contents of
+/// `data/tweets.js`, that match the filter expression `expr` as of `now`, or
+/// every tweet without one
+///
+/// `now` is UTC unix time. If `lenient` is true, problems are repaired or
+/// skipped as when importing with `--lenient`.
+///
+/// Whether tweets are retweets isn't in the archive, so `is_retweet` is never
+/// known, and the text is matched as in the archive, with t.co links.
+pub fn preview(data: &str, expr: Option<&str>, now: i64, lenient: bool) -> Result<Preview> {
+    let expr: Option<Expr> = expr.map(str::parse).transpose()?;
+    let mut repairs = Vec::new();
+    let mut tweets = Vec::new();
+    for tweet in parse_tweets("data/tweets.js", data, lenient, &mut repairs)? {
+        let created_at = PrimitiveDateTime::parse(&tweet.created_at, TWITTER_DATE)?
+            .assume_utc()
+            .unix_timestamp();
+        let previewed = Previewed {
+            created_at,
+            likes: tweet.likes.parse()?,
+            retweets: tweet.retweets.parse()?,
+            has_media: tweet.has_media(),
+            is_reply: tweet.in_reply_to_status_id_str.is_some(),
+            id: tweet.id_str,
+            text: tweet.full_text,
+        };
+        let facts = Facts {
+            age: now - previewed.created_at,
+            likes: previewed.likes,
+            retweets: previewed.retweets,
+            score: None,
+            text: previewed.text.as_deref(),
+            has_media: Some(previewed.has_media),
+            is_retweet: None,
+            is_reply: previewed.is_reply,
+        };
+        if expr.as_ref().map_or(true, |e| e.eval(&facts) == Some(true)) {
+            tweets.push(previewed);
+        }
+    }
+    Ok(Preview { tweets, repairs })
+}
+
+/// [`preview`] as JSON, or `{"error": "..."}`
+fn preview_json(data: &str, expr: Option<&str>, now: i64, lenient: bool) -> String {
+    let json = preview(data, expr, now, lenient).and_then(|p| Ok(serde_json::to_string(&p)?));
+    json.unwrap_or_else(|e| serde_json::json!({ "error": format!("{e:#}") }).to_string())
+}
+
+/// [`preview`] for C
+///
+/// `data` and `expr` are UTF-8, and `expr` may be null to find every tweet.
+///
+/// Returns the [`Preview`] as JSON, or `{"error": "..."}`, which must be freed
+/// with [`twitter_delete_free`].
+///
+/// # Safety
+///
+/// `data`, and `expr` unless it's null, must be valid nul terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn twitter_delete_preview(
+    data: *const c_char,
+    expr: *const c_char,
+    now: i64,
+    lenient: bool,
+) -> *mut c_char {
+    let utf8 = |s: *const c_char| {
+        CStr::from_ptr(s)
+            .to_str()
+            .map_err(|e| anyhow!("Invalid UTF-8: {e}"))
+    };
+    let json = match (utf8(data), (!expr.is_null()).then(|| utf8(expr)).transpose()) {
+        (Ok(data), Ok(expr)) => preview_json(data, expr, now, lenient),
+        (Err(e), _) | (_, Err(e)) => serde_json::json!({ "error": e.to_string() }).to_string(),
+    };
+    CString::new(json)
+        .expect("BUG: JSON has no nul bytes")
+        .into_raw()
+}
+
+/// Free a string returned by [`twitter_delete_preview`]
+///
+/// # Safety
+///
+/// `s` must have been returned by [`twitter_delete_preview`], and not
+/// already freed, or be null.
+#[no_mangle]
+pub unsafe extern "C" fn twitter_delete_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// [`preview`] for javascript, exported as `preview`
+///
+/// `now` is UTC unix time in seconds. Returns the [`Preview`] as JSON.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen::prelude::wasm_bindgen(js_name = preview)]
+pub fn preview_js(
+    data: &str,
+    expr: Option<String>,
+    now: f64,
+    lenient: bool,
+) -> Result<String, wasm_bindgen::JsError> {
+    let preview = preview(data, expr.as_deref(), now as i64, lenient)
+        .map_err(|e| wasm_bindgen::JsError::new(&format!("{e:#}")))?;
+    Ok(serde_json::to_string(&preview)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+
+    /// 2023-01-01
+    const NOW: i64 = 1672531200;
+
+    const TWEETS: &str = r#"window.YTD.tweets.part0 = [
+        {"tweet": {"id_str": "1", "retweet_count": "0", "favorite_count": "2",
+         "created_at": "Fri Mar 04 12:00:00 +0000 2022", "full_text": "Old news"}},
+        {"tweet": {"id_str": "2", "retweet_count": "5", "favorite_count": "50",
+         "created_at": "Fri Mar 04 12:00:00 +0000 2022", "full_text": "Popular"}},
+        {"tweet": {"id_str": "3", "retweet_count": "0", "favorite_count": "0",
+         "created_at": "Sat Dec 31 12:00:00 +0000 2022", "full_text": "New"}}
+    ]"#;
+
+    fn ids(preview: &Preview) -> Vec<String> {
+        preview.tweets.iter().map(|t| t.id.to_string()).collect()
+    }
+
+    #[test]
+    fn previews_matching_tweets() {
+        let all = preview(TWEETS, None, NOW, false).unwrap();
+        assert_eq!(ids(&all), ["1", "2", "3"]);
+        let old = preview(TWEETS, Some("age > 30d && likes < 10"), NOW, false).unwrap();
+        assert_eq!(ids(&old), ["1"]);
+        assert_eq!(old.tweets[0].created_at, 1646395200);
+        assert!(preview(TWEETS, Some("likes <"), NOW, false).is_err());
+    }
+
+    #[test]
+    fn previews_from_c() {
+        let call = |expr: Option<&str>| -> Value {
+            let data = CString::new(TWEETS).unwrap();
+            let expr = expr.map(|e| CString::new(e).unwrap());
+            let expr_ptr = expr.as_ref().map_or(std::ptr::null(), |e| e.as_ptr());
+            unsafe {
+                let json = twitter_delete_preview(data.as_ptr(), expr_ptr, NOW, true);
+                let value = serde_json::from_str(CStr::from_ptr(json).to_str().unwrap()).unwrap();
+                twitter_delete_free(json);
+                value
+            }
+        };
+        assert_eq!(call(None)["tweets"].as_array().unwrap().len(), 3);
+        assert_eq!(call(Some("text ~ /popular/i"))["tweets"][0]["id"], "2");
+        assert!(call(Some("nonsense"))["error"].is_string());
+    }
+}
@@ -0,0 +1,159 @@
+//! Validated tweet IDs
+use std::{
+    cmp::Ordering,
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use anyhow::{anyhow, Error, Result};
+#[cfg(feature = "diesel")]
+use diesel::{
+    backend::RawValue,
+    deserialize::{self, FromSql, FromSqlRow},
+    expression::AsExpression,
+    serialize::{self, Output, ToSql},
+    sql_types::Text,
+    sqlite::Sqlite,
+};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+/// Twitters snowflake epoch, in UTC unix milliseconds
+const SNOWFLAKE_EPOCH: i64 = 1288834974657;
+
+/// First snowflake ID, anything smaller is sequential
+const FIRST_SNOWFLAKE: u64 = 29700859247;
+
+/// A tweet ID, such as `1234567890`
+///
+/// Always decimal digits without leading zeros, so IDs that are the same
+/// number are the same ID.
+///
+/// Stored as text, like twitter sends them, but ordered numerically, unlike
+/// text, where `"9"` sorts after `"10"`.
+///
+/// Only for tweets, including liked tweets. Direct messages have IDs of
+/// their own, which are kept as text.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "diesel", derive(AsExpression, FromSqlRow), diesel(sql_type = Text))]
+#[serde(try_from = "String", into = "String")]
+pub struct TweetId(String);
+
+impl TweetId {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The ID as a number
+    pub fn get(&self) -> u64 {
+        self.0.parse().expect("BUG: TweetId wasn't validated")
+    }
+
+    /// When this tweet was created, decoded from its snowflake ID
+    ///
+    /// Returns [`None`] for tweets from before snowflake IDs were introduced
+    /// in November 2010.
+    ///
+    /// # Implementation Details
+    ///
+    /// The top 41 bits of a snowflake ID are milliseconds since
+    /// [`SNOWFLAKE_EPOCH`]. Older sequential IDs are too small to decode, and
+    /// would otherwise appear to be from 2010.
+    pub fn snowflake_time(&self) -> Option<OffsetDateTime> {
+        let id = self.get();
+        if id < FIRST_SNOWFLAKE {
+            return None;
+        }
+        let ms = (id >> 22) as i64 + SNOWFLAKE_EPOCH;
+        OffsetDateTime::from_unix_timestamp_nanos(ms as i128 * 1_000_000).ok()
+    }
+}
+
+impl FromStr for TweetId {
+    type Err = Error;
+
+    /// Parse a tweet ID, ignoring surrounding whitespace and leading zeros
+    fn from_str(s: &str) -> Result<Self> {
+        let id = s.trim();
+        if id.is_empty() || !id.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(anyhow!("Invalid tweet ID `{s}`, expected a number"));
+        }
+        match id.parse::<u64>() {
+            Ok(0) => Err(anyhow!("Invalid tweet ID `{s}`, IDs start at 1")),
+            Ok(n) => Ok(Self(n.to_string())),
+            Err(_) => Err(anyhow!("Invalid tweet ID `{s}`, too large")),
+        }
+    }
+}
+
+impl TryFrom<String> for TweetId {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<TweetId> for String {
+    fn from(id: TweetId) -> Self {
+        id.0
+    }
+}
+
+impl Display for TweetId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Ord for TweetId {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Without leading zeros, longer numbers are always larger
+        self.0
+            .len()
+            .cmp(&other.0.len())
+            .then_with(|| self.0.cmp(&other.0))
+    }
+}
+
+impl PartialOrd for TweetId {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(feature = "diesel")]
+impl ToSql<Text, Sqlite> for TweetId {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Sqlite>) -> serialize::Result {
+        <str as ToSql<Text, Sqlite>>::to_sql(self.as_str(), out)
+    }
+}
+
+#[cfg(feature = "diesel")]
+impl FromSql<Text, Sqlite> for TweetId {
+    fn from_sql(bytes: RawValue<'_, Sqlite>) -> deserialize::Result<Self> {
+        let id = <String as FromSql<Text, Sqlite>>::from_sql(bytes)?;
+        Ok(id.parse()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(s: &str) -> TweetId {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn orders_numerically() {
+        let short = id("999999999999999999");
+        let long = id("1000000000000000000");
+        assert!(short < long);
+        assert_eq!(short.cmp(&long), short.get().cmp(&long.get()));
+        // As text the 18 digit ID would sort after
+        assert!(short.as_str() > long.as_str());
+        assert_eq!(id("9").cmp(&id("10")), Ordering::Less);
+        assert_eq!(id("0010").cmp(&id("10")), Ordering::Equal);
+    }
+}
@@ -0,0 +1,305 @@
+//! Handles old-format twitter archives, from before 2019
+//!
+//! These have tweets split by month into `data/js/tweets/YYYY_MM.js`, indexed
+//! by `data/js/tweet_index.js`, and account information in
+//! `data/js/user_details.js`.
+use std::{collections::HashMap, fs, path::Path};
+
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::from_str;
+use time::{format_description::FormatItem, macros::format_description, PrimitiveDateTime};
+
+use crate::{
+    archive::{
+        parse_lenient_with,
+        read_archive_file,
+        remove_prefix,
+        Account,
+        Collected,
+        Entities,
+        Tweet,
+        TWITTER_DATE,
+    },
+    id::TweetId,
+};
+
+/// The format of old-format archive `created_at` dates
+static LEGACY_DATE: &[FormatItem] =
+    format_description!("[year]-[month]-[day] [hour]:[minute]:[second] +0000");
+
+/// A Tweet in an old-format twitter archive.
+#[derive(Debug, Deserialize)]
+struct LegacyTweet {
+    /// Tweet ID
+    id_str: TweetId,
+
+    /// Text of the tweet
+    text: String,
+
+    /// Time of tweet
+    ///
+    /// Usually [`LEGACY_DATE`], but some archives use [`TWITTER_DATE`]
+    created_at: String,
+
+    /// Number of retweets, not in most archives
+    #[serde(default)]
+    retweet_count: u64,
+
+    /// Number of likes, not in most archives
+    #[serde(default)]
+    favorite_count: u64,
+
+    /// Tweet ID this is a reply to
+    #[serde(default)]
+    in_reply_to_status_id_str: Option<String>,
+
+    /// Account ID this is a reply to
+    #[serde(default)]
+    in_reply_to_user_id_str: Option<String>,
+
+    /// Account handle this is a reply to
+    #[serde(default)]
+    in_reply_to_screen_name: Option<String>,
+
+    /// Entities, such as mentions, in the tweet
+    #[serde(default)]
+    entities: Entities,
+}
+
+impl LegacyTweet {
+    /// Convert to the current archive format
+    fn into_tweet(self) -> Tweet {
+        let created_at = PrimitiveDateTime::parse(&self.created_at, LEGACY_DATE)
+            .ok()
+            .and_then(|t| t.assume_utc().format(TWITTER_DATE).ok())
+            .unwrap_or(self.created_at);
+        Tweet {
+            id_str: self.id_str,
+            retweets: self.retweet_count.to_string(),
+            likes: self.favorite_count.to_string(),
+            created_at,
+            full_text: Some(self.text),
+            in_reply_to_status_id_str: self.in_reply_to_status_id_str,
+            in_reply_to_user_id_str: self.in_reply_to_user_id_str,
+            in_reply_to_screen_name: self.in_reply_to_screen_name,
+            entities: self.entities,
+            extended_entities: None,
+            edit_info: None,
+        }
+    }
+}
+
+/// Account in an old-format twitter archive.
+#[derive(Debug, Deserialize)]
+struct UserDetails {
+    /// Account ID
+    id: String,
+
+    /// Account username at time of archive
+    screen_name: String,
+
+    /// Account display name at time of archive
+    full_name: String,
+}
+
+/// A file in `data/js/tweet_index.js`
+#[derive(Debug, Deserialize)]
+struct IndexFile {
+    /// Path relative to the archive
+    file_name: String,
+
+    /// How many tweets are in the file
+    tweet_count: usize,
+}
+
+/// Whether the archive at `path` is an old-format archive
+pub fn is_legacy(path: &Path) -> bool {
+    path.join("data")
+        .join("js")
+        .join("tweet_index.js")
+        .is_file()
+}
+
+/// Get the account for this old-format twitter archive
+///
+/// Invalid UTF-8, as in a mangled name, is replaced with U+FFFD.
+pub fn get_account(path: &Path) -> Result<Account> {
+    let path = path.join("data").join("js").join("user_details.js");
+    let data = String::from_utf8_lossy(&fs::read(path)?).into_owned();
+    let user: UserDetails = from_str(remove_prefix(&data))?;
+
+    Ok(Account {
+        id_str: user.id,
+        user_name: user.screen_name,
+        display_name: user.full_name,
+    })
+}
+
+/// Collect all tweets from an old-format twitter archive
+///
+/// Tweets are expected to exist at `data/js/tweets/YYYY_MM.js`.
+///
+/// If `lenient` is true, invalid UTF-8 is replaced and tweets that fail to
+/// parse are skipped, instead of failing the entire file, see
+/// [`Collected::repairs`].
+///
+/// These archives usually don't record likes or retweets, so tweets have none.
+pub fn collect_tweets(path: &Path, lenient: bool) -> Result<Collected> {
+    let mut out = Collected::default();
+    for file in path.join("data").join("js").join("tweets").read_dir()? {
+        let file = file?;
+        if !file.file_type()?.is_file() {
+            continue;
+        }
+        let name = file.file_name();
+        let name = name
+            .to_str()
+            .ok_or_else(|| anyhow!("Invalid UTF-8 in filename {:?}", file.file_name()))?;
+        if !name.ends_with(".js") {
+            continue;
+        }
+
+        let name = format!("data/js/tweets/{name}");
+        let data = read_archive_file(&file.path(), &name, lenient, &mut out.repairs)?;
+        let tweets = parse_month(&name, &data, lenient, &mut out.repairs)?;
+        out.counts.insert(name, tweets.len());
+        out.tweets
+            .extend(tweets.into_iter().map(LegacyTweet::into_tweet));
+    }
+
+    Ok(out)
+}
+
+/// Parse the tweets file `name`, with contents `data`
+///
+/// If `lenient` is true, tweets that fail to parse are skipped and recorded in
+/// `repairs`, see [`parse_lenient_with`].
+fn parse_month(
+    name: &str,
+    data: &str,
+    lenient: bool,
+    repairs: &mut Vec<String>,
+) -> Result<Vec<LegacyTweet>> {
+    // The full line is `Grailbird.data.tweets_2014_12 =`
+    let data = remove_prefix(data);
+    if !lenient {
+        return Ok(from_str(data)?);
+    }
+    // Only tweets start at the beginning of a line, the objects in them are
+    // indented
+    let next_tweet = Regex::new(r"(?m)^\},\s*(?P<tweet>\{)").expect("BUG: Invalid regex");
+    Ok(parse_lenient_with(name, data, &next_tweet, "/id_str", repairs))
+}
+
+/// Check `counts` against `data/js/tweet_index.js`, returning warnings for
+/// every file that is missing or truncated
+///
+/// See [`verify_manifest`](crate::archive::verify_manifest)
+pub fn verify_index(path: &Path, counts: &HashMap<String, usize>) -> Result<Vec<String>> {
+    let path = path.join("data").join("js").join("tweet_index.js");
+    // Only warns, so invalid UTF-8 shouldn't stop an import
+    let data = String::from_utf8_lossy(&fs::read(&path)?).into_owned();
+    check_index(&data, counts)
+}
+
+/// [`verify_index`], with the contents of `data/js/tweet_index.js` as `data`
+fn check_index(data: &str, counts: &HashMap<String, usize>) -> Result<Vec<String>> {
+    let index: Vec<IndexFile> = from_str(remove_prefix(data))?;
+
+    let mut warnings = Vec::new();
+    for file in index {
+        let expected = file.tweet_count;
+        match counts.get(&file.file_name) {
+            Some(&found) if found < expected => warnings.push(format!(
+                "{} appears truncated, found {found} out of {expected} tweets",
+                file.file_name
+            )),
+            Some(_) => (),
+            None => warnings.push(format!(
+                "{} is missing, {expected} tweets were not imported",
+                file.file_name
+            )),
+        }
+    }
+
+    Ok(warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tweet(id: u32, created_at: &str) -> String {
+        format!(
+            "{{\n  \"id_str\" : \"{id}\",\n  \"text\" : \"Hi\",\n  \"entities\" : {{\n    \
+             \"user_mentions\" : [ ]\n  }},\n  \"created_at\" : \"{created_at}\"\n}}"
+        )
+    }
+
+    fn created_at(tweet: &str) -> String {
+        let tweet: LegacyTweet = from_str(tweet).unwrap();
+        tweet.into_tweet().created_at
+    }
+
+    #[test]
+    fn converts_legacy_dates() {
+        assert_eq!(
+            created_at(&tweet(1, "2014-12-01 10:09:08 +0000")),
+            "Mon Dec 01 10:09:08 +0000 2014"
+        );
+        // Already in the current format
+        assert_eq!(
+            created_at(&tweet(1, "Mon Dec 01 10:09:08 +0000 2014")),
+            "Mon Dec 01 10:09:08 +0000 2014"
+        );
+    }
+
+    #[test]
+    fn lenient_skips_to_next_tweet() {
+        let date = "2014-12-01 10:09:08 +0000";
+        let data = format!(
+            "Grailbird.data.tweets_2014_12 = \n [ {}, {{\n  \"id_str\" : \"2\",,\n}}, {} ]",
+            tweet(1, date),
+            tweet(3, date)
+        );
+        let name = "data/js/tweets/2014_12.js";
+        assert!(parse_month(name, &data, false, &mut Vec::new()).is_err());
+
+        let mut repairs = Vec::new();
+        let ids: Vec<String> = parse_month(name, &data, true, &mut repairs)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.id_str.to_string())
+            .collect();
+        assert_eq!(ids, ["1", "3"]);
+        assert_eq!(repairs.len(), 1, "{repairs:?}");
+    }
+
+    #[test]
+    fn index_warns_of_missing_and_truncated() {
+        let index = r#"var tweet_index = [ {
+  "file_name" : "data/js/tweets/2014_12.js",
+  "tweet_count" : 3
+}, {
+  "file_name" : "data/js/tweets/2014_11.js",
+  "tweet_count" : 2
+}, {
+  "file_name" : "data/js/tweets/2014_10.js",
+  "tweet_count" : 1
+} ]"#;
+        let counts = HashMap::from([
+            ("data/js/tweets/2014_12.js".to_owned(), 2),
+            ("data/js/tweets/2014_10.js".to_owned(), 1),
+        ]);
+        let warnings = check_index(index, &counts).unwrap();
+        assert_eq!(
+            warnings,
+            [
+                "data/js/tweets/2014_12.js appears truncated, found 2 out of 3 tweets",
+                "data/js/tweets/2014_11.js is missing, 2 tweets were not imported",
+            ]
+        );
+    }
+}
@@ -0,0 +1,10 @@
+//! The parts of `twitter_delete` that need neither twitter, its database,
+//! nor the command line: parsing archives, tweet IDs, and filter expressions
+//!
+//! These build on their own, so they can be used from other languages, and
+//! in browsers, see [`ffi`].
+pub mod archive;
+pub mod expr;
+pub mod ffi;
+pub mod id;
+pub mod legacy;
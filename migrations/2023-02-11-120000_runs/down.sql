@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE tweets DROP COLUMN note;
+ALTER TABLE tweets DROP COLUMN protected;
+
+DROP TABLE runs;
@@ -0,0 +1,12 @@
+-- Your SQL goes here
+CREATE TABLE runs (
+    id INTEGER PRIMARY KEY NOT NULL,
+    command TEXT NOT NULL,
+    tag TEXT,
+    started_at INTEGER NOT NULL,
+    finished_at INTEGER,
+    deleted INTEGER NOT NULL DEFAULT 0
+) STRICT;
+
+ALTER TABLE tweets ADD COLUMN protected INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE tweets ADD COLUMN note TEXT;
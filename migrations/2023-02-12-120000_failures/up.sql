@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE tweets ADD COLUMN failures INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX mentionsindex;
+DROP TABLE mentions;
+
+ALTER TABLE tweets DROP COLUMN in_reply_to_screen_name;
+ALTER TABLE tweets DROP COLUMN in_reply_to_user_id;
+ALTER TABLE tweets DROP COLUMN in_reply_to_status_id;
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+ALTER TABLE tweets ADD COLUMN in_reply_to_status_id TEXT;
+ALTER TABLE tweets ADD COLUMN in_reply_to_user_id TEXT;
+ALTER TABLE tweets ADD COLUMN in_reply_to_screen_name TEXT COLLATE NOCASE;
+
+-- Twitter handles are case insensitive
+CREATE TABLE mentions (
+    tweet_id TEXT NOT NULL REFERENCES tweets(id_str),
+    user_id TEXT NOT NULL,
+    screen_name TEXT NOT NULL COLLATE NOCASE,
+    PRIMARY KEY (tweet_id, user_id)
+) STRICT;
+
+CREATE INDEX mentionsindex ON mentions(screen_name);
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+CREATE TABLE events (
+    id INTEGER PRIMARY KEY NOT NULL,
+    tweet_id TEXT NOT NULL REFERENCES tweets(id_str),
+    kind TEXT NOT NULL,
+    at INTEGER NOT NULL,
+    detail TEXT
+) STRICT;
+
+CREATE INDEX eventsindex ON events(tweet_id);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE run_tweets;
+
+ALTER TABLE runs DROP COLUMN filters;
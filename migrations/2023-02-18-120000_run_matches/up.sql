@@ -0,0 +1,8 @@
+-- Your SQL goes here
+ALTER TABLE runs ADD COLUMN filters TEXT;
+
+CREATE TABLE run_tweets (
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    tweet_id TEXT NOT NULL REFERENCES tweets(id_str),
+    PRIMARY KEY (run_id, tweet_id)
+) STRICT;
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+ALTER TABLE tweets ADD COLUMN deleted_at INTEGER;
+
+-- Tweets deleted before this was recorded use their event history, if any
+UPDATE tweets SET deleted_at = (
+    SELECT MAX(at) FROM events
+    WHERE events.tweet_id = tweets.id_str AND events.kind = 'deleted'
+) WHERE deleted = 1;
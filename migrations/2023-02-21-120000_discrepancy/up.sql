@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE runs ADD COLUMN discrepancy INTEGER;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE accounts DROP COLUMN keys_path;
+ALTER TABLE accounts DROP COLUMN api_key;
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE accounts ADD COLUMN api_key TEXT;
+ALTER TABLE accounts ADD COLUMN keys_path TEXT;
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX tweetsaccountcheckedindex;
+DROP INDEX tweetscandidatesindex;
+DROP INDEX tweetscheckedindex;
+DROP INDEX tweetsdeletedindex;
+DROP INDEX tweetscreatedindex;
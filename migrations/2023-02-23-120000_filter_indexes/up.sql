@@ -0,0 +1,10 @@
+-- Your SQL goes here
+CREATE INDEX tweetscreatedindex ON tweets(created_at);
+CREATE INDEX tweetsdeletedindex ON tweets(deleted);
+CREATE INDEX tweetscheckedindex ON tweets(checked);
+
+-- Matches the candidate selection in `delete`, `stage`, and `nuke`
+CREATE INDEX tweetscandidatesindex ON tweets(account_id, deleted, protected, created_at);
+
+-- Matches the per-account counts in `stats`
+CREATE INDEX tweetsaccountcheckedindex ON tweets(account_id, checked);
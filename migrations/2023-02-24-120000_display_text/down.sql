@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE urls;
+
+ALTER TABLE tweets DROP COLUMN display_text;
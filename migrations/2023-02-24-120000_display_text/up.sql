@@ -0,0 +1,10 @@
+-- Your SQL goes here
+ALTER TABLE tweets ADD COLUMN display_text TEXT;
+
+-- Links in tweets are shortened to t.co, this is where they really went
+CREATE TABLE urls (
+    tweet_id TEXT NOT NULL REFERENCES tweets(id_str),
+    url TEXT NOT NULL,
+    expanded_url TEXT NOT NULL,
+    PRIMARY KEY (tweet_id, url)
+) STRICT;
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE tweets ADD COLUMN archived_at INTEGER;
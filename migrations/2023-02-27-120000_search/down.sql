@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER tweets_search_delete;
+DROP TRIGGER tweets_search_update;
+DROP TRIGGER tweets_search_insert;
+DROP TABLE tweets_search;
@@ -0,0 +1,29 @@
+-- Your SQL goes here
+-- Full text search over tweet text, as displayed if known
+CREATE VIRTUAL TABLE tweets_search USING fts5(tweet_id UNINDEXED, text);
+
+INSERT INTO tweets_search(tweet_id, text)
+    SELECT id_str, coalesce(display_text, full_text) FROM tweets
+    WHERE coalesce(display_text, full_text) IS NOT NULL;
+
+CREATE TRIGGER tweets_search_insert AFTER INSERT ON tweets
+WHEN coalesce(new.display_text, new.full_text) IS NOT NULL
+BEGIN
+    INSERT INTO tweets_search(tweet_id, text)
+        VALUES (new.id_str, coalesce(new.display_text, new.full_text));
+END;
+
+-- Re-importing refreshes the text of every tweet, only changes matter
+CREATE TRIGGER tweets_search_update AFTER UPDATE OF full_text, display_text ON tweets
+WHEN old.full_text IS NOT new.full_text OR old.display_text IS NOT new.display_text
+BEGIN
+    DELETE FROM tweets_search WHERE tweet_id = old.id_str;
+    INSERT INTO tweets_search(tweet_id, text)
+        SELECT new.id_str, coalesce(new.display_text, new.full_text)
+        WHERE coalesce(new.display_text, new.full_text) IS NOT NULL;
+END;
+
+CREATE TRIGGER tweets_search_delete AFTER DELETE ON tweets
+BEGIN
+    DELETE FROM tweets_search WHERE tweet_id = old.id_str;
+END;
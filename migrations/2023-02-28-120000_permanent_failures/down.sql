@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE tweets DROP COLUMN permanently_failed;
+ALTER TABLE tweets DROP COLUMN forbidden;
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+-- Consecutive 403 Forbidden responses to deleting the tweet
+ALTER TABLE tweets ADD COLUMN forbidden INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE tweets ADD COLUMN permanently_failed INTEGER NOT NULL DEFAULT 0;
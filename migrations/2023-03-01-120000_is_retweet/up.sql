@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- NULL until checked with the lookup API
+ALTER TABLE tweets ADD COLUMN is_retweet INTEGER;
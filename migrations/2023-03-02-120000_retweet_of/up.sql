@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- ID of the retweeted tweet, for unretweeting
+ALTER TABLE tweets ADD COLUMN retweet_of TEXT;
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+-- Tweets liked by an account, from its twitter archive
+CREATE TABLE likes (
+    tweet_id TEXT NOT NULL,
+    account_id TEXT NOT NULL REFERENCES accounts(id_str),
+    full_text TEXT,
+    unliked INTEGER NOT NULL DEFAULT 0,
+    unliked_at INTEGER,
+    PRIMARY KEY (tweet_id, account_id)
+) STRICT;
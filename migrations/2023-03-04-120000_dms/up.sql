@@ -0,0 +1,14 @@
+-- Your SQL goes here
+-- Direct messages in an account's view of its conversations, from its
+-- twitter archive
+CREATE TABLE dms (
+    id TEXT NOT NULL,
+    account_id TEXT NOT NULL REFERENCES accounts(id_str),
+    conversation_id TEXT NOT NULL,
+    sender_id TEXT,
+    text TEXT,
+    created_at INTEGER NOT NULL,
+    deleted INTEGER NOT NULL DEFAULT 0,
+    deleted_at INTEGER,
+    PRIMARY KEY (id, account_id)
+) STRICT;
@@ -0,0 +1,9 @@
+-- Your SQL goes here
+-- Named filter combinations, saved with `filters save`
+--
+-- `filters` is the canonical JSON of the filters
+CREATE TABLE saved_filters (
+    name TEXT PRIMARY KEY NOT NULL,
+    filters TEXT NOT NULL,
+    saved_at INTEGER NOT NULL
+) STRICT;
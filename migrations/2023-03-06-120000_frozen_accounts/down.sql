@@ -0,0 +1,11 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER dms_frozen_delete;
+DROP TRIGGER dms_frozen_update;
+DROP TRIGGER dms_frozen_insert;
+DROP TRIGGER likes_frozen_delete;
+DROP TRIGGER likes_frozen_update;
+DROP TRIGGER likes_frozen_insert;
+DROP TRIGGER tweets_frozen_delete;
+DROP TRIGGER tweets_frozen_update;
+DROP TRIGGER tweets_frozen_insert;
+ALTER TABLE accounts DROP COLUMN frozen_at;
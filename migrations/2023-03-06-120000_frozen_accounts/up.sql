@@ -0,0 +1,57 @@
+-- Your SQL goes here
+-- Archived accounts, whose tweets, likes, and messages can no longer change
+ALTER TABLE accounts ADD COLUMN frozen_at INTEGER;
+
+CREATE TRIGGER tweets_frozen_insert BEFORE INSERT ON tweets
+WHEN (SELECT frozen_at FROM accounts WHERE id_str = new.account_id) IS NOT NULL
+BEGIN
+    SELECT RAISE(ABORT, 'The account has been archived with `archive-account`, and can''t be changed');
+END;
+
+CREATE TRIGGER tweets_frozen_update BEFORE UPDATE ON tweets
+WHEN (SELECT frozen_at FROM accounts WHERE id_str = old.account_id) IS NOT NULL
+BEGIN
+    SELECT RAISE(ABORT, 'The account has been archived with `archive-account`, and can''t be changed');
+END;
+
+CREATE TRIGGER tweets_frozen_delete BEFORE DELETE ON tweets
+WHEN (SELECT frozen_at FROM accounts WHERE id_str = old.account_id) IS NOT NULL
+BEGIN
+    SELECT RAISE(ABORT, 'The account has been archived with `archive-account`, and can''t be changed');
+END;
+
+CREATE TRIGGER likes_frozen_insert BEFORE INSERT ON likes
+WHEN (SELECT frozen_at FROM accounts WHERE id_str = new.account_id) IS NOT NULL
+BEGIN
+    SELECT RAISE(ABORT, 'The account has been archived with `archive-account`, and can''t be changed');
+END;
+
+CREATE TRIGGER likes_frozen_update BEFORE UPDATE ON likes
+WHEN (SELECT frozen_at FROM accounts WHERE id_str = old.account_id) IS NOT NULL
+BEGIN
+    SELECT RAISE(ABORT, 'The account has been archived with `archive-account`, and can''t be changed');
+END;
+
+CREATE TRIGGER likes_frozen_delete BEFORE DELETE ON likes
+WHEN (SELECT frozen_at FROM accounts WHERE id_str = old.account_id) IS NOT NULL
+BEGIN
+    SELECT RAISE(ABORT, 'The account has been archived with `archive-account`, and can''t be changed');
+END;
+
+CREATE TRIGGER dms_frozen_insert BEFORE INSERT ON dms
+WHEN (SELECT frozen_at FROM accounts WHERE id_str = new.account_id) IS NOT NULL
+BEGIN
+    SELECT RAISE(ABORT, 'The account has been archived with `archive-account`, and can''t be changed');
+END;
+
+CREATE TRIGGER dms_frozen_update BEFORE UPDATE ON dms
+WHEN (SELECT frozen_at FROM accounts WHERE id_str = old.account_id) IS NOT NULL
+BEGIN
+    SELECT RAISE(ABORT, 'The account has been archived with `archive-account`, and can''t be changed');
+END;
+
+CREATE TRIGGER dms_frozen_delete BEFORE DELETE ON dms
+WHEN (SELECT frozen_at FROM accounts WHERE id_str = old.account_id) IS NOT NULL
+BEGIN
+    SELECT RAISE(ABORT, 'The account has been archived with `archive-account`, and can''t be changed');
+END;
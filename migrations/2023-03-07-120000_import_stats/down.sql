@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE runs DROP COLUMN peak_memory;
+ALTER TABLE runs DROP COLUMN imported_rows;
+ALTER TABLE runs DROP COLUMN insert_ms;
+ALTER TABLE runs DROP COLUMN parse_ms;
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+-- Performance of `import` runs
+ALTER TABLE runs ADD COLUMN parse_ms INTEGER;
+ALTER TABLE runs ADD COLUMN insert_ms INTEGER;
+ALTER TABLE runs ADD COLUMN imported_rows INTEGER;
+ALTER TABLE runs ADD COLUMN peak_memory INTEGER;
@@ -0,0 +1,9 @@
+-- Your SQL goes here
+-- Hashtags are case insensitive
+CREATE TABLE hashtags (
+    tweet_id TEXT NOT NULL REFERENCES tweets(id_str),
+    hashtag TEXT NOT NULL COLLATE NOCASE,
+    PRIMARY KEY (tweet_id, hashtag)
+) STRICT;
+
+CREATE INDEX hashtagsindex ON hashtags(hashtag);
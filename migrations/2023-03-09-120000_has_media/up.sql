@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- NULL for tweets imported before this was recorded
+ALTER TABLE tweets ADD COLUMN has_media INTEGER;
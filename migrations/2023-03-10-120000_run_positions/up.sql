@@ -0,0 +1,3 @@
+-- Your SQL goes here
+-- Order tweets were queued in, NULL for runs recorded before this
+ALTER TABLE run_tweets ADD COLUMN position INTEGER;
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+CREATE TABLE lookup_batches (
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    batch INTEGER NOT NULL,
+    requested INTEGER NOT NULL,
+    returned INTEGER NOT NULL,
+    gone INTEGER NOT NULL,
+    missing INTEGER NOT NULL,
+    PRIMARY KEY (run_id, batch)
+) STRICT;
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+-- The filters `watch` last ran with for each account, so a more aggressive
+-- policy has to be confirmed first
+--
+-- `filters` is the canonical JSON of the filters
+CREATE TABLE watch_policies (
+    account_id TEXT PRIMARY KEY NOT NULL REFERENCES accounts(id_str),
+    filters TEXT NOT NULL,
+    accepted_at INTEGER NOT NULL
+) STRICT;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX tweetsinitialindex;
+ALTER TABLE tweets DROP COLUMN initial_id;
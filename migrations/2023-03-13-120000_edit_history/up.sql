@@ -0,0 +1,6 @@
+-- Your SQL goes here
+-- The first version of edited tweets, shared by every version. NULL for tweets
+-- that were never edited, or imported before this was recorded
+ALTER TABLE tweets ADD COLUMN initial_id TEXT;
+
+CREATE INDEX tweetsinitialindex ON tweets(initial_id);
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+-- Account the run was for, so it can't be committed with another account's
+-- keys. NULL for runs not for one account, or from before this was recorded
+ALTER TABLE runs ADD COLUMN account_id TEXT REFERENCES accounts(id_str);
@@ -0,0 +1,385 @@
+//! Analyzes tweet contents
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use regex::{Regex, RegexBuilder};
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::id::TweetId;
+
+/// Bundled starter wordlist, see [`Wordlist::load`]
+static STARTER_WORDLIST: &str = include_str!("../wordlists/starter.txt");
+
+/// What a tweet consists of, for finding low-value tweets
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentKind {
+    /// Only emoji
+    Emoji,
+
+    /// Only mentions of other accounts
+    Mentions,
+
+    /// Only a single link, ignoring leading mentions
+    Link,
+
+    /// Anything else
+    Text,
+}
+
+impl ContentKind {
+    /// Classify the tweet text `text`, ignoring `media`, the links to its
+    /// attached photos, videos, and GIFs
+    ///
+    /// Leading mentions, as added to replies, are ignored unless they are
+    /// all there is. Tweets without any text, such as only a photo, are
+    /// [`ContentKind::Text`].
+    pub fn classify<'a, I>(text: &str, media: I) -> Self
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let media: Vec<&str> = media.into_iter().collect();
+        let mut words = text
+            .split_whitespace()
+            .filter(|w| !media.contains(w))
+            .peekable();
+        let mut mentions = false;
+        while words.next_if(|w| w.starts_with('@')).is_some() {
+            mentions = true;
+        }
+        let rest: Vec<&str> = words.collect();
+
+        match rest.as_slice() {
+            [] if mentions => Self::Mentions,
+            [] => Self::Text,
+            [link] if link.starts_with("https://") || link.starts_with("http://") => Self::Link,
+            rest if rest.iter().flat_map(|w| w.chars()).all(is_emoji) => Self::Emoji,
+            _ => Self::Text,
+        }
+    }
+
+    /// Representation stored in the database
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Emoji => "emoji",
+            Self::Mentions => "mentions",
+            Self::Link => "link",
+            Self::Text => "text",
+        }
+    }
+}
+
+/// Whether `c` is part of an emoji
+///
+/// # Implementation Details
+///
+/// This is an approximation based on the Unicode blocks emoji are found in,
+/// including the joiners, selectors, and modifiers used to build
+/// multi-character emoji.
+fn is_emoji(c: char) -> bool {
+    matches!(c as u32,
+        0x1F000..=0x1FAFF
+        | 0x2600..=0x27BF
+        | 0x2B00..=0x2BFF
+        | 0x2190..=0x21FF
+        | 0x2300..=0x23FF
+        | 0x25A0..=0x25FF
+        | 0x2900..=0x297F
+        | 0x3030 | 0x303D | 0x3297 | 0x3299
+        | 0x00A9 | 0x00AE | 0x203C | 0x2049 | 0x2122 | 0x2139 | 0x24C2
+        // Keycap, zero width joiner, variation selectors, tags
+        | 0x20E3 | 0x200D | 0xFE00..=0xFE0F | 0xE0020..=0xE007F
+    )
+}
+
+/// Normalize the archived tweet text `text` to how it would be displayed
+///
+/// HTML entities are decoded, and t.co links in `urls`, pairs of the t.co
+/// link and where it goes, are replaced with where they actually go.
+///
+/// # Implementation Details
+///
+/// Twitter only escapes `&`, `<`, and `>`, but quotes are also handled in
+/// case of older archives. `&amp;` is decoded last, so text that was
+/// literally `&amp;lt;` isn't decoded twice.
+pub fn display_text<'a, I>(text: &str, urls: I) -> String
+where
+    I: IntoIterator<Item = (&'a str, &'a str)>,
+{
+    let mut text = text
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    // After decoding, so expanded links are left as-is
+    for (url, expanded) in urls {
+        text = replace_link(&text, url, expanded);
+    }
+    text
+}
+
+/// `text` with the link `url` replaced with `expanded`
+///
+/// Only whole links are replaced, so `https://t.co/ab` is left alone inside
+/// `https://t.co/abc`.
+fn replace_link(text: &str, url: &str, expanded: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for (at, _) in text.match_indices(url) {
+        let end = at + url.len();
+        if text[end..].starts_with(|c: char| c.is_ascii_alphanumeric()) {
+            continue;
+        }
+        out.push_str(&text[last..at]);
+        out.push_str(expanded);
+        last = end;
+    }
+    out.push_str(&text[last..]);
+    out
+}
+
+/// A list of terms to match against tweet text
+///
+/// Each line is a term or regular expression, matched case insensitively.
+/// Empty lines and lines starting with `#` are ignored, except for
+/// `# priority N: Name`, which puts the terms after it in a category, see
+/// [`Wordlist::priority`].
+#[derive(Debug)]
+pub struct Wordlist {
+    re: Regex,
+
+    /// Terms in each category, by priority, highest priority first
+    categories: Vec<(u32, Regex)>,
+}
+
+impl Wordlist {
+    /// Load the wordlist at `path`, and/or the bundled starter wordlist if
+    /// `starter` is true.
+    ///
+    /// Returns [`None`] if neither was requested
+    pub fn load(path: Option<&Path>, starter: bool) -> Result<Option<Self>> {
+        if path.is_none() && !starter {
+            return Ok(None);
+        }
+        let mut terms = Vec::new();
+        let mut categories = BTreeMap::new();
+        if let Some(path) = path {
+            parse(&fs::read_to_string(path)?, &mut terms, &mut categories)?;
+        }
+        if starter {
+            parse(STARTER_WORDLIST, &mut terms, &mut categories)?;
+        }
+
+        // An empty pattern would match everything
+        if terms.is_empty() {
+            return Err(anyhow!("Wordlist contains no terms"));
+        }
+        let build = |terms: &[String]| {
+            RegexBuilder::new(&terms.join("|"))
+                .case_insensitive(true)
+                .build()
+        };
+        let categories = categories
+            .into_iter()
+            .map(|(priority, terms)| Ok((priority, build(&terms)?)))
+            .collect::<Result<_>>()?;
+        Ok(Some(Self {
+            re: build(&terms)?,
+            categories,
+        }))
+    }
+
+    /// Whether `text` contains any term in the wordlist
+    pub fn is_match(&self, text: &str) -> bool {
+        self.re.is_match(text)
+    }
+
+    /// Priority of the highest priority category with a term in `text`, if
+    /// any
+    ///
+    /// Priority `1` is the highest, and is deleted first with
+    /// `--order sensitive`.
+    pub fn priority(&self, text: &str) -> Option<u32> {
+        self.categories
+            .iter()
+            .find(|(_, re)| re.is_match(text))
+            .map(|(priority, _)| *priority)
+    }
+}
+
+/// Add the terms in wordlist `data` to `terms`, and those in a category to
+/// `categories` too, by priority
+///
+/// Categories only last until the end of `data`.
+fn parse(
+    data: &str,
+    terms: &mut Vec<String>,
+    categories: &mut BTreeMap<u32, Vec<String>>,
+) -> Result<()> {
+    let mut priority = None;
+    for line in data.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix('#') {
+            if let Some(rest) = rest.trim_start().strip_prefix("priority ") {
+                let n = rest.split(':').next().unwrap_or_default().trim();
+                priority = Some(n.parse::<u32>().map_err(|e| {
+                    anyhow!(
+                        "Invalid wordlist category `{line}`, expected `# priority N: Name`: {e}"
+                    )
+                })?);
+            }
+            continue;
+        }
+        if line.is_empty() {
+            continue;
+        }
+        let term = format!("(?:{line})");
+        if let Some(priority) = priority {
+            categories.entry(priority).or_default().push(term.clone());
+        }
+        terms.push(term);
+    }
+    Ok(())
+}
+
+/// How to score how worth keeping a tweet is, from `0.0` to `1.0`
+#[derive(Debug)]
+pub enum Scorer {
+    /// Simple built-in heuristic, see [`Scorer::builtin`]
+    Builtin(Wordlist),
+
+    /// External shell command, which receives the tweet text on stdin and
+    /// prints the score to stdout
+    Command(String),
+
+    /// HTTP endpoint, which receives a JSON `{"id", "text"}` POST and
+    /// responds with JSON `{"score"}`
+    Http(String),
+}
+
+/// Request sent to a [`Scorer::Http`] endpoint
+#[derive(Debug, Serialize)]
+struct ScoreReq<'a> {
+    id: &'a TweetId,
+    text: &'a str,
+}
+
+/// Response from a [`Scorer::Http`] endpoint
+#[derive(Debug, Deserialize)]
+struct ScoreResp {
+    score: f64,
+}
+
+impl Scorer {
+    /// The built-in heuristic scorer
+    ///
+    /// # Implementation Details
+    ///
+    /// Tweets start from how substantive they are, based on their
+    /// [`ContentKind`] and length, and are penalized for matching the bundled
+    /// starter wordlist.
+    pub fn builtin() -> Result<Self> {
+        let wordlist = Wordlist::load(None, true)?.expect("BUG: Starter wordlist missing");
+        Ok(Self::Builtin(wordlist))
+    }
+
+    /// Score tweet `id` with text `text`
+    ///
+    /// The score is clamped to between `0.0` and `1.0`
+    pub fn score(&self, client: &Client, id: &TweetId, text: &str) -> Result<f64> {
+        let score = match self {
+            Self::Builtin(wordlist) => {
+                // Which links are attached media isn't known from the text
+                let mut score = match ContentKind::classify(text, []) {
+                    ContentKind::Text => 0.5,
+                    _ => 0.1,
+                };
+                score += (text.chars().count() as f64 / 280.0).min(1.0) * 0.3;
+                if wordlist.is_match(text) {
+                    score -= 0.4;
+                }
+                score
+            }
+            Self::Command(cmd) => {
+                let mut child = Command::new("sh")
+                    .arg("-c")
+                    .arg(cmd)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .spawn()?;
+                // Dropping stdin closes it, so the command sees EOF
+                child
+                    .stdin
+                    .take()
+                    .expect("BUG: Scoring command stdin not piped")
+                    .write_all(text.as_bytes())?;
+                let out = child.wait_with_output()?;
+                if !out.status.success() {
+                    return Err(anyhow!(
+                        "Scoring command `{cmd}` failed with {}",
+                        out.status
+                    ));
+                }
+                String::from_utf8(out.stdout)?.trim().parse()?
+            }
+            Self::Http(url) => {
+                let res: ScoreResp = client
+                    .post(url)
+                    .json(&ScoreReq { id, text })
+                    .send()?
+                    .error_for_status()?
+                    .json()?;
+                res.score
+            }
+        };
+        Ok(score.clamp(0.0, 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_empty_is_text() {
+        assert_eq!(ContentKind::classify("", []), ContentKind::Text);
+        assert_eq!(ContentKind::classify(" \n\t", []), ContentKind::Text);
+        assert_eq!(ContentKind::classify("@a @b", []), ContentKind::Mentions);
+    }
+
+    #[test]
+    fn classify_ignores_media() {
+        let photo = "https://twitter.com/me/status/1/photo/1";
+        let classify = |text: &str| ContentKind::classify(text, [photo]);
+        assert_eq!(classify(photo), ContentKind::Text);
+        assert_eq!(classify(&format!("\u{1F525} {photo}")), ContentKind::Emoji);
+        assert_eq!(classify(&format!("@a {photo}")), ContentKind::Mentions);
+        assert_eq!(
+            classify(&format!("https://example.com {photo}")),
+            ContentKind::Link
+        );
+        // Without knowing it's media, it's just a link
+        assert_eq!(ContentKind::classify(photo, []), ContentKind::Link);
+    }
+
+    #[test]
+    fn display_text_replaces_whole_links() {
+        let urls = [
+            ("https://t.co/ab", "https://example.com/short"),
+            ("https://t.co/abc", "https://example.com/long"),
+        ];
+        assert_eq!(
+            display_text("https://t.co/abc, https://t.co/ab. &amp;lt;", urls),
+            "https://example.com/long, https://example.com/short. &lt;"
+        );
+    }
+}
@@ -0,0 +1,32 @@
+//! Source of the current time
+
+use time::OffsetDateTime;
+
+/// Source of the current time
+///
+/// Used instead of [`OffsetDateTime::now_utc`] so that time can be frozen,
+/// for reproducing behavior exactly.
+pub trait Clock {
+    /// The current time, in UTC
+    fn now(&self) -> OffsetDateTime;
+}
+
+/// The real system clock
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> OffsetDateTime {
+        OffsetDateTime::now_utc()
+    }
+}
+
+/// A clock frozen at a fixed time
+#[derive(Debug, Clone, Copy)]
+pub struct FrozenClock(pub OffsetDateTime);
+
+impl Clock for FrozenClock {
+    fn now(&self) -> OffsetDateTime {
+        self.0
+    }
+}
@@ -0,0 +1,251 @@
+//! Defaults from `config.toml` in the data directory
+//!
+//! Anything given on the command line takes precedence, so safety thresholds
+//! like `unless_likes` only need typing once, eg
+//!
+//! ```toml
+//! older_than = 30
+//! unless_likes = 10
+//! exclude = ["1234567890"]
+//! expr = "!has_media"
+//!
+//! [profiles.alt]
+//! db = "/home/me/alt.db"
+//! older_than = 7
+//! ```
+use std::{
+    collections::HashMap,
+    fs,
+    io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use clap::{ArgMatches, ValueSource};
+use serde::Deserialize;
+
+use crate::{expr::Expr, id::TweetId, Access, Args, Cmd};
+
+/// Defaults for flags not given on the command line
+///
+/// Every field is optional, and unknown fields are an error so a mistyped
+/// threshold isn't silently ignored.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Default for `--older-than`, used when no other age or date filter is
+    /// given
+    pub older_than: Option<u32>,
+
+    /// Default for `--unless-likes`
+    pub unless_likes: Option<u32>,
+
+    /// Tweet IDs to always exclude, in addition to `--exclude`
+    #[serde(default)]
+    pub exclude: Vec<TweetId>,
+
+    /// Default for `--filter`, a filter expression
+    pub expr: Option<String>,
+
+    /// Default for `--db`
+    pub db: Option<PathBuf>,
+
+    /// Default for `--credentials`
+    pub credentials: Option<PathBuf>,
+
+    /// Default API keys, as in `credentials.json`, instead of `credentials`
+    ///
+    /// Only one of them can be given.
+    pub keys: Option<Access>,
+
+    /// Default for `watch --accept-policy`
+    pub accept_policy: Option<String>,
+
+    /// Named sets of defaults, chosen with `--profile`, that replace those
+    /// above
+    #[serde(default)]
+    pub profiles: HashMap<String, Config>,
+}
+
+impl Config {
+    /// Load the config at `path`, with `profile` applied, or the empty config
+    /// if there is no file
+    pub fn load(path: &Path, profile: Option<&str>) -> Result<Self> {
+        let config = match fs::read_to_string(path) {
+            Ok(data) => Self::parse(&data)
+                .map_err(|e| anyhow!("Invalid config in {}: {e}", path.display()))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(anyhow!("Couldn't read config {}: {e}", path.display())),
+        };
+        match profile {
+            Some(name) => config.profile(name),
+            None => Ok(config),
+        }
+    }
+
+    /// Parse and check the config `data`
+    fn parse(data: &str) -> Result<Self> {
+        let config: Self = toml::from_str(data)?;
+        if config.profiles.values().any(|p| !p.profiles.is_empty()) {
+            return Err(anyhow!("Profiles can't contain profiles"));
+        }
+        let sections = || config.profiles.values().chain([&config]);
+        if sections().any(|c| c.keys.is_some() && c.credentials.is_some()) {
+            return Err(anyhow!("Only one of `keys` and `credentials` can be given"));
+        }
+        // Checked now so mistakes are blamed on the config, not `--filter`
+        for expr in sections().filter_map(|c| c.expr.as_deref()) {
+            expr.parse::<Expr>().map_err(|e| anyhow!("`expr`: {e}"))?;
+        }
+        Ok(config)
+    }
+
+    /// These defaults, replaced by those of profile `name`
+    ///
+    /// A profile giving either `keys` or `credentials` replaces both.
+    fn profile(mut self, name: &str) -> Result<Self> {
+        let profile = self
+            .profiles
+            .remove(name)
+            .ok_or_else(|| anyhow!("No profile `{name}` in config.toml"))?;
+        self.exclude.extend(profile.exclude);
+        let (keys, credentials) = match (profile.keys, profile.credentials) {
+            (None, None) => (self.keys, self.credentials),
+            given => given,
+        };
+        Ok(Self {
+            older_than: profile.older_than.or(self.older_than),
+            unless_likes: profile.unless_likes.or(self.unless_likes),
+            exclude: self.exclude,
+            expr: profile.expr.or(self.expr),
+            db: profile.db.or(self.db),
+            credentials,
+            keys,
+            accept_policy: profile.accept_policy.or(self.accept_policy),
+            profiles: HashMap::new(),
+        })
+    }
+
+    /// Use these defaults for anything in `args` not given on the command
+    /// line, as parsed into `matches`
+    pub fn apply(self, args: &mut Args, matches: &ArgMatches) {
+        if args.db.is_none() {
+            args.db = self.db;
+        }
+        // `$TWITTER_DELETE_*` still takes precedence over these, see
+        // `default_keys`
+        if args.credentials.is_none() {
+            match self.keys {
+                Some(keys) => args.keys = Some(keys),
+                None => args.credentials = self.credentials,
+            }
+        }
+        if let Cmd::Watch { accept_policy, .. } = &mut args.cmd {
+            if accept_policy.is_none() {
+                *accept_policy = self.accept_policy;
+            }
+        }
+
+        let filter = match args.cmd.filter_mut() {
+            Some(filter) => filter,
+            None => return,
+        };
+        // Saved filters are used exactly as saved
+        if filter.filter_name.is_some() {
+            return;
+        }
+        let mut matches = matches;
+        while let Some((_, sub)) = matches.subcommand() {
+            matches = sub;
+        }
+        let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        let dated = filter.older_than.is_some()
+            || filter.newer_than.is_some()
+            || filter.before.is_some()
+            || filter.after.is_some();
+        if !dated {
+            filter.older_than = self.older_than;
+        }
+        if let (false, Some(likes)) = (given("unless-likes"), self.unless_likes) {
+            filter.unless_likes = likes;
+        }
+        if filter.filter_expr.is_none() {
+            filter.filter_expr = self.expr;
+        }
+        for id in self.exclude {
+            if !filter.exclude.contains(&id) {
+                filter.exclude.push(id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+older_than = 30
+unless_likes = 10
+exclude = ["1"]
+expr = "!has_media"
+credentials = "/home/me/credentials.json"
+
+[profiles.alt]
+older_than = 7
+exclude = ["2"]
+
+[profiles.alt.keys]
+API_KEY = "key"
+API_SECRET = "secret"
+
+[profiles.quiet]
+expr = "likes < 3"
+"#;
+
+    #[test]
+    fn profiles_replace_defaults() {
+        let config = Config::parse(CONFIG).unwrap();
+        assert_eq!(config.older_than, Some(30));
+
+        let alt = Config::parse(CONFIG).unwrap().profile("alt").unwrap();
+        assert_eq!(alt.older_than, Some(7));
+        assert_eq!(alt.unless_likes, Some(10));
+        assert_eq!(alt.expr.as_deref(), Some("!has_media"));
+        // Added to, rather than replaced
+        let exclude: Vec<&str> = alt.exclude.iter().map(TweetId::as_str).collect();
+        assert_eq!(exclude, ["1", "2"]);
+        // Keys replace the credentials file, instead of being ignored for it
+        assert_eq!(alt.keys.map(|k| k.api_key).as_deref(), Some("key"));
+        assert_eq!(alt.credentials, None);
+
+        let quiet = Config::parse(CONFIG).unwrap().profile("quiet").unwrap();
+        assert_eq!(quiet.expr.as_deref(), Some("likes < 3"));
+        assert_eq!(quiet.older_than, Some(30));
+        assert_eq!(
+            quiet.credentials.as_deref(),
+            Some(Path::new("/home/me/credentials.json"))
+        );
+        assert!(quiet.profiles.is_empty());
+
+        let e = Config::parse(CONFIG).unwrap().profile("missing").unwrap_err();
+        assert_eq!(e.to_string(), "No profile `missing` in config.toml");
+    }
+
+    #[test]
+    fn rejects_conflicting_keys() {
+        let invalid = [
+            "older_then = 30",
+            "older_than = -1",
+            "[profiles.a]\n[profiles.a.profiles.b]",
+            "credentials = \"c.json\"\n[keys]\nAPI_KEY = \"k\"\nAPI_SECRET = \"s\"",
+            "[profiles.a]\ncredentials = \"c.json\"\n[profiles.a.keys]\nAPI_KEY = \"k\"\n\
+             API_SECRET = \"s\"",
+            "expr = \"likes <\"",
+            "[profiles.a]\nexpr = \"bogus\"",
+        ];
+        for config in invalid {
+            assert!(Config::parse(config).is_err(), "{config}");
+        }
+    }
+}
@@ -1,19 +1,40 @@
 //! Handles interfacing with the tweets database
 
-use std::path::Path;
+use std::{collections::HashMap, path::Path, time::Duration as StdDuration};
 
 use anyhow::{anyhow, Result};
 use diesel::{
-    dsl::{sql, And, Eq, Lt},
+    dsl::{sql, And, Asc, Eq, Lt, Nullable as NullableExpr},
     prelude::*,
+    query_builder::{AstPass, Query, QueryFragment, QueryId},
     result::Error as DieselError,
-    sql_types::Untyped,
+    sql_query,
+    sql_types::{BigInt, Bool, Integer, Nullable, Text, Untyped},
+    sqlite::Sqlite,
 };
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use serde::Serialize;
+use tracing::debug;
 
 use crate::{
-    models::{Account, Tweet},
-    schema::{accounts as adb, tweets as db},
+    clock::Clock,
+    id::TweetId,
+    models::{Account, Dm, EventKind, Hashtag, Like, LookupBatch, Mention, Tweet, Url},
+    schema::{
+        accounts as adb,
+        dms as dmdb,
+        events as edb,
+        hashtags as hdb,
+        likes as ldb,
+        lookup_batches as lbdb,
+        mentions as mdb,
+        run_tweets as rtdb,
+        runs as rdb,
+        saved_filters as sfdb,
+        tweets as db,
+        urls as udb,
+        watch_policies as wpdb,
+    },
 };
 
 const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
@@ -22,8 +43,16 @@ type ExistingDeleted = Eq<db::dsl::deleted, bool>;
 type ExistingChecked = Eq<db::dsl::checked, bool>;
 type CreatedBeforeAt = Lt<db::dsl::created_at, i64>;
 
+type IdLength = length::HelperType<NullableExpr<db::dsl::id_str>>;
+
 pub type Existing = And<ExistingDeleted, ExistingChecked>;
 pub type CreatedBefore = CreatedBeforeAt;
+pub type ById = (Asc<IdLength>, Asc<db::dsl::id_str>);
+
+sql_function! {
+    /// Number of characters in a string
+    fn length(x: Nullable<Text>) -> Nullable<Integer>;
+}
 
 /// Create or open a database at `db_path`
 ///
@@ -40,16 +69,327 @@ pub fn create_db(db_path: &Path) -> Result<SqliteConnection> {
     Ok(conn)
 }
 
-/// Add tweets to the database, returning how many were added
+/// A secondary index, see [`drop_indexes`]
+#[derive(Debug, QueryableByName)]
+struct Index {
+    #[diesel(sql_type = Text)]
+    name: String,
+
+    /// SQL used to create the index
+    #[diesel(sql_type = Text)]
+    sql: String,
+}
+
+/// Drop every secondary index, returning the SQL to recreate them with
+/// [`create_indexes`]
+///
+/// Primary keys are not affected.
+pub fn drop_indexes(conn: &mut SqliteConnection) -> Result<Vec<String>> {
+    // Indexes without SQL are automatically created for primary keys
+    let indexes: Vec<Index> =
+        sql_query("SELECT name, sql FROM sqlite_master WHERE type = 'index' AND sql IS NOT NULL")
+            .load(conn)?;
+    for index in &indexes {
+        sql_query(format!("DROP INDEX \"{}\"", index.name)).execute(conn)?;
+    }
+    Ok(indexes.into_iter().map(|i| i.sql).collect())
+}
+
+/// A tweet that differs between two databases, see [`changes`]
+#[derive(Debug, QueryableByName)]
+pub struct Change {
+    /// Tweet ID
+    #[diesel(sql_type = Text)]
+    pub id_str: TweetId,
+
+    /// Whether the tweet is now marked as deleted
+    #[diesel(sql_type = Bool)]
+    pub deleted: bool,
+
+    /// Number of failed attempts to delete the tweet now
+    #[diesel(sql_type = Integer)]
+    pub failures: i32,
+}
+
+/// Compare the tweets in `conn` to those in the database at `original`,
+/// returning every tweet whose deletion state differs
+pub fn changes(conn: &mut SqliteConnection, original: &Path) -> Result<Vec<Change>> {
+    let original = original
+        .to_str()
+        .ok_or_else(|| anyhow!("Invalid UTF-8 in database path {}", original.display()))?;
+    sql_query(format!(
+        "ATTACH DATABASE '{}' AS original",
+        original.replace('\'', "''")
+    ))
+    .execute(conn)?;
+    let changes: QueryResult<Vec<Change>> = sql_query(
+        "SELECT t.id_str, t.deleted, t.failures FROM tweets t \
+         JOIN original.tweets o ON o.id_str = t.id_str \
+         WHERE t.deleted != o.deleted OR t.failures != o.failures \
+         ORDER BY length(t.id_str), t.id_str",
+    )
+    .load(conn);
+    sql_query("DETACH DATABASE original").execute(conn)?;
+    Ok(changes?)
+}
+
+/// A tweet matched by [`search`]
+#[derive(Debug, QueryableByName)]
+struct Found {
+    #[diesel(sql_type = Text)]
+    id_str: TweetId,
+}
+
+/// Search the text of tweets that haven't been deleted for `query`,
+/// returning the IDs of up to `limit` matches, best first
 ///
-/// Ignores duplicate tweets, as determined by the tweet ID
-pub fn add_tweets(conn: &mut SqliteConnection, tweets: &[Tweet]) -> Result<usize> {
-    let added = diesel::insert_or_ignore_into(db::table)
-        .values(tweets)
+/// `query` uses the SQLite FTS5 syntax, eg `"exact phrase"`, `word*`, and
+/// `this OR that`.
+pub fn search(conn: &mut SqliteConnection, query: &str, limit: i64) -> Result<Vec<TweetId>> {
+    let found: Vec<Found> = sql_query(
+        "SELECT t.id_str FROM tweets_search s \
+         JOIN tweets t ON t.id_str = s.tweet_id \
+         WHERE tweets_search MATCH ? AND t.deleted = 0 \
+         ORDER BY s.rank LIMIT ?",
+    )
+    .bind::<Text, _>(query)
+    .bind::<BigInt, _>(limit)
+    .load(conn)
+    .map_err(|e| anyhow!("Invalid search `{query}`: {e}"))?;
+    Ok(found.into_iter().map(|f| f.id_str).collect())
+}
+
+/// Recreate `indexes` dropped with [`drop_indexes`]
+pub fn create_indexes(conn: &mut SqliteConnection, indexes: &[String]) -> Result<()> {
+    for index in indexes {
+        sql_query(index).execute(conn)?;
+    }
+    Ok(())
+}
+
+/// How many tweets [`add_tweets`] added or updated
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Merged {
+    /// Tweets that weren't in the database
+    pub added: usize,
+
+    /// Tweets that had their likes or retweets changed by a newer archive
+    pub updated: usize,
+}
+
+/// Add tweets to the database, returning how many were added or updated
+///
+/// Duplicate tweets, as determined by the tweet ID, keep their existing state
+/// but have their text, reply, media, and edit information refreshed, for
+/// tweets imported before it was recorded.
+///
+/// Their likes and retweets are only updated if they're from an archive at
+/// least as new as the one they were last imported from, according to
+/// [`Tweet::archived_at`], so importing an older archive doesn't undo a newer
+/// one.
+pub fn add_tweets(
+    conn: &mut SqliteConnection,
+    clock: &dyn Clock,
+    tweets: &[Tweet],
+) -> Result<Merged> {
+    let merged = conn.transaction::<_, anyhow::Error, _>(|conn| {
+        let mut merged = Merged::default();
+        for tweet in tweets {
+            use db::dsl::*;
+            use diesel::upsert::excluded;
+            // `tweets` is shadowed by the argument
+            let existing: Option<(Option<i64>, i32, i32)> = db::table
+                .find(&tweet.id_str)
+                .select((archived_at, likes, retweets))
+                .first(conn)
+                .optional()?;
+            let newer = match existing {
+                Some((Some(existing), ..)) => tweet.archived_at.map_or(false, |at| at >= existing),
+                Some((None, ..)) => true,
+                None => false,
+            };
+            let query = diesel::insert_into(db::table)
+                .values(tweet)
+                .on_conflict(id_str)
+                .do_update();
+            let refreshed = (
+                in_reply_to_status_id.eq(excluded(in_reply_to_status_id)),
+                in_reply_to_user_id.eq(excluded(in_reply_to_user_id)),
+                in_reply_to_screen_name.eq(excluded(in_reply_to_screen_name)),
+                full_text.eq(excluded(full_text)),
+                content_kind.eq(excluded(content_kind)),
+                display_text.eq(excluded(display_text)),
+                has_media.eq(excluded(has_media)),
+                initial_id.eq(excluded(initial_id)),
+            );
+            if newer {
+                query
+                    .set((
+                        refreshed,
+                        likes.eq(excluded(likes)),
+                        retweets.eq(excluded(retweets)),
+                        archived_at.eq(excluded(archived_at)),
+                    ))
+                    .execute(conn)?;
+                if let Some((_, old_likes, old_retweets)) = existing {
+                    if (old_likes, old_retweets) != (tweet.likes, tweet.retweets) {
+                        merged.updated += 1;
+                    }
+                }
+            } else {
+                query.set(refreshed).execute(conn)?;
+            }
+            if existing.is_none() {
+                log_event(conn, clock, &tweet.id_str, EventKind::Imported, None)?;
+                merged.added += 1;
+            }
+        }
+        Ok(merged)
+    })?;
+    Ok(merged)
+}
+
+/// Add mentions to the database, returning how many were added
+///
+/// Ignores duplicate mentions
+pub fn add_mentions(conn: &mut SqliteConnection, mentions: &[Mention]) -> Result<usize> {
+    let added = diesel::insert_or_ignore_into(mdb::table)
+        .values(mentions)
         .execute(conn)?;
     Ok(added)
 }
 
+/// Add hashtags to the database, returning how many were added
+///
+/// Ignores duplicate hashtags
+pub fn add_hashtags(conn: &mut SqliteConnection, hashtags: &[Hashtag]) -> Result<usize> {
+    let added = diesel::insert_or_ignore_into(hdb::table)
+        .values(hashtags)
+        .execute(conn)?;
+    Ok(added)
+}
+
+/// Record a lookup request made while checking tweets
+pub fn add_lookup_batch(conn: &mut SqliteConnection, batch: &LookupBatch) -> Result<()> {
+    diesel::insert_into(lbdb::table)
+        .values(batch)
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Get every recorded lookup request that looks wrong, in order
+///
+/// That is, where twitter left tweets out, answered for a different number
+/// of tweets than requested, or reported every one of several tweets deleted.
+pub fn lookup_anomalies(conn: &mut SqliteConnection) -> Result<Vec<LookupBatch>> {
+    use lbdb::dsl::*;
+    let batches = lookup_batches
+        .filter(
+            missing
+                .gt(0)
+                .or((returned + gone + missing).ne(requested))
+                .or(gone.eq(requested).and(requested.gt(1))),
+        )
+        .order((run_id.asc(), batch.asc()))
+        .load(conn)?;
+    Ok(batches)
+}
+
+/// Add t.co links to the database, returning how many were added
+///
+/// Ignores duplicate links
+pub fn add_urls(conn: &mut SqliteConnection, urls: &[Url]) -> Result<usize> {
+    let added = diesel::insert_or_ignore_into(udb::table)
+        .values(urls)
+        .execute(conn)?;
+    Ok(added)
+}
+
+/// Add liked tweets to the database, returning how many were added
+///
+/// Likes already in the database keep whether they've been unliked
+pub fn add_likes(conn: &mut SqliteConnection, likes: &[Like]) -> Result<usize> {
+    let added = diesel::insert_or_ignore_into(ldb::table)
+        .values(likes)
+        .execute(conn)?;
+    Ok(added)
+}
+
+/// Get the IDs of the tweets `account` has liked and not yet unliked, in ID
+/// order
+pub fn liked(conn: &mut SqliteConnection, account: &str) -> Result<Vec<TweetId>> {
+    use ldb::dsl::*;
+    let ids = likes
+        .filter(account_id.eq(account))
+        .filter(unliked.eq(false))
+        // Numerically, as in `by_id`
+        .order((length(tweet_id.nullable()).asc(), tweet_id.asc()))
+        .select(tweet_id)
+        .load(conn)?;
+    Ok(ids)
+}
+
+/// Mark `tweet` as unliked by `account` at `now`
+///
+/// `now` is UTC unix time
+pub fn set_unliked(
+    conn: &mut SqliteConnection,
+    account: &str,
+    tweet: &TweetId,
+    now: i64,
+) -> Result<()> {
+    use ldb::dsl::*;
+    debug!(account, %tweet, "Marking like as unliked");
+    diesel::update(likes.find((tweet, account)))
+        .set((unliked.eq(true), unliked_at.eq(now)))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Add direct messages to the database, returning how many were added
+///
+/// Messages already in the database keep whether they've been deleted
+pub fn add_dms(conn: &mut SqliteConnection, dms: &[Dm]) -> Result<usize> {
+    let added = diesel::insert_or_ignore_into(dmdb::table)
+        .values(dms)
+        .execute(conn)?;
+    Ok(added)
+}
+
+/// Get the IDs of the direct messages in `account`s conversations that were
+/// sent before `before` and not yet deleted, in ID order
+///
+/// `before` is UTC unix time
+pub fn dms_before(conn: &mut SqliteConnection, account: &str, before: i64) -> Result<Vec<String>> {
+    use dmdb::dsl::*;
+    let ids = dms
+        .filter(account_id.eq(account))
+        .filter(deleted.eq(false))
+        .filter(created_at.lt(before))
+        // Numerically, as in `by_id`
+        .order((length(id.nullable()).asc(), id.asc()))
+        .select(id)
+        .load(conn)?;
+    Ok(ids)
+}
+
+/// Mark direct message `dm` as deleted from `account`s view at `now`
+///
+/// `now` is UTC unix time
+pub fn set_dm_deleted(
+    conn: &mut SqliteConnection,
+    account: &str,
+    dm: &str,
+    now: i64,
+) -> Result<()> {
+    use dmdb::dsl::*;
+    debug!(account, dm, "Marking direct message as deleted");
+    diesel::update(dms.find((dm, account)))
+        .set((deleted.eq(true), deleted_at.eq(now)))
+        .execute(conn)?;
+    Ok(())
+}
+
 pub fn add_account(conn: &mut SqliteConnection, accounts: &[Account]) -> Result<usize> {
     let added = diesel::insert_or_ignore_into(adb::table)
         .values(accounts)
@@ -57,6 +397,56 @@ pub fn add_account(conn: &mut SqliteConnection, accounts: &[Account]) -> Result<
     Ok(added)
 }
 
+/// Record whether `account` has its API keys stored in the OS keyring
+///
+/// Keys stored in the keyring replace any set with [`set_keys`]
+pub fn set_keyring(conn: &mut SqliteConnection, account: &str, keyring: bool) -> Result<()> {
+    let query = diesel::update(adb::dsl::accounts.find(account));
+    if keyring {
+        query
+            .set((
+                adb::dsl::keyring.eq(true),
+                adb::dsl::keys_path.eq(None::<String>),
+            ))
+            .execute(conn)?;
+    } else {
+        query.set(adb::dsl::keyring.eq(false)).execute(conn)?;
+    }
+    Ok(())
+}
+
+/// Archive `account` as of `at`, or unarchive it if [`None`]
+///
+/// The tweets, likes, and messages of archived accounts can't be changed,
+/// which the database enforces.
+pub fn set_frozen(conn: &mut SqliteConnection, account: &str, at: Option<i64>) -> Result<()> {
+    diesel::update(adb::dsl::accounts.find(account))
+        .set(adb::dsl::frozen_at.eq(at))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Record that `account` uses the app with `api_key`, and optionally the
+/// keys at `keys_path`
+pub fn set_keys(
+    conn: &mut SqliteConnection,
+    account: &str,
+    api_key: &str,
+    keys_path: Option<&str>,
+) -> Result<()> {
+    let query = diesel::update(adb::dsl::accounts.find(account));
+    match keys_path {
+        Some(keys_path) => query
+            .set((
+                adb::dsl::api_key.eq(api_key),
+                adb::dsl::keys_path.eq(keys_path),
+            ))
+            .execute(conn)?,
+        None => query.set(adb::dsl::api_key.eq(api_key)).execute(conn)?,
+    };
+    Ok(())
+}
+
 /// Return how many tweets there are in the database
 pub fn count_tweets(conn: &mut SqliteConnection) -> Result<i64> {
     let c = db::dsl::tweets.count().get_result::<i64>(conn)?;
@@ -71,6 +461,161 @@ pub fn _count_accounts(conn: &mut SqliteConnection) -> Result<i64> {
     Ok(c)
 }
 
+/// Counts of tweets as they stand now, see [`count_totals`]
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Totals {
+    /// Tweets imported
+    pub imported: i64,
+
+    /// Tweets deleted
+    pub deleted: i64,
+
+    /// Tweets checked for whether they were already deleted
+    pub checked: i64,
+}
+
+/// Count tweets, optionally only for `account`
+pub fn count_totals(conn: &mut SqliteConnection, account: Option<&str>) -> Result<Totals> {
+    use db::dsl::*;
+    let all = || {
+        let mut query = tweets.into_boxed();
+        if let Some(account) = account {
+            query = query.filter(account_id.eq(account));
+        }
+        query
+    };
+
+    Ok(Totals {
+        imported: all().count().get_result(conn)?,
+        deleted: all().filter(deleted.eq(true)).count().get_result(conn)?,
+        checked: all().filter(checked.eq(true)).count().get_result(conn)?,
+    })
+}
+
+/// Counts of tweets as they stood at a past time, see [`count_as_of`]
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct AsOf {
+    /// Tweets that had been created and not yet deleted
+    pub existing: i64,
+
+    /// Tweets that had been deleted
+    pub deleted: i64,
+
+    /// Tweets that are deleted, but were deleted before deletion times were
+    /// recorded, so it is unknown whether they had been
+    pub unknown: i64,
+}
+
+/// Count tweets, optionally only for `account`, as they stood at `at`
+///
+/// `at` is UTC unix time
+///
+/// Note that tweets found to be already deleted when importing are
+/// considered deleted when they were found, not when they were actually
+/// deleted.
+pub fn count_as_of(conn: &mut SqliteConnection, account: Option<&str>, at: i64) -> Result<AsOf> {
+    use db::dsl::*;
+    let created = || {
+        let mut query = tweets.filter(created_at.lt(at)).into_boxed();
+        if let Some(account) = account {
+            query = query.filter(account_id.eq(account));
+        }
+        query
+    };
+
+    Ok(AsOf {
+        existing: created()
+            .filter(deleted.eq(false).or(deleted_at.assume_not_null().ge(at)))
+            .count()
+            .get_result(conn)?,
+        deleted: created()
+            .filter(deleted_at.assume_not_null().lt(at))
+            .count()
+            .get_result(conn)?,
+        unknown: created()
+            .filter(deleted.eq(true))
+            .filter(deleted_at.is_null())
+            .count()
+            .get_result(conn)?,
+    })
+}
+
+/// Save `filters` under `name` at `now`, replacing any already saved under it
+///
+/// `filters` is the canonical JSON of the filters, see
+/// [`FilterArgs::canonical`][crate::filter::FilterArgs::canonical]
+pub fn save_filter(conn: &mut SqliteConnection, name: &str, filters: &str, now: i64) -> Result<()> {
+    use sfdb::dsl;
+    diesel::replace_into(sfdb::table)
+        .values((
+            dsl::name.eq(name),
+            dsl::filters.eq(filters),
+            dsl::saved_at.eq(now),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Get the filters saved under `name`, if any
+pub fn saved_filter(conn: &mut SqliteConnection, name: &str) -> Result<Option<String>> {
+    use sfdb::dsl;
+    let filters = sfdb::table
+        .find(name)
+        .select(dsl::filters)
+        .first(conn)
+        .optional()?;
+    Ok(filters)
+}
+
+/// Get every saved filter, as `(name, filters)`, by name
+pub fn saved_filters(conn: &mut SqliteConnection) -> Result<Vec<(String, String)>> {
+    use sfdb::dsl::*;
+    let saved = saved_filters
+        .order(name.asc())
+        .select((name, filters))
+        .load(conn)?;
+    Ok(saved)
+}
+
+/// Remove the filters saved under `name`, returning whether there were any
+pub fn remove_filter(conn: &mut SqliteConnection, name: &str) -> Result<bool> {
+    let removed = diesel::delete(sfdb::table.find(name)).execute(conn)?;
+    Ok(removed > 0)
+}
+
+/// Get the filters `watch` last ran with for `account`, if any
+pub fn watch_policy(conn: &mut SqliteConnection, account: &str) -> Result<Option<String>> {
+    use wpdb::dsl;
+    let filters = wpdb::table
+        .find(account)
+        .select(dsl::filters)
+        .first(conn)
+        .optional()?;
+    Ok(filters)
+}
+
+/// Record that `watch` runs with `filters` for `account` as of `now`,
+/// replacing the previous policy
+///
+/// `filters` is the canonical JSON of the filters, see
+/// [`FilterArgs::canonical`][crate::filter::FilterArgs::canonical]
+pub fn accept_watch_policy(
+    conn: &mut SqliteConnection,
+    account: &str,
+    filters: &str,
+    now: i64,
+) -> Result<()> {
+    use wpdb::dsl;
+    diesel::replace_into(wpdb::table)
+        .values((
+            dsl::account_id.eq(account),
+            dsl::filters.eq(filters),
+            dsl::accepted_at.eq(now),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
 /// Gets all tweets created before `utc`
 ///
 /// Uses UTC unix time.
@@ -79,6 +624,15 @@ pub fn created_before(utc: i64) -> CreatedBefore {
     created_at.lt(utc)
 }
 
+/// Orders tweets by ID, numerically
+///
+/// IDs are stored as text, where `"9"` sorts after `"10"`, but without
+/// leading zeros a longer ID is always the larger one.
+pub fn by_id() -> ById {
+    use db::dsl::*;
+    (length(id_str.nullable()).asc(), id_str.asc())
+}
+
 /// Gets all existing tweets, meaning not marked as deleted and not already
 /// checked?
 pub fn existing() -> Existing {
@@ -89,45 +643,801 @@ pub fn existing() -> Existing {
 /// Mark `tweets` as checked, returning how many were marked
 ///
 /// This all occurs in a single transaction.
-///
-/// It is a logic error for `tweets` not to be in sorted order
 pub fn checked<'a>(
     conn: &mut SqliteConnection,
-    tweets: impl Iterator<Item = &'a str>,
+    clock: &dyn Clock,
+    tweets: impl Iterator<Item = &'a TweetId>,
 ) -> Result<usize> {
-    let gone = conn.transaction::<_, DieselError, _>(|conn| {
+    let ids: Vec<&TweetId> = tweets.collect();
+    let gone = conn.transaction::<_, anyhow::Error, _>(|conn| {
+        use db::dsl::*;
         let mut gone = 0;
-        // TODO: use range of some sort?
-        for tweet in tweets {
-            use db::dsl::*;
-            gone += diesel::update(tweets.find(tweet))
+        // Stay under SQLites limit on query parameters
+        for chunk in ids.chunks(500) {
+            let found: Vec<TweetId> = tweets
+                .filter(id_str.eq_any(chunk))
+                .select(id_str)
+                .load(conn)?;
+            gone += diesel::update(tweets.filter(id_str.eq_any(&found)))
                 .set(checked.eq(true))
                 .execute(conn)?;
+            for tweet in &found {
+                log_event(conn, clock, tweet, EventKind::Checked, None)?;
+            }
         }
         Ok(gone)
     })?;
     Ok(gone)
 }
 
-/// Mark `tweets` as deleted, returning how many were marked
+/// Mark `tweets` as deleted now, according to `clock`, returning how many were
+/// marked
 ///
-/// This all occurs in a single transaction.
+/// Tweets already marked as deleted are ignored.
 ///
-/// It is a logic error for `tweets` not to be in sorted order
+/// This all occurs in a single transaction.
 pub fn deleted<'a>(
     conn: &mut SqliteConnection,
-    tweets: impl Iterator<Item = &'a str>,
+    clock: &dyn Clock,
+    tweets: impl Iterator<Item = &'a TweetId>,
 ) -> Result<usize> {
-    let gone = conn.transaction::<_, DieselError, _>(|conn| {
+    let now = clock.now().unix_timestamp();
+    let ids: Vec<&TweetId> = tweets.collect();
+    let gone = conn.transaction::<_, anyhow::Error, _>(|conn| {
+        use db::dsl::*;
         let mut gone = 0;
-        // TODO: use range of some sort?
-        for tweet in tweets {
-            use db::dsl::*;
-            gone += diesel::update(tweets.find(tweet))
-                .set(deleted.eq(true))
+        // Stay under SQLites limit on query parameters
+        for chunk in ids.chunks(500) {
+            let found: Vec<TweetId> = tweets
+                .filter(id_str.eq_any(chunk))
+                .filter(deleted.eq(false))
+                .select(id_str)
+                .load(conn)?;
+            gone += diesel::update(tweets.filter(id_str.eq_any(&found)))
+                .set((deleted.eq(true), deleted_at.eq(now)))
                 .execute(conn)?;
+            for tweet in &found {
+                log_event(conn, clock, tweet, EventKind::Deleted, None)?;
+            }
         }
         Ok(gone)
     })?;
     Ok(gone)
 }
+
+/// Record a failed attempt to delete `tweet`, with the reason `why`,
+/// returning how many were marked
+pub fn failed(
+    conn: &mut SqliteConnection,
+    clock: &dyn Clock,
+    tweet: &TweetId,
+    why: &str,
+) -> Result<usize> {
+    use db::dsl::*;
+    let marked = diesel::update(tweets.find(tweet))
+        .set(failures.eq(failures + 1))
+        .execute(conn)?;
+    if marked > 0 {
+        log_event(conn, clock, tweet, EventKind::Failed, Some(why))?;
+    }
+    Ok(marked)
+}
+
+/// Consecutive 403 Forbidden responses after which a tweet is permanently
+/// failed, see [`forbidden`]
+pub const PERMANENT_FAILURE_ATTEMPTS: i32 = 3;
+
+/// Record twitter refusing to delete `tweet` with 403 Forbidden, with the
+/// reason `why`, returning whether it is now permanently failed
+///
+/// Filters skip permanently failed tweets unless told otherwise, see
+/// [`FilterArgs::include_permanent_failures`](crate::filter::FilterArgs::include_permanent_failures)
+pub fn forbidden(
+    conn: &mut SqliteConnection,
+    clock: &dyn Clock,
+    tweet: &TweetId,
+    why: &str,
+) -> Result<bool> {
+    use db::dsl::*;
+    failed(conn, clock, tweet, why)?;
+    diesel::update(tweets.find(tweet))
+        .set((
+            forbidden.eq(forbidden + 1),
+            permanently_failed.eq((forbidden + 1).ge(PERMANENT_FAILURE_ATTEMPTS)),
+        ))
+        .execute(conn)?;
+    Ok(tweets
+        .find(tweet)
+        .select(permanently_failed)
+        .first(conn)
+        .optional()?
+        .unwrap_or(false))
+}
+
+/// Record twitter responding to deleting `tweet` with something other than
+/// 403 Forbidden, so forbidden responses are no longer consecutive
+pub fn not_forbidden(conn: &mut SqliteConnection, tweet: &TweetId) -> Result<()> {
+    use db::dsl::*;
+    diesel::update(tweets.find(tweet))
+        .set(forbidden.eq(0))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Record whether each of `found`, as found by the lookup API, is a retweet,
+/// and of which tweet ID
+pub fn set_retweets<'a>(
+    conn: &mut SqliteConnection,
+    found: impl Iterator<Item = (&'a TweetId, Option<&'a TweetId>)>,
+) -> Result<()> {
+    use db::dsl::*;
+    for (tweet, original) in found {
+        diesel::update(tweets.find(tweet))
+            .set((is_retweet.eq(original.is_some()), retweet_of.eq(original)))
+            .execute(conn)?;
+    }
+    Ok(())
+}
+
+/// Record the versions of edited tweets found by looking them up, as each
+/// tweet and the ID of its every version, oldest first, returning how many
+/// versions were added
+///
+/// Every version gets the first as its [`Tweet::initial_id`]. Versions that
+/// aren't in the database, such as edits made after the archive, are added
+/// as unchecked copies of the tweet they were found through, so filters treat
+/// them the same.
+pub fn add_versions<'a>(
+    conn: &mut SqliteConnection,
+    found: impl Iterator<Item = (&'a TweetId, &'a [TweetId])>,
+) -> Result<usize> {
+    use db::dsl::*;
+    let mut added = 0;
+    for (tweet, versions) in found {
+        let Some(initial) = versions.first() else {
+            continue;
+        };
+        let Some(found) = tweets.find(tweet).first::<Tweet>(conn).optional()? else {
+            continue;
+        };
+        diesel::update(tweets.filter(id_str.eq_any(versions)))
+            .set(initial_id.eq(initial))
+            .execute(conn)?;
+        let known: Vec<TweetId> = tweets
+            .filter(id_str.eq_any(versions))
+            .select(id_str)
+            .load(conn)?;
+        for version in versions.iter().filter(|v| !known.contains(v)) {
+            let copy = Tweet {
+                id_str: version.clone(),
+                deleted: false,
+                checked: false,
+                failures: 0,
+                deleted_at: None,
+                forbidden: 0,
+                permanently_failed: false,
+                initial_id: Some(initial.clone()),
+                ..found.clone()
+            };
+            added += diesel::insert_into(db::table)
+                .values(&copy)
+                .execute(conn)?;
+        }
+    }
+    Ok(added)
+}
+
+/// Get the tweet ID each of `ids` retweeted, for those known to be retweets
+pub fn retweets_of(
+    conn: &mut SqliteConnection,
+    ids: &[TweetId],
+) -> Result<HashMap<TweetId, TweetId>> {
+    use db::dsl::*;
+    let mut found = HashMap::new();
+    // Stay under SQLites limit on query parameters
+    for chunk in ids.chunks(500) {
+        let rows: Vec<(TweetId, TweetId)> = tweets
+            .filter(id_str.eq_any(chunk))
+            .select((id_str, retweet_of.assume_not_null()))
+            .filter(retweet_of.is_not_null())
+            .load(conn)?;
+        found.extend(rows);
+    }
+    Ok(found)
+}
+
+/// Whether `tweet` is a retweet, if known
+pub fn is_retweet(conn: &mut SqliteConnection, tweet: &TweetId) -> Result<Option<bool>> {
+    use db::dsl::*;
+    Ok(tweets
+        .find(tweet)
+        .select(is_retweet)
+        .first(conn)
+        .optional()?
+        .flatten())
+}
+
+/// Set the score of `tweet`, returning how many were updated
+pub fn set_score(conn: &mut SqliteConnection, tweet: &TweetId, value: f64) -> Result<usize> {
+    use db::dsl::*;
+    let updated = diesel::update(tweets.find(tweet))
+        .set(score.eq(value))
+        .execute(conn)?;
+    Ok(updated)
+}
+
+/// Mark `tweets` as protected from deletion, with an optional `note`
+/// explaining why, returning how many were marked
+///
+/// This all occurs in a single transaction.
+pub fn protect<'a>(
+    conn: &mut SqliteConnection,
+    clock: &dyn Clock,
+    tweets: impl Iterator<Item = &'a TweetId>,
+    note: Option<&str>,
+) -> Result<usize> {
+    let marked = conn.transaction::<_, anyhow::Error, _>(|conn| {
+        let mut marked = 0;
+        for tweet in tweets {
+            let updated = diesel::update(db::dsl::tweets.find(tweet))
+                .set((db::dsl::protected.eq(true), db::dsl::note.eq(note)))
+                .execute(conn)?;
+            if updated > 0 {
+                log_event(conn, clock, tweet, EventKind::Protected, note)?;
+            }
+            marked += updated;
+        }
+        Ok(marked)
+    })?;
+    Ok(marked)
+}
+
+/// Remove protection from `tweets`, clearing any note, returning how many
+/// were unmarked
+///
+/// This all occurs in a single transaction.
+pub fn unprotect<'a>(
+    conn: &mut SqliteConnection,
+    clock: &dyn Clock,
+    tweets: impl Iterator<Item = &'a TweetId>,
+) -> Result<usize> {
+    let unmarked = conn.transaction::<_, anyhow::Error, _>(|conn| {
+        let mut unmarked = 0;
+        for tweet in tweets {
+            use db::dsl::*;
+            let updated = diesel::update(tweets.find(tweet))
+                .set((protected.eq(false), note.eq(None::<String>)))
+                .execute(conn)?;
+            if updated > 0 {
+                log_event(conn, clock, tweet, EventKind::Unprotected, None)?;
+            }
+            unmarked += updated;
+        }
+        Ok(unmarked)
+    })?;
+    Ok(unmarked)
+}
+
+/// Record that `kind` happened to `tweet` now, according to `clock`, with
+/// optional extra `detail`
+pub fn log_event(
+    conn: &mut SqliteConnection,
+    clock: &dyn Clock,
+    tweet: &TweetId,
+    kind: EventKind,
+    detail: Option<&str>,
+) -> Result<()> {
+    debug!(%tweet, kind = kind.as_str(), detail, "Tweet event");
+    diesel::insert_into(edb::table)
+        .values((
+            edb::dsl::tweet_id.eq(tweet),
+            edb::dsl::kind.eq(kind.as_str()),
+            edb::dsl::at.eq(clock.now().unix_timestamp()),
+            edb::dsl::detail.eq(detail),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Record the start of a `command` run for `account`, with an optional user
+/// `tag` and canonical `filters`, returning its ID
+///
+/// `now` is UTC unix time
+pub fn start_run(
+    conn: &mut SqliteConnection,
+    command: &str,
+    account: &str,
+    tag: Option<&str>,
+    filters: Option<&str>,
+    now: i64,
+) -> Result<i32> {
+    let id = conn.transaction::<_, DieselError, _>(|conn| {
+        diesel::insert_into(rdb::table)
+            .values((
+                rdb::dsl::command.eq(command),
+                rdb::dsl::account_id.eq(account),
+                rdb::dsl::tag.eq(tag),
+                rdb::dsl::filters.eq(filters),
+                rdb::dsl::started_at.eq(now),
+            ))
+            .execute(conn)?;
+        rdb::dsl::runs
+            .select(diesel::dsl::max(rdb::dsl::id))
+            .first::<Option<i32>>(conn)
+    })?;
+    let id = id.ok_or_else(|| anyhow!("Failed to record run"))?;
+    debug!(run = id, command, account, tag, filters, "Started run");
+    Ok(id)
+}
+
+/// Record that run `run` finished at `now`, having deleted `count` tweets
+///
+/// `now` is UTC unix time
+pub fn finish_run(conn: &mut SqliteConnection, run: i32, count: usize, now: i64) -> Result<()> {
+    use rdb::dsl::*;
+    debug!(run, deleted = count, "Finished run");
+    diesel::update(runs.find(run))
+        .set((finished_at.eq(now), deleted.eq(count as i32)))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// How an `import` run performed, see [`record_import`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ImportStats {
+    /// Time spent reading and parsing archives
+    pub parse: StdDuration,
+
+    /// Time spent adding tweets to the database
+    pub insert: StdDuration,
+
+    /// Tweets read from archives
+    pub rows: usize,
+
+    /// Peak memory use in bytes, if it could be found
+    pub peak_memory: Option<u64>,
+}
+
+/// Record how `import` run `run` performed
+pub fn record_import(conn: &mut SqliteConnection, run: i32, stats: &ImportStats) -> Result<()> {
+    use rdb::dsl::*;
+    diesel::update(runs.find(run))
+        .set((
+            parse_ms.eq(stats.parse.as_millis() as i64),
+            insert_ms.eq(stats.insert.as_millis() as i64),
+            imported_rows.eq(stats.rows as i32),
+            peak_memory.eq(stats.peak_memory.map(|m| m as i64)),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Record that `tweets` matched the filters for run `run`
+///
+/// This all occurs in a single transaction.
+pub fn record_matches(conn: &mut SqliteConnection, run: i32, tweets: &[TweetId]) -> Result<()> {
+    debug!(run, matched = tweets.len(), "Recording matched tweets");
+    conn.transaction::<_, DieselError, _>(|conn| {
+        // Stay under SQLites limit on bound parameters
+        for (i, chunk) in tweets.chunks(300).enumerate() {
+            let rows: Vec<_> = chunk
+                .iter()
+                .enumerate()
+                .map(|(j, t)| {
+                    (
+                        rtdb::dsl::run_id.eq(run),
+                        rtdb::dsl::tweet_id.eq(t),
+                        rtdb::dsl::position.eq((i * 300 + j) as i32),
+                    )
+                })
+                .collect();
+            diesel::insert_or_ignore_into(rtdb::table)
+                .values(&rows)
+                .execute(conn)?;
+        }
+        Ok(())
+    })?;
+    Ok(())
+}
+
+/// Stage `tweets` of `account` as a batch named `batch`, to be deleted later
+/// by committing it, with an optional user `tag` and canonical `filters`,
+/// returning its run ID
+///
+/// `now` is UTC unix time
+///
+/// This all occurs in a single transaction.
+pub fn stage_batch(
+    conn: &mut SqliteConnection,
+    account: &str,
+    tag: Option<&str>,
+    filters: &str,
+    batch: &str,
+    tweets: &[TweetId],
+    now: i64,
+) -> Result<i32> {
+    conn.transaction::<_, anyhow::Error, _>(|conn| {
+        let run = start_run(conn, "stage", account, tag, Some(filters), now)?;
+        diesel::update(rdb::dsl::runs.find(run))
+            .set(rdb::dsl::batch.eq(batch))
+            .execute(conn)?;
+        record_matches(conn, run, tweets)?;
+        Ok(run)
+    })
+}
+
+/// Get the tweets matched by run `run` that still need deleting, in the
+/// order they were queued
+///
+/// Tweets that have since been deleted or protected are skipped. Runs
+/// recorded before their order was kept are in ID order.
+pub fn remaining(conn: &mut SqliteConnection, run: i32) -> Result<Vec<TweetId>> {
+    let ids = rtdb::dsl::run_tweets
+        .inner_join(db::table)
+        .filter(rtdb::dsl::run_id.eq(run))
+        .filter(db::dsl::deleted.eq(false))
+        .filter(db::dsl::protected.eq(false))
+        .order(rtdb::dsl::position.asc())
+        .then_order_by(by_id())
+        .select(db::dsl::id_str)
+        .load(conn)?;
+    Ok(ids)
+}
+
+/// Get the account run `run` was for, if it was for one
+///
+/// Runs recorded before their account was kept are taken to be for the
+/// account of the tweets they matched, if known.
+pub fn run_account(conn: &mut SqliteConnection, run: i32) -> Result<Option<String>> {
+    let account = rdb::dsl::runs
+        .find(run)
+        .select(rdb::dsl::account_id)
+        .first::<Option<String>>(conn)
+        .optional()?
+        .flatten();
+    if account.is_some() {
+        return Ok(account);
+    }
+    Ok(rtdb::dsl::run_tweets
+        .inner_join(db::table)
+        .filter(rtdb::dsl::run_id.eq(run))
+        // Tweets imported before accounts were
+        .filter(db::dsl::account_id.ne("0"))
+        .select(db::dsl::account_id)
+        .first(conn)
+        .optional()?)
+}
+
+/// Get the tweets matched by run `run` that twitter failed to delete and
+/// are still not deleted, in ID order, with how many attempts failed and the
+/// reason the last one did, if recorded
+pub fn run_failures(
+    conn: &mut SqliteConnection,
+    run: i32,
+) -> Result<Vec<(TweetId, i32, Option<String>)>> {
+    let failed: Vec<(TweetId, i32)> = rtdb::dsl::run_tweets
+        .inner_join(db::table)
+        .filter(rtdb::dsl::run_id.eq(run))
+        .filter(db::dsl::deleted.eq(false))
+        .filter(db::dsl::failures.gt(0))
+        .order(by_id())
+        .select((db::dsl::id_str, db::dsl::failures))
+        .load(conn)?;
+    failed
+        .into_iter()
+        .map(|(id, failures)| {
+            let why = edb::dsl::events
+                .filter(edb::dsl::tweet_id.eq(&id))
+                .filter(edb::dsl::kind.eq(EventKind::Failed.as_str()))
+                .order(edb::dsl::id.desc())
+                .select(edb::dsl::detail)
+                .first::<Option<String>>(conn)
+                .optional()?
+                .flatten();
+            Ok((id, failures, why))
+        })
+        .collect()
+}
+
+/// Get the tweets matched by run `run` that are marked as deleted, in ID
+/// order
+pub fn run_deleted(conn: &mut SqliteConnection, run: i32) -> Result<Vec<TweetId>> {
+    let matched = rtdb::dsl::run_tweets
+        .filter(rtdb::dsl::run_id.eq(run))
+        .select(rtdb::dsl::tweet_id);
+    let ids = db::dsl::tweets
+        .filter(db::dsl::id_str.eq_any(matched))
+        .filter(db::dsl::deleted.eq(true))
+        .order(by_id())
+        .select(db::dsl::id_str)
+        .load(conn)?;
+    Ok(ids)
+}
+
+/// Count how many of the tweets matched by run `run` are marked as deleted
+pub fn count_run_deleted(conn: &mut SqliteConnection, run: i32) -> Result<i64> {
+    let matched = rtdb::dsl::run_tweets
+        .filter(rtdb::dsl::run_id.eq(run))
+        .select(rtdb::dsl::tweet_id);
+    let c = db::dsl::tweets
+        .filter(db::dsl::id_str.eq_any(matched))
+        .filter(db::dsl::deleted.eq(true))
+        .count()
+        .get_result(conn)?;
+    Ok(c)
+}
+
+/// Count how many tweets run `run` matched
+pub fn count_run_matches(conn: &mut SqliteConnection, run: i32) -> Result<i64> {
+    let c = rtdb::dsl::run_tweets
+        .filter(rtdb::dsl::run_id.eq(run))
+        .count()
+        .get_result(conn)?;
+    Ok(c)
+}
+
+/// Record that run `run` had a `discrepancy` between twitter and the
+/// database
+pub fn record_discrepancy(conn: &mut SqliteConnection, run: i32, discrepancy: i64) -> Result<()> {
+    debug!(
+        run,
+        discrepancy, "Recording discrepancy between twitter and the database"
+    );
+    diesel::update(rdb::dsl::runs.find(run))
+        .set(rdb::dsl::discrepancy.eq(discrepancy as i32))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Get the ID and matched tweets of the most recent `command` run with the
+/// same `tag` and `filters`, if any
+pub fn last_matches(
+    conn: &mut SqliteConnection,
+    command: &str,
+    tag: Option<&str>,
+    filters: &str,
+) -> Result<Option<(i32, Vec<TweetId>)>> {
+    let mut query = rdb::dsl::runs
+        .filter(rdb::dsl::command.eq(command))
+        .filter(rdb::dsl::filters.eq(filters))
+        .select(rdb::dsl::id)
+        .order(rdb::dsl::id.desc())
+        .into_boxed();
+    query = match tag {
+        Some(tag) => query.filter(rdb::dsl::tag.eq(tag)),
+        None => query.filter(rdb::dsl::tag.is_null()),
+    };
+    let Some(run) = query.first::<i32>(conn).optional()? else {
+        return Ok(None);
+    };
+
+    let matches = rtdb::dsl::run_tweets
+        .filter(rtdb::dsl::run_id.eq(run))
+        .select(rtdb::dsl::tweet_id)
+        .load(conn)?;
+    Ok(Some((run, matches)))
+}
+
+/// Get the ID of the most recent unfinished run of `command`, with the same
+/// `tag` if given
+pub fn last_unfinished(
+    conn: &mut SqliteConnection,
+    command: &str,
+    tag: Option<&str>,
+) -> Result<Option<i32>> {
+    let mut query = rdb::dsl::runs
+        .filter(rdb::dsl::command.eq(command))
+        .filter(rdb::dsl::finished_at.is_null())
+        .select(rdb::dsl::id)
+        .order(rdb::dsl::id.desc())
+        .into_boxed();
+    if let Some(tag) = tag {
+        query = query.filter(rdb::dsl::tag.eq(tag));
+    }
+    Ok(query.first(conn).optional()?)
+}
+
+/// Wraps a query in `EXPLAIN QUERY PLAN`, see [`explain`]
+struct Explain<Q>(Q);
+
+impl<Q> QueryId for Explain<Q> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+impl<Q> Query for Explain<Q> {
+    /// `id`, `parent`, `notused`, `detail`
+    type SqlType = (Integer, Integer, Integer, Text);
+}
+
+impl<Q: QueryFragment<Sqlite>> QueryFragment<Sqlite> for Explain<Q> {
+    fn walk_ast<'b>(&'b self, mut out: AstPass<'_, 'b, Sqlite>) -> QueryResult<()> {
+        out.push_sql("EXPLAIN QUERY PLAN ");
+        self.0.walk_ast(out.reborrow())
+    }
+}
+
+impl<Q> RunQueryDsl<SqliteConnection> for Explain<Q> {}
+
+/// Get SQLites query plan for `query`, one step per line, indented under
+/// their parent steps
+pub fn explain<Q: QueryFragment<Sqlite>>(
+    conn: &mut SqliteConnection,
+    query: Q,
+) -> Result<Vec<String>> {
+    let steps: Vec<(i32, i32, i32, String)> = Explain(query).load(conn)?;
+
+    let mut depths = HashMap::new();
+    let plan = steps
+        .into_iter()
+        .map(|(id, parent, _, detail)| {
+            let depth = depths.get(&parent).map_or(0, |d| d + 1);
+            depths.insert(id, depth);
+            format!("{}{detail}", "  ".repeat(depth))
+        })
+        .collect();
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FrozenClock;
+
+    const NOW: FrozenClock = FrozenClock(time::macros::datetime!(2023-01-01 0:00 UTC));
+
+    /// IDs either side of where they go from 18 to 19 digits, more than a
+    /// chunk of them
+    fn boundary() -> Vec<TweetId> {
+        (999_999_999_999_999_400u64..1_000_000_000_000_000_600)
+            .map(|id| id.to_string().parse().unwrap())
+            .collect()
+    }
+
+    fn setup(ids: &[TweetId]) -> SqliteConnection {
+        let mut conn = create_db(Path::new(":memory:")).unwrap();
+        // Newest first, so insertion order isn't mistaken for ID order
+        let tweets: Vec<Tweet> = ids
+            .iter()
+            .rev()
+            .map(|id| Tweet::new(id.clone(), 0, 0, 0, "0".into()))
+            .collect();
+        add_tweets(&mut conn, &NOW, &tweets).unwrap();
+        conn
+    }
+
+    #[test]
+    fn by_id_is_numeric() {
+        let ids = boundary();
+        let conn = &mut setup(&ids);
+        let sorted: Vec<TweetId> = db::table
+            .order(by_id())
+            .select(db::dsl::id_str)
+            .load(conn)
+            .unwrap();
+        assert_eq!(sorted, ids);
+        let short = sorted
+            .iter()
+            .position(|id| id.as_str() == "999999999999999999")
+            .unwrap();
+        assert_eq!(sorted[short + 1].as_str(), "1000000000000000000");
+    }
+
+    #[test]
+    fn checked_and_deleted_mark_exactly_given() {
+        let ids = boundary();
+        let conn = &mut setup(&ids);
+        // Every other ID, so both sides of the boundary and of each chunk
+        let given: Vec<&TweetId> = ids.iter().step_by(2).collect();
+        let rest: Vec<&TweetId> = ids.iter().skip(1).step_by(2).collect();
+
+        assert_eq!(
+            checked(conn, &NOW, given.iter().copied()).unwrap(),
+            given.len()
+        );
+        let marked: Vec<TweetId> = db::table
+            .filter(db::dsl::checked.eq(true))
+            .order(by_id())
+            .select(db::dsl::id_str)
+            .load(conn)
+            .unwrap();
+        assert_eq!(marked.iter().collect::<Vec<_>>(), given);
+
+        assert_eq!(
+            deleted(conn, &NOW, rest.iter().copied()).unwrap(),
+            rest.len()
+        );
+        let marked: Vec<(TweetId, Option<i64>)> = db::table
+            .filter(db::dsl::deleted.eq(true))
+            .order(by_id())
+            .select((db::dsl::id_str, db::dsl::deleted_at))
+            .load(conn)
+            .unwrap();
+        assert_eq!(marked.iter().map(|(id, _)| id).collect::<Vec<_>>(), rest);
+        // At the time of the clock given, not the real time
+        let at = NOW.now().unix_timestamp();
+        assert!(marked.iter().all(|(_, deleted_at)| *deleted_at == Some(at)));
+
+        // Already deleted tweets are ignored
+        assert_eq!(deleted(conn, &NOW, rest.iter().copied()).unwrap(), 0);
+    }
+
+    #[test]
+    fn add_versions_links_and_adds_missing() {
+        let ids: Vec<TweetId> = ["1", "3"].iter().map(|i| i.parse().unwrap()).collect();
+        let conn = &mut setup(&ids);
+        protect(conn, &NOW, [ids[1].clone()].iter(), None).unwrap();
+        let versions: Vec<TweetId> = ["1", "2", "3"]
+            .iter()
+            .map(|i| i.parse().unwrap())
+            .collect();
+        // Tweets that were never edited have no versions from lookups
+        let found = [(&ids[1], &versions[..]), (&ids[0], &[][..])];
+        assert_eq!(add_versions(conn, found.into_iter()).unwrap(), 1);
+        let rows: Vec<(TweetId, Option<TweetId>, bool, bool)> = db::table
+            .order(by_id())
+            .select((
+                db::dsl::id_str,
+                db::dsl::initial_id,
+                db::dsl::checked,
+                db::dsl::protected,
+            ))
+            .load(conn)
+            .unwrap();
+        let rows: Vec<_> = rows
+            .iter()
+            .map(|(id, initial, checked, protected)| {
+                (
+                    id.as_str(),
+                    initial.as_ref().map(TweetId::as_str),
+                    *checked,
+                    *protected,
+                )
+            })
+            .collect();
+        // The new version is a copy of the protected one it was found through
+        assert_eq!(
+            rows,
+            [
+                ("1", Some("1"), false, false),
+                ("2", Some("1"), false, true),
+                ("3", Some("1"), false, true),
+            ]
+        );
+
+        // Already known versions aren't added again
+        assert_eq!(add_versions(conn, found.into_iter()).unwrap(), 0);
+    }
+
+    #[test]
+    fn run_account_falls_back_to_tweets() {
+        let ids: Vec<TweetId> = ["1", "2"].iter().map(|i| i.parse().unwrap()).collect();
+        let conn = &mut setup(&ids);
+        diesel::insert_into(adb::table)
+            .values((
+                adb::dsl::id_str.eq("10"),
+                adb::dsl::user_name.eq("ten"),
+                adb::dsl::display_name.eq("Ten"),
+            ))
+            .execute(conn)
+            .unwrap();
+        let run = start_run(conn, "delete", "10", None, None, 0).unwrap();
+        assert_eq!(run_account(conn, run).unwrap().as_deref(), Some("10"));
+
+        // As recorded before runs kept their account
+        let legacy = start_run(conn, "delete", "10", None, None, 0).unwrap();
+        diesel::update(rdb::dsl::runs.find(legacy))
+            .set(rdb::dsl::account_id.eq(None::<String>))
+            .execute(conn)
+            .unwrap();
+        record_matches(conn, legacy, &ids).unwrap();
+        // Tweets imported before accounts were don't say
+        assert_eq!(run_account(conn, legacy).unwrap(), None);
+        diesel::update(db::table)
+            .set(db::dsl::account_id.eq("10"))
+            .execute(conn)
+            .unwrap();
+        assert_eq!(run_account(conn, legacy).unwrap().as_deref(), Some("10"));
+    }
+}
@@ -0,0 +1,319 @@
+//! Exporting tweet state for consumption by other tools
+
+use std::{collections::HashMap, io::Write, path::Path};
+
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use diesel::{prelude::*, sqlite::Sqlite};
+use serde::Serialize;
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use crate::{db::by_id, filter::text, id::TweetId, schema::tweets as db, DATE};
+
+/// Which tweets to export
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum State {
+    /// Tweets that have been deleted
+    Deleted,
+
+    /// Tweets that have not been deleted
+    Kept,
+
+    /// Tweets that have not been deleted, after failing to be deleted at
+    /// least once
+    Failed,
+}
+
+/// Format to export tweets in
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Format {
+    /// Plain text, one tweet ID per line
+    Ids,
+
+    /// CSV with a header line, tweet ID first
+    Csv,
+
+    /// JSON, one object per line
+    Json,
+
+    /// Markdown document, one section per tweet, or per thread with
+    /// `--threads`
+    Markdown,
+
+    /// HTML document, one article per tweet, or per thread with `--threads`
+    Html,
+}
+
+impl Format {
+    /// The format for a file at `path`, from its extension
+    pub fn from_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("txt") => Ok(Self::Ids),
+            Some("csv") => Ok(Self::Csv),
+            Some("json" | "jsonl") => Ok(Self::Json),
+            Some("md") => Ok(Self::Markdown),
+            Some("html" | "htm") => Ok(Self::Html),
+            _ => Err(anyhow!(
+                "Unknown export format for {}, use a .txt, .csv, .json, .md, or .html file",
+                path.display()
+            )),
+        }
+    }
+}
+
+/// A tweet as exported in [`Format::Json`]
+#[derive(Debug, Serialize)]
+struct Exported<'a> {
+    id: &'a str,
+    created_at: &'a str,
+    likes: i32,
+    retweets: i32,
+    failures: i32,
+    text: Option<&'a str>,
+}
+
+/// A tweet as stored, for exporting
+#[derive(Debug, Queryable)]
+struct Part {
+    id: TweetId,
+    created_at: i64,
+    account_id: String,
+    in_reply_to_status_id: Option<String>,
+    in_reply_to_user_id: Option<String>,
+    likes: i32,
+    retweets: i32,
+    failures: i32,
+    text: Option<String>,
+}
+
+/// Escape `text` for HTML
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Query for every tweet in `state`
+fn in_state(state: State) -> db::BoxedQuery<'static, Sqlite> {
+    use db::dsl::*;
+    let query = tweets.into_boxed();
+    match state {
+        State::Deleted => query.filter(deleted.eq(true)),
+        State::Kept => query.filter(deleted.eq(false)),
+        State::Failed => query.filter(deleted.eq(false)).filter(failures.gt(0)),
+    }
+}
+
+/// Load every tweet matching `query`
+fn load_parts(conn: &mut SqliteConnection, query: db::BoxedQuery<Sqlite>) -> Result<Vec<Part>> {
+    use db::dsl::*;
+    Ok(query
+        .select((
+            id_str,
+            created_at,
+            account_id,
+            in_reply_to_status_id,
+            in_reply_to_user_id,
+            likes,
+            retweets,
+            failures,
+            text(),
+        ))
+        .load(conn)?)
+}
+
+/// Quote `field` for CSV, if needed
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// Write every tweet in `state` to `out` in `format`, returning how many were
+/// written
+///
+/// Tweet text is exported as displayed, with t.co links expanded, as they
+/// stop working once the tweet is deleted.
+///
+/// The ID and CSV formats can be re-imported later with `import-ids`
+///
+/// If `threads` is true, only threads are exported, see [`export_documents`]
+pub fn export_state(
+    conn: &mut SqliteConnection,
+    state: State,
+    format: Format,
+    threads: bool,
+    out: &mut dyn Write,
+) -> Result<usize> {
+    let parts = load_parts(conn, in_state(state).order(by_id()))?;
+    export_parts(parts, format, threads, out)
+}
+
+/// Write the tweets `ids` to `out` in `format`, in the same order, returning
+/// how many were written
+///
+/// Used to keep a record of tweets before deleting them, see
+/// [`export_state`] for the formats.
+pub fn export_ids(
+    conn: &mut SqliteConnection,
+    ids: &[TweetId],
+    format: Format,
+    out: &mut dyn Write,
+) -> Result<usize> {
+    use db::dsl::*;
+    let mut found: HashMap<TweetId, Part> = HashMap::with_capacity(ids.len());
+    // Stay under SQLites limit on query parameters
+    for chunk in ids.chunks(500) {
+        let query = tweets.filter(id_str.eq_any(chunk)).into_boxed();
+        found.extend(
+            load_parts(conn, query)?
+                .into_iter()
+                .map(|p| (p.id.clone(), p)),
+        );
+    }
+    let parts = ids
+        .iter()
+        .map(|id| {
+            found
+                .remove(id)
+                .ok_or_else(|| anyhow!("Tweet {id} is not in the database"))
+        })
+        .collect::<Result<_>>()?;
+    export_parts(parts, format, false, out)
+}
+
+/// Write `parts` to `out` in `format`, in order except for documents,
+/// returning how many were written
+fn export_parts(
+    parts: Vec<Part>,
+    format: Format,
+    threads: bool,
+    out: &mut dyn Write,
+) -> Result<usize> {
+    match format {
+        Format::Markdown | Format::Html => {
+            return export_documents(parts, format, threads, out);
+        }
+        _ if threads => {
+            return Err(anyhow!("Threads can only be exported as Markdown or HTML"));
+        }
+        _ => (),
+    }
+
+    if let Format::Csv = format {
+        writeln!(out, "id,created_at,likes,retweets,failures,text")?;
+    }
+    for part in &parts {
+        let created = OffsetDateTime::from_unix_timestamp(part.created_at)?.format(&Rfc3339)?;
+        match format {
+            Format::Ids => writeln!(out, "{}", part.id)?,
+            Format::Csv => writeln!(
+                out,
+                "{},{created},{},{},{},{}",
+                part.id,
+                part.likes,
+                part.retweets,
+                part.failures,
+                csv_field(part.text.as_deref().unwrap_or_default())
+            )?,
+            Format::Json => {
+                let tweet = Exported {
+                    id: part.id.as_str(),
+                    created_at: &created,
+                    likes: part.likes,
+                    retweets: part.retweets,
+                    failures: part.failures,
+                    text: part.text.as_deref(),
+                };
+                writeln!(out, "{}", serde_json::to_string(&tweet)?)?;
+            }
+            Format::Markdown | Format::Html => unreachable!("Documents are exported above"),
+        }
+    }
+
+    Ok(parts.len())
+}
+
+/// Write `parts` to `out` as a document in `format`, oldest first, returning
+/// how many tweets were written
+///
+/// If `threads` is true, self-reply chains are grouped together, oldest
+/// first, and tweets that aren't part of one are skipped.
+///
+/// # Implementation Details
+///
+/// Replies are always newer than what they reply to, so going through tweets
+/// oldest first each self-reply joins the thread of its parent, if the
+/// parent was exported.
+fn export_documents(
+    mut parts: Vec<Part>,
+    format: Format,
+    threads: bool,
+    out: &mut dyn Write,
+) -> Result<usize> {
+    parts.sort_unstable_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+
+    // Index into `documents` of the thread each tweet is in
+    let mut thread_of: HashMap<&str, usize> = HashMap::new();
+    let mut documents: Vec<Vec<&Part>> = Vec::new();
+    for part in &parts {
+        let parent = part
+            .in_reply_to_status_id
+            .as_deref()
+            .filter(|_| threads && part.in_reply_to_user_id.as_ref() == Some(&part.account_id))
+            .and_then(|parent| thread_of.get(parent).copied());
+        let thread = match parent {
+            Some(thread) => thread,
+            None => {
+                documents.push(Vec::new());
+                documents.len() - 1
+            }
+        };
+        documents[thread].push(part);
+        thread_of.insert(part.id.as_str(), thread);
+    }
+    if threads {
+        documents.retain(|d| d.len() > 1);
+    }
+
+    if let Format::Html = format {
+        writeln!(out, "<!DOCTYPE html>\n<html>\n<head>")?;
+        writeln!(
+            out,
+            "<meta charset=\"utf-8\">\n<title>Tweets</title>\n</head>\n<body>"
+        )?;
+    }
+    let mut written = 0;
+    for document in &documents {
+        let first = document[0];
+        let date = OffsetDateTime::from_unix_timestamp(first.created_at)?.format(DATE)?;
+        let link = format!("https://twitter.com/i/web/status/{}", first.id);
+        match format {
+            Format::Html => writeln!(out, "<article>\n<h2><a href=\"{link}\">{date}</a></h2>")?,
+            _ => writeln!(out, "## [{date}]({link})\n")?,
+        }
+        for part in document {
+            let part_text = part.text.as_deref().unwrap_or_default();
+            match format {
+                Format::Html => writeln!(
+                    out,
+                    "<p>{}</p>",
+                    html_escape(part_text).replace('\n', "<br>\n")
+                )?,
+                _ => writeln!(out, "{}\n", part_text.replace('\n', "  \n"))?,
+            }
+        }
+        if let Format::Html = format {
+            writeln!(out, "</article>")?;
+        }
+        written += document.len();
+    }
+    if let Format::Html = format {
+        writeln!(out, "</body>\n</html>")?;
+    }
+
+    Ok(written)
+}
@@ -0,0 +1,1068 @@
+//! Builds queries for the tweets matching the users filters
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    str::FromStr,
+};
+
+use anyhow::{anyhow, Error, Result};
+use clap::{Args, ValueEnum, ValueHint};
+use diesel::{
+    prelude::*,
+    sql_types::{Nullable, Text},
+    sqlite::Sqlite,
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use time::{Date, Duration, OffsetDateTime, Time, UtcOffset};
+
+use crate::{
+    analyze::{ContentKind, Wordlist},
+    db::{by_id, created_before, length, saved_filter},
+    expr::{Expr, Facts},
+    id::TweetId,
+    schema::{hashtags as hdb, mentions as mdb, tweets as db},
+    util,
+    DATE,
+};
+
+/// Filters for selecting tweets to delete, as given by the user
+///
+/// See [`Filter`] for the filters actually used
+///
+/// Sent as JSON to `serve`, where everything but one of `older_than`,
+/// `before`, `after`, or `newer_than` is optional, or everything but `filter_name` when
+/// using saved filters
+///
+/// Saved with `filters save`, and used again with `--filter-name`, see
+/// [`FilterArgs::resolve`]
+#[derive(Args, Debug, Clone, Serialize, Deserialize)]
+pub struct FilterArgs {
+    /// Exclude these tweet IDs
+    #[clap(long, short, value_delimiter = ',', value_hint = ValueHint::Other)]
+    #[serde(default)]
+    pub exclude: Vec<TweetId>,
+
+    /// Use the filters saved with `filters save` under this name, instead
+    /// of giving them here
+    #[clap(long, value_hint = ValueHint::Other)]
+    #[serde(default, skip_serializing)]
+    pub filter_name: Option<String>,
+
+    /// Delete tweets older than this many days
+    ///
+    /// `0` means every tweet, regardless of age, and `delete` asks for
+    /// confirmation first.
+    ///
+    /// Required unless another age or date filter is given, or `older_than`
+    /// is set in `config.toml`.
+    #[clap(long, short, value_hint = ValueHint::Other)]
+    #[serde(default)]
+    pub older_than: Option<u32>,
+
+    /// Only delete tweets newer than this many days, such as a recent burst
+    /// of tweets
+    ///
+    /// On its own this matches every tweet since then, and can be combined
+    /// with `--older-than` or `--before` for a range.
+    #[clap(long, conflicts_with = "after", value_hint = ValueHint::Other)]
+    #[serde(default)]
+    pub newer_than: Option<u32>,
+
+    /// Where days start, for `--older-than` and `--newer-than`
+    #[clap(long, value_enum, default_value = "rolling")]
+    #[serde(default)]
+    pub boundary: Boundary,
+
+    /// Delete tweets created before this date, eg `2019-01-01`, instead of
+    /// by age
+    ///
+    /// Days start at local midnight, or in the time zone given with `--tz`.
+    #[clap(long, conflicts_with = "older-than", value_hint = ValueHint::Other)]
+    #[serde(default)]
+    pub before: Option<String>,
+
+    /// Only delete tweets created on or after this date, eg `2019-01-01`
+    ///
+    /// On its own this matches every tweet since then, and can be combined
+    /// with `--older-than` or `--before` for a range.
+    #[clap(long, value_hint = ValueHint::Other)]
+    #[serde(default)]
+    pub after: Option<String>,
+
+    /// Don't delete tweets unless they have *more* than this many likes.
+    ///
+    /// WARNING, this is based on likes in your imported twitter archive.
+    /// This DOES NOT check for the latest information on twitter
+    #[clap(long, short = 'l', value_hint = ValueHint::Other, default_value = "0")]
+    #[serde(default)]
+    pub unless_likes: u32,
+
+    /// Don't delete tweets unless they have *more* than this many retweets.
+    ///
+    /// WARNING, this is based on retweets in your imported twitter archive.
+    /// This DOES NOT check for the latest information on twitter
+    #[clap(long, short = 'r', value_hint = ValueHint::Other, default_value = "0")]
+    #[serde(default)]
+    pub unless_retweets: u32,
+
+    /// Don't delete tweets unless their engagement score is *more* than
+    /// this.
+    ///
+    /// The score is calculated from likes and retweets,
+    /// weighted by `--engagement-weights`.
+    ///
+    /// WARNING, this is based on your imported twitter archive.
+    /// This DOES NOT check for the latest information on twitter
+    #[clap(long, value_hint = ValueHint::Other)]
+    #[serde(default)]
+    pub unless_engagement: Option<u32>,
+
+    /// Weights for likes and retweets in the engagement score,
+    /// as `LIKES:RETWEETS`
+    #[clap(long, value_hint = ValueHint::Other, default_value = "1:2")]
+    #[serde(default)]
+    pub engagement_weights: Weights,
+
+    /// Only delete tweets with at most this many characters
+    #[clap(long, value_hint = ValueHint::Other)]
+    #[serde(default)]
+    pub max_chars: Option<u32>,
+
+    /// Only delete tweets with at least this many characters
+    #[clap(long, value_hint = ValueHint::Other)]
+    #[serde(default)]
+    pub min_chars: Option<u32>,
+
+    /// Only delete tweets consisting solely of these kinds of content
+    #[clap(long, value_enum, value_delimiter = ',')]
+    #[serde(default)]
+    pub content_kind: Vec<ContentKind>,
+
+    /// Only delete tweets with at most this score, from `0.0` to `1.0`
+    ///
+    /// Tweets must be scored first with the `score` subcommand.
+    /// Unscored tweets are never deleted with this filter.
+    #[clap(long, value_hint = ValueHint::Other)]
+    #[serde(default)]
+    pub max_score: Option<f64>,
+
+    /// Only delete tweets containing a term from this wordlist file
+    ///
+    /// Each line is a term or regular expression, matched case
+    /// insensitively against the tweet text.
+    ///
+    /// A line `# priority N: Name` puts the terms after it in a category,
+    /// deleted in order of priority with `--order sensitive`, `1` first.
+    #[clap(long, value_hint = ValueHint::FilePath)]
+    #[serde(default)]
+    pub wordlist: Option<PathBuf>,
+
+    /// Use the bundled starter wordlist of common profanity,
+    /// in addition to any `--wordlist`
+    #[clap(long)]
+    #[serde(default)]
+    pub starter_wordlist: bool,
+
+    /// Only delete tweets whose text matches this regular expression
+    ///
+    /// Matching is case sensitive unless the expression starts with `(?i)`.
+    #[clap(long, value_hint = ValueHint::Other)]
+    #[serde(default)]
+    pub matching: Option<String>,
+
+    /// Don't delete tweets whose text matches this regular expression
+    ///
+    /// Matching is case sensitive unless the expression starts with `(?i)`.
+    #[clap(long, value_hint = ValueHint::Other)]
+    #[serde(default)]
+    pub not_matching: Option<String>,
+
+    /// Also retry tweets twitter has repeatedly refused to delete
+    ///
+    /// These are usually retweets of accounts that have since gone private,
+    /// and are skipped by default so they don't waste rate limits.
+    #[clap(long)]
+    #[serde(default)]
+    pub include_permanent_failures: bool,
+
+    /// Only delete retweets, keeping tweets you wrote
+    ///
+    /// Which tweets are retweets is only known once they've been checked
+    /// after importing, unchecked tweets never match.
+    #[clap(long, conflicts_with = "only-originals")]
+    #[serde(default)]
+    pub only_retweets: bool,
+
+    /// Only delete tweets you wrote, keeping retweets
+    ///
+    /// Which tweets are retweets is only known once they've been checked
+    /// after importing, unchecked tweets never match.
+    #[clap(long)]
+    #[serde(default)]
+    pub only_originals: bool,
+
+    /// Only delete tweets with attached photos, videos, or GIFs
+    ///
+    /// Tweets imported before this was recorded never match, import the
+    /// archive again to record it.
+    #[clap(long, conflicts_with = "without-media")]
+    #[serde(default)]
+    pub with_media: bool,
+
+    /// Only delete tweets without attached media, keeping photo tweets
+    ///
+    /// Tweets imported before this was recorded never match, import the
+    /// archive again to record it.
+    #[clap(long)]
+    #[serde(default)]
+    pub without_media: bool,
+
+    /// Only delete replies, keeping standalone tweets
+    #[clap(long)]
+    #[serde(default)]
+    pub only_replies: bool,
+
+    /// Don't delete replies to these account handles
+    #[clap(long, value_delimiter = ',', value_hint = ValueHint::Other)]
+    #[serde(default)]
+    pub keep_replies_to: Vec<String>,
+
+    /// Only delete tweets matching this filter expression, in addition to
+    /// the other filters
+    ///
+    /// eg `age > 2y && likes < 10 && !has_media && text ~ /conference/i`
+    ///
+    /// See the README for everything that can be used.
+    #[clap(long = "filter", value_hint = ValueHint::Other)]
+    #[serde(default)]
+    pub filter_expr: Option<String>,
+
+    /// Only delete tweets that are part of a conversation with this
+    /// account, meaning replies to or mentions of it.
+    #[clap(long, value_hint = ValueHint::Other)]
+    #[serde(default)]
+    pub conversation_with: Option<String>,
+
+    /// Only delete tweets with any of these hashtags
+    ///
+    /// Hashtags are only known for tweets imported since they started being
+    /// recorded, import the archive again to add them to older imports.
+    #[clap(long, value_delimiter = ',', value_hint = ValueHint::Other)]
+    #[serde(default)]
+    pub with_hashtag: Vec<String>,
+
+    /// Don't delete tweets with any of these hashtags
+    #[clap(long, value_delimiter = ',', value_hint = ValueHint::Other)]
+    #[serde(default)]
+    pub without_hashtag: Vec<String>,
+
+    /// Only delete tweets mentioning any of these account handles
+    #[clap(long, value_delimiter = ',', value_hint = ValueHint::Other)]
+    #[serde(default)]
+    pub mentioning: Vec<String>,
+
+    /// Don't delete tweets mentioning any of these account handles
+    #[clap(long, value_delimiter = ',', value_hint = ValueHint::Other)]
+    #[serde(default)]
+    pub not_mentioning: Vec<String>,
+}
+
+impl FilterArgs {
+    /// The filters saved under `filter_name`, or these filters if not using
+    /// saved filters
+    ///
+    /// Saved filters are used as they were saved, so can't be combined with
+    /// any others. Otherwise one of `older_than`, `newer_than`, `before`, or
+    /// `after` is required, so tweets aren't deleted by mistake.
+    pub fn resolve(self, conn: &mut SqliteConnection) -> Result<Self> {
+        let name = match &self.filter_name {
+            Some(name) => name.clone(),
+            None if self.older_than.is_none()
+                && self.newer_than.is_none()
+                && self.before.is_none()
+                && self.after.is_none() =>
+            {
+                return Err(anyhow!(
+                    "Choose which tweets with `--older-than`, `--before`, `--after`, or \
+                     `--newer-than`, or set `older_than` in config.toml"
+                ))
+            }
+            None => return Ok(self),
+        };
+        let none: Self = serde_json::from_str("{}").expect("BUG: Filters have no defaults");
+        if self.canonical() != none.canonical() {
+            return Err(anyhow!(
+                "Saved filters `{name}` can't be combined with other filters, save a new \
+                 combination with `filters save` instead"
+            ));
+        }
+        Self::saved(conn, &name)
+    }
+
+    /// The filters saved under `name`
+    pub fn saved(conn: &mut SqliteConnection, name: &str) -> Result<Self> {
+        let saved = saved_filter(conn, name)?
+            .ok_or_else(|| anyhow!("No filters are saved as `{name}`, see `filters list`"))?;
+        serde_json::from_str(&saved).map_err(|e| anyhow!("Saved filters `{name}` are invalid: {e}"))
+    }
+
+    /// Build the [`Filter`] for these arguments, as of `now`
+    ///
+    /// Local days start at midnight in the offset of `now`.
+    ///
+    /// Saved filters must already be [resolved][FilterArgs::resolve].
+    pub fn build(&self, now: OffsetDateTime) -> Result<Filter> {
+        // Local midnight at the start of `date`
+        let start_of = |date: &str| -> Result<i64> {
+            let day = Date::parse(date, DATE)
+                .map_err(|e| anyhow!("Invalid date `{date}`, expected YYYY-MM-DD: {e}"))?;
+            Ok(day.midnight().assume_offset(now.offset()).unix_timestamp())
+        };
+        let created_before = match (self.older_than, &self.before) {
+            (Some(_), Some(_)) => {
+                return Err(anyhow!("Only one of `older_than` and `before` can be used"));
+            }
+            (Some(0), None) => i64::MAX,
+            (Some(days), None) => self.boundary.cutoff(now, days)?.unix_timestamp(),
+            (None, Some(before)) => start_of(before)?,
+            (None, None) if self.after.is_some() || self.newer_than.is_some() => i64::MAX,
+            (None, None) => {
+                return Err(anyhow!(
+                    "One of `older_than`, `before`, `after`, or `newer_than` is required unless \
+                     using saved filters"
+                ));
+            }
+        };
+        let created_after = match (self.newer_than, &self.after) {
+            (Some(_), Some(_)) => {
+                return Err(anyhow!("Only one of `newer_than` and `after` can be used"));
+            }
+            (Some(days), None) => Some(self.boundary.cutoff(now, days)?.unix_timestamp()),
+            (None, Some(after)) => Some(start_of(after)?),
+            (None, None) => None,
+        };
+        if created_after.map_or(false, |after| after >= created_before) {
+            return Err(anyhow!(
+                "`after` or `newer_than` must be earlier than `before` or `older_than`"
+            ));
+        }
+
+        Ok(Filter {
+            exclude: self.exclude.clone(),
+            created_before,
+            created_after,
+            unless_likes: self.unless_likes,
+            unless_retweets: self.unless_retweets,
+            conversation_with: self.conversation_with.clone(),
+            with_hashtags: strip(&self.with_hashtag, '#'),
+            without_hashtags: strip(&self.without_hashtag, '#'),
+            unless_engagement: self.unless_engagement,
+            engagement_weights: self.engagement_weights,
+            max_chars: self.max_chars,
+            min_chars: self.min_chars,
+            content_kind: self.content_kind.clone(),
+            max_score: self.max_score,
+            wordlist: Wordlist::load(self.wordlist.as_deref(), self.starter_wordlist)?,
+            matching: self.matching.as_deref().map(regex).transpose()?,
+            not_matching: self.not_matching.as_deref().map(regex).transpose()?,
+            include_permanent_failures: self.include_permanent_failures,
+            expr: self.filter_expr.as_deref().map(str::parse).transpose()?,
+            now: now.unix_timestamp(),
+            retweets: match (self.only_retweets, self.only_originals) {
+                (true, true) => {
+                    return Err(anyhow!(
+                        "Only one of `--only-retweets` and `--only-originals` can be used"
+                    ))
+                }
+                (true, false) => Some(true),
+                (false, true) => Some(false),
+                (false, false) => None,
+            },
+            media: match (self.with_media, self.without_media) {
+                (true, true) => {
+                    return Err(anyhow!(
+                        "Only one of `--with-media` and `--without-media` can be used"
+                    ))
+                }
+                (true, false) => Some(true),
+                (false, true) => Some(false),
+                (false, false) => None,
+            },
+            only_replies: self.only_replies,
+            keep_replies_to: strip(&self.keep_replies_to, '@'),
+            mentioning: strip(&self.mentioning, '@'),
+            not_mentioning: strip(&self.not_mentioning, '@'),
+            account: None,
+        })
+    }
+
+    /// Canonical representation of these filters, for comparing runs
+    pub fn canonical(&self) -> String {
+        serde_json::to_string(self).expect("BUG: Filters failed to serialize")
+    }
+
+    /// How many days tweets are kept, if these filters are a retention
+    /// policy, only ever deleting tweets once they're older than that
+    ///
+    /// Anything else, such as `after` on its own, would delete every new
+    /// tweet as soon as it was seen.
+    ///
+    /// Saved filters must already be [resolved][FilterArgs::resolve].
+    pub fn retention(&self) -> Result<u32, NotRetention> {
+        match (self.older_than, &self.before) {
+            (_, Some(_)) => Err(NotRetention::FixedDate),
+            (Some(0), None) => Err(NotRetention::EveryAge),
+            (Some(days), None) => Ok(days),
+            (None, None) => Err(NotRetention::NoAge),
+        }
+    }
+}
+
+/// Why [`FilterArgs`] aren't a retention policy, see
+/// [`FilterArgs::retention`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotRetention {
+    /// No `older_than`, so tweets are deleted no matter how new
+    NoAge,
+
+    /// `older_than` is `0`, matching tweets of every age
+    EveryAge,
+
+    /// `before`, a fixed date rather than an age
+    FixedDate,
+}
+
+/// Filters for selecting tweets to delete
+///
+/// See [`FilterArgs`] for details on each filter
+#[derive(Debug)]
+pub struct Filter {
+    /// Exclude these tweet IDs
+    pub exclude: Vec<TweetId>,
+
+    /// Only tweets created before this, UTC unix time
+    pub created_before: i64,
+
+    /// Only tweets created at or after this, UTC unix time
+    pub created_after: Option<i64>,
+
+    /// Only tweets with at most this many likes
+    pub unless_likes: u32,
+
+    /// Only tweets with at most this many retweets
+    pub unless_retweets: u32,
+
+    /// Only tweets replying to or mentioning this account handle
+    pub conversation_with: Option<String>,
+
+    /// Only tweets with any of these hashtags, if any
+    pub with_hashtags: Vec<String>,
+
+    /// Never tweets with any of these hashtags
+    pub without_hashtags: Vec<String>,
+
+    /// Only tweets mentioning any of these account handles, if any
+    pub mentioning: Vec<String>,
+
+    /// Never tweets mentioning any of these account handles
+    pub not_mentioning: Vec<String>,
+
+    /// Only tweets with at most this weighted engagement score
+    pub unless_engagement: Option<u32>,
+
+    /// Weights used to calculate the engagement score
+    pub engagement_weights: Weights,
+
+    /// Only tweets with at most this many characters
+    pub max_chars: Option<u32>,
+
+    /// Only tweets with at least this many characters
+    pub min_chars: Option<u32>,
+
+    /// Only tweets consisting of these kinds of content, if any
+    pub content_kind: Vec<ContentKind>,
+
+    /// Only tweets with at most this score
+    pub max_score: Option<f64>,
+
+    /// Only tweets containing a term in this wordlist
+    ///
+    /// This is matched outside the database, see [`Filter::load`]
+    pub wordlist: Option<Wordlist>,
+
+    /// Only tweets whose text matches this
+    ///
+    /// This is matched outside the database, see [`Filter::load`]
+    pub matching: Option<Regex>,
+
+    /// Only tweets whose text doesn't match this
+    ///
+    /// This is matched outside the database, see [`Filter::load`]
+    pub not_matching: Option<Regex>,
+
+    /// Whether to include permanently failed tweets
+    pub include_permanent_failures: bool,
+
+    /// Only tweets known to match this expression
+    ///
+    /// This is matched outside the database, see [`Filter::load`]
+    pub expr: Option<Expr>,
+
+    /// Current UTC unix time, for ages in [`Filter::expr`]
+    pub now: i64,
+
+    /// Only retweets if `true`, or only tweets that aren't if `false`
+    ///
+    /// Tweets not known to be either never match.
+    pub retweets: Option<bool>,
+
+    /// Only tweets with media if `true`, or only tweets without if `false`
+    ///
+    /// Tweets not known to be either never match.
+    pub media: Option<bool>,
+
+    /// Only tweets replying to another tweet
+    pub only_replies: bool,
+
+    /// Never tweets replying to these account handles
+    pub keep_replies_to: Vec<String>,
+
+    /// Only tweets belonging to this account ID
+    ///
+    /// This is not set by the user, but by the account being operated on
+    pub account: Option<String>,
+}
+
+/// Order to delete matching tweets in
+///
+/// Runs large enough to span multiple rate limit windows delete the first
+/// tweets in this order first.
+#[derive(ValueEnum, Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Order {
+    /// By tweet ID
+    #[default]
+    Id,
+
+    /// Oldest tweets first
+    Oldest,
+
+    /// Tweets matching the wordlist first, or the bundled starter wordlist
+    /// if there is none, and then oldest first
+    ///
+    /// Matches in a category of the wordlist go first, by priority.
+    Sensitive,
+}
+
+sql_function! {
+    /// First of `x` and `y` that isn't `NULL`
+    fn coalesce(x: Nullable<Text>, y: Nullable<Text>) -> Nullable<Text>;
+}
+
+/// Text of a tweet as displayed, or the raw text for tweets imported before
+/// that was recorded
+///
+/// See [`display_text`](crate::analyze::display_text)
+pub fn text() -> coalesce::HelperType<db::display_text, db::full_text> {
+    coalesce(db::display_text, db::full_text)
+}
+
+/// `n` as a database integer, saturating
+///
+/// Likes, retweets and tweet lengths are never more than [`i32::MAX`], so a
+/// larger limit behaves as it would if it fit.
+fn db_int(n: u32) -> i32 {
+    i32::try_from(n).unwrap_or(i32::MAX)
+}
+
+/// Compile the user provided regular expression `re`
+fn regex(re: &str) -> Result<Regex> {
+    Regex::new(re).map_err(|e| anyhow!("Invalid regular expression `{re}`: {e}"))
+}
+
+/// `values` without any leading `prefix`, such as the `@` of handles
+fn strip(values: &[String], prefix: char) -> Vec<String> {
+    values
+        .iter()
+        .map(|v| v.trim_start_matches(prefix).to_owned())
+        .collect()
+}
+
+/// Where days start, for deciding which tweets are older than some number of
+/// days
+#[derive(ValueEnum, Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Boundary {
+    /// Exactly that many 24 hour days before now, to the second
+    #[default]
+    Rolling,
+
+    /// Midnight UTC, so only tweets from before the start of that UTC day
+    Utc,
+
+    /// Local midnight, so only tweets from before the start of that day in
+    /// the local timezone, or the one given with `--tz`
+    Local,
+}
+
+impl Boundary {
+    /// Tweets created before this are older than `days` days as of `now`
+    ///
+    /// For [`Boundary::Utc`] and [`Boundary::Local`] this is the start of
+    /// the day `days` days before today, so that a tweet is only older than
+    /// `days` days once that many whole days have passed since the day it
+    /// was created. Local days are in the offset of `now`.
+    pub fn cutoff(self, now: OffsetDateTime, days: u32) -> Result<OffsetDateTime> {
+        let off = Duration::days(days.into());
+        let off = now.checked_sub(off).ok_or_else(|| {
+            anyhow!(
+                "Specified offset of {} ({off}) is too far in the past",
+                util::human_dur(off),
+            )
+        })?;
+        Ok(match self {
+            Self::Rolling => off,
+            Self::Utc => off.to_offset(UtcOffset::UTC).replace_time(Time::MIDNIGHT),
+            Self::Local => off.replace_time(Time::MIDNIGHT),
+        })
+    }
+}
+
+/// Weights for likes and retweets when calculating an engagement score
+///
+/// The score is `likes * likes_weight + retweets * retweets_weight`
+///
+/// Parsed from `LIKES:RETWEETS`, eg `1:2`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Weights {
+    pub likes: u32,
+    pub retweets: u32,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            likes: 1,
+            retweets: 2,
+        }
+    }
+}
+
+impl FromStr for Weights {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (likes, retweets) = s
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Expected weights as `LIKES:RETWEETS`, got `{s}`"))?;
+        let weight = |w: &str| -> Result<u32> {
+            let w: u32 = w.trim().parse()?;
+            if i32::try_from(w).is_err() {
+                return Err(anyhow!("Weight {w} is too large, at most {}", i32::MAX));
+            }
+            Ok(w)
+        };
+        Ok(Self {
+            likes: weight(likes)?,
+            retweets: weight(retweets)?,
+        })
+    }
+}
+
+impl Filter {
+    /// Whether this filter keeps tweets based on their likes or retweets, as
+    /// recorded in the archive
+    pub fn uses_engagement(&self) -> bool {
+        self.unless_likes > 0
+            || self.unless_retweets > 0
+            || self.unless_engagement.is_some()
+            || self.max_score.is_some()
+    }
+
+    /// Build a query selecting every tweet matching this filter
+    ///
+    /// Deleted and protected tweets never match, nor do permanently failed
+    /// tweets unless [`Filter::include_permanent_failures`] is set.
+    pub fn query(&self) -> db::BoxedQuery<'_, Sqlite> {
+        use db::dsl::*;
+        let mut query = tweets
+            .filter(created_before(self.created_before))
+            .filter(deleted.eq(false))
+            .filter(protected.eq(false))
+            .filter(diesel::dsl::not(id_str.eq_any(&self.exclude)))
+            .filter(likes.le(db_int(self.unless_likes)))
+            .filter(retweets.le(db_int(self.unless_retweets)))
+            .into_boxed();
+
+        if let Some(after) = self.created_after {
+            query = query.filter(created_at.ge(after));
+        }
+
+        if !self.include_permanent_failures {
+            query = query.filter(permanently_failed.eq(false));
+        }
+
+        if let Some(retweets_only) = self.retweets {
+            query = query.filter(is_retweet.eq(retweets_only));
+        }
+
+        if let Some(with_media) = self.media {
+            query = query.filter(has_media.eq(with_media));
+        }
+
+        if self.only_replies {
+            query = query.filter(in_reply_to_status_id.is_not_null());
+        }
+
+        if !self.keep_replies_to.is_empty() {
+            // Handles are compared case insensitively by the database
+            query = query.filter(
+                in_reply_to_screen_name.is_null().or(diesel::dsl::not(
+                    in_reply_to_screen_name
+                        .assume_not_null()
+                        .eq_any(&self.keep_replies_to),
+                )),
+            );
+        }
+
+        // Not `score`, which is a column
+        if let Some(max_engagement) = self.unless_engagement {
+            let w = self.engagement_weights;
+            // SQLite does the arithmetic in 64 bits, so it can't overflow
+            query = query.filter(
+                (likes * db_int(w.likes) + retweets * db_int(w.retweets))
+                    .le(db_int(max_engagement)),
+            );
+        }
+
+        // Tweets imported without their text never match these
+        if let Some(max) = self.max_chars {
+            query = query.filter(length(text()).le(db_int(max)));
+        }
+        if let Some(min) = self.min_chars {
+            query = query.filter(length(text()).ge(db_int(min)));
+        }
+
+        if !self.content_kind.is_empty() {
+            let kinds: Vec<&str> = self.content_kind.iter().map(|k| k.as_str()).collect();
+            query = query.filter(content_kind.eq_any(kinds));
+        }
+
+        // Unscored tweets never match
+        if let Some(max) = self.max_score {
+            query = query.filter(score.le(max));
+        }
+
+        if let Some(account) = &self.account {
+            query = query.filter(account_id.eq(account));
+        }
+
+        if let Some(handle) = &self.conversation_with {
+            // Handles are compared case insensitively by the database
+            let handle = handle.trim_start_matches('@');
+            let mentioned = mdb::table
+                .filter(mdb::dsl::screen_name.eq(handle))
+                .select(mdb::dsl::tweet_id);
+            query = query.filter(
+                in_reply_to_screen_name
+                    .assume_not_null()
+                    .eq(handle)
+                    .or(id_str.eq_any(mentioned)),
+            );
+        }
+
+        // Hashtags are compared case insensitively by the database
+        if !self.with_hashtags.is_empty() {
+            let tagged = hdb::table
+                .filter(hdb::dsl::hashtag.eq_any(&self.with_hashtags))
+                .select(hdb::dsl::tweet_id);
+            query = query.filter(id_str.eq_any(tagged));
+        }
+        if !self.without_hashtags.is_empty() {
+            let tagged = hdb::table
+                .filter(hdb::dsl::hashtag.eq_any(&self.without_hashtags))
+                .select(hdb::dsl::tweet_id);
+            query = query.filter(diesel::dsl::not(id_str.eq_any(tagged)));
+        }
+
+        // Handles are compared case insensitively by the database
+        if !self.mentioning.is_empty() {
+            let mentioned = mdb::table
+                .filter(mdb::dsl::screen_name.eq_any(&self.mentioning))
+                .select(mdb::dsl::tweet_id);
+            query = query.filter(id_str.eq_any(mentioned));
+        }
+        if !self.not_mentioning.is_empty() {
+            let mentioned = mdb::table
+                .filter(mdb::dsl::screen_name.eq_any(&self.not_mentioning))
+                .select(mdb::dsl::tweet_id);
+            query = query.filter(diesel::dsl::not(id_str.eq_any(mentioned)));
+        }
+
+        query
+    }
+
+    /// Load the IDs of every tweet matching this filter, in `order`
+    ///
+    /// Unlike [`Filter::query`] this also applies filters that can't be
+    /// done by the database, such as [`Filter::wordlist`],
+    /// [`Filter::matching`], and [`Filter::expr`]
+    ///
+    /// Edited tweets are followed by their other versions, see
+    /// [`Filter::with_versions`].
+    pub fn load(&self, conn: &mut SqliteConnection, order: Order) -> Result<Vec<TweetId>> {
+        use db::dsl::*;
+        type Row = (
+            TweetId,
+            Option<String>,
+            i64,
+            i32,
+            i32,
+            Option<f64>,
+            Option<bool>,
+            Option<bool>,
+            bool,
+        );
+        let query = self.query().select((
+            id_str,
+            text(),
+            created_at,
+            likes,
+            retweets,
+            score,
+            has_media,
+            is_retweet,
+            in_reply_to_status_id.is_not_null(),
+        ));
+        let rows: Vec<Row> = match order {
+            Order::Id => query.order(by_id()).load(conn)?,
+            Order::Oldest | Order::Sensitive => query
+                .order(created_at.asc())
+                .then_order_by(by_id())
+                .load(conn)?,
+        };
+
+        let mut rows: Vec<(TweetId, Option<String>)> = rows
+            .into_iter()
+            .filter(
+                |(
+                    _,
+                    text,
+                    created,
+                    like_count,
+                    retweet_count,
+                    tweet_score,
+                    media,
+                    retweet,
+                    reply,
+                )| {
+                    let facts = Facts {
+                        age: self.now - created,
+                        likes: *like_count,
+                        retweets: *retweet_count,
+                        score: *tweet_score,
+                        text: text.as_deref(),
+                        has_media: *media,
+                        is_retweet: *retweet,
+                        is_reply: *reply,
+                    };
+                    self.matches_text(text.as_deref())
+                        && self
+                            .expr
+                            .as_ref()
+                            .map_or(true, |e| e.eval(&facts) == Some(true))
+                },
+            )
+            .map(|(id, text, ..)| (id, text))
+            .collect();
+
+        if let Order::Sensitive = order {
+            let starter;
+            let wordlist = match &self.wordlist {
+                Some(wordlist) => wordlist,
+                None => {
+                    starter = Wordlist::load(None, true)?.expect("BUG: Starter wordlist missing");
+                    &starter
+                }
+            };
+            // Stable, so each group stays oldest first
+            rows.sort_by_key(|(_, text)| match text.as_deref() {
+                Some(t) if wordlist.is_match(t) => {
+                    (false, wordlist.priority(t).unwrap_or(u32::MAX))
+                }
+                _ => (true, u32::MAX),
+            });
+        }
+
+        let ids = rows.into_iter().map(|(id, _)| id).collect();
+        self.with_versions(conn, ids)
+    }
+
+    /// `ids`, each followed by the other versions of it if it was edited, so
+    /// deleting a tweet doesn't leave its other versions up
+    ///
+    /// Versions that could never match, because they're deleted, protected,
+    /// excluded, or from another account, are left out.
+    fn with_versions(
+        &self,
+        conn: &mut SqliteConnection,
+        ids: Vec<TweetId>,
+    ) -> Result<Vec<TweetId>> {
+        use db::dsl::*;
+        let mut query = tweets
+            .filter(initial_id.is_not_null())
+            .filter(deleted.eq(false))
+            .filter(protected.eq(false))
+            .filter(diesel::dsl::not(id_str.eq_any(&self.exclude)))
+            .select((id_str, initial_id.assume_not_null()))
+            .into_boxed();
+        if let Some(account) = &self.account {
+            query = query.filter(account_id.eq(account));
+        }
+        if !self.include_permanent_failures {
+            query = query.filter(permanently_failed.eq(false));
+        }
+        let edited: Vec<(TweetId, TweetId)> = query.order(by_id()).load(conn)?;
+        if edited.is_empty() {
+            return Ok(ids);
+        }
+
+        let mut versions: HashMap<&TweetId, Vec<&TweetId>> = HashMap::new();
+        for (id, initial) in &edited {
+            versions.entry(initial).or_default().push(id);
+        }
+        let initial: HashMap<&TweetId, &TweetId> =
+            edited.iter().map(|(id, initial)| (id, initial)).collect();
+        let mut seen: HashSet<TweetId> = ids.iter().cloned().collect();
+        let mut out = Vec::with_capacity(ids.len());
+        for id in ids {
+            let others = initial.get(&id).and_then(|i| versions.get(i));
+            out.push(id);
+            for other in others.into_iter().flatten() {
+                if seen.insert((*other).clone()) {
+                    out.push((*other).clone());
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// Whether the tweet text `text` matches the filters that can't be done
+    /// by the database
+    ///
+    /// Tweets imported without their text never match text filters,
+    /// including [`Filter::not_matching`], as it can't be known what they said
+    fn matches_text(&self, text: Option<&str>) -> bool {
+        let filtered =
+            self.wordlist.is_some() || self.matching.is_some() || self.not_matching.is_some();
+        let text = match text {
+            Some(text) => text,
+            None => return !filtered,
+        };
+        self.wordlist.as_ref().map_or(true, |w| w.is_match(text))
+            && self.matching.as_ref().map_or(true, |re| re.is_match(text))
+            && !self
+                .not_matching
+                .as_ref()
+                .map_or(false, |re| re.is_match(text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use time::macros::datetime;
+
+    use super::*;
+    use crate::{
+        clock::{Clock, FrozenClock},
+        db::{add_tweets, create_db, protect},
+        models::Tweet,
+    };
+
+    const NOW: FrozenClock = FrozenClock(datetime!(2023-03-10 1:30 +02:00));
+
+    #[test]
+    fn rolling_is_to_the_second() {
+        let cut = Boundary::Rolling.cutoff(NOW.now(), 2).unwrap();
+        assert_eq!(cut, datetime!(2023-03-08 1:30 +02:00));
+    }
+
+    #[test]
+    fn utc_is_utc_midnight() {
+        // Still the 9th in UTC
+        let cut = Boundary::Utc.cutoff(NOW.now(), 2).unwrap();
+        assert_eq!(cut, datetime!(2023-03-07 0:00 UTC));
+    }
+
+    #[test]
+    fn local_is_midnight_in_offset() {
+        let cut = Boundary::Local.cutoff(NOW.now(), 2).unwrap();
+        assert_eq!(cut, datetime!(2023-03-08 0:00 +02:00));
+    }
+
+    #[test]
+    fn too_far_back() {
+        for b in [Boundary::Rolling, Boundary::Utc, Boundary::Local] {
+            assert!(b.cutoff(NOW.now(), u32::MAX).is_err());
+        }
+    }
+
+    #[test]
+    fn retention_needs_an_age() {
+        let retention = |args: &str| {
+            let args: FilterArgs = serde_json::from_str(args).unwrap();
+            args.retention()
+        };
+        assert_eq!(retention(r#"{"older_than": 90}"#), Ok(90));
+        assert_eq!(
+            retention(r#"{"older_than": 90, "after": "2019-01-01"}"#),
+            Ok(90)
+        );
+        assert_eq!(
+            retention(r#"{"older_than": 0}"#),
+            Err(NotRetention::EveryAge)
+        );
+        assert_eq!(
+            retention(r#"{"after": "2019-01-01"}"#),
+            Err(NotRetention::NoAge)
+        );
+        assert_eq!(retention(r#"{"newer_than": 7}"#), Err(NotRetention::NoAge));
+        assert_eq!(
+            retention(r#"{"before": "2019-01-01"}"#),
+            Err(NotRetention::FixedDate)
+        );
+    }
+
+    #[test]
+    fn load_adds_other_versions() {
+        let conn = &mut create_db(Path::new(":memory:")).unwrap();
+        let tweet = |id: &str, initial: Option<&str>, likes| Tweet {
+            initial_id: initial.map(|i| i.parse().unwrap()),
+            ..Tweet::new(id.parse().unwrap(), 0, likes, 0, "0".into())
+        };
+        let tweets = [
+            // Only the first version matches, the rest got more likes
+            tweet("1", Some("1"), 0),
+            tweet("2", Some("1"), 5),
+            tweet("3", Some("1"), 5),
+            tweet("4", None, 5),
+            tweet("5", None, 0),
+        ];
+        add_tweets(conn, &NOW, &tweets).unwrap();
+        protect(conn, &NOW, [tweets[2].id_str.clone()].iter(), None).unwrap();
+
+        let args: FilterArgs = serde_json::from_str(r#"{"older_than": 0}"#).unwrap();
+        let filter = args.build(NOW.now()).unwrap();
+        let ids: Vec<String> = filter
+            .load(conn, Order::Id)
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(ids, ["1", "2", "5"]);
+    }
+}
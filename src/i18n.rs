@@ -0,0 +1,711 @@
+//! Translations of user facing messages
+//!
+//! Messages are written in English with named placeholders, eg
+//! `tr!("Deleted {total} tweets", total = total)`, and looked up by that
+//! English text in the catalog for the chosen language. Anything not yet
+//! translated falls back to English.
+//!
+//! Placeholders are filled in after translating, so translations can put them
+//! in a different order, but must keep their names.
+use std::{
+    env,
+    fmt::Display,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+use clap::ValueEnum;
+
+/// Translate a message, filling in its placeholders
+///
+/// `tr!("Deleted {total} tweets from @{handle}", total = n, handle = name)`
+macro_rules! tr {
+    ($msg:literal $(, $name:ident = $value:expr)* $(,)?) => {
+        $crate::i18n::translate(
+            $msg,
+            &[$((stringify!($name), &$value as &dyn ::std::fmt::Display)),*],
+        )
+    };
+}
+
+/// Language for messages
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Lang {
+    /// English
+    En,
+
+    /// Spanish
+    Es,
+}
+
+/// The [`Lang`] used by [`translate`], see [`set_lang`]
+static LANG: AtomicU8 = AtomicU8::new(Lang::En as u8);
+
+impl Lang {
+    /// The language of the environment, from `LC_ALL`, `LC_MESSAGES`, or
+    /// `LANG`, eg `es_ES.UTF-8`, or English if unknown
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|locale| !locale.is_empty())
+            .and_then(|locale| {
+                let lang = locale.split(['_', '.', '@']).next()?;
+                Self::from_str(lang, true).ok()
+            })
+            .unwrap_or(Self::En)
+    }
+
+    /// Translations of English messages, empty for English
+    fn catalog(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Self::En => &[],
+            Self::Es => ES,
+        }
+    }
+}
+
+/// Use `lang` for every message from now on
+pub fn set_lang(lang: Lang) {
+    LANG.store(lang as u8, Ordering::Relaxed);
+}
+
+/// Translate `msg` into the current language, see [`set_lang`], and fill in
+/// its `{name}` placeholders from `args`
+///
+/// Use [`tr!`] instead of calling this directly.
+pub fn translate(msg: &'static str, args: &[(&str, &dyn Display)]) -> String {
+    let lang = match LANG.load(Ordering::Relaxed) {
+        x if x == Lang::Es as u8 => Lang::Es,
+        _ => Lang::En,
+    };
+    let template = lang
+        .catalog()
+        .iter()
+        .find(|(en, _)| *en == msg)
+        .map_or(msg, |(_, translated)| translated);
+    let mut out = template.to_owned();
+    for (name, value) in args {
+        out = out.replace(&format!("{{{name}}}"), &value.to_string());
+    }
+    out
+}
+
+/// Spanish translations
+static ES: &[(&str, &str)] = &[
+    // logging
+    ("Warning: {message}", "Aviso: {message}"),
+    // global options
+    (
+        "`--now` can't be used with commands that send requests to twitter, try \
+         `delete --dry-run`",
+        "`--now` no se puede usar con comandos que envían peticiones a twitter, prueba \
+         `delete --dry-run`",
+    ),
+    (
+        "Missing $HOME, choose a data directory with `--data-dir`",
+        "Falta $HOME, elige un directorio de datos con `--data-dir`",
+    ),
+    (
+        "Invalid UTC offset `{offset}`, expected eg `+02:00`: {error}",
+        "Desfase UTC `{offset}` no válido, se esperaba p. ej. `+02:00`: {error}",
+    ),
+    (
+        "Couldn't find the local time zone, using UTC. Choose one with `--tz` or `--utc-offset`",
+        "No se encontró la zona horaria local, se usa UTC. Elige una con `--tz` o `--utc-offset`",
+    ),
+    // import
+    (
+        "Imported {added} tweets. Total tweets {total}",
+        "Importados {added} tuits. Total de tuits: {total}",
+    ),
+    (
+        "Imported {likes} liked tweets",
+        "Importados {likes} tuits con me gusta",
+    ),
+    (
+        "Imported {messages} direct messages",
+        "Importados {messages} mensajes directos",
+    ),
+    (
+        "Couldn't read the account from {path}: {error}\nGive it with `import --account-id ID \
+         --handle NAME` instead",
+        "No se pudo leer la cuenta de {path}: {error}\nIndícala en su lugar con `import \
+         --account-id ID --handle NOMBRE`",
+    ),
+    (
+        "Invalid Twitter account ID 0 for @{handle} {name}\nGive the real one with `import \
+         --account-id ID --handle {handle}` instead",
+        "ID de cuenta de Twitter 0 no válido para @{handle} {name}\nIndica el real en su lugar \
+         con `import --account-id ID --handle {handle}`",
+    ),
+    (
+        "Invalid Twitter account ID `{id}`, expected a number",
+        "ID de cuenta de Twitter `{id}` no válido, se esperaba un número",
+    ),
+    (
+        "{path} is for @{archived}, not @{handle}",
+        "{path} es de @{archived}, no de @{handle}",
+    ),
+    (
+        "--handle @{handle} doesn't match --account @{want}",
+        "--handle @{handle} no coincide con --account @{want}",
+    ),
+    (
+        "{path} is for @{archived}, not @{handle}, archives for different accounts must be \
+         imported separately",
+        "{path} es de @{archived}, no de @{handle}, los archivos de cuentas distintas se deben \
+         importar por separado",
+    ),
+    (
+        "Dropped indexes in {dropped}, imported in {imported}, rebuilt indexes in {rebuilt}",
+        "Índices eliminados en {dropped}, importado en {imported}, índices reconstruidos en \
+         {rebuilt}",
+    ),
+    (
+        "{added} new tweets, {updated} with newer likes or retweets",
+        "{added} tuits nuevos, {updated} con me gusta o retuits más recientes",
+    ),
+    (
+        "Marked {marked} tweets as deleted, out of {count} tweet IDs in {path}",
+        "Marcados {marked} tuits como eliminados, de {count} IDs de tuits en {path}",
+    ),
+    (
+        "Marked {marked} tweets as protected, out of {count} tweet IDs in {path}",
+        "Marcados {marked} tuits como protegidos, de {count} IDs de tuits en {path}",
+    ),
+    // check
+    (
+        "All tweets have already been checked",
+        "Ya se han comprobado todos los tuits",
+    ),
+    (
+        "Checking whether {count} tweets were already deleted, out of {total} total tweets, as \
+         @{handle}",
+        "Comprobando si {count} tuits ya fueron eliminados, de {total} tuits en total, como \
+         @{handle}",
+    ),
+    (
+        "Marked {gone} tweets as already deleted",
+        "Marcados {gone} tuits como ya eliminados",
+    ),
+    (
+        "Marked {total} total tweets as already deleted from twitter",
+        "Marcados {total} tuits en total como ya eliminados de twitter",
+    ),
+    (
+        "Twitter left {count} tweets out of its responses, they are still unchecked, run \
+         `check` to try again",
+        "Twitter omitió {count} tuits de sus respuestas, siguen sin comprobar, ejecuta \
+         `check` para volver a intentarlo",
+    ),
+    // delete
+    (
+        "Nothing matches your filters ({total} tweets total, {gone} already deleted)",
+        "Ningún tuit coincide con tus filtros ({total} tuits en total, {gone} ya eliminados)",
+    ),
+    (
+        "Hint: Import your twitter archive first with `import`",
+        "Sugerencia: Importa primero tu archivo de twitter con `import`",
+    ),
+    (
+        "Hint: Every tweet is already deleted, or protected. See `protect list`",
+        "Sugerencia: Todos los tuits ya están eliminados o protegidos. Consulta `protect list`",
+    ),
+    (
+        "Hint: Only tweets older than `--older-than` days, with at most `--unless-likes` \
+         likes\nand `--unless-retweets` retweets match, both of which default to 0",
+        "Sugerencia: Solo coinciden los tuits con más de `--older-than` días, con como mucho \
+         `--unless-likes` me gusta\ny `--unless-retweets` retuits, ambos 0 por defecto",
+    ),
+    (
+        "{count} tweets would be deleted from @{handle}",
+        "Se eliminarían {count} tuits de @{handle}",
+    ),
+    ("For example", "Por ejemplo"),
+    (
+        "{count} tweets from @{handle} will be deleted, created between {first} and {last}",
+        "Se eliminarán {count} tuits de @{handle}, creados entre {first} y {last}",
+    ),
+    (
+        "`--older-than 0` matches tweets of every age",
+        "`--older-than 0` coincide con tuits de cualquier antigüedad",
+    ),
+    ("This CAN NOT be undone.", "Esto NO se puede deshacer."),
+    (
+        "Warning: {count} of these tweets were posted less than a week before their archive was \
+         generated, or come from an archive without a date, so their likes and retweets may be \
+         too low for your filters to keep them",
+        "Aviso: {count} de estos tuits se publicaron menos de una semana antes de generarse su \
+         archivo, o vienen de un archivo sin fecha, así que sus me gusta y retuits pueden ser \
+         demasiado bajos para que tus filtros los conserven",
+    ),
+    (
+        "Warning: The newest archive for @{handle} is from {date}, tweets posted since then \
+         aren't imported and won't be deleted, import a newer archive first",
+        "Aviso: El archivo más reciente de @{handle} es del {date}, los tuits publicados desde \
+         entonces no están importados y no se eliminarán, importa primero un archivo más reciente",
+    ),
+    (
+        "Type `{phrase}` to continue: ",
+        "Escribe `{phrase}` para continuar: ",
+    ),
+    (
+        "Confirmation did not match, nothing was deleted",
+        "La confirmación no coincide, no se eliminó nada",
+    ),
+    (
+        "Exported {exported} tweets to {path}",
+        "Exportados {exported} tuits a {path}",
+    ),
+    (
+        "Deleting tweets from @{handle}",
+        "Eliminando tuits de @{handle}",
+    ),
+    (
+        "Rate limited, waiting until {time} ({secs} seconds)",
+        "Límite de peticiones alcanzado, esperando hasta las {time} ({secs} segundos)",
+    ),
+    ("Deleted tweet {id}", "Tuit eliminado {id}"),
+    ("Unretweeted {id}", "Retuit deshecho {id}"),
+    (
+        "Already deleted (re)tweet? {id}",
+        "¿(Re)tuit ya eliminado? {id}",
+    ),
+    (
+        "Failed to unretweet {id}",
+        "No se pudo deshacer el retuit {id}",
+    ),
+    (
+        "Giving up on unretweeting {id}",
+        "Se abandona deshacer el retuit {id}",
+    ),
+    ("Deleted {total} tweets", "Eliminados {total} tuits"),
+    (
+        "No interrupted `delete` run to resume",
+        "No hay ninguna ejecución de `delete` interrumpida que reanudar",
+    ),
+    (
+        "No previous run with the same tag and filters",
+        "No hay ninguna ejecución anterior con la misma etiqueta y filtros",
+    ),
+    (
+        "{count} tweets newly matched since run #{run}, out of {total} total matches",
+        "{count} tuits coinciden desde la ejecución #{run}, de {total} coincidencias en total",
+    ),
+    (
+        "Couldn't export to {path}: {error}",
+        "No se pudo exportar a {path}: {error}",
+    ),
+    ("{id} deleted", "{id} eliminado"),
+    (
+        "{id} failed, {failures} failures",
+        "{id} falló, {failures} fallos",
+    ),
+    (
+        "Simulated run #{run} would change {count} tweets, {deleted} deleted",
+        "La ejecución simulada #{run} cambiaría {count} tuits, {deleted} eliminados",
+    ),
+    // watch
+    ("Nothing to delete", "Nada que eliminar"),
+    (
+        "Pass failed, trying again next pass: {error}",
+        "La pasada falló, se volverá a intentar en la siguiente: {error}",
+    ),
+    (
+        "These filters would delete {count} tweets from @{handle} that the filters `watch` last \
+         ran with kept",
+        "Estos filtros eliminarían {count} tuits de @{handle} que los filtros con los que se \
+         ejecutó `watch` por última vez conservaban",
+    ),
+    (
+        "Type `{hash}` to continue, or give it with `--accept-policy`: ",
+        "Escribe `{hash}` para continuar, o indícalo con `--accept-policy`: ",
+    ),
+    ("Deleting, {count} remaining", "Eliminando, quedan {count}"),
+    (
+        "Rate limited, {count} remaining",
+        "Límite de peticiones alcanzado, quedan {count}",
+    ),
+    ("Sleeping until {time}", "Durmiendo hasta el {time}"),
+    (
+        "`watch` deletes without asking, so won't delete tweets of every age with `--older-than 0`",
+        "`watch` elimina sin preguntar, así que no eliminará tuits de cualquier antigüedad con \
+         `--older-than 0`",
+    ),
+    (
+        "`watch` deletes without asking, so needs `--older-than` to keep new tweets for that many \
+         days",
+        "`watch` elimina sin preguntar, así que necesita `--older-than` para conservar los tuits \
+         nuevos esos días",
+    ),
+    (
+        "`watch` keeps deleting tweets as they get older, so needs `--older-than` instead of \
+         `--before`",
+        "`watch` sigue eliminando tuits a medida que envejecen, así que necesita `--older-than` \
+         en lugar de `--before`",
+    ),
+    // likes and direct messages
+    (
+        "No liked tweets left to unlike were created before {date}",
+        "No quedan tuits con me gusta por quitar creados antes del {date}",
+    ),
+    (
+        "Removing likes from @{handle}",
+        "Quitando los me gusta de @{handle}",
+    ),
+    ("Already unliked? {id}", "¿Me gusta ya quitado? {id}"),
+    ("Unliked tweet {id}", "Me gusta quitado del tuit {id}"),
+    (
+        "Unliked {unliked} tweets",
+        "Quitado el me gusta de {unliked} tuits",
+    ),
+    (
+        "No direct messages left to delete are older than {days} days",
+        "No quedan mensajes directos por eliminar con más de {days} días",
+    ),
+    (
+        "Deleting direct messages from @{handle}",
+        "Eliminando mensajes directos de @{handle}",
+    ),
+    (
+        "Already deleted message? {id}",
+        "¿Mensaje ya eliminado? {id}",
+    ),
+    ("Deleted message {id}", "Mensaje eliminado {id}"),
+    (
+        "Deleted {gone} direct messages",
+        "Eliminados {gone} mensajes directos",
+    ),
+    (
+        "Import your twitter archive first with `import`",
+        "Importa primero tu archivo de twitter con `import`",
+    ),
+    // nuke
+    (
+        "This will delete, from @{handle}:\n{tweets} tweets\n{likes} likes\n{messages} direct \
+         messages",
+        "Esto eliminará, de @{handle}:\n{tweets} tuits\n{likes} me gusta\n{messages} mensajes \
+         directos",
+    ),
+    ("And then post:", "Y luego publicará:"),
+    ("Backed up to {path}", "Copia de seguridad en {path}"),
+    (
+        "Deleted {total} tweets, {unliked} likes, and {gone} direct messages",
+        "Eliminados {total} tuits, {unliked} me gusta y {gone} mensajes directos",
+    ),
+    (
+        "Posted farewell tweet {id}",
+        "Tuit de despedida publicado {id}",
+    ),
+    // notifications
+    ("`{name}` finished", "`{name}` terminó"),
+    ("twitter_delete is done", "twitter_delete ha terminado"),
+    ("`{name}` failed", "`{name}` falló"),
+    ("Rate limited", "Límite de peticiones alcanzado"),
+    (
+        "Waiting {minutes} minutes for Twitter before continuing",
+        "Esperando {minutes} minutos a Twitter antes de continuar",
+    ),
+    // stage and commit
+    (
+        "Staged batch #{run} \"{batch}\" with {tweets} tweets",
+        "Lote #{run} \"{batch}\" preparado con {tweets} tuits",
+    ),
+    (
+        "Nothing left to delete in run #{run}, every tweet is already deleted or protected",
+        "No queda nada por eliminar en la ejecución #{run}, todos los tuits ya están eliminados \
+         o protegidos",
+    ),
+    (
+        "Deleting tweets from @{handle} in batch #{run} \"{batch}\"",
+        "Eliminando tuits de @{handle} en el lote #{run} \"{batch}\"",
+    ),
+    (
+        "Resuming {command} run #{run} for @{handle}",
+        "Reanudando la ejecución #{run} de {command} para @{handle}",
+    ),
+    (
+        "Progress has been saved. Once your keys are updated, resume with\ntwitter_delete commit \
+         {run}",
+        "Se ha guardado el progreso. Cuando tus claves estén actualizadas, reanuda \
+         con\ntwitter_delete commit {run}",
+    ),
+    // review
+    (
+        "Review cancelled, nothing was changed",
+        "Revisión cancelada, no se cambió nada",
+    ),
+    ("Protected {kept} tweets", "Protegidos {kept} tuits"),
+    (
+        "Staged batch #{run} \"review\" with {count} tweets, delete them with `commit {run}`",
+        "Lote #{run} \"review\" preparado con {count} tuits, elimínalos con `commit {run}`",
+    ),
+    (
+        "Tweet {at} of {total}, {keep} to keep, {delete} to delete",
+        "Tuit {at} de {total}, {keep} para conservar, {delete} para eliminar",
+    ),
+    (
+        "{likes} likes {retweets} retweets",
+        "{likes} me gusta {retweets} retuits",
+    ),
+    ("KEEP", "CONSERVAR"),
+    ("DELETE", "ELIMINAR"),
+    ("undecided", "sin decidir"),
+    (
+        "(text not imported, import the archive again to see it)",
+        "(texto no importado, importa el archivo de nuevo para verlo)",
+    ),
+    (
+        "k keep, d delete, u undo, n/p next/previous, q finish and save, Esc cancel",
+        "k conservar, d eliminar, u deshacer, n/p siguiente/anterior, q terminar y guardar, Esc \
+         cancelar",
+    ),
+    // backup and post
+    (
+        "Copied {files} media files from {tweets} tweets to {path}",
+        "Copiados {files} archivos multimedia de {tweets} tuits a {path}",
+    ),
+    (
+        "Posted tweet {id} as @{handle}",
+        "Tuit {id} publicado como @{handle}",
+    ),
+    // score, export, and filters
+    ("Scoring tweets", "Puntuando tuits"),
+    ("Scored {count} tweets", "Puntuados {count} tuits"),
+    (
+        "Saved filters as `{name}`",
+        "Filtros guardados como `{name}`",
+    ),
+    (
+        "No filters are saved as `{name}`",
+        "No hay filtros guardados como `{name}`",
+    ),
+    (
+        "Removed saved filters `{name}`",
+        "Eliminados los filtros guardados `{name}`",
+    ),
+    // protect
+    (
+        "Removed protection from {unmarked} tweets",
+        "Quitada la protección de {unmarked} tuits",
+    ),
+    (
+        "Removed protection from {unmarked} tweets, out of {count} tweet IDs in {path}",
+        "Quitada la protección de {unmarked} tuits, de {count} IDs de tuits en {path}",
+    ),
+    (
+        "Protected {marked} tweets, out of {count} tweet IDs in {path}",
+        "Protegidos {marked} tuits, de {count} IDs de tuits en {path}",
+    ),
+    (
+        "Exported {count} protected tweets to {path}",
+        "Exportados {count} tuits protegidos a {path}",
+    ),
+    // search, serve, and show
+    (
+        "No tweets match `{query}`",
+        "Ningún tuit coincide con `{query}`",
+    ),
+    (
+        "{count} tweets match `{query}`",
+        "{count} tuits coinciden con `{query}`",
+    ),
+    (
+        "`serve` is only supported on unix",
+        "`serve` solo funciona en unix",
+    ),
+    (
+        "Invalid tweet ID or URL {id}",
+        "ID o URL de tuit no válido {id}",
+    ),
+    (
+        "Tweet {id} has not been imported",
+        "El tuit {id} no se ha importado",
+    ),
+    (
+        "Tweet {id} by account {account}\nCreated: {created}\nLikes: {likes}\nRetweets: \
+         {retweets}\nDeleted: {deleted}\nChecked: {checked}\nProtected: {protected}\nFailed \
+         deletions: {failures}\nPermanently failed: {permanently_failed}",
+        "Tuit {id} de la cuenta {account}\nCreado: {created}\nMe gusta: {likes}\nRetuits: \
+         {retweets}\nEliminado: {deleted}\nComprobado: {checked}\nProtegido: \
+         {protected}\nEliminaciones fallidas: {failures}\nFallo permanente: {permanently_failed}",
+    ),
+    ("Note: {note}", "Nota: {note}"),
+    ("History:", "Historial:"),
+    // reports, doctor, and history
+    (
+        "No report for run #{run}",
+        "No hay informe de la ejecución #{run}",
+    ),
+    (" (unfinished)", " (sin terminar)"),
+    (
+        "#{run} {command}{tag} at {started}{unfinished}: {matched} matched, {deleted} deleted, \
+         {not_deleted} not deleted, {failed} failed, {limits} rate limits, {path}",
+        "#{run} {command}{tag} el {started}{unfinished}: {matched} coincidencias, {deleted} \
+         eliminados, {not_deleted} no eliminados, {failed} fallidos, {limits} límites de \
+         peticiones, {path}",
+    ),
+    (
+        "{count} tweets were left out of the response",
+        "se omitieron {count} tuits de la respuesta",
+    ),
+    (
+        "{count} tweets were answered for instead of {requested}",
+        "se respondió por {count} tuits en lugar de {requested}",
+    ),
+    (
+        "every tweet was said to be deleted, which may be twitter failing lookups rather than the \
+         tweets being deleted",
+        "todos los tuits se dieron por eliminados, lo que puede deberse a que twitter falle al \
+         consultarlos y no a que estén eliminados",
+    ),
+    (
+        "Run #{run} lookup {batch}: Asked about {requested} tweets, {returned} exist, {gone} \
+         deleted, {missing} missing, {why}",
+        "Ejecución #{run} consulta {batch}: Se preguntó por {requested} tuits, {returned} \
+         existen, {gone} eliminados, {missing} omitidos, {why}",
+    ),
+    (
+        "Run #{run} {command}: Twitter deleted {count} more tweets than were marked deleted in \
+         the database",
+        "Ejecución #{run} {command}: Twitter eliminó {count} tuits más de los marcados como \
+         eliminados en la base de datos",
+    ),
+    ("No problems found", "No se encontraron problemas"),
+    (
+        "WARNING: Run #{run} deleted {discrepancy} more tweets on twitter than in the database",
+        "AVISO: La ejecución #{run} eliminó {discrepancy} tuits más en twitter que en la base de \
+         datos",
+    ),
+    (", peak memory {mib} MiB", ", memoria máxima {mib} MiB"),
+    (
+        "at {started}: Read {rows} tweets, parsed in {parse}, inserted in {insert}, {per_sec} \
+         tweets/sec{memory}",
+        "el {started}: Leídos {rows} tuits, analizados en {parse}, insertados en {insert}, \
+         {per_sec} tuits/s{memory}",
+    ),
+    (
+        "at {started}: Marked {count} tweets as already deleted",
+        "el {started}: Marcados {count} tuits como ya eliminados",
+    ),
+    (
+        "at {started}: Deleted {count} tweets",
+        "el {started}: Eliminados {count} tuits",
+    ),
+    (
+        "at {started}: Not yet committed",
+        "el {started}: Aún sin confirmar",
+    ),
+    ("at {started}: Interrupted", "el {started}: Interrumpida"),
+    // auth and accounts
+    (
+        "API keys for @{handle} are now stored in the OS keyring",
+        "Las claves de API de @{handle} ahora se guardan en el llavero del sistema",
+    ),
+    (
+        "{path} is no longer used, and can be deleted",
+        "{path} ya no se usa, y se puede eliminar",
+    ),
+    (
+        "Removed the API keys for @{handle} from the OS keyring, the default keys will be used",
+        "Eliminadas las claves de API de @{handle} del llavero del sistema, se usarán las claves \
+         por defecto",
+    ),
+    ("unknown", "desconocido"),
+    (
+        "API keys for @{handle} have {level} access",
+        "Las claves de API de @{handle} tienen acceso {level}",
+    ),
+    ("allowed", "permitido"),
+    ("NOT allowed", "NO permitido"),
+    (
+        "Deleting and posting need read-write access, change the app permissions and authorize \
+         again with `auth`",
+        "Eliminar y publicar necesitan acceso de lectura y escritura, cambia los permisos de la \
+         aplicación y vuelve a autorizar con `auth`",
+    ),
+    (
+        "Open this URL, authorize the app, and enter the PIN twitter shows",
+        "Abre esta URL, autoriza la aplicación e introduce el PIN que muestra twitter",
+    ),
+    (
+        "Twitter returned an invalid access token",
+        "Twitter devolvió un token de acceso no válido",
+    ),
+    (
+        "Authorized @{handle}, keys saved to {path}",
+        "@{handle} autorizado, claves guardadas en {path}",
+    ),
+    (
+        "Invalid UTF-8 in keys path {path}",
+        "UTF-8 no válido en la ruta de claves {path}",
+    ),
+    (
+        "Account @{handle} now uses these keys",
+        "La cuenta @{handle} ahora usa estas claves",
+    ),
+    (
+        "Once your archive is imported, use them with `accounts set-keys @{handle} {path}`",
+        "Cuando tu archivo esté importado, úsalas con `accounts set-keys @{handle} {path}`",
+    ),
+    (
+        "Account @{handle} now uses the keys at {path}",
+        "La cuenta @{handle} ahora usa las claves de {path}",
+    ),
+    ("OS keyring", "Llavero del sistema"),
+    ("Default keys", "Claves por defecto"),
+    (", archived", ", archivada"),
+    (
+        "Account @{handle} is not archived",
+        "La cuenta @{handle} no está archivada",
+    ),
+    (
+        "Account @{handle} can be changed again",
+        "La cuenta @{handle} se puede volver a cambiar",
+    ),
+    (
+        "Account @{handle} is already archived",
+        "La cuenta @{handle} ya está archivada",
+    ),
+    (
+        "Account @{handle} still has {left} tweets to delete, delete them first or use `--force`",
+        "A la cuenta @{handle} aún le quedan {left} tuits por eliminar, elimínalos primero o usa \
+         `--force`",
+    ),
+    (
+        "Archived @{handle}, its tweets, likes, and direct messages can no longer be changed",
+        "@{handle} archivada, sus tuits, me gusta y mensajes directos ya no se pueden cambiar",
+    ),
+    // stats
+    (
+        "Invalid date `{date}`, expected YYYY-MM-DD: {error}",
+        "Fecha `{date}` no válida, se esperaba AAAA-MM-DD: {error}",
+    ),
+    ("As of {date}", "A {date}"),
+    (
+        "Account @{handle} {name} ({id})",
+        "Cuenta @{handle} {name} ({id})",
+    ),
+    (
+        "Tweets: {existing}\nDeleted Tweets: {deleted}\nDeleted Tweets, unknown when: \
+         {unknown}\n---\n",
+        "Tuits: {existing}\nTuits eliminados: {deleted}\nTuits eliminados, sin saber cuándo: \
+         {unknown}\n---\n",
+    ),
+    (
+        "Account @{handle} {name} ({id})\n\nImported Tweets: {imported}\nDeleted Tweets: \
+         {deleted}\nChecked* Tweets: {checked}\n---\n",
+        "Cuenta @{handle} {name} ({id})\n\nTuits importados: {imported}\nTuits eliminados: \
+         {deleted}\nTuits comprobados*: {checked}\n---\n",
+    ),
+    (
+        "Total Imported Tweets: {imported}\nDeleted Tweets: {deleted}\nChecked* Tweets: \
+         {checked}\n\n*During Twitter Archive importing, tweets are checked for whether they've \
+         already\nbeen deleted or not. If this process was not interrupted, this is the same as \
+         the total tweets.\n",
+        "Total de tuits importados: {imported}\nTuits eliminados: {deleted}\nTuits comprobados*: \
+         {checked}\n\n*Al importar el archivo de Twitter, se comprueba si los tuits ya se \
+         habían\neliminado. Si este proceso no se interrumpió, es igual al total de tuits.\n",
+    ),
+];
@@ -0,0 +1,1886 @@
+//! Delete tweets using your twitter archive
+//!
+//! This is what the `twitter_delete` command line is built on, for using it
+//! from other programs. Everything is kept in a SQLite database, opened with
+//! [`db::create_db`].
+//!
+//! Tweets are imported from an archive with [`import_archive`], after which
+//! the tweets matching some [filters][filter::FilterArgs] are found with
+//! [`plan_deletion`], recorded as a run with [`db::start_run`], and deleted
+//! with [`execute_deletion`]. Tweets already deleted some other way are found
+//! with [`check_tweets`], and large deletions can be split into batches to
+//! delete later with [`stage`]. What a run would change can be tried on a
+//! copy of the database first with [`simulate`], and [`watch_pass`] deletes
+//! everything older than some age each time it's called.
+//!
+//! Likes and direct messages are imported with [`import_likes`] and
+//! [`import_dms`], and deleted with [`remove_likes`] and [`delete_messages`].
+//! Everything is deleted from an account at once with [`plan_nuke`] and
+//! [`nuke`].
+//!
+//! Accounts are found with [`find_account`], and the API keys to use for one
+//! with [`keys_for`].
+//!
+//! What's already in the database is looked up with functions like
+//! [`find_tweet`], [`protected`], and [`runs`], or directly with [`db`].
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    env,
+    fs,
+    path::{Path, PathBuf},
+    process,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use diesel::{debug_query, prelude::*, sql_query, sqlite::Sqlite};
+use reqwest::{
+    blocking::{Client, Response},
+    Response as AsyncResponse,
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::from_str;
+use sha1::{Digest, Sha1};
+use time::{
+    format_description::{well_known::Rfc3339, FormatItem},
+    macros::format_description,
+    OffsetDateTime,
+    PrimitiveDateTime,
+    UtcOffset,
+};
+use tracing::{info, warn};
+
+use crate::{
+    analyze::{display_text, ContentKind},
+    archive::{
+        archive_date,
+        collect_dms,
+        collect_likes,
+        collect_tweets,
+        verify_manifest,
+        Account,
+        Collected,
+        TWITTER_DATE,
+    },
+    clock::Clock,
+    db::{
+        add_account,
+        add_dms,
+        add_likes,
+        add_lookup_batch,
+        add_versions,
+        by_id,
+        changes,
+        checked,
+        count_run_deleted,
+        create_indexes,
+        deleted,
+        dms_before,
+        drop_indexes,
+        existing,
+        explain,
+        finish_run,
+        forbidden,
+        is_retweet,
+        liked,
+        log_event,
+        not_forbidden,
+        record_discrepancy,
+        record_matches,
+        remaining,
+        retweets_of,
+        run_deleted,
+        run_account,
+        run_failures,
+        set_dm_deleted,
+        set_keys,
+        set_retweets,
+        set_unliked,
+        stage_batch,
+        start_run,
+        watch_policy,
+        Change,
+        ImportStats,
+        Merged,
+    },
+    filter::{text, Filter, FilterArgs, Order},
+    id::TweetId,
+    models::{
+        Account as MAccount,
+        Dm as MDm,
+        Event,
+        EventKind,
+        Hashtag as MHashtag,
+        Like as MLike,
+        LookupBatch,
+        Mention as MMention,
+        Run,
+        Tweet as MTweet,
+        Url as MUrl,
+    },
+    schema::{accounts as adb, events as edb, runs as rdb, tweets as tdb},
+    twitter::{
+        delete_dms,
+        delete_tweets,
+        error_for_status,
+        lookup_tweets,
+        post_tweet,
+        unlike_tweets,
+        Api,
+        Concurrent,
+        RateLimit,
+        Unauthorized,
+    },
+};
+
+pub mod analyze;
+pub mod clock;
+pub mod db;
+pub mod filter;
+pub mod models;
+pub mod schema;
+pub mod secrets;
+pub mod twitter;
+pub mod util;
+
+pub use twitter_delete_core::{archive, expr, id, legacy};
+
+/// Dates given by the user, eg `2020-01-01`
+pub static DATE: &[FormatItem] = format_description!("[year]-[month]-[day]");
+
+/// Twitter API keys.
+///
+/// The access token may be left out, such as for the app used with `auth`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "UPPERCASE", deny_unknown_fields)]
+pub struct Access {
+    // test_path: PathBuf,
+    pub api_key: String,
+    pub api_secret: String,
+    #[serde(default)]
+    pub access: String,
+    #[serde(default)]
+    pub access_secret: String,
+}
+
+impl Access {
+    /// Account ID these keys are for
+    ///
+    /// Twitter access tokens start with the ID of the account they're for,
+    /// eg `1234-...`
+    pub fn user_id(&self) -> Option<&str> {
+        self.access.split_once('-').map(|(id, _)| id)
+    }
+
+    /// Check these keys are for `account`
+    pub fn check(&self, account: &MAccount) -> Result<()> {
+        match self.user_id() {
+            Some(id) if id == account.id_str => Ok(()),
+            None if self.access.is_empty() => Err(anyhow!(
+                "API keys have no access token, authorize @{} with `auth`",
+                account.user_name
+            )),
+            id => Err(anyhow!(
+                "API keys are for account ID {}, not @{} ({})",
+                id.unwrap_or("<Unknown>"),
+                account.user_name,
+                account.id_str
+            )),
+        }
+    }
+}
+
+/// Find the imported account with handle `handle`, or if not given the only
+/// imported account
+pub fn find_account(conn: &mut SqliteConnection, handle: Option<&str>) -> Result<MAccount> {
+    let accounts: Vec<MAccount> = adb::dsl::accounts
+        .filter(adb::dsl::id_str.ne("0"))
+        .get_results(conn)?;
+    match handle {
+        Some(handle) => {
+            let handle = handle.trim_start_matches('@');
+            accounts
+                .into_iter()
+                .find(|a| a.user_name.eq_ignore_ascii_case(handle))
+                .ok_or_else(|| anyhow!("Account @{handle} has not been imported"))
+        }
+        None => match <[MAccount; 1]>::try_from(accounts) {
+            Ok([account]) => Ok(account),
+            Err(accounts) if accounts.is_empty() => Err(anyhow!(
+                "No accounts have been imported, import your twitter archive first with `import`"
+            )),
+            Err(_) => Err(anyhow!(
+                "Multiple accounts have been imported, choose one with `--account`"
+            )),
+        },
+    }
+}
+
+/// Get the imported account with ID `id`, if it has been imported
+pub fn account_by_id(conn: &mut SqliteConnection, id: &str) -> Result<Option<MAccount>> {
+    Ok(adb::dsl::accounts.find(id).first(conn).optional()?)
+}
+
+/// Every imported account, including the unknown account tweets imported
+/// before accounts were recorded belong to, with ID `0`
+pub fn accounts(conn: &mut SqliteConnection) -> Result<Vec<MAccount>> {
+    Ok(adb::dsl::accounts.get_results(conn)?)
+}
+
+/// Give `account` the tweets of the unknown account, which were imported
+/// before accounts were recorded, adding it if it's new
+///
+/// For updating databases from before v0.1.1, whose tweets are all from the
+/// one account their archive was for.
+pub fn claim_unknown(conn: &mut SqliteConnection, account: &Account) -> Result<()> {
+    conn.transaction::<_, anyhow::Error, _>(|conn| {
+        add_account(
+            conn,
+            &[MAccount {
+                id_str: account.id_str.clone(),
+                user_name: account.user_name.clone(),
+                display_name: account.display_name.clone(),
+                api_key: None,
+                keys_path: None,
+                keyring: false,
+                frozen_at: None,
+            }],
+        )?;
+
+        diesel::update(tdb::dsl::tweets)
+            .filter(tdb::dsl::account_id.eq("0"))
+            .set(tdb::dsl::account_id.eq(&account.id_str))
+            .execute(conn)?;
+        Ok(())
+    })
+}
+
+/// How many of `account`s tweets are left to delete, those neither deleted
+/// nor protected, such as before archiving it
+pub fn left_to_delete(conn: &mut SqliteConnection, account: &str) -> Result<i64> {
+    Ok(tdb::dsl::tweets
+        .filter(tdb::dsl::account_id.eq(account))
+        .filter(tdb::dsl::deleted.eq(false))
+        .filter(tdb::dsl::protected.eq(false))
+        .count()
+        .get_result(conn)?)
+}
+
+/// Load the API keys at `path`
+///
+/// Misspelled, missing, or empty keys are errors here, with the line they're
+/// on, rather than when twitter rejects them partway through a run.
+pub fn load_keys(path: &Path) -> Result<Access> {
+    let data = fs::read_to_string(path)
+        .map_err(|e| anyhow!("Couldn't read API keys from {}: {e}", path.display()))?;
+    let keys: Access =
+        from_str(&data).map_err(|e| anyhow!("Invalid API keys in {}: {e}", path.display()))?;
+    check_keys(keys, &path.display().to_string())
+}
+
+/// Where the default API keys come from, see [`default_keys`]
+#[derive(Debug)]
+pub struct Credentials {
+    /// `credentials.json` in the data directory, or `--credentials`
+    pub path: PathBuf,
+
+    /// Keys from `config.toml`, used instead of `path`
+    pub configured: Option<Access>,
+}
+
+/// Load the default API keys, from the `TWITTER_DELETE_API_KEY`,
+/// `TWITTER_DELETE_API_SECRET`, `TWITTER_DELETE_ACCESS`, and
+/// `TWITTER_DELETE_ACCESS_SECRET` environment variables if
+/// `TWITTER_DELETE_API_KEY` is set, otherwise from `config.toml` or the
+/// credentials file
+///
+/// This lets the keys come from secrets where there's no file to put them in.
+pub fn default_keys(credentials: &Credentials) -> Result<Access> {
+    let var = |name: &str| match env::var(format!("TWITTER_DELETE_{name}")) {
+        Ok(value) => Ok(value),
+        Err(env::VarError::NotPresent) => Ok(String::new()),
+        Err(e) => Err(anyhow!("Invalid TWITTER_DELETE_{name}: {e}")),
+    };
+    if env::var_os("TWITTER_DELETE_API_KEY").is_none() {
+        return match &credentials.configured {
+            Some(keys) => check_keys(keys.clone(), "config.toml"),
+            None => load_keys(&credentials.path),
+        };
+    }
+    let keys = Access {
+        api_key: var("API_KEY")?,
+        api_secret: var("API_SECRET")?,
+        access: var("ACCESS")?,
+        access_secret: var("ACCESS_SECRET")?,
+    };
+    check_keys(keys, "$TWITTER_DELETE_*")
+}
+
+/// Check the required keys are present in `keys`, loaded from `from`
+fn check_keys(keys: Access, from: &str) -> Result<Access> {
+    let missing: Vec<_> = [("API_KEY", &keys.api_key), ("API_SECRET", &keys.api_secret)]
+        .into_iter()
+        .filter(|(_, v)| v.trim().is_empty())
+        .map(|(k, _)| k)
+        .collect();
+    if !missing.is_empty() {
+        return Err(anyhow!(
+            "Invalid API keys in {from}: {} must not be empty",
+            missing.join(", ")
+        ));
+    }
+    if keys.access.is_empty() != keys.access_secret.is_empty() {
+        return Err(anyhow!(
+            "Invalid API keys in {from}: ACCESS and ACCESS_SECRET must be given together"
+        ));
+    }
+    Ok(keys)
+}
+
+/// Get the API keys to use for `account`
+///
+/// These are the keys stored with `auth store`, or set with
+/// `accounts set-keys`, or else the default keys, see [`default_keys`].
+///
+/// The keys must be for `account`, and for the same app last used with it,
+/// which is recorded the first time.
+pub fn keys_for(
+    conn: &mut SqliteConnection,
+    default: &Credentials,
+    account: &MAccount,
+) -> Result<Access> {
+    let keys = match &account.keys_path {
+        _ if account.keyring => secrets::load(&account.id_str)?,
+        Some(path) => load_keys(Path::new(path))?,
+        None => default_keys(default)?,
+    };
+    keys.check(account)?;
+    match &account.api_key {
+        Some(api_key) if *api_key != keys.api_key => {
+            return Err(anyhow!(
+                "Account @{} was last used with a different app, use `accounts set-keys` if this \
+                 is intended",
+                account.user_name
+            ));
+        }
+        Some(_) => (),
+        None => set_keys(conn, &account.id_str, &keys.api_key, None)?,
+    }
+    Ok(keys)
+}
+
+/// Import tweets from the twitter archive to our database
+///
+/// Tweets already in the database keep their state, see [`db::add_tweets`]
+/// for how they're merged.
+/// The archive is considered to have been created when its manifest says,
+/// or else when its newest tweet was.
+///
+/// Warns about tweet files that are missing or truncated, according to the
+/// archive manifest
+///
+/// See [`collect_tweets`] for `lenient`
+///
+/// Tweets are imported for `account`, see [`archive::get_account`], and
+/// recorded as imported at the time of `clock`
+///
+/// Time spent and tweets read are added to `stats`
+pub fn import_archive(
+    conn: &mut SqliteConnection,
+    clock: &dyn Clock,
+    path: &Path,
+    account: &Account,
+    lenient: bool,
+    stats: &mut ImportStats,
+) -> Result<Merged> {
+    let start = Instant::now();
+    let Collected {
+        tweets,
+        counts,
+        repairs,
+    } = collect_tweets(path, lenient)?;
+
+    for repair in repairs {
+        info!("Repaired: {repair}");
+    }
+    for warning in verify_manifest(path, &counts)? {
+        warn!("{warning}");
+    }
+
+    let mentions: Vec<MMention> = tweets
+        .iter()
+        .flat_map(|tw| {
+            tw.entities.user_mentions.iter().map(|m| MMention {
+                tweet_id: tw.id_str.clone(),
+                user_id: m.id_str.clone(),
+                screen_name: m.screen_name.clone(),
+            })
+        })
+        .collect();
+
+    let hashtags: Vec<MHashtag> = tweets
+        .iter()
+        .flat_map(|tw| {
+            tw.entities.hashtags.iter().map(|h| MHashtag {
+                tweet_id: tw.id_str.clone(),
+                hashtag: h.text.clone(),
+            })
+        })
+        .collect();
+
+    let urls: Vec<MUrl> = tweets
+        .iter()
+        .flat_map(|tw| {
+            let entities = &tw.entities;
+            entities.urls.iter().chain(&entities.media).map(|u| MUrl {
+                tweet_id: tw.id_str.clone(),
+                url: u.url.clone(),
+                expanded_url: u.expanded_url.clone(),
+            })
+        })
+        .collect();
+
+    let mut tweets: Vec<MTweet> = tweets
+        .into_iter()
+        .map(|tw| -> Result<MTweet> {
+            let entities = &tw.entities;
+            let text = tw.full_text.as_deref().map(|t| {
+                let urls = entities.urls.iter().chain(&entities.media);
+                display_text(t, urls.map(|u| (u.url.as_str(), u.expanded_url.as_str())))
+            });
+            let media = entities.media.iter().map(|m| m.expanded_url.as_str());
+            let content_kind = text
+                .as_deref()
+                .map(|t| ContentKind::classify(t, media).as_str().to_owned());
+            let has_media = tw.has_media();
+            let initial_id = tw.initial_id().cloned();
+            // Should only fail if twitter archive is bad/evil
+            Ok(MTweet {
+                in_reply_to_status_id: tw.in_reply_to_status_id_str,
+                in_reply_to_user_id: tw.in_reply_to_user_id_str,
+                in_reply_to_screen_name: tw.in_reply_to_screen_name,
+                has_media: Some(has_media),
+                initial_id,
+                content_kind,
+                full_text: tw.full_text,
+                display_text: text,
+                ..MTweet::new(
+                    tw.id_str,
+                    tw.retweets.parse()?,
+                    tw.likes.parse()?,
+                    PrimitiveDateTime::parse(&tw.created_at, TWITTER_DATE)?
+                        .assume_utc()
+                        .unix_timestamp(),
+                    account.id_str.clone(),
+                )
+            })
+        })
+        .collect::<Result<_>>()?;
+
+    let archived = archive_date(path)
+        .map(|t| t.unix_timestamp())
+        .or_else(|| tweets.iter().map(|t| t.created_at).max());
+    for tweet in &mut tweets {
+        tweet.archived_at = archived;
+    }
+    stats.parse += start.elapsed();
+    stats.rows += tweets.len();
+
+    let start = Instant::now();
+    let merged = conn.transaction::<_, anyhow::Error, _>(|conn| {
+        add_account(
+            conn,
+            &[MAccount {
+                id_str: account.id_str.clone(),
+                user_name: account.user_name.clone(),
+                display_name: account.display_name.clone(),
+                api_key: None,
+                keys_path: None,
+                keyring: false,
+                frozen_at: None,
+            }],
+        )?;
+
+        let merged = db::add_tweets(conn, clock, &tweets)?;
+        db::add_mentions(conn, &mentions)?;
+        db::add_hashtags(conn, &hashtags)?;
+        db::add_urls(conn, &urls)?;
+        Ok(merged)
+    })?;
+    stats.insert += start.elapsed();
+
+    Ok(merged)
+}
+
+/// How long each step of [`import_fast`] took
+#[derive(Debug, Clone, Copy)]
+pub struct FastImport {
+    /// Dropping indexes
+    pub dropped: Duration,
+
+    /// Importing
+    pub imported: Duration,
+
+    /// Rebuilding indexes
+    pub rebuilt: Duration,
+}
+
+/// Run `import`, such as [`import_archive`] for each archive, without
+/// maintaining indexes as it goes, rebuilding them once it's done
+///
+/// Much faster for large archives. Everything happens in one transaction,
+/// without waiting for it to reach the disk until the end, so an import that
+/// fails or is interrupted leaves the database as it was.
+pub fn import_fast<T>(
+    conn: &mut SqliteConnection,
+    import: impl FnOnce(&mut SqliteConnection) -> Result<T>,
+) -> Result<(T, FastImport)> {
+    // Not durable until the import finishes, but it all happens in one
+    // transaction anyway
+    sql_query("PRAGMA synchronous = OFF").execute(conn)?;
+    let start = Instant::now();
+    let res = conn.transaction::<_, anyhow::Error, _>(|conn| {
+        let indexes = drop_indexes(conn)?;
+        let dropped = start.elapsed();
+        let imported = import(conn)?;
+        let done = start.elapsed();
+        create_indexes(conn, &indexes)?;
+        Ok((
+            imported,
+            FastImport {
+                dropped,
+                imported: done - dropped,
+                rebuilt: start.elapsed() - done,
+            },
+        ))
+    });
+    sql_query("PRAGMA synchronous = FULL").execute(conn)?;
+    res
+}
+
+/// Import the liked tweets from the twitter archive at `path` for `account`,
+/// returning how many were added
+///
+/// Archives without `data/like.js` have no likes to import.
+pub fn import_likes(conn: &mut SqliteConnection, path: &Path, account: &Account) -> Result<usize> {
+    if !path.join("data").join("like.js").is_file() {
+        return Ok(0);
+    }
+    let likes: Vec<MLike> = collect_likes(path)?
+        .into_iter()
+        .map(|l| MLike {
+            tweet_id: l.tweet_id,
+            account_id: account.id_str.clone(),
+            full_text: l.full_text,
+            unliked: false,
+            unliked_at: None,
+        })
+        .collect();
+    add_likes(conn, &likes)
+}
+
+/// Import the direct messages from the twitter archive at `path` for
+/// `account`, returning how many were added
+pub fn import_dms(conn: &mut SqliteConnection, path: &Path, account: &Account) -> Result<usize> {
+    let dms = collect_dms(path)?
+        .into_iter()
+        .map(|m| {
+            let created_at = OffsetDateTime::parse(&m.created_at, &Rfc3339)
+                .map_err(|e| anyhow!("Invalid date for direct message {}: {e}", m.id))?
+                .unix_timestamp();
+            Ok(MDm {
+                id: m.id,
+                account_id: account.id_str.clone(),
+                conversation_id: m.conversation_id,
+                sender_id: m.sender_id,
+                text: m.text,
+                created_at,
+                deleted: false,
+                deleted_at: None,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    add_dms(conn, &dms)
+}
+
+/// Progress of [`check_tweets`]
+#[derive(Debug, Clone, Copy)]
+pub enum Checking {
+    /// Looking up this many unchecked tweets
+    Started(usize),
+
+    /// Looked up a batch of `requested` tweets, `gone` of which were newly
+    /// marked as already deleted
+    Batch { requested: usize, gone: usize },
+
+    /// Looking up this many tweets that twitter left out once more
+    Retrying(usize),
+}
+
+/// What [`check_tweets`] found
+#[derive(Debug, Clone, Copy)]
+pub struct Checked {
+    /// How many tweets were already deleted
+    pub gone: usize,
+
+    /// How many tweets twitter left out of its responses, which are still
+    /// unchecked
+    pub missing: usize,
+}
+
+/// Look up the unchecked tweets of `account` on twitter, marking the ones
+/// that are already deleted, as a `check` run
+///
+/// Tweets twitter leaves out of the response entirely are looked up once
+/// more, and left unchecked if still missing, for a later check.
+///
+/// With API v2, the other versions of edited tweets are recorded too, adding
+/// any missing from the archive, see [`add_versions`].
+///
+/// `on_progress` is called as tweets are looked up, eg to show progress.
+///
+/// Returns [`None`] if every tweet has already been checked.
+pub fn check_tweets<OnLimit, OnProgress>(
+    conn: &mut SqliteConnection,
+    keys: &Access,
+    options: ApiOptions,
+    account: &str,
+    mut on_limit: OnLimit,
+    mut on_progress: OnProgress,
+) -> Result<Option<Checked>>
+where
+    OnLimit: FnMut(RateLimit, &AsyncResponse) -> Result<()>,
+    OnProgress: FnMut(Checking),
+{
+    let unchecked_tweets: Vec<TweetId> = tdb::dsl::tweets
+        .order(by_id())
+        .filter(existing())
+        .filter(tdb::dsl::account_id.eq(account))
+        .select(tdb::dsl::id_str)
+        .load(conn)?;
+    if unchecked_tweets.is_empty() {
+        return Ok(None);
+    }
+    on_progress(Checking::Started(unchecked_tweets.len()));
+
+    let mut total = 0;
+    let clock = options.clock;
+    let run = start_run(conn, "check", account, None, None, clock.now().unix_timestamp())?;
+    let mut batch = 0;
+    let mut to_check = unchecked_tweets;
+    let mut retried = false;
+    let missing = loop {
+        let mut missing = Vec::new();
+        lookup_tweets(
+            keys,
+            clock,
+            options.api,
+            options.concurrent,
+            to_check.iter(),
+            &mut on_limit,
+            |res| {
+                let missed = res.missing.len();
+                missing.extend(res.missing);
+                let ids = res.id.iter().filter(|(_, v)| v.is_none()).map(|(k, _)| k);
+
+                let gone = conn.transaction::<_, anyhow::Error, _>(|conn| {
+                    let returned = res.id.values().flatten().count();
+                    add_lookup_batch(
+                        conn,
+                        &LookupBatch {
+                            run_id: run,
+                            batch,
+                            requested: res.requested as i32,
+                            returned: returned as i32,
+                            gone: (res.id.len() - returned) as i32,
+                            missing: missed as i32,
+                        },
+                    )?;
+                    // Mark all tweets as checked
+                    checked(conn, clock, res.id.keys())?;
+                    set_retweets(
+                        conn,
+                        res.id
+                            .values()
+                            .flatten()
+                            .map(|t| (&t.id_str, t.retweeted_status.as_ref().map(|r| &r.id_str))),
+                    )?;
+                    add_versions(
+                        conn,
+                        res.id
+                            .values()
+                            .flatten()
+                            .map(|t| (&t.id_str, &t.edit_history_tweet_ids[..])),
+                    )?;
+                    let gone = deleted(conn, clock, ids)?;
+                    Ok(gone)
+                })?;
+                total += gone;
+                batch += 1;
+                on_progress(Checking::Batch {
+                    requested: res.requested,
+                    gone,
+                });
+
+                Ok(())
+            },
+        )?;
+
+        if missing.is_empty() || retried {
+            break missing.len();
+        }
+        retried = true;
+        on_progress(Checking::Retrying(missing.len()));
+        to_check = missing;
+    };
+    finish_run(conn, run, total, clock.now().unix_timestamp())?;
+
+    Ok(Some(Checked {
+        gone: total,
+        missing,
+    }))
+}
+
+/// The IDs of the tweets of `account` matching `filter` as of `now`, in
+/// `order`, which are the tweets to give [`execute_deletion`]
+///
+/// Local days start at midnight in the offset of `now`, see
+/// [`FilterArgs::build`]. Saved filters are [resolved](FilterArgs::resolve)
+/// first, as they are by [`stage`], [`simulate`], and [`watch_pass`].
+pub fn plan_deletion(
+    conn: &mut SqliteConnection,
+    filter: FilterArgs,
+    account: &str,
+    now: OffsetDateTime,
+    order: Order,
+) -> Result<Vec<TweetId>> {
+    let mut filter = filter.resolve(conn)?.build(now)?;
+    filter.account = Some(account.to_owned());
+    filter.load(conn, order)
+}
+
+/// A tweet as shown to the user, see [`summarize`]
+#[derive(Debug, Queryable, Serialize)]
+pub struct Summary {
+    pub id: TweetId,
+
+    /// UTC unix time
+    pub created_at: i64,
+    pub likes: i32,
+    pub retweets: i32,
+
+    /// Text as displayed, [`None`] for tweets imported before their text
+    /// was recorded
+    pub text: Option<String>,
+}
+
+/// Get the [`Summary`] of each of `ids`, in order
+pub fn summarize(conn: &mut SqliteConnection, ids: &[TweetId]) -> Result<Vec<Summary>> {
+    let mut summaries: HashMap<TweetId, Summary> = HashMap::with_capacity(ids.len());
+    // Stay under SQLites limit on query parameters
+    for chunk in ids.chunks(500) {
+        let rows: Vec<Summary> = tdb::dsl::tweets
+            .filter(tdb::dsl::id_str.eq_any(chunk))
+            .select((
+                tdb::dsl::id_str,
+                tdb::dsl::created_at,
+                tdb::dsl::likes,
+                tdb::dsl::retweets,
+                text(),
+            ))
+            .load(conn)?;
+        summaries.extend(rows.into_iter().map(|s| (s.id.clone(), s)));
+    }
+
+    ids.iter()
+        .map(|id| {
+            summaries
+                .remove(id)
+                .ok_or_else(|| anyhow!("Tweet {id} is not in the database"))
+        })
+        .collect()
+}
+
+/// Get tweet `id`, if it has been imported
+pub fn find_tweet(conn: &mut SqliteConnection, id: &TweetId) -> Result<Option<MTweet>> {
+    Ok(tdb::dsl::tweets.find(id).first(conn).optional()?)
+}
+
+/// Everything that happened to tweet `id`, oldest first
+pub fn tweet_history(conn: &mut SqliteConnection, id: &TweetId) -> Result<Vec<Event>> {
+    Ok(edb::dsl::events
+        .filter(edb::dsl::tweet_id.eq(id))
+        .order(edb::dsl::id.asc())
+        .load(conn)?)
+}
+
+/// Every protected tweet, in ID order, with its note if it has one
+pub fn protected(conn: &mut SqliteConnection) -> Result<Vec<(TweetId, Option<String>)>> {
+    Ok(tdb::dsl::tweets
+        .order(by_id())
+        .filter(tdb::dsl::protected.eq(true))
+        .select((tdb::dsl::id_str, tdb::dsl::note))
+        .load(conn)?)
+}
+
+/// Every tweet not yet deleted that has text to score, in ID order, with its
+/// text, skipping those already scored unless `rescore`
+pub fn unscored(conn: &mut SqliteConnection, rescore: bool) -> Result<Vec<(TweetId, String)>> {
+    let mut query = tdb::dsl::tweets
+        .order(by_id())
+        .filter(tdb::dsl::deleted.eq(false))
+        .select((tdb::dsl::id_str, text().assume_not_null()))
+        .filter(tdb::dsl::full_text.is_not_null())
+        .into_boxed();
+    if !rescore {
+        query = query.filter(tdb::dsl::score.is_null());
+    }
+    Ok(query.load(conn)?)
+}
+
+/// Every run, oldest first, only those tagged `tag` if given
+pub fn runs(conn: &mut SqliteConnection, tag: Option<&str>) -> Result<Vec<Run>> {
+    let mut query = rdb::dsl::runs.order(rdb::dsl::id.asc()).into_boxed();
+    if let Some(tag) = tag {
+        query = query.filter(rdb::dsl::tag.eq(tag));
+    }
+    Ok(query.load(conn)?)
+}
+
+/// Every run where twitter reported deleting more tweets than were marked as
+/// deleted, oldest first, see [`db::record_discrepancy`]
+pub fn discrepancies(conn: &mut SqliteConnection) -> Result<Vec<Run>> {
+    Ok(rdb::dsl::runs
+        .filter(rdb::dsl::discrepancy.is_not_null())
+        .order(rdb::dsl::id.asc())
+        .load(conn)?)
+}
+
+/// SQLites query for the tweets `filter` matches, in ID order, and its plan
+/// for running it, see [`db::explain`]
+pub fn explain_filter(
+    conn: &mut SqliteConnection,
+    filter: &Filter,
+) -> Result<(String, Vec<String>)> {
+    let query = filter
+        .query()
+        .order(by_id())
+        .select((tdb::dsl::id_str, text()));
+    let sql = debug_query::<Sqlite, _>(&query).to_string();
+    Ok((sql, explain(conn, query)?))
+}
+
+/// Tweets created this soon before their archive was generated, in seconds,
+/// had little time to be liked or retweeted
+const SETTLING: i64 = 7 * 24 * 60 * 60;
+
+/// How far the likes and retweets of some tweets can be trusted, see
+/// [`engagement`]
+#[derive(Debug, Clone, Copy)]
+pub struct Engagement {
+    /// How many were posted less than a week before their archive was
+    /// generated, or come from an archive without a date, so may have more
+    /// likes and retweets by now
+    pub unsettled: i64,
+
+    /// When the newest archive of the account was generated, as UTC unix
+    /// time, as tweets posted since then aren't imported
+    pub newest_archive: Option<i64>,
+}
+
+/// How far the likes and retweets of `ids`, from `account`, can be trusted
+///
+/// Likes and retweets are only known as of when an archive was generated, so
+/// tweets posted shortly before then look less popular than they are, and
+/// tweets posted since aren't imported at all.
+pub fn engagement(
+    conn: &mut SqliteConnection,
+    ids: &[TweetId],
+    account: &str,
+) -> Result<Engagement> {
+    use tdb::dsl::*;
+    let mut unsettled: i64 = 0;
+    // Stay under SQLites limit on query parameters
+    for chunk in ids.chunks(500) {
+        unsettled += tweets
+            .filter(id_str.eq_any(chunk))
+            .filter(
+                archived_at
+                    .is_null()
+                    .or(created_at.gt(archived_at.assume_not_null() - SETTLING)),
+            )
+            .count()
+            .get_result::<i64>(conn)?;
+    }
+
+    let newest_archive: Option<i64> = tweets
+        .filter(account_id.eq(account))
+        .select(diesel::dsl::max(archived_at))
+        .first(conn)?;
+    Ok(Engagement {
+        unsettled,
+        newest_archive,
+    })
+}
+
+/// Counts of an accounts tweets explaining why nothing matched, see
+/// [`unmatched`]
+#[derive(Debug, Clone, Copy)]
+pub struct Unmatched {
+    /// Tweets imported
+    pub imported: i64,
+
+    /// Tweets deleted
+    pub deleted: i64,
+
+    /// Tweets not deleted, but protected
+    pub protected: i64,
+}
+
+/// Count `account`s tweets, for explaining why nothing of theirs matched
+pub fn unmatched(conn: &mut SqliteConnection, account: &str) -> Result<Unmatched> {
+    let all = || tdb::dsl::tweets.filter(tdb::dsl::account_id.eq(account));
+    Ok(Unmatched {
+        imported: all().count().get_result(conn)?,
+        deleted: all()
+            .filter(tdb::dsl::deleted.eq(true))
+            .count()
+            .get_result(conn)?,
+        protected: all()
+            .filter(tdb::dsl::deleted.eq(false))
+            .filter(tdb::dsl::protected.eq(true))
+            .count()
+            .get_result(conn)?,
+    })
+}
+
+/// What to stage with [`stage`]
+#[derive(Debug, Clone, Deserialize)]
+pub struct StageArgs {
+    pub filter: FilterArgs,
+
+    /// Split the matching tweets into batches
+    #[serde(default)]
+    pub split_by: Option<Split>,
+
+    /// Number of tweets in each batch, when split by [`Split::Count`]
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+
+    /// Order to stage tweets in
+    #[serde(default)]
+    pub order: Order,
+
+    /// Tag the batches in the run history
+    #[serde(default)]
+    pub tag: Option<String>,
+}
+
+/// Default [`StageArgs::batch_size`], for [`StageArgs`] that are deserialized
+fn default_batch_size() -> usize {
+    3000
+}
+
+/// How to split staged tweets into batches, see [`StageArgs`]
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Split {
+    /// One batch per year the tweets were created in
+    Year,
+
+    /// Batches of at most [`StageArgs::batch_size`] tweets
+    Count,
+}
+
+/// A batch staged by [`stage`]
+#[derive(Debug, Serialize)]
+pub struct Staged {
+    /// Run ID to delete it with, see [`db::remaining`]
+    pub run: i32,
+
+    /// Name of the batch, eg `2019` or `1/3`
+    pub batch: String,
+
+    /// Number of tweets in the batch
+    pub tweets: usize,
+}
+
+/// Stage the tweets from `account` matching `what`, as of `now`, as
+/// unfinished runs to be deleted later
+///
+/// Saved filters are [resolved](FilterArgs::resolve) first.
+///
+/// Returns every batch staged, none if nothing matched.
+pub fn stage(
+    conn: &mut SqliteConnection,
+    what: &StageArgs,
+    account: &str,
+    now: OffsetDateTime,
+) -> Result<Vec<Staged>> {
+    let batch_size = what.batch_size;
+    if batch_size == 0 {
+        return Err(anyhow!("Batch size must be more than 0"));
+    }
+    let filter = what.filter.clone().resolve(conn)?;
+    let filters = filter.canonical();
+    let mut filter = filter.build(now)?;
+    filter.account = Some(account.to_owned());
+    let to_process = filter.load(conn, what.order)?;
+    if to_process.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let batches: Vec<(String, Vec<TweetId>)> = match what.split_by {
+        None => vec![("all".to_owned(), to_process)],
+        Some(Split::Count) => {
+            let count = to_process.chunks(batch_size).len();
+            to_process
+                .chunks(batch_size)
+                .enumerate()
+                .map(|(i, chunk)| (format!("{}/{count}", i + 1), chunk.to_vec()))
+                .collect()
+        }
+        Some(Split::Year) => {
+            let created: HashMap<TweetId, i64> = filter
+                .query()
+                .select((tdb::dsl::id_str, tdb::dsl::created_at))
+                .load::<(TweetId, i64)>(conn)?
+                .into_iter()
+                .collect();
+            let mut years: BTreeMap<i32, Vec<TweetId>> = BTreeMap::new();
+            for id in to_process {
+                let year = OffsetDateTime::from_unix_timestamp(created[&id])?.year();
+                years.entry(year).or_default().push(id);
+            }
+            years
+                .into_iter()
+                .map(|(year, ids)| (year.to_string(), ids))
+                .collect()
+        }
+    };
+
+    let now = now.unix_timestamp();
+    let mut staged = Vec::new();
+    for (batch, ids) in batches.into_iter().filter(|(_, ids)| !ids.is_empty()) {
+        let run = stage_batch(conn, account, what.tag.as_deref(), &filters, &batch, &ids, now)?;
+        staged.push(Staged {
+            run,
+            batch,
+            tweets: ids.len(),
+        });
+    }
+    Ok(staged)
+}
+
+/// Get staged batch, or interrupted deletion, `run`, and the tweets it still
+/// needs to delete, for `commit` with the keys of `account`
+///
+/// Runs for another account are refused, as its tweets can't be deleted
+/// with these keys, and would be recorded as failures.
+pub fn unfinished(
+    conn: &mut SqliteConnection,
+    run: i32,
+    account: &str,
+) -> Result<(Run, Vec<TweetId>)> {
+    let batch: Run = rdb::dsl::runs
+        .find(run)
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| anyhow!("No run #{run}"))?;
+    if !["stage", "delete", "watch", "nuke"].contains(&batch.command.as_str()) {
+        return Err(anyhow!("Run #{run} is not a staged batch or deletion"));
+    }
+    if batch.finished_at.is_some() {
+        return Err(anyhow!("Run #{run} has already finished"));
+    }
+    if let Some(owner) = run_account(conn, run)?.filter(|owner| owner != account) {
+        let handle: String = adb::dsl::accounts
+            .find(&owner)
+            .select(adb::dsl::user_name)
+            .first(conn)
+            .optional()?
+            .unwrap_or(owner);
+        return Err(anyhow!(
+            "Run #{run} is for @{handle}, commit it with `--account {handle}`"
+        ));
+    }
+    Ok((batch, remaining(conn, run)?))
+}
+
+/// How to send requests to twitter, such as for [`execute_deletion`]
+#[derive(Clone, Copy)]
+pub struct ApiOptions<'a> {
+    /// Which API to use
+    pub api: Api,
+
+    /// Where to send requests, with how many in flight at once, created once
+    /// with [`create_concurrent`](twitter::create_concurrent)
+    pub concurrent: &'a Concurrent,
+
+    /// The current time, to sign requests with and to record twitters
+    /// responses at
+    pub clock: &'a dyn Clock,
+}
+
+/// How twitter responded to deleting a tweet, see [`record_delete`]
+#[derive(Debug, Clone, Copy)]
+pub enum Deletion {
+    /// Deleted, with how many tweets were newly marked as deleted
+    Deleted(usize),
+
+    /// Already gone, with how many tweets were newly marked as deleted
+    Gone(usize),
+
+    /// Couldn't be deleted, and was marked as failed, with whether it's now
+    /// permanently failed
+    Forbidden(bool),
+
+    /// Any other error, which should stop deleting
+    Error,
+}
+
+/// Record twitters response `status` to deleting tweet `id` in the database,
+/// at the time of `clock`
+///
+/// Used for both real and simulated deletions, such as by `simulate`
+pub fn record_delete(
+    conn: &mut SqliteConnection,
+    clock: &dyn Clock,
+    id: &TweetId,
+    status: StatusCode,
+) -> Result<Deletion> {
+    log_event(
+        conn,
+        clock,
+        id,
+        EventKind::DeleteAttempted,
+        Some(status.as_str()),
+    )?;
+    // Retweets of accounts that have gone private can't be deleted, but your
+    // own tweets always should be, so something else is wrong, such as
+    // read-only keys.
+    // The archive doesn't say which tweets are retweets, only the lookup API
+    // does, so tweets not checked since then are assumed to be retweets.
+    if status == StatusCode::FORBIDDEN {
+        let why = match is_retweet(conn, id)? {
+            Some(false) => return Ok(Deletion::Error),
+            Some(true) => "Forbidden, a retweet of a private account",
+            None => "Forbidden, probably a private retweet",
+        };
+        return Ok(Deletion::Forbidden(forbidden(conn, clock, id, why)?));
+    }
+    not_forbidden(conn, id)?;
+    // Probably also a RT, this time thats been deleted
+    // Sigh.
+    if status == StatusCode::NOT_FOUND {
+        return Ok(Deletion::Gone(deleted(conn, clock, [id].into_iter())?));
+    }
+    if status.is_client_error() || status.is_server_error() {
+        return Ok(Deletion::Error);
+    }
+    Ok(Deletion::Deleted(deleted(conn, clock, [id].into_iter())?))
+}
+
+/// Delete `ids`, matched by run `run`, on twitter, marking them as deleted,
+/// or as failed, in the database
+///
+/// Requests are sent as configured by `options`.
+///
+/// `on_deleted` is called with each tweet twitter responded to, how, and
+/// whether it was a retweet, eg to show progress.
+///
+/// Returns how many were deleted
+///
+/// Afterwards, even if deleting failed partway, the number of tweets
+/// twitter reported as deleted is checked against the number newly marked as
+/// deleted in the database, warning about and recording any discrepancy in
+/// the run.
+pub fn execute_deletion<OnLimit, OnDeleted>(
+    conn: &mut SqliteConnection,
+    keys: &Access,
+    options: ApiOptions,
+    run: i32,
+    ids: &[TweetId],
+    on_limit: OnLimit,
+    mut on_deleted: OnDeleted,
+) -> Result<usize>
+where
+    OnLimit: FnMut(RateLimit, &AsyncResponse) -> Result<()>,
+    OnDeleted: FnMut(&TweetId, Deletion, bool),
+{
+    let before = count_run_deleted(conn, run)?;
+    let retweets = retweets_of(conn, ids)?;
+    let mut total = 0;
+    let mut succeeded: i64 = 0;
+    let res = delete_tweets(
+        keys,
+        options.clock,
+        options.api,
+        options.concurrent,
+        ids.iter().map(|f| (f, retweets.get(f))),
+        on_limit,
+        |res, id| {
+            let deletion = record_delete(conn, options.clock, id, res.status())?;
+            match deletion {
+                Deletion::Forbidden(_) => (),
+                Deletion::Gone(marked) | Deletion::Deleted(marked) => {
+                    succeeded += 1;
+                    total += marked;
+                }
+                Deletion::Error if res.status() == StatusCode::FORBIDDEN => {
+                    return Err(anyhow!(
+                        "Twitter refused to delete your tweet {id} with 403 Forbidden, check \
+                         what the API keys are allowed to do with `auth test`"
+                    ));
+                }
+                Deletion::Error => {
+                    error_for_status(res)?;
+                }
+            }
+            on_deleted(id, deletion, retweets.contains_key(id));
+
+            Ok(())
+        },
+    );
+
+    let marked = count_run_deleted(conn, run)? - before;
+    if marked != succeeded {
+        warn!(
+            "\
+Twitter reported {succeeded} tweets as deleted, but {marked} were marked as deleted.
+The database may not match twitter, and run #{run} has been marked as such."
+        );
+        record_discrepancy(conn, run, succeeded - marked)?;
+    }
+
+    res?;
+    Ok(total)
+}
+
+/// What [`simulate`] would have done
+#[derive(Debug)]
+pub struct Simulated {
+    /// The run the deletion was recorded as in the copy
+    pub run: i32,
+
+    /// Every tweet that would change
+    pub changes: Vec<Change>,
+}
+
+/// Simulate deleting the tweets of `account` matching `filter`, as of
+/// `clock` in `offset`, in `order`, against a copy of the database at
+/// `db_path`, open as `conn`
+///
+/// Twitter is never contacted, and every tweet is assumed to be deleted
+/// successfully, but the copy is updated exactly as [`execute_deletion`]
+/// would update the real one, and compared to it, see [`db::changes`]. The
+/// copy is made in the temporary directory, and removed afterwards.
+///
+/// Saved filters are [resolved](FilterArgs::resolve) first.
+///
+/// Returns [`None`] if nothing matches.
+pub fn simulate(
+    conn: &mut SqliteConnection,
+    db_path: &Path,
+    filter: &FilterArgs,
+    account: &str,
+    clock: &dyn Clock,
+    offset: UtcOffset,
+    order: Order,
+) -> Result<Option<Simulated>> {
+    let copy = env::temp_dir().join(format!("twitter_delete-simulate-{}.db", process::id()));
+    let copy_str = copy
+        .to_str()
+        .ok_or_else(|| anyhow!("Invalid UTF-8 in temporary path {}", copy.display()))?;
+    // `VACUUM INTO` fails if the file exists
+    let _ = fs::remove_file(&copy);
+    sql_query(format!("VACUUM INTO '{}'", copy_str.replace('\'', "''"))).execute(conn)?;
+
+    let filter = filter.clone().resolve(conn)?;
+    let simulate = || -> Result<Option<Simulated>> {
+        let sim = &mut db::create_db(&copy)?;
+        let filters = filter.canonical();
+        let mut filter = filter.build(clock.now().to_offset(offset))?;
+        filter.account = Some(account.to_owned());
+        let to_process = filter.load(sim, order)?;
+        if to_process.is_empty() {
+            return Ok(None);
+        }
+
+        let run = start_run(
+            sim,
+            "delete",
+            account,
+            None,
+            Some(filters.as_str()),
+            clock.now().unix_timestamp(),
+        )?;
+        record_matches(sim, run, &to_process)?;
+        let mut total = 0;
+        for id in &to_process {
+            if let Deletion::Deleted(marked) = record_delete(sim, clock, id, StatusCode::OK)? {
+                total += marked;
+            }
+        }
+        finish_run(sim, run, total, clock.now().unix_timestamp())?;
+        Ok(Some(Simulated {
+            run,
+            changes: changes(sim, db_path)?,
+        }))
+    };
+    let res = simulate();
+    let _ = fs::remove_file(&copy);
+    res
+}
+
+/// What a [`watch_pass`] did
+#[derive(Debug)]
+pub struct Pass {
+    /// How many tweets matched
+    pub matched: usize,
+
+    /// How many of them are now marked as deleted
+    pub deleted: usize,
+
+    /// How many of them twitter failed to delete
+    pub failed: usize,
+
+    /// Why the pass stopped early, if it did
+    ///
+    /// Whatever is left is tried again by the next pass.
+    pub error: Option<anyhow::Error>,
+}
+
+/// Delete the tweets of `account` matching `filter` as of `options.clock` in
+/// `offset`, as one pass of `watch`
+///
+/// `filter` should be a [retention policy](FilterArgs::retention), as
+/// nobody is asked before deleting. The matching tweets are recorded as a
+/// `watch` run tagged `tag`, which `on_start` is called with, along with how
+/// many matched, before deleting them with [`execute_deletion`].
+///
+/// The run is finished even if deleting fails, as the next pass tries
+/// whatever is left again, except when twitter rejects the keys, see
+/// [`twitter::Unauthorized`], which is returned as an error, as every later
+/// pass would fail the same way.
+///
+/// Saved filters are [resolved](FilterArgs::resolve) first.
+///
+/// Returns [`None`] if nothing matched.
+#[allow(clippy::too_many_arguments)]
+pub fn watch_pass<OnStart, OnLimit, OnDeleted>(
+    conn: &mut SqliteConnection,
+    keys: &Access,
+    options: ApiOptions,
+    filter: &FilterArgs,
+    account: &str,
+    offset: UtcOffset,
+    tag: Option<&str>,
+    on_start: OnStart,
+    on_limit: OnLimit,
+    on_deleted: OnDeleted,
+) -> Result<Option<Pass>>
+where
+    OnStart: FnOnce(i32, usize),
+    OnLimit: FnMut(RateLimit, &AsyncResponse) -> Result<()>,
+    OnDeleted: FnMut(&TweetId, Deletion, bool),
+{
+    let clock = options.clock;
+    let now = clock.now();
+    let filter = filter.clone().resolve(conn)?;
+    let mut pass = filter.build(now.to_offset(offset))?;
+    pass.account = Some(account.to_owned());
+    let to_process = pass.load(conn, Order::Id)?;
+    if to_process.is_empty() {
+        return Ok(None);
+    }
+
+    let filters = filter.canonical();
+    let run = start_run(
+        conn,
+        "watch",
+        account,
+        tag,
+        Some(filters.as_str()),
+        now.unix_timestamp(),
+    )?;
+    record_matches(conn, run, &to_process)?;
+    on_start(run, to_process.len());
+    let res = execute_deletion(
+        conn,
+        keys,
+        options,
+        run,
+        &to_process,
+        on_limit,
+        on_deleted,
+    );
+    let error = match res {
+        Err(e) if e.is::<Unauthorized>() => return Err(e),
+        Ok(_) => None,
+        Err(e) => Some(e),
+    };
+    let deleted = run_deleted(conn, run)?.len();
+    finish_run(conn, run, deleted, clock.now().unix_timestamp())?;
+    Ok(Some(Pass {
+        matched: to_process.len(),
+        deleted,
+        failed: run_failures(conn, run)?.len(),
+        error,
+    }))
+}
+
+/// Short hash identifying the canonical filters `filters`, for accepting a
+/// `watch` policy that deletes more than the last one, see [`check_policy`]
+pub fn policy_hash(filters: &str) -> String {
+    Sha1::digest(filters.as_bytes())
+        .iter()
+        .take(6)
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Filters for `watch` that would delete tweets the ones it last ran with
+/// kept, see [`check_policy`]
+#[derive(Debug)]
+pub struct PolicyChange {
+    /// Canonical filters `watch` last ran with
+    pub previous: String,
+
+    /// Canonical new filters
+    pub filters: String,
+
+    /// How many tweets the new filters would delete that the previous ones
+    /// kept
+    pub more: usize,
+
+    /// [Hash][policy_hash] of the new filters, to accept them with
+    pub hash: String,
+}
+
+/// Check `filter`, which `watch` is about to run with for `account` as of
+/// `now`, against the filters it last ran with
+///
+/// Filters that would delete tweets the last ones kept, such as a smaller
+/// `older_than` from a typo in config.toml, should be confirmed before
+/// [recording them][db::accept_watch_policy] as the new policy, as nobody is
+/// asked again before every pass deletes them. Filters from before an
+/// upgrade that can't be read any more are treated as having kept
+/// everything.
+///
+/// Returns [`None`] if there's nothing to confirm.
+pub fn check_policy(
+    conn: &mut SqliteConnection,
+    filter: &FilterArgs,
+    account: &str,
+    now: OffsetDateTime,
+) -> Result<Option<PolicyChange>> {
+    let filters = filter.canonical();
+    let previous = match watch_policy(conn, account)? {
+        Some(previous) if previous != filters => previous,
+        // Nothing to compare to, or unchanged
+        _ => return Ok(None),
+    };
+    let kept: HashSet<TweetId> = match from_str::<FilterArgs>(&previous) {
+        Ok(previous) => plan_deletion(conn, previous, account, now, Order::Id)?
+            .into_iter()
+            .collect(),
+        Err(_) => HashSet::new(),
+    };
+    let more = plan_deletion(conn, filter.clone(), account, now, Order::Id)?
+        .into_iter()
+        .filter(|id| !kept.contains(id))
+        .count();
+    if more == 0 {
+        return Ok(None);
+    }
+    Ok(Some(PolicyChange {
+        previous,
+        hash: policy_hash(&filters),
+        filters,
+        more,
+    }))
+}
+
+/// The tweets `account` liked and hasn't yet unliked that were created
+/// before `before`, to give [`remove_likes`]
+///
+/// Twitter doesn't record when tweets were liked, so this goes by when the
+/// liked tweet was created, as decoded from its ID. Tweets from before
+/// November 2010 are always old enough.
+pub fn likes_before(
+    conn: &mut SqliteConnection,
+    account: &str,
+    before: OffsetDateTime,
+) -> Result<Vec<TweetId>> {
+    Ok(liked(conn, account)?
+        .into_iter()
+        .filter(|id| match id.snowflake_time() {
+            Some(created) => created < before,
+            // From before snowflake IDs, so always old enough
+            None => true,
+        })
+        .collect())
+}
+
+/// Unlike `ids` on twitter, marking them as unliked by `account` in the
+/// database, at the time of `clock`
+///
+/// `on_unliked` is called with each tweet, and whether it was already
+/// unliked, or gone, eg to show progress.
+///
+/// Returns how many were unliked
+#[allow(clippy::too_many_arguments)]
+pub fn remove_likes<OnLimit, OnUnliked>(
+    conn: &mut SqliteConnection,
+    client: &Client,
+    keys: &Access,
+    clock: &dyn Clock,
+    account: &str,
+    ids: &[TweetId],
+    on_limit: OnLimit,
+    mut on_unliked: OnUnliked,
+) -> Result<usize>
+where
+    OnLimit: FnMut(RateLimit, &Response) -> Result<()>,
+    OnUnliked: FnMut(&TweetId, bool),
+{
+    let mut unliked = 0;
+    unlike_tweets(client, keys, clock, ids.iter(), on_limit, |res, id| {
+        let now = clock.now().unix_timestamp();
+        // Already unliked, or the tweet is gone
+        if res.status() == StatusCode::NOT_FOUND {
+            set_unliked(conn, account, id, now)?;
+            on_unliked(id, true);
+            return Ok(());
+        }
+        res.error_for_status()?;
+        set_unliked(conn, account, id, now)?;
+        unliked += 1;
+        on_unliked(id, false);
+        Ok(())
+    })?;
+    Ok(unliked)
+}
+
+/// Delete direct messages `ids` on twitter, marking them as deleted from
+/// `account`s view in the database, at the time of `clock`
+///
+/// `on_deleted` is called with each message, and whether it was already
+/// deleted, eg to show progress.
+///
+/// Returns how many were deleted
+#[allow(clippy::too_many_arguments)]
+pub fn delete_messages<OnLimit, OnDeleted>(
+    conn: &mut SqliteConnection,
+    client: &Client,
+    keys: &Access,
+    clock: &dyn Clock,
+    account: &str,
+    ids: &[String],
+    on_limit: OnLimit,
+    mut on_deleted: OnDeleted,
+) -> Result<usize>
+where
+    OnLimit: FnMut(RateLimit, &Response) -> Result<()>,
+    OnDeleted: FnMut(&str, bool),
+{
+    let mut gone = 0;
+    delete_dms(
+        client,
+        keys,
+        clock,
+        ids.iter().map(|f| f.as_str()),
+        on_limit,
+        |res, id| {
+            let now = clock.now().unix_timestamp();
+            if res.status() == StatusCode::NOT_FOUND {
+                set_dm_deleted(conn, account, id, now)?;
+                on_deleted(id, true);
+                return Ok(());
+            }
+            res.error_for_status()?;
+            set_dm_deleted(conn, account, id, now)?;
+            gone += 1;
+            on_deleted(id, false);
+            Ok(())
+        },
+    )?;
+    Ok(gone)
+}
+
+/// Everything `nuke` deletes from an account, see [`plan_nuke`]
+#[derive(Debug, Clone)]
+pub struct NukePlan {
+    /// The account being nuked
+    pub account: MAccount,
+
+    /// How many tweets were newly imported from the archive
+    pub imported: usize,
+
+    /// Tweets not yet deleted, except protected ones
+    pub tweets: Vec<TweetId>,
+
+    /// Liked tweets not yet unliked
+    pub likes: Vec<TweetId>,
+
+    /// Direct messages not yet deleted, if asked for
+    pub messages: Vec<String>,
+}
+
+/// Import the archive at `path` of `archived`, with its likes, and if `dms`
+/// its direct messages, and find everything to delete from the account with
+/// [`nuke`]
+pub fn plan_nuke(
+    conn: &mut SqliteConnection,
+    clock: &dyn Clock,
+    path: &Path,
+    archived: &Account,
+    dms: bool,
+) -> Result<NukePlan> {
+    let imported = import_archive(
+        conn,
+        clock,
+        path,
+        archived,
+        false,
+        &mut ImportStats::default(),
+    )?
+    .added;
+
+    let account: MAccount = adb::dsl::accounts.find(&archived.id_str).first(conn)?;
+    let tweets: Vec<TweetId> = tdb::dsl::tweets
+        .order(by_id())
+        .filter(tdb::dsl::account_id.eq(&account.id_str))
+        .filter(tdb::dsl::deleted.eq(false))
+        .filter(tdb::dsl::protected.eq(false))
+        .select(tdb::dsl::id_str)
+        .load(conn)?;
+    import_likes(conn, path, archived)?;
+    let likes = liked(conn, &account.id_str)?;
+    let messages = if dms {
+        import_dms(conn, path, archived)?;
+        dms_before(conn, &account.id_str, clock.now().unix_timestamp())?
+    } else {
+        Vec::new()
+    };
+
+    Ok(NukePlan {
+        account,
+        imported,
+        tweets,
+        likes,
+        messages,
+    })
+}
+
+/// What [`nuke`] is doing, eg to show progress
+#[derive(Debug)]
+pub enum Nuking<'a> {
+    /// Started deleting tweets, as run `run`
+    Tweets { run: i32 },
+
+    /// Twitter responded to deleting a tweet, see [`execute_deletion`]
+    Tweet(&'a TweetId, Deletion, bool),
+
+    /// Started removing likes
+    Likes,
+
+    /// Unliked a tweet, and whether it was already unliked
+    Unliked(&'a TweetId, bool),
+
+    /// Started deleting direct messages
+    Messages,
+
+    /// Deleted a direct message, and whether it was already deleted
+    Message(&'a str, bool),
+}
+
+/// What [`nuke`] deleted
+#[derive(Debug, Clone)]
+pub struct Nuked {
+    /// Tweets deleted
+    pub tweets: usize,
+
+    /// Likes removed
+    pub likes: usize,
+
+    /// Direct messages deleted
+    pub messages: usize,
+
+    /// The farewell tweet, if one was posted
+    pub farewell: Option<TweetId>,
+}
+
+/// Delete everything in `plan` on twitter, and then post `farewell`
+///
+/// The tweets are recorded as a `nuke` run tagged `tag`, which can be
+/// resumed with `commit` if deleting them is interrupted. Tweets are deleted
+/// as configured by `options`, and everything else is sent with `client`.
+///
+/// `on_limit` is called before waiting on a rate limit, and `on_progress` as
+/// each step starts and with everything deleted.
+///
+/// Stops at the first error
+#[allow(clippy::too_many_arguments)]
+pub fn nuke<OnLimit, OnProgress>(
+    conn: &mut SqliteConnection,
+    client: &Client,
+    keys: &Access,
+    options: ApiOptions,
+    plan: &NukePlan,
+    tag: Option<&str>,
+    farewell: Option<&str>,
+    on_limit: OnLimit,
+    on_progress: OnProgress,
+) -> Result<Nuked>
+where
+    OnLimit: FnMut(RateLimit) -> Result<()>,
+    OnProgress: FnMut(Nuking),
+{
+    let (mut on_limit, mut on_progress) = (on_limit, on_progress);
+    let clock = options.clock;
+    let account = &plan.account.id_str;
+
+    let run = start_run(conn, "nuke", account, tag, None, clock.now().unix_timestamp())?;
+    record_matches(conn, run, &plan.tweets)?;
+    on_progress(Nuking::Tweets { run });
+    let tweets = execute_deletion(
+        conn,
+        keys,
+        options,
+        run,
+        &plan.tweets,
+        |r, _| on_limit(r),
+        |id, deletion, retweet| on_progress(Nuking::Tweet(id, deletion, retweet)),
+    )?;
+    finish_run(conn, run, tweets, clock.now().unix_timestamp())?;
+
+    on_progress(Nuking::Likes);
+    let likes = remove_likes(
+        conn,
+        client,
+        keys,
+        clock,
+        account,
+        &plan.likes,
+        |r, _| on_limit(r),
+        |id, already| on_progress(Nuking::Unliked(id, already)),
+    )?;
+
+    on_progress(Nuking::Messages);
+    let messages = delete_messages(
+        conn,
+        client,
+        keys,
+        clock,
+        account,
+        &plan.messages,
+        |r, _| on_limit(r),
+        |id, already| on_progress(Nuking::Message(id, already)),
+    )?;
+
+    let farewell = match farewell {
+        Some(text) => Some(post_tweet(client, keys, clock, text, |r, _| on_limit(r))?.id_str),
+        None => None,
+    };
+
+    Ok(Nuked {
+        tweets,
+        likes,
+        messages,
+        farewell,
+    })
+}
+
+/// Back up the database, and every file in the archives `data` folder,
+/// to a new timestamped folder in `backups`, returning its path
+///
+/// Media folders in the archive are not backed up.
+pub fn backup(
+    conn: &mut SqliteConnection,
+    archive: &Path,
+    backups: &Path,
+    now: OffsetDateTime,
+) -> Result<PathBuf> {
+    let out = backups.join(now.unix_timestamp().to_string());
+    let data = out.join("data");
+    fs::create_dir_all(&data)?;
+
+    let db = out.join("tweets.db");
+    let db = db
+        .to_str()
+        .ok_or_else(|| anyhow!("Invalid UTF-8 in backup path {}", db.display()))?;
+    sql_query(format!("VACUUM INTO '{}'", db.replace('\'', "''"))).execute(conn)?;
+
+    for file in archive.join("data").read_dir()? {
+        let file = file?;
+        if !file.file_type()?.is_file() {
+            continue;
+        }
+        fs::copy(file.path(), data.join(file.file_name()))?;
+    }
+
+    Ok(out)
+}
+
+/// Copy the media of tweets `ids` from the twitter archive at `archive` to
+/// `out`, in a folder per tweet named by the date it was created in
+/// `offset`, and its ID, returning how many files were copied, for how many
+/// tweets
+///
+/// Media files in the archive start with the ID of their tweet, eg
+/// `data/tweets_media/1234-abcd.jpg`, or in `data/tweet_media` in older
+/// archives.
+pub fn backup_media(
+    conn: &mut SqliteConnection,
+    archive: &Path,
+    ids: &[TweetId],
+    out: &Path,
+    offset: UtcOffset,
+) -> Result<(usize, usize)> {
+    let mut created: HashMap<String, i64> = HashMap::with_capacity(ids.len());
+    // Stay under SQLites limit on query parameters
+    for chunk in ids.chunks(500) {
+        created.extend(
+            tdb::dsl::tweets
+                .filter(tdb::dsl::id_str.eq_any(chunk))
+                .select((tdb::dsl::id_str, tdb::dsl::created_at))
+                .load::<(String, i64)>(conn)?,
+        );
+    }
+    let media = ["tweets_media", "tweet_media"]
+        .into_iter()
+        .map(|dir| archive.join("data").join(dir))
+        .find(|dir| dir.is_dir())
+        .ok_or_else(|| anyhow!("No media folder in the archive {}", archive.display()))?;
+
+    let mut files = 0;
+    let mut tweets = HashSet::new();
+    for file in media.read_dir()? {
+        let file = file?;
+        if !file.file_type()?.is_file() {
+            continue;
+        }
+        let name = file.file_name();
+        let (id, rest) = match name.to_str().and_then(|n| n.split_once('-')) {
+            Some(split) => split,
+            None => continue,
+        };
+        let created_at = match created.get(id) {
+            Some(created_at) => *created_at,
+            None => continue,
+        };
+        let date = OffsetDateTime::from_unix_timestamp(created_at)?
+            .to_offset(offset)
+            .format(DATE)?;
+        let dir = out.join(date).join(id);
+        fs::create_dir_all(&dir)?;
+        fs::copy(file.path(), dir.join(rest))?;
+        files += 1;
+        tweets.insert(id.to_owned());
+    }
+
+    Ok((files, tweets.len()))
+}
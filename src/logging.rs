@@ -0,0 +1,86 @@
+//! Logging, to stderr and to `logs` in the data directory
+//!
+//! Messages on stderr are shown as they always were, without timestamps or
+//! levels, except that warnings are labelled as such, and how many can be
+//! changed with `-v` and `-q`. The log files always record every request to
+//! twitter and change to the database, so a tweet that failed to delete hours
+//! into a run can be looked into afterwards.
+use std::{fmt, io, path::Path};
+
+use anyhow::Result;
+use tracing::{metadata::LevelFilter, Event, Level, Subscriber};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::{
+    filter::Targets,
+    fmt::{
+        format::{FormatEvent, FormatFields, Writer},
+        FmtContext,
+    },
+    prelude::*,
+    registry::LookupSpan,
+};
+
+/// Formats messages for stderr, labelling only warnings, in the users
+/// language
+///
+/// Warnings don't include the label in their message, as the log files
+/// already show the level.
+struct Plain;
+
+impl<S, N> FormatEvent<S, N> for Plain
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        if *event.metadata().level() != Level::WARN {
+            ctx.field_format().format_fields(writer.by_ref(), event)?;
+            return writeln!(writer);
+        }
+        let mut message = String::new();
+        ctx.field_format()
+            .format_fields(Writer::new(&mut message), event)?;
+        writeln!(writer, "{}", tr!("Warning: {message}", message = message))
+    }
+}
+
+/// Start logging, with log files in `dir`, rotated daily
+///
+/// `verbose` and `quiet` are how many times `-v` and `-q` were given. By
+/// default informational messages and warnings are shown.
+///
+/// Log files are written from a background thread, which is flushed when
+/// the returned guard is dropped.
+pub fn init(dir: &Path, verbose: u8, quiet: u8) -> Result<WorkerGuard> {
+    let level = match i16::from(verbose) - i16::from(quiet) {
+        i16::MIN..=-2 => LevelFilter::ERROR,
+        -1 => LevelFilter::WARN,
+        0 => LevelFilter::INFO,
+        1 => LevelFilter::DEBUG,
+        2..=i16::MAX => LevelFilter::TRACE,
+    };
+    // Only our own messages, not those of the libraries used
+    let ours = |level: LevelFilter| Targets::new().with_target("twitter_delete", level);
+    let (file, guard) =
+        tracing_appender::non_blocking(tracing_appender::rolling::daily(dir, "twitter_delete.log"));
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(io::stderr)
+                .event_format(Plain)
+                .with_filter(ours(level)),
+        )
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(file)
+                .with_ansi(false)
+                .with_filter(ours(LevelFilter::DEBUG)),
+        )
+        .try_init()?;
+    Ok(guard)
+}
@@ -1,110 +1,846 @@
 use std::{
-    fs,
-    io::{stdout, Write},
+    collections::HashSet,
+    env,
+    fs::{self, File},
+    io::{self, stderr, stdin, stdout, Write},
+    net::SocketAddr,
     path::{Path, PathBuf},
+    time::Duration as StdDuration,
 };
 
 use anyhow::{anyhow, Result};
-use clap::{Parser, ValueHint};
-use db::add_account;
+use clap::{ArgAction, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum, ValueHint};
+use clap_complete::Shell;
 use diesel::prelude::*;
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
 use reqwest::{
-    blocking::{ClientBuilder, Response},
-    StatusCode,
+    blocking::Response,
+    Response as AsyncResponse,
 };
-use serde::Deserialize;
+use serde::Serialize;
 use serde_json::from_str;
 use time::{
     format_description::FormatItem,
     macros::format_description,
+    Date,
     Duration,
     OffsetDateTime,
-    PrimitiveDateTime,
     UtcOffset,
 };
-use twitter::{get_account, Account};
+use tracing::{debug, warn};
+use twitter_delete::{
+    account_by_id,
+    accounts,
+    analyze,
+    archive::{get_account, Account},
+    backup,
+    backup_media,
+    check_policy,
+    check_tweets,
+    claim_unknown,
+    clock,
+    db,
+    default_keys,
+    delete_messages,
+    discrepancies,
+    engagement,
+    execute_deletion,
+    explain_filter,
+    expr,
+    filter,
+    find_account,
+    find_tweet,
+    id,
+    import_archive,
+    import_dms,
+    import_fast,
+    import_likes,
+    keys_for,
+    left_to_delete,
+    likes_before,
+    load_keys,
+    models,
+    nuke,
+    plan_nuke,
+    protected,
+    remove_likes,
+    runs,
+    schema,
+    secrets,
+    simulate,
+    stage,
+    summarize,
+    tweet_history,
+    twitter,
+    unfinished,
+    unmatched,
+    unscored,
+    util,
+    watch_pass,
+    Access,
+    ApiOptions,
+    Checking,
+    Credentials,
+    Deletion,
+    Engagement,
+    Nuking,
+    Simulated,
+    Split,
+    StageArgs,
+    Staged,
+    Summary,
+    Unmatched,
+    DATE,
+};
 
 use crate::{
-    db::{checked, count_tweets, created_before, deleted, existing},
-    models::{Account as MAccount, Tweet as MTweet},
-    schema::{accounts as adb, tweets as tdb},
-    twitter::{collect_tweets, delete_tweets, lookup_tweets, LookupResp, RateLimit, TWITTER_DATE},
+    analyze::Scorer,
+    clock::{Clock, FrozenClock, SystemClock},
+    config::Config,
+    db::{
+        accept_watch_policy,
+        count_as_of,
+        count_totals,
+        count_tweets,
+        deleted,
+        dms_before,
+        finish_run,
+        last_matches,
+        last_unfinished,
+        lookup_anomalies,
+        protect,
+        record_import,
+        record_matches,
+        remove_filter,
+        run_deleted,
+        save_filter,
+        saved_filters,
+        search,
+        set_frozen,
+        set_keyring,
+        set_keys,
+        set_score,
+        stage_batch,
+        start_run,
+        unprotect,
+        ImportStats,
+        Merged,
+    },
+    filter::{Boundary, Filter, FilterArgs, NotRetention, Order},
+    i18n::Lang,
+    id::TweetId,
+    models::Account as MAccount,
+    review::Decision,
+    twitter::{
+        access_token,
+        create_client,
+        create_concurrent,
+        post_tweet,
+        request_token,
+        test_auth,
+        Api,
+        Authorized,
+        RateLimit,
+        Unauthorized,
+    },
 };
 
 mod config;
-mod db;
-mod models;
-mod schema;
-mod twitter;
-mod util;
-
-/// Twitter API keys, in a simple JSON format
-///
-/// See [Access]
-static ACCESS: &str = include_str!("../scratch/access.json");
+mod export;
+#[macro_use]
+mod i18n;
+mod logging;
+mod metrics;
+mod notify;
+mod progress;
+mod report;
+mod review;
+#[cfg(unix)]
+mod serve;
+mod systemd;
 
 static HUMAN_TIME: &[FormatItem] = format_description!("[hour repr:12]:[minute]:[second] [period]");
 
-/// Twitter API keys.
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "UPPERCASE")]
-pub struct Access {
-    // test_path: PathBuf,
-    api_key: String,
-    api_secret: String,
-    access: String,
-    access_secret: String,
-}
+/// UTC offsets given by the user, eg `+02:00`
+static OFFSET: &[FormatItem] = format_description!("[offset_hour sign:mandatory]:[offset_minute]");
+
+static HUMAN_DATE: &[FormatItem] =
+    format_description!("[year]-[month]-[day] [hour repr:12]:[minute]:[second] [period]");
 
 /// Parse tweets from your twitter archive
 #[derive(Parser, Debug)]
-enum Args {
+struct Args {
+    /// Pretend the current time is this UTC unix timestamp,
+    /// eg to reproduce the behavior of filters exactly
+    ///
+    /// Only for commands that don't send requests to twitter, such as
+    /// `delete --dry-run`, as twitter always uses the real time.
+    #[clap(long, global = true, value_hint = ValueHint::Other)]
+    now: Option<i64>,
+
+    /// Directory to keep the database, keys, and backups in, instead of
+    /// `$HOME/.config/twitter_delete`
+    #[clap(
+        long,
+        global = true,
+        env = "TWITTER_DELETE_DATA_DIR",
+        value_hint = ValueHint::DirPath
+    )]
+    data_dir: Option<PathBuf>,
+
+    /// Use the defaults from this profile in `config.toml` in the data
+    /// directory, see the README
+    #[clap(long, global = true, env = "TWITTER_DELETE_PROFILE", value_hint = ValueHint::Other)]
+    profile: Option<String>,
+
+    /// Database to use, instead of `tweets.db` in the data directory
+    #[clap(long, global = true, env = "TWITTER_DELETE_DB", value_hint = ValueHint::FilePath)]
+    db: Option<PathBuf>,
+
+    /// Default API keys to use, instead of `credentials.json` in the data
+    /// directory
+    ///
+    /// Ignored if `TWITTER_DELETE_API_KEY` is set, see the README.
+    #[clap(
+        long,
+        global = true,
+        env = "TWITTER_DELETE_CREDENTIALS",
+        value_hint = ValueHint::FilePath
+    )]
+    credentials: Option<PathBuf>,
+
+    /// Default API keys from `config.toml`, see [`Config::apply`]
+    #[clap(skip)]
+    keys: Option<Access>,
+
+    /// Account to operate on, by handle
+    ///
+    /// Only needed if multiple accounts have been imported
+    #[clap(long, global = true, env = "TWITTER_DELETE_ACCOUNT", value_hint = ValueHint::Other)]
+    account: Option<String>,
+
+    /// Time zone to show times and interpret dates in, as an IANA name such
+    /// as `Europe/London`, instead of the local time zone
+    ///
+    /// Uses the current offset of the time zone.
+    #[clap(long, global = true, env = "TWITTER_DELETE_TZ", value_hint = ValueHint::Other)]
+    tz: Option<String>,
+
+    /// Fixed offset from UTC to show times and interpret dates in, eg
+    /// `+02:00`, instead of the local time zone
+    ///
+    /// For when the local time zone can't be found, such as in containers.
+    #[clap(
+        long,
+        global = true,
+        env = "TWITTER_DELETE_UTC_OFFSET",
+        conflicts_with = "tz",
+        value_hint = ValueHint::Other
+    )]
+    utc_offset: Option<String>,
+
+    /// Twitter API version to look up and delete tweets with
+    #[clap(
+        long,
+        global = true,
+        env = "TWITTER_DELETE_API",
+        value_enum,
+        default_value = "v1"
+    )]
+    api: Api,
+
+    /// How many requests to have in flight at once when looking up and
+    /// deleting tweets
+    ///
+    /// Rate limits are still respected. Requests wait once those in flight
+    /// would use up what twitter says is left of the window, and every request
+    /// pauses until it resets when one is rate limited.
+    #[clap(
+        long,
+        visible_alias = "workers",
+        global = true,
+        env = "TWITTER_DELETE_CONCURRENCY",
+        value_hint = ValueHint::Other,
+        default_value = "1"
+    )]
+    concurrency: usize,
+
+    /// Be gentle with twitter, for worries about mass deletions looking like
+    /// abuse
+    ///
+    /// Sends one request at a time, overriding `--concurrency`, and waits 3
+    /// to 5 seconds, at random, before each, far below the rate limits.
+    #[clap(long, global = true, env = "TWITTER_DELETE_GENTLE")]
+    gentle: bool,
+
+    /// Language for messages
+    ///
+    /// Defaults to the language of the environment, from `LANG`, and
+    /// English for anything not translated.
+    #[clap(long, global = true, value_enum, env = "TWITTER_DELETE_LANG")]
+    lang: Option<Lang>,
+
+    /// Print progress as a plain line every few seconds, instead of redrawing
+    /// a progress bar
+    ///
+    /// For screen readers and log files.
+    #[clap(long, global = true, env = "TWITTER_DELETE_PLAIN_PROGRESS")]
+    plain_progress: bool,
+
+    /// Format of results
+    ///
+    /// With `json`, `import`, `delete`, and `stats` print a single JSON
+    /// document to stdout instead of prose, and no progress bar, for
+    /// scripts. Errors are printed as `{"error": "..."}`.
+    #[clap(
+        long,
+        global = true,
+        value_enum,
+        env = "TWITTER_DELETE_OUTPUT",
+        default_value = "text"
+    )]
+    output: Output,
+
+    /// Show more of what's happening, such as every request to twitter,
+    /// more with `-vv`
+    ///
+    /// Everything is always logged to `logs` in the data directory.
+    #[clap(short, long, global = true, action = ArgAction::Count, conflicts_with = "quiet")]
+    verbose: u8,
+
+    /// Only show warnings, or only errors with `-qq`
+    #[clap(short, long, global = true, action = ArgAction::Count)]
+    quiet: u8,
+
+    /// Show a desktop notification when a long command, such as `delete`,
+    /// finishes or fails, or is rate limited for longer than
+    /// `--notify-stalled`
+    ///
+    /// Requires building with `--features notify-rust`.
+    #[clap(long, global = true, env = "TWITTER_DELETE_NOTIFY")]
+    notify: bool,
+
+    /// Minutes to be rate limited for before notifying, with `--notify`
+    #[clap(
+        long,
+        global = true,
+        env = "TWITTER_DELETE_NOTIFY_STALLED",
+        value_name = "MINUTES",
+        value_hint = ValueHint::Other,
+        default_value = "10"
+    )]
+    notify_stalled: i64,
+
+    #[clap(subcommand)]
+    cmd: Cmd,
+}
+
+#[derive(Subcommand, Debug)]
+enum Cmd {
     /// Import tweets from the twitter archive for processing
     ///
-    /// Tweets are imported into a local database at
-    /// `$HOME/.config/twitter_delete/tweets.db`
+    /// Tweets are imported into a local database at `tweets.db` in the data
+    /// directory, `$HOME/.config/twitter_delete` by default
     Import {
-        /// Path to your twitter archive
+        /// Paths to your twitter archives
         ///
         /// This is the folder with "Your archive.html" in it.
-        #[clap(value_hint = ValueHint::DirPath)]
+        ///
+        /// Multiple archives for the same account, such as archives from years
+        /// apart, are merged, with the likes and retweets from the newest
+        /// archive winning.
+        #[clap(required = true, value_hint = ValueHint::DirPath)]
+        paths: Vec<PathBuf>,
+
+        /// Repair or skip malformed tweets instead of failing,
+        /// reporting everything that was repaired or skipped
+        #[clap(long)]
+        lenient: bool,
+
+        /// Faster initial import of very large archives
+        ///
+        /// Drops indexes during the import, rebuilding them afterwards,
+        /// and reports how long each step took.
+        #[clap(long)]
+        fast: bool,
+
+        /// ID of the account the archives are for, when `account.js` in the
+        /// archive is missing, corrupt, or has an ID of `0`
+        #[clap(long, requires = "handle", value_hint = ValueHint::Other)]
+        account_id: Option<String>,
+
+        /// Handle of the account the archives are for, with `--account-id`
+        #[clap(long, requires = "account-id", value_hint = ValueHint::Other)]
+        handle: Option<String>,
+    },
+
+    /// Seed tweet state from the export of another deletion tool
+    ///
+    /// Accepts plain text or CSV files containing tweet IDs or tweet URLs,
+    /// such as those produced by semiphemeral or tweepy scripts. CSV files
+    /// need a header naming the column to use, `id`, `id_str`, `tweet_id`,
+    /// or `url`.
+    ///
+    /// Only affects tweets that have already been imported.
+    ImportIds {
+        /// Path to the exported file
+        #[clap(value_hint = ValueHint::FilePath)]
         path: PathBuf,
+
+        /// What to mark the tweets in the file as
+        #[clap(long, short, value_enum)]
+        mark: Mark,
     },
 
+    /// Check whether imported tweets were already deleted from twitter
+    ///
+    /// This happens after every `import`, but can be run again to finish a
+    /// check that was interrupted.
+    ///
+    /// Only tweets of `--account` are checked, so other accounts in the same
+    /// database don't use up its rate limits.
+    Check {},
+
     /// Delete tweets that have been imported, subject to the provided filters
     ///
     /// Without any filters this will do nothing, as a precaution against
     /// accidental deletions.
     ///
-    /// If you really want to delete ***ALL*** tweets, pass in `--older_than 0`
+    /// If you really want to delete ***ALL*** tweets, pass in `--older_than 0`,
+    /// which asks for confirmation unless given `--yes`
     Delete {
-        /// Exclude these tweet IDs
-        #[clap(long, short, value_delimiter = ',', value_hint = ValueHint::Other)]
-        exclude: Vec<String>,
+        #[clap(flatten)]
+        filter: FilterArgs,
+
+        /// Instead of deleting, show only the tweets newly matched since the
+        /// last run with the same tag and filters
+        #[clap(long)]
+        diff_last_run: bool,
+
+        /// Instead of deleting, show the matching tweets, when they were
+        /// created, their likes and retweets, and the start of their text
+        #[clap(long)]
+        dry_run: bool,
+
+        /// Order to delete tweets in, so that runs spanning multiple rate
+        /// limit windows remove the most important tweets first
+        #[clap(long, value_enum, default_value = "id")]
+        order: Order,
+
+        /// Tag this run in the run history, eg "pre-jobhunt purge"
+        #[clap(long, short, value_hint = ValueHint::Other)]
+        tag: Option<String>,
+
+        /// Don't show what will be deleted and ask for confirmation first, for
+        /// scripts
+        #[clap(long)]
+        yes: bool,
+
+        /// Export the matching tweets to this new file before deleting them,
+        /// as a record of what was deleted
+        ///
+        /// The format is chosen by the extension, `.txt` for IDs, `.csv`,
+        /// `.json`, `.md`, or `.html`, see `export`.
+        #[clap(long, value_hint = ValueHint::FilePath)]
+        export_before_delete: Option<PathBuf>,
 
-        /// Delete tweets older than this many days
+        /// Resume the last interrupted `delete` run, with the same `--tag` if
+        /// given, instead of matching tweets again
+        ///
+        /// The tweets matched when it started that aren't deleted yet are
+        /// deleted, in the same order, and any filters are ignored. This is
+        /// the same as `commit` with its run ID, see `history`.
+        #[clap(long)]
+        resume: bool,
+    },
+
+    /// Simulate deleting tweets subject to the provided filters, against a
+    /// copy of the database
+    ///
+    /// Twitter is never contacted, and every tweet is assumed to be deleted
+    /// successfully, but the copy is updated exactly as `delete` would
+    /// update the real database. Every tweet that would change is shown.
+    Simulate {
+        #[clap(flatten)]
+        filter: FilterArgs,
+
+        /// Order to delete tweets in, see `delete --order`
+        #[clap(long, value_enum, default_value = "id")]
+        order: Order,
+    },
+
+    /// Stage tweets to delete later, subject to the provided filters,
+    /// optionally split into multiple batches
+    ///
+    /// Each batch is recorded in the run history, and can be committed
+    /// independently with `commit`, eg on different days to stay within API
+    /// limits.
+    Stage {
+        #[clap(flatten)]
+        stage: StageOpts,
+    },
+
+    /// Review the tweets matching the provided filters one at a time,
+    /// choosing which to keep and which to delete
+    ///
+    /// Tweets marked to keep are protected, and tweets marked to delete are
+    /// staged as a batch to delete with `commit`. Nothing is changed if the
+    /// review is cancelled.
+    Review {
+        #[clap(flatten)]
+        filter: FilterArgs,
+
+        /// Order to review tweets in, see `delete --order`
+        #[clap(long, value_enum, default_value = "id")]
+        order: Order,
+
+        /// Tag the staged batch in the run history
+        #[clap(long, short, value_hint = ValueHint::Other)]
+        tag: Option<String>,
+    },
+
+    /// Delete the tweets in a batch staged with `stage`,
+    /// or resume an interrupted `delete` or `nuke` run
+    ///
+    /// Tweets deleted or protected since the batch was staged are skipped.
+    /// Interrupted batches can be committed again to resume.
+    ///
+    /// Resuming `nuke` only deletes the remaining tweets, not likes or
+    /// direct messages.
+    Commit {
+        /// Run ID of the batch or run, as shown by `stage` and `history`
+        #[clap(value_hint = ValueHint::Other)]
+        run: i32,
+    },
+
+    /// Keep deleting tweets subject to the provided filters, forever,
+    /// eg everything older than `--older-than 90`
+    ///
+    /// `--older-than` is required, so new tweets are always kept that many
+    /// days, and can't be `0`.
+    ///
+    /// Each pass deletes the matching tweets like `delete` would, without
+    /// asking, then sleeps until the next. Only imported tweets can be
+    /// deleted, so newer tweets need importing from a newer archive, or with
+    /// `import-ids`, while this is running.
+    Watch {
+        #[clap(flatten)]
+        filter: FilterArgs,
+
+        /// Hours to sleep between passes, at most a year
+        #[clap(
+            long,
+            value_hint = ValueHint::Other,
+            default_value = "6",
+            value_parser = clap::value_parser!(u64).range(1..=24 * 365)
+        )]
+        every: u64,
+
+        /// Tag each pass in the run history
+        #[clap(long, short, value_hint = ValueHint::Other)]
+        tag: Option<String>,
+
+        /// Accept filters that would delete tweets the ones `watch` last ran
+        /// with kept, by the hash shown when asked, instead of typing it
+        #[clap(long, value_hint = ValueHint::Other)]
+        accept_policy: Option<String>,
+
+        /// Serve Prometheus metrics at `http://ADDR/metrics`, eg
+        /// `127.0.0.1:9150`
+        #[clap(long, value_name = "ADDR", value_hint = ValueHint::Other)]
+        metrics: Option<SocketAddr>,
+    },
+
+    /// Unlike liked tweets from the twitter archive, by the age of the liked
+    /// tweet
+    ///
+    /// The archives likes are imported first, then unliked as with `unlike`.
+    ///
+    /// The archive does not record when tweets were liked, so this is based
+    /// on when the liked tweet was *created*, as decoded from its ID.
+    /// Tweets from before November 2010 are always considered old enough.
+    DeleteLikes {
+        /// Path to your twitter archive
+        ///
+        /// This is the folder with "Your archive.html" in it.
+        #[clap(value_hint = ValueHint::DirPath)]
+        path: PathBuf,
+
+        /// Unlike tweets that were created more than this many days ago
+        #[clap(long, short, value_hint = ValueHint::Other)]
+        older_than: u32,
+    },
+
+    /// Unlike tweets liked in imported twitter archives, by the age of the
+    /// liked tweet
+    ///
+    /// Like `delete-likes`, but without an archive, so only likes already
+    /// imported are unliked. Tweets already unliked are skipped, so
+    /// interrupted runs pick up where they left off.
+    ///
+    /// Twitter doesn't record *when* tweets were liked, so this goes by when
+    /// the liked tweet was created, as decoded from its ID.
+    Unlike {
+        /// Unlike tweets that were created more than this many days ago
+        #[clap(long, short, value_hint = ValueHint::Other)]
+        older_than: u32,
+    },
+
+    /// Delete direct messages from imported twitter archives, by age
+    ///
+    /// This only deletes them from your view of the conversation, everyone
+    /// else in it still has them.
+    ///
+    /// Messages already deleted are skipped, so interrupted runs pick up
+    /// where they left off.
+    DeleteDms {
+        /// Delete messages that were sent more than this many days ago
         #[clap(long, short, value_hint = ValueHint::Other)]
         older_than: u32,
+    },
+
+    /// Delete ***EVERYTHING*** on the account, for leaving twitter entirely
+    ///
+    /// Imports the archive, backs up the database and archive data to
+    /// `backups` in the data directory, and then, after a typed
+    /// confirmation, deletes all tweets, then all likes,
+    /// then optionally all direct messages.
+    ///
+    /// Protected tweets are still kept.
+    ///
+    /// Optionally posts a final farewell tweet once everything is deleted.
+    Nuke {
+        /// Path to your twitter archive
+        ///
+        /// This is the folder with "Your archive.html" in it.
+        #[clap(value_hint = ValueHint::DirPath)]
+        path: PathBuf,
+
+        /// Also delete all direct messages
+        #[clap(long)]
+        dms: bool,
+
+        /// After everything is deleted, post a tweet with this text,
+        /// eg pointing to your new home
+        #[clap(long, value_hint = ValueHint::Other)]
+        farewell: Option<String>,
+
+        /// Tag this run in the run history
+        #[clap(long, short, value_hint = ValueHint::Other)]
+        tag: Option<String>,
+    },
 
-        /// Don't delete tweets unless they have *more* than this many likes.
+    /// Copy the photos and videos of tweets matching the provided filters out
+    /// of the twitter archive, to keep them before the tweets are deleted
+    ///
+    /// Media is copied to `OUTPUT/DATE/ID/`, by the date each tweet was
+    /// created and its ID.
+    BackupMedia {
+        /// Path to your twitter archive
         ///
-        /// WARNING, this is based on likes in your imported twitter archive.
-        /// This DOES NOT check for the latest information on twitter
-        #[clap(long, short = 'l', value_hint = ValueHint::Other, default_value = "0")]
-        unless_likes: u32,
+        /// This is the folder with "Your archive.html" in it.
+        #[clap(value_hint = ValueHint::DirPath)]
+        path: PathBuf,
+
+        /// Directory to copy media to
+        #[clap(long, value_hint = ValueHint::DirPath)]
+        output: PathBuf,
+
+        #[clap(flatten)]
+        filter: FilterArgs,
+    },
+
+    /// Post a single tweet, eg pointing to your new home
+    Post {
+        /// Text of the tweet
+        #[clap(value_hint = ValueHint::Other)]
+        text: String,
+    },
+
+    /// Score imported tweets by how worth keeping they are, from `0.0` to
+    /// `1.0`, for use with `delete --max-score`
+    ///
+    /// By default a simple built-in heuristic is used.
+    Score {
+        /// Score using this shell command, which receives the tweet text on
+        /// stdin and must print the score
+        #[clap(long, conflicts_with = "url", value_hint = ValueHint::CommandString)]
+        command: Option<String>,
+
+        /// Score using this HTTP endpoint, which receives a JSON
+        /// `{"id", "text"}` POST and must respond with JSON `{"score"}`
+        #[clap(long, value_hint = ValueHint::Url)]
+        url: Option<String>,
+
+        /// Also re-score tweets that already have a score
+        #[clap(long)]
+        rescore: bool,
+    },
+
+    /// Export tweets by state, or those matching saved filters, for use by
+    /// other scripts or as a record
+    Export {
+        /// Which tweets to export
+        #[clap(long, short, value_enum, required_unless_present = "filter-name")]
+        state: Option<export::State>,
 
-        /// Don't delete tweets unless they have *more* than this many retweets.
+        /// Export the tweets matching these saved filters instead, in the
+        /// order they would be deleted
+        #[clap(long, conflicts_with_all = &["state", "threads"], value_hint = ValueHint::Other)]
+        filter_name: Option<String>,
+
+        /// Format to export in
+        #[clap(long, short, value_enum, default_value = "ids")]
+        format: export::Format,
+
+        /// Only export threads, each as a single document, oldest first
         ///
-        /// WARNING, this is based on retweets in your imported twitter archive.
-        /// This DOES NOT check for the latest information on twitter
-        #[clap(long, short = 'r', value_hint = ValueHint::Other, default_value = "0")]
-        unless_retweets: u32,
+        /// Requires the Markdown or HTML formats
+        #[clap(long)]
+        threads: bool,
+
+        /// Write to this file instead of stdout
+        #[clap(long, short, value_hint = ValueHint::FilePath)]
+        output: Option<PathBuf>,
+    },
+
+    /// Save combinations of filters to use again with `--filter-name`
+    Filters {
+        #[clap(subcommand)]
+        cmd: FiltersCmd,
+    },
+
+    /// Protect tweets from ever being deleted
+    Protect {
+        #[clap(subcommand)]
+        cmd: ProtectCmd,
+    },
+
+    /// Search the text of tweets that haven't been deleted
+    ///
+    /// Matches are shown best first, with when they were created, their likes
+    /// and retweets, and the start of their text.
+    Search {
+        /// What to search for, eg `"exact phrase"`, `word*`, or `this OR that`
+        #[clap(value_hint = ValueHint::Other)]
+        query: String,
+
+        /// Show at most this many matches
+        #[clap(long, short = 'n', value_hint = ValueHint::Other, default_value = "50")]
+        limit: u32,
+    },
+
+    /// Serve core operations over a local socket, for GUI wrappers and
+    /// editor plugins
+    ///
+    /// Speaks JSON-RPC 2.0 over a unix socket, one message per line.
+    /// See the README for the available methods.
+    Serve {
+        /// Path of the socket to create
+        #[clap(long, value_hint = ValueHint::FilePath)]
+        socket: PathBuf,
+    },
+
+    /// Show everything known about a single tweet
+    Show {
+        /// Tweet ID or URL
+        #[clap(value_hint = ValueHint::Other)]
+        id: String,
+
+        /// Also show everything that happened to the tweet, in order
+        #[clap(long)]
+        history: bool,
+    },
+
+    /// Look for signs that twitter changed how its API behaves
+    ///
+    /// Every lookup made while checking tweets is recorded, and any where
+    /// twitter left tweets out, answered for more or fewer tweets than were
+    /// asked about, or said every tweet was deleted, are shown. As are
+    /// deletions that didn't match the database, see `history`.
+    Doctor {},
+
+    /// List the reports written at the end of each run, or show one
+    ///
+    /// Reports are JSON files in `$XDG_DATA_HOME/twitter_delete/reports`,
+    /// usually `~/.local/share/twitter_delete/reports`, or in `reports` in
+    /// `--data-dir` if given. They have the filters, counts, timing, rate
+    /// limits, and the tweets that failed to delete and why, of a run. Attach
+    /// them to bug reports.
+    Reports {
+        /// Print the report for this run
+        #[clap(value_hint = ValueHint::Other)]
+        run: Option<i32>,
+
+        /// Only list reports of runs with this tag
+        #[clap(long, short, value_hint = ValueHint::Other, conflicts_with = "run")]
+        tag: Option<String>,
+    },
+
+    /// Show previous runs that modified tweets
+    History {
+        /// Only show runs with this tag
+        #[clap(long, short, value_hint = ValueHint::Other)]
+        tag: Option<String>,
+    },
+
+    /// Manage imported accounts
+    Accounts {
+        #[clap(subcommand)]
+        cmd: AccountsCmd,
+    },
+
+    /// Archive an account after deleting everything from it, such as before
+    /// deactivating it
+    ///
+    /// Its tweets, likes, and direct messages can still be listed, searched,
+    /// and exported, but can no longer be changed, even by mistake.
+    ///
+    /// Refuses to archive an account with tweets left to delete, other than
+    /// protected tweets.
+    ArchiveAccount {
+        /// Archive the account even if tweets are left to delete
+        #[clap(long)]
+        force: bool,
+
+        /// Unarchive the account, so it can be changed again
+        #[clap(long, conflicts_with = "force")]
+        undo: bool,
+    },
+
+    /// Authorize an account with the app, using a PIN shown by twitter
+    ///
+    /// The resulting API keys are saved to `keys` in the data directory, and
+    /// used for the account if it has been imported.
+    Auth {
+        #[clap(subcommand)]
+        cmd: Option<AuthCmd>,
     },
 
     /// Show information about tweets in the database
     Stats {
-        //
+        /// Show the tweets as they stood at the start of this date instead,
+        /// eg `2020-01-01`
+        ///
+        /// Tweets deleted before deletion times were recorded are counted
+        /// separately.
+        #[clap(long, value_hint = ValueHint::Other)]
+        as_of: Option<String>,
+    },
+
+    /// Print shell completions for every subcommand and flag
+    ///
+    /// eg `twitter_delete completions bash > /etc/bash_completion.d/twitter_delete`
+    Completions {
+        #[clap(value_enum)]
+        shell: Shell,
+    },
+
+    /// Diagnose the database
+    #[clap(hide = true)]
+    Db {
+        #[clap(subcommand)]
+        cmd: DbCmd,
     },
 
     /// Update the application database if needed
@@ -123,323 +859,2848 @@ enum Args {
     },
 }
 
-fn get_acc(path: &Path) -> Result<Account> {
-    let account = get_account(path)?;
-    if account.id_str == "0" {
-        return Err(anyhow!(
-            "Invalid Twitter account ID 0 for @{} {}",
-            &account.user_name,
-            &account.display_name
-        ));
-    };
-    Ok(account)
-}
+impl Cmd {
+    /// Name of this command, if it can take long enough to notify when it's
+    /// done, see `--notify`
+    fn long_running(&self) -> Option<&'static str> {
+        match self {
+            Self::Import { .. } => Some("import"),
+            Self::Check {} => Some("check"),
+            Self::Delete {
+                dry_run: false,
+                diff_last_run: false,
+                ..
+            } => Some("delete"),
+            Self::Commit { .. } => Some("commit"),
+            Self::Watch { .. } => Some("watch"),
+            Self::DeleteLikes { .. } => Some("delete-likes"),
+            Self::Unlike { .. } => Some("unlike"),
+            Self::DeleteDms { .. } => Some("delete-dms"),
+            Self::Nuke { .. } => Some("nuke"),
+            _ => None,
+        }
+    }
 
-/// Import tweets from the twitter archive to our database
-///
-/// Ignores any tweets already in the database
-fn import_tweets(conn: &mut SqliteConnection, path: &Path) -> Result<usize> {
-    let tweets = collect_tweets(path)?;
-    let account = get_acc(path)?;
+    /// Whether this command sends requests to twitter, so can't pretend it's
+    /// another time with `--now`
+    fn sends_requests(&self) -> bool {
+        match self {
+            Self::Delete {
+                dry_run,
+                diff_last_run,
+                ..
+            } => !dry_run && !diff_last_run,
+            Self::Auth {
+                cmd: Some(AuthCmd::Store {} | AuthCmd::Forget {}),
+            } => false,
+            Self::Import { .. }
+            | Self::Check {}
+            | Self::Review { .. }
+            | Self::Commit { .. }
+            | Self::Watch { .. }
+            | Self::DeleteLikes { .. }
+            | Self::Unlike { .. }
+            | Self::DeleteDms { .. }
+            | Self::Nuke { .. }
+            | Self::Post { .. }
+            | Self::Serve { .. }
+            | Self::Auth { .. } => true,
+            _ => false,
+        }
+    }
 
-    let tweets: Vec<MTweet> = tweets
-        .into_iter()
-        .map(|tw| {
-            // Unwrap should only fail if twitter archive is bad/evil
-            // Also `?` cant be used here
-            MTweet::new(
-                tw.id_str,
-                tw.retweets.parse().unwrap(),
-                tw.likes.parse().unwrap(),
-                PrimitiveDateTime::parse(&tw.created_at, TWITTER_DATE)
-                    .unwrap()
-                    .assume_utc()
-                    .unix_timestamp(),
-                account.id_str.clone(),
-            )
-        })
-        .collect();
-
-    let added = conn.transaction::<_, anyhow::Error, _>(|conn| {
-        add_account(
-            conn,
-            &[MAccount {
-                id_str: account.id_str,
-                user_name: account.user_name,
-                display_name: account.display_name,
-            }],
-        )?;
+    /// The filters given to this command, if it takes any
+    fn filter_mut(&mut self) -> Option<&mut FilterArgs> {
+        match self {
+            Self::Delete { filter, .. }
+            | Self::Simulate { filter, .. }
+            | Self::Review { filter, .. }
+            | Self::Watch { filter, .. }
+            | Self::BackupMedia { filter, .. }
+            | Self::Db {
+                cmd: DbCmd::ExplainQuery { filter },
+            } => Some(filter),
+            Self::Filters {
+                cmd: FiltersCmd::Save { filter, .. },
+            } => Some(filter),
+            Self::Stage { stage } => Some(&mut stage.filter),
+            _ => None,
+        }
+    }
+}
 
-        let added = db::add_tweets(conn, &tweets)?;
-        Ok(added)
-    })?;
+/// Format of results, see `--output`
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Output {
+    /// Prose for people
+    Text,
 
-    Ok(added)
+    /// JSON for scripts
+    Json,
 }
 
-fn main() -> Result<()> {
-    let home = std::env::var_os("HOME").ok_or_else(|| anyhow!("Missing $HOME"))?;
-    let config_path = Path::new(&home).join(".config/twitter_delete");
-    let db_path = config_path.join("tweets.db");
-    let utc_offset = UtcOffset::current_local_offset()?;
+/// State to seed tweets with, see [`Cmd::ImportIds`]
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Mark {
+    /// Tweets were already deleted by another tool
+    Deleted,
 
-    fs::create_dir_all(config_path)?;
-    let keys: Access = from_str(ACCESS)?;
+    /// Tweets should never be deleted
+    Protected,
+}
 
-    let args = Args::parse();
+/// What to stage with `stage`, see [`StageArgs`]
+#[derive(clap::Args, Debug)]
+struct StageOpts {
+    #[clap(flatten)]
+    filter: FilterArgs,
 
-    let mut conn = crate::db::create_db(&db_path)?;
-    let conn = &mut conn;
+    /// Split the matching tweets into batches
+    #[clap(long, value_enum)]
+    split_by: Option<SplitBy>,
 
-    let client = ClientBuilder::new().build()?;
+    /// Number of tweets in each batch, with `--split-by count`
+    #[clap(long, value_hint = ValueHint::Other, default_value = "3000")]
+    batch_size: usize,
 
-    let progress_style = ProgressStyle::with_template(
-        "{msg}\n[{elapsed_precise}] {wide_bar} {pos:>7}/{len:7} ({percent}%) \nETA: {eta_precise}\n{prefix}",
-    )?;
-    let pb = ProgressBar::new(0);
-    pb.set_style(progress_style);
+    /// Order to stage tweets in, see `delete --order`
+    #[clap(long, value_enum, default_value = "id")]
+    order: Order,
 
-    let rate_limited = |limit, _res: &Response| {
-        let secs = match limit {
-            RateLimit::Until(secs) => secs,
-            RateLimit::Unknown => 60 * 15,
-        } as i64;
-        let secs = secs
-            .checked_sub(OffsetDateTime::now_utc().unix_timestamp())
-            .unwrap_or(60 * 15);
-
-        pb.set_prefix(format!(
-            "Rate limited, waiting until {} ({secs} seconds)",
-            (OffsetDateTime::now_utc() + Duration::seconds(secs))
-                .to_offset(utc_offset)
-                .time()
-                .format(HUMAN_TIME)?
-        ));
+    /// Tag the batches in the run history
+    #[clap(long, short, value_hint = ValueHint::Other)]
+    tag: Option<String>,
+}
 
-        Ok(())
-    };
-    let mut stdout = stdout().lock();
+impl From<StageOpts> for StageArgs {
+    fn from(opts: StageOpts) -> Self {
+        Self {
+            filter: opts.filter,
+            split_by: opts.split_by.map(|split| match split {
+                SplitBy::Year => Split::Year,
+                SplitBy::Count => Split::Count,
+            }),
+            batch_size: opts.batch_size,
+            order: opts.order,
+            tag: opts.tag,
+        }
+    }
+}
 
-    match args {
-        Args::Import { path } => {
-            let added = import_tweets(conn, &path)?;
-            writeln!(
-                stdout,
-                "Imported {added} tweets. Total tweets {}",
-                count_tweets(conn)?
-            )?;
+/// How to split staged tweets into batches, see `stage --split-by`
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum SplitBy {
+    /// One batch per year the tweets were created in
+    Year,
 
-            // Lookup `tweets` on twitter and mark the ones that are already
-            // deleted
-            //
-            // This skips tweets that have already been checked
-            let unchecked_tweets: Vec<String> = tdb::dsl::tweets
-                .order(tdb::dsl::id_str.asc())
-                .filter(existing())
-                .select(tdb::dsl::id_str)
-                .load::<String>(conn)?;
-
-            let mut total = 0;
-
-            pb.set_length(unchecked_tweets.len() as u64);
-            pb.set_message(format!(
-                "Checking whether {} tweets were already deleted, out of {} total tweets",
-                unchecked_tweets.len(),
-                count_tweets(conn)?
-            ));
+    /// Batches of at most `--batch-size` tweets
+    Count,
+}
 
-            lookup_tweets(
-                &client,
-                &keys,
-                unchecked_tweets.iter().map(|f| f.as_str()),
-                |r, l| {
-                    pb.enable_steady_tick(std::time::Duration::from_secs(1));
-                    rate_limited(r, l)
-                },
-                |res| {
-                    pb.disable_steady_tick();
-                    let res = res.error_for_status()?;
-                    let res: LookupResp = res.json()?;
-                    let mut ids: Vec<&str> = res
-                        .id
-                        .iter()
-                        .filter(|(_, v)| v.is_none())
-                        .map(|(k, _)| k.as_str())
-                        .collect();
-                    // Make sure its sorted
-                    ids.sort();
-
-                    let gone = conn.transaction::<_, anyhow::Error, _>(|conn| {
-                        // Mark all tweets as checked
-                        checked(conn, res.id.keys().map(|k| k.as_str()))?;
-                        let gone = deleted(conn, ids.iter().copied())?;
-                        Ok(gone)
-                    })?;
-                    total += gone;
+/// Manage imported accounts
+#[derive(Subcommand, Debug)]
+enum AccountsCmd {
+    /// List imported accounts and the API keys they use
+    List {
+        //
+    },
 
-                    // Advance progress bar
-                    pb.inc(100);
-                    pb.set_prefix(format!("Marked {gone} tweets as already deleted"));
+    /// Use the API keys in a file for an account, instead of the default
+    ///
+    /// The keys must be for the account.
+    SetKeys {
+        /// Account handle
+        #[clap(value_hint = ValueHint::Other)]
+        handle: String,
 
-                    Ok(())
-                },
-            )?;
-            pb.finish();
-            writeln!(
-                stdout,
-                "Marked {total} total tweets as already deleted from twitter"
-            )?;
-        }
-        Args::Delete {
-            exclude,
-            older_than,
-            unless_likes,
-            unless_retweets,
-        } => {
-            let off = Duration::days(older_than.into());
-            let off = OffsetDateTime::now_utc().checked_sub(off).ok_or_else(|| {
-                anyhow!(
-                    "Specified offset of {} ({off}) is too far in the past",
-                    util::human_dur(off),
-                )
-            })?;
-            let off = off.unix_timestamp();
-
-            let to_process: Vec<String> = tdb::dsl::tweets
-                .order(tdb::dsl::id_str.asc())
-                .filter(created_before(off))
-                .filter(tdb::dsl::deleted.eq(false))
-                .filter(diesel::dsl::not(tdb::dsl::id_str.eq_any(&exclude)))
-                .filter(tdb::dsl::likes.le(unless_likes as i32))
-                .filter(tdb::dsl::retweets.le(unless_retweets as i32))
-                .select(tdb::dsl::id_str)
-                .load::<String>(conn)?;
+        /// Path to the API keys, in the same JSON format as the default keys
+        #[clap(value_hint = ValueHint::FilePath)]
+        path: PathBuf,
+    },
+}
 
-            pb.set_length(to_process.len() as u64);
-            pb.set_message("Deleting tweets");
+/// Manage where API keys are stored
+#[derive(Subcommand, Debug)]
+enum AuthCmd {
+    /// Move the API keys for an account into the OS keyring
+    ///
+    /// Requires building with the `keyring` feature.
+    Store {
+        //
+    },
 
-            let mut total = 0;
+    /// Remove the API keys for an account from the OS keyring
+    ///
+    /// The default keys are used afterwards.
+    Forget {
+        //
+    },
 
-            delete_tweets(
-                &client,
+    /// Check what the API keys for an account are allowed to do
+    ///
+    /// Sends a harmless request to every kind of endpoint used, deleting
+    /// only IDs that don't exist, so permission problems such as read-only
+    /// keys are found before a long run.
+    Test {
+        //
+    },
+}
+
+/// Diagnose the database
+#[derive(Subcommand, Debug)]
+enum DbCmd {
+    /// Show the SQL and SQLite query plan used to select tweets for the
+    /// provided filters, for diagnosing slow runs
+    ExplainQuery {
+        #[clap(flatten)]
+        filter: FilterArgs,
+    },
+}
+
+/// Manage saved filters
+#[derive(Subcommand, Debug)]
+enum FiltersCmd {
+    /// Save these filters under `name`, replacing any already saved under it
+    Save {
+        /// Name to use the filters with, as `--filter-name <NAME>`
+        #[clap(value_hint = ValueHint::Other)]
+        name: String,
+
+        #[clap(flatten)]
+        filter: Box<FilterArgs>,
+    },
+
+    /// List saved filters
+    List {
+        //
+    },
+
+    /// Remove the filters saved under `name`
+    Remove {
+        /// Name the filters were saved under
+        #[clap(value_hint = ValueHint::Other)]
+        name: String,
+    },
+}
+
+/// Manage protected tweets
+#[derive(Subcommand, Debug)]
+enum ProtectCmd {
+    /// Protect these tweet IDs from deletion
+    Add {
+        /// Tweet IDs to protect
+        #[clap(value_delimiter = ',', value_hint = ValueHint::Other, required = true)]
+        ids: Vec<TweetId>,
+
+        /// Note explaining why these tweets are kept
+        #[clap(long, short, value_hint = ValueHint::Other)]
+        note: Option<String>,
+    },
+
+    /// Remove protection from these tweet IDs
+    Remove {
+        /// Tweet IDs to stop protecting
+        #[clap(value_delimiter = ',', value_hint = ValueHint::Other, required = true)]
+        ids: Vec<TweetId>,
+    },
+
+    /// List protected tweets and their notes
+    List {
+        //
+    },
+
+    /// Protect, or remove protection from, every tweet ID or URL in a file
+    ///
+    /// Accepts plain text or CSV, as with `import-ids`
+    Apply {
+        /// Path to the file
+        #[clap(value_hint = ValueHint::FilePath)]
+        path: PathBuf,
+
+        /// Remove protection instead
+        #[clap(long, conflicts_with = "note")]
+        clear: bool,
+
+        /// Note explaining why these tweets are kept
+        #[clap(long, short, value_hint = ValueHint::Other)]
+        note: Option<String>,
+    },
+
+    /// Export protected tweet IDs, one per line, for use with `protect apply`
+    Export {
+        /// Write to this file instead of stdout
+        #[clap(long, short, value_hint = ValueHint::FilePath)]
+        output: Option<PathBuf>,
+    },
+}
+
+/// Save `keys` for `user_id` into `dir`, returning where they were saved
+///
+/// They can tweet and delete as the account, so on unix only the owner can
+/// read them.
+fn save_keys(dir: &Path, user_id: &str, keys: &Access) -> Result<PathBuf> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+        builder.mode(0o700);
+        options.mode(0o600);
+    }
+    builder.create(dir)?;
+    let path = dir.join(format!("{user_id}.json"));
+    let mut file = options.open(&path)?;
+    // The mode only applies to new files
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(serde_json::to_string_pretty(keys)?.as_bytes())?;
+    Ok(path)
+}
+
+fn get_acc(path: &Path) -> Result<Account> {
+    let account = get_account(path).map_err(|e| {
+        anyhow!(
+            "{}",
+            tr!(
+                "Couldn't read the account from {path}: {error}\nGive it with `import \
+                 --account-id ID --handle NAME` instead",
+                path = path.display(),
+                error = e
+            )
+        )
+    })?;
+    if account.id_str == "0" {
+        return Err(anyhow!(
+            "{}",
+            tr!(
+                "Invalid Twitter account ID 0 for @{handle} {name}\nGive the real one with \
+                 `import --account-id ID --handle {handle}` instead",
+                handle = account.user_name,
+                name = account.display_name
+            )
+        ));
+    };
+    Ok(account)
+}
+
+/// The account `id` with handle `handle`, for archives where it can't be
+/// read, see [`get_acc`]
+///
+/// The display name is taken from the archive at `path` if possible.
+fn manual_acc(path: &Path, id: &str, handle: &str) -> Result<Account> {
+    let handle = handle.trim_start_matches('@');
+    if !matches!(id.parse::<u64>(), Ok(n) if n > 0) {
+        return Err(anyhow!(
+            "{}",
+            tr!(
+                "Invalid Twitter account ID `{id}`, expected a number",
+                id = id
+            )
+        ));
+    }
+    Ok(Account {
+        id_str: id.to_owned(),
+        user_name: handle.to_owned(),
+        display_name: get_account(path)
+            .map(|a| a.display_name)
+            .unwrap_or_else(|_| handle.to_owned()),
+    })
+}
+
+/// Check the twitter archive at `path` is for the account with handle
+/// `handle`, if given, so it can't be used for the wrong account
+fn check_archive(path: &Path, handle: Option<&str>) -> Result<()> {
+    let handle = match handle {
+        Some(handle) => handle.trim_start_matches('@'),
+        None => return Ok(()),
+    };
+    let account = get_acc(path)?;
+    if !account.user_name.eq_ignore_ascii_case(handle) {
+        return Err(anyhow!(
+            "{}",
+            tr!(
+                "{path} is for @{archived}, not @{handle}",
+                path = path.display(),
+                archived = account.user_name,
+                handle = handle
+            )
+        ));
+    }
+    Ok(())
+}
+
+/// Check the tweets of `account` with [`check_tweets`], showing progress on
+/// `pb`
+///
+/// Returns how many were already deleted, or [`None`] if every tweet has
+/// already been checked.
+fn check_account<OnLimit>(
+    conn: &mut SqliteConnection,
+    keys: &Access,
+    api: ApiOptions,
+    account: &MAccount,
+    pb: &ProgressBar,
+    on_limit: OnLimit,
+) -> Result<Option<usize>>
+where
+    OnLimit: FnMut(RateLimit, &AsyncResponse) -> Result<()>,
+{
+    let total = count_totals(conn, Some(&account.id_str))?.imported;
+    let checked = check_tweets(
+        conn,
+        keys,
+        api,
+        &account.id_str,
+        on_limit,
+        |progress| match progress {
+            Checking::Started(count) => {
+                pb.set_length(count as u64);
+                pb.set_message(tr!(
+                    "Checking whether {count} tweets were already deleted, out of {total} total \
+                     tweets, as @{handle}",
+                    count = count,
+                    total = total,
+                    handle = account.user_name
+                ));
+            }
+            Checking::Batch { requested, gone } => {
+                pb.disable_steady_tick();
+                pb.inc(requested as u64);
+                pb.set_prefix(tr!("Marked {gone} tweets as already deleted", gone = gone));
+            }
+            Checking::Retrying(count) => pb.inc_length(count as u64),
+        },
+    )?;
+    let checked = match checked {
+        Some(checked) => checked,
+        None => return Ok(None),
+    };
+    pb.finish();
+    if checked.missing > 0 {
+        warn!(
+            "{}",
+            tr!(
+                "Twitter left {count} tweets out of its responses, they are still unchecked, run \
+                 `check` to try again",
+                count = checked.missing
+            )
+        );
+    }
+    Ok(Some(checked.gone))
+}
+
+/// Delete `ids` with [`execute_deletion`], showing progress on `pb`
+fn delete_ids<OnLimit>(
+    conn: &mut SqliteConnection,
+    keys: &Access,
+    api: ApiOptions,
+    run: i32,
+    ids: &[TweetId],
+    pb: &ProgressBar,
+    on_limit: OnLimit,
+) -> Result<usize>
+where
+    OnLimit: FnMut(RateLimit, &AsyncResponse) -> Result<()>,
+{
+    let res = execute_deletion(
+        conn,
+        keys,
+        api,
+        run,
+        ids,
+        on_limit,
+        |id, deletion, retweet| {
+            // Long passes of `watch` would otherwise look hung
+            systemd::ping();
+            show_deletion(pb, id, deletion, retweet);
+        },
+    );
+    suggest_resume(&res, run);
+    res
+}
+
+/// Show on `pb` how twitter responded to deleting `id`, see
+/// [`execute_deletion`]
+fn show_deletion(pb: &ProgressBar, id: &TweetId, deletion: Deletion, retweet: bool) {
+    pb.disable_steady_tick();
+    pb.inc(1);
+    match deletion {
+        Deletion::Forbidden(true) => pb.set_prefix(tr!("Giving up on unretweeting {id}", id = id)),
+        Deletion::Forbidden(false) => pb.set_prefix(tr!("Failed to unretweet {id}", id = id)),
+        Deletion::Gone(_) => pb.set_prefix(tr!("Already deleted (re)tweet? {id}", id = id)),
+        Deletion::Deleted(_) if retweet => pb.set_prefix(tr!("Unretweeted {id}", id = id)),
+        Deletion::Deleted(_) => pb.set_prefix(tr!("Deleted tweet {id}", id = id)),
+        Deletion::Error => (),
+    }
+}
+
+/// Tell the user how to resume `run` once their keys are updated, if `res`
+/// failed because twitter rejected them
+fn suggest_resume<T>(res: &Result<T>, run: i32) {
+    if let Err(e) = res {
+        if e.is::<Unauthorized>() {
+            warn!(
+                "{}",
+                tr!(
+                    "Progress has been saved. Once your keys are updated, resume \
+                     with\ntwitter_delete commit {run}",
+                    run = run
+                )
+            );
+        }
+    }
+}
+
+/// Show on `pb` that `id` was unliked, or `already` was
+fn show_unliked(pb: &ProgressBar, id: &TweetId, already: bool) {
+    pb.disable_steady_tick();
+    pb.inc(1);
+    if already {
+        pb.set_prefix(tr!("Already unliked? {id}", id = id));
+    } else {
+        pb.set_prefix(tr!("Unliked tweet {id}", id = id));
+    }
+}
+
+/// Show on `pb` that direct message `id` was deleted, or `already` was
+fn show_message_deleted(pb: &ProgressBar, id: &str, already: bool) {
+    pb.disable_steady_tick();
+    pb.inc(1);
+    if already {
+        pb.set_prefix(tr!("Already deleted message? {id}", id = id));
+    } else {
+        pb.set_prefix(tr!("Deleted message {id}", id = id));
+    }
+}
+
+/// Show `ids`, in order, with when they were created, their likes and
+/// retweets, and the start of their text, for `delete --dry-run` and `search`
+fn preview(conn: &mut SqliteConnection, ids: &[TweetId], out: &mut impl Write) -> Result<()> {
+    for Summary {
+        id,
+        created_at,
+        likes,
+        retweets,
+        text,
+    } in summarize(conn, ids)?
+    {
+        let created = OffsetDateTime::from_unix_timestamp(created_at)?.format(DATE)?;
+        writeln!(
+            out,
+            "{id} {created} {}",
+            tr!(
+                "{likes} likes {retweets} retweets",
+                likes = likes,
+                retweets = retweets
+            )
+        )?;
+        // Tweets imported before their text was recorded have none
+        if let Some(tweet_text) = text {
+            let line = tweet_text.split_whitespace().collect::<Vec<_>>().join(" ");
+            match line.char_indices().nth(100) {
+                Some((i, _)) => writeln!(out, "    {}...", &line[..i])?,
+                None => writeln!(out, "    {line}")?,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Show `out` how many of `account`s tweets are about to be deleted, when they
+/// were created, and a few of them, then ask for confirmation
+///
+/// Deleting tweets of `every` age asks for a longer phrase.
+fn confirm_delete(
+    conn: &mut SqliteConnection,
+    ids: &[TweetId],
+    account: &MAccount,
+    every: bool,
+    out: &mut impl Write,
+) -> Result<()> {
+    let summaries = summarize(conn, ids)?;
+    let first = summaries
+        .iter()
+        .map(|s| s.created_at)
+        .min()
+        .unwrap_or_default();
+    let last = summaries
+        .iter()
+        .map(|s| s.created_at)
+        .max()
+        .unwrap_or_default();
+    // Spread out, so it's not all from the start of the order
+    let step = (ids.len() / 5).max(1);
+    let sample: Vec<TweetId> = ids.iter().step_by(step).take(5).cloned().collect();
+
+    writeln!(out, "{}", tr!("For example"))?;
+    preview(conn, &sample, out)?;
+    writeln!(
+        out,
+        "{}",
+        tr!(
+            "{count} tweets from @{handle} will be deleted, created between {first} and {last}",
+            count = ids.len(),
+            handle = account.user_name,
+            first = OffsetDateTime::from_unix_timestamp(first)?.format(DATE)?,
+            last = OffsetDateTime::from_unix_timestamp(last)?.format(DATE)?
+        )
+    )?;
+    if every {
+        writeln!(
+            out,
+            "{}",
+            tr!("`--older-than 0` matches tweets of every age")
+        )?;
+    }
+    writeln!(out, "{}", tr!("This CAN NOT be undone."))?;
+
+    let phrase = if every { "delete everything" } else { "delete" };
+    write!(
+        out,
+        "{}",
+        tr!("Type `{phrase}` to continue: ", phrase = phrase)
+    )?;
+    out.flush()?;
+    let mut input = String::new();
+    stdin().read_line(&mut input)?;
+    if input.trim() != phrase {
+        return Err(anyhow!(
+            "{}",
+            tr!("Confirmation did not match, nothing was deleted")
+        ));
+    }
+    Ok(())
+}
+
+/// Check `filter`, which `watch` is about to run with for `account` as of
+/// `now`, against the filters it last ran with, then record it as the
+/// policy for `account`
+///
+/// Filters that would delete tweets the last ones kept, such as a smaller
+/// `older_than` from a typo in config.toml, have to be confirmed by typing
+/// their [hash][twitter_delete::policy_hash], or giving it as `accepted`, as
+/// nobody is asked again before every pass deletes them. See [`check_policy`].
+fn confirm_policy(
+    conn: &mut SqliteConnection,
+    filter: &FilterArgs,
+    account: &MAccount,
+    now: OffsetDateTime,
+    accepted: Option<&str>,
+    out: &mut impl Write,
+) -> Result<()> {
+    let filters = filter.canonical();
+    let change = match check_policy(conn, filter, &account.id_str, now)? {
+        Some(change) if accepted != Some(change.hash.as_str()) => change,
+        _ => return accept_watch_policy(conn, &account.id_str, &filters, now.unix_timestamp()),
+    };
+    writeln!(
+        out,
+        "{}",
+        tr!(
+            "These filters would delete {count} tweets from @{handle} that the filters \
+             `watch` last ran with kept",
+            count = change.more,
+            handle = account.user_name
+        )
+    )?;
+    let before: serde_json::Value = from_str(&change.previous).unwrap_or_default();
+    let after: serde_json::Value = from_str(&change.filters)?;
+    if let (Some(before), Some(after)) = (before.as_object(), after.as_object()) {
+        for (name, value) in after {
+            let old = before.get(name).unwrap_or(&serde_json::Value::Null);
+            if old != value {
+                writeln!(out, "  {name}: {old} -> {value}")?;
+            }
+        }
+    }
+    write!(
+        out,
+        "{}",
+        tr!(
+            "Type `{hash}` to continue, or give it with `--accept-policy`: ",
+            hash = change.hash
+        )
+    )?;
+    out.flush()?;
+    let mut input = String::new();
+    stdin().read_line(&mut input)?;
+    if input.trim() != change.hash {
+        return Err(anyhow!(
+            "{}",
+            tr!("Confirmation did not match, nothing was deleted")
+        ));
+    }
+    accept_watch_policy(conn, &account.id_str, &filters, now.unix_timestamp())
+}
+
+/// What `import` did, for `--output json`
+#[derive(Debug, Serialize)]
+struct ImportReport {
+    /// New tweets
+    added: usize,
+
+    /// Existing tweets with newer likes or retweets
+    updated: usize,
+
+    /// Tweets in the database
+    total: i64,
+
+    /// Liked tweets
+    likes: usize,
+
+    /// Direct messages
+    messages: usize,
+
+    /// Tweets found to be already deleted, [`None`] if every tweet was
+    /// already checked
+    already_deleted: Option<usize>,
+}
+
+/// What `delete` did, for `--output json`
+#[derive(Debug, Serialize)]
+struct DeleteReport {
+    /// Run ID, or the previous run compared to with `--diff-last-run`
+    run: Option<i32>,
+
+    /// Whether nothing was deleted, with `--dry-run` or `--diff-last-run`
+    dry_run: bool,
+
+    /// Tweets matching the filters
+    matched: usize,
+
+    /// Tweets deleted, or marked deleted as they already were
+    deleted: usize,
+
+    /// IDs of the tweets deleted by the run, including before it was
+    /// resumed, that would be deleted with `--dry-run`, or newly matched
+    /// with `--diff-last-run`
+    ids: Vec<TweetId>,
+}
+
+/// Counts of tweets for one account, or every account, for `--output json`
+#[derive(Debug, Serialize)]
+struct AccountStats<T> {
+    /// Account handle, [`None`] for the total of every account
+    handle: Option<String>,
+
+    /// Account ID, [`None`] for the total of every account
+    id: Option<String>,
+
+    #[serde(flatten)]
+    counts: T,
+}
+
+/// Print `value` to `out` as a single line of JSON, for `--output json`
+fn print_json(out: &mut impl Write, value: &impl Serialize) -> Result<()> {
+    serde_json::to_writer(&mut *out, value)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Warn `out` if `filter`s exceptions for likes and retweets can't be trusted
+/// for some of the tweets it matched, `ids`, from `account`, see [`engagement`]
+fn warn_engagement(
+    conn: &mut SqliteConnection,
+    filter: &Filter,
+    ids: &[TweetId],
+    account: &MAccount,
+    out: &mut impl Write,
+) -> Result<()> {
+    if !filter.uses_engagement() {
+        return Ok(());
+    }
+    let Engagement {
+        unsettled,
+        newest_archive,
+    } = engagement(conn, ids, &account.id_str)?;
+    if unsettled > 0 {
+        writeln!(
+            out,
+            "{}",
+            tr!(
+                "Warning: {count} of these tweets were posted less than a week before their \
+                 archive was generated, or come from an archive without a date, so their likes \
+                 and retweets may be too low for your filters to keep them",
+                count = unsettled
+            )
+        )?;
+    }
+
+    if let Some(newest) = newest_archive.filter(|newest| filter.created_before > *newest) {
+        writeln!(
+            out,
+            "{}",
+            tr!(
+                "Warning: The newest archive for @{handle} is from {date}, tweets posted since \
+                 then aren't imported and won't be deleted, import a newer archive first",
+                handle = account.user_name,
+                date = OffsetDateTime::from_unix_timestamp(newest)?.format(DATE)?
+            )
+        )?;
+    }
+    Ok(())
+}
+
+/// Explain that nothing of `account`s matched the users filters, with hints
+/// as to why, see [`unmatched`]
+fn nothing_matches(conn: &mut SqliteConnection, account: &str, out: &mut impl Write) -> Result<()> {
+    let Unmatched {
+        imported: total,
+        deleted: gone,
+        protected,
+    } = unmatched(conn, account)?;
+
+    writeln!(
+        out,
+        "{}",
+        tr!(
+            "Nothing matches your filters ({total} tweets total, {gone} already deleted)",
+            total = total,
+            gone = gone
+        )
+    )?;
+    if total == 0 {
+        writeln!(
+            out,
+            "{}",
+            tr!("Hint: Import your twitter archive first with `import`")
+        )?;
+    } else if gone + protected == total {
+        writeln!(
+            out,
+            "{}",
+            tr!("Hint: Every tweet is already deleted, or protected. See `protect list`")
+        )?;
+    } else {
+        writeln!(
+            out,
+            "{}",
+            tr!(
+                "Hint: Only tweets older than `--older-than` days, with at most `--unless-likes` \
+                 likes\nand `--unless-retweets` retweets match, both of which default to 0"
+            )
+        )?;
+    }
+    Ok(())
+}
+
+/// The data directory, see `--data-dir`
+fn data_dir(args: &Args) -> Result<PathBuf> {
+    match &args.data_dir {
+        Some(dir) => Ok(dir.clone()),
+        None => {
+            let home = env::var_os("HOME").ok_or_else(|| {
+                anyhow!(
+                    "{}",
+                    tr!("Missing $HOME, choose a data directory with `--data-dir`")
+                )
+            })?;
+            Ok(Path::new(&home).join(".config/twitter_delete"))
+        }
+    }
+}
+
+/// Where run reports are written, see `reports`
+fn reports_dir(args: &Args) -> Result<PathBuf> {
+    if let Some(dir) = &args.data_dir {
+        return Ok(dir.join("reports"));
+    }
+    let data = match env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => {
+            let home = env::var_os("HOME").ok_or_else(|| {
+                anyhow!(
+                    "{}",
+                    tr!("Missing $HOME, choose a data directory with `--data-dir`")
+                )
+            })?;
+            Path::new(&home).join(".local/share")
+        }
+    };
+    Ok(data.join("twitter_delete/reports"))
+}
+
+fn main() -> Result<()> {
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    i18n::set_lang(args.lang.unwrap_or_else(Lang::from_env));
+    if args.gentle {
+        args.concurrency = 1;
+        twitter::set_gentle(true);
+    }
+    // Before anything else, so they can be generated without a data directory
+    if let Cmd::Completions { shell } = args.cmd {
+        clap_complete::generate(shell, &mut Args::command(), "twitter_delete", &mut stdout());
+        return Ok(());
+    }
+    Config::load(
+        &data_dir(&args)?.join("config.toml"),
+        args.profile.as_deref(),
+    )?
+    .apply(&mut args, &matches);
+    // Flushed when dropped, at the end of main
+    let _log = logging::init(&data_dir(&args)?.join("logs"), args.verbose, args.quiet)?;
+    if args.notify {
+        notify::supported()?;
+    }
+    let name = args.notify.then(|| args.cmd.long_running()).flatten();
+    let output = args.output;
+    let result = run(args);
+    if let Some(name) = name {
+        notify::finished(name, &result);
+    }
+    if let (Output::Json, Err(e)) = (output, &result) {
+        println!("{}", serde_json::json!({ "error": format!("{e:#}") }));
+    }
+    result
+}
+
+/// Run the command in `args`
+fn run(args: Args) -> Result<()> {
+    let config_path = data_dir(&args)?;
+    let db_path = args
+        .db
+        .clone()
+        .unwrap_or_else(|| config_path.join("tweets.db"));
+
+    let backups_path = config_path.join("backups");
+    fs::create_dir_all(&config_path)?;
+
+    let credentials = Credentials {
+        path: args
+            .credentials
+            .clone()
+            .unwrap_or_else(|| config_path.join("credentials.json")),
+        configured: args.keys.clone(),
+    };
+    let clock: Box<dyn Clock> = match args.now {
+        // Would delete tweets that aren't old enough yet, or never move on
+        Some(_) if args.cmd.sends_requests() => {
+            return Err(anyhow!(
+                "{}",
+                tr!(
+                    "`--now` can't be used with commands that send requests to twitter, try \
+                     `delete --dry-run`"
+                )
+            ));
+        }
+        Some(now) => Box::new(FrozenClock(OffsetDateTime::from_unix_timestamp(now)?)),
+        None => Box::new(SystemClock),
+    };
+    let concurrent = create_concurrent(args.concurrency)?;
+    let api = ApiOptions {
+        api: args.api,
+        concurrent: &concurrent,
+        clock: clock.as_ref(),
+    };
+    // Only used for dates and display, so not knowing it isn't fatal
+    let utc_offset = match (&args.tz, &args.utc_offset) {
+        (Some(tz), _) => util::tz_offset(tz, clock.now())?,
+        (None, Some(offset)) => UtcOffset::parse(offset, OFFSET).map_err(|e| {
+            anyhow!(
+                "{}",
+                tr!(
+                    "Invalid UTC offset `{offset}`, expected eg `+02:00`: {error}",
+                    offset = offset,
+                    error = e
+                )
+            )
+        })?,
+        (None, None) => UtcOffset::current_local_offset().unwrap_or_else(|_| {
+            warn!(
+                "{}",
+                tr!(
+                    "Couldn't find the local time zone, using UTC. Choose one with `--tz` or \
+                     `--utc-offset`"
+                )
+            );
+            UtcOffset::UTC
+        }),
+    };
+
+    let mut conn = crate::db::create_db(&db_path)?;
+    let conn = &mut conn;
+    let reports_path = reports_dir(&args)?;
+    // Written when dropped, at the end of the command
+    let reports = report::Reports::new(conn, &db_path, reports_path.clone(), clock.as_ref())?;
+
+    let client = create_client()?;
+
+    let progress_style = ProgressStyle::with_template(
+        "{msg}\n[{elapsed_precise}] {wide_bar} {pos:>7}/{len:7} ({percent}%) \nETA: {eta_precise}\n{prefix}",
+    )?;
+    let pb = ProgressBar::new(0);
+    pb.set_style(progress_style);
+    let json = args.output == Output::Json;
+    if json {
+        pb.set_draw_target(ProgressDrawTarget::hidden());
+    }
+    if args.plain_progress {
+        progress::report_plain(&pb);
+    }
+
+    let rate_limited = |limit: RateLimit| {
+        let secs = limit.wait(clock.as_ref()) as i64;
+        debug!(seconds = secs, "Rate limited");
+        report::rate_limited(clock.now().unix_timestamp(), secs);
+        metrics::rate_limited(secs as u64);
+        if args.notify && secs >= args.notify_stalled * 60 {
+            notify::send(
+                &tr!("Rate limited"),
+                &tr!(
+                    "Waiting {minutes} minutes for Twitter before continuing",
+                    minutes = secs / 60
+                ),
+            );
+        }
+
+        pb.set_prefix(tr!(
+            "Rate limited, waiting until {time} ({secs} seconds)",
+            time = (clock.now() + Duration::seconds(secs))
+                .to_offset(utc_offset)
+                .time()
+                .format(HUMAN_TIME)?,
+            secs = secs
+        ));
+
+        Ok(())
+    };
+    // Prompts and warnings go to stderr, so stdout only has results
+    let mut stdout = stdout().lock();
+    // Not locked, the progress bar draws to it from another thread
+    let mut stderr = stderr();
+    // Where prose goes instead of stdout with `--output json`
+    let mut sink = io::sink();
+
+    // Resuming a delete run is the same as committing it
+    let cmd = match args.cmd {
+        Cmd::Delete {
+            resume: true, tag, ..
+        } => Cmd::Commit {
+            run: last_unfinished(conn, "delete", tag.as_deref())?
+                .ok_or_else(|| anyhow!("{}", tr!("No interrupted `delete` run to resume")))?,
+        },
+        cmd => cmd,
+    };
+    match cmd {
+        Cmd::Import {
+            paths,
+            lenient,
+            fast,
+            account_id,
+            handle,
+        } => {
+            let text: &mut dyn Write = if json { &mut sink } else { &mut stdout };
+            let account = match (&account_id, &handle) {
+                (Some(id), Some(handle)) => {
+                    let account = manual_acc(&paths[0], id, handle)?;
+                    if let Some(want) = args.account.as_deref() {
+                        let want = want.trim_start_matches('@');
+                        if !account.user_name.eq_ignore_ascii_case(want) {
+                            return Err(anyhow!(
+                                "{}",
+                                tr!(
+                                    "--handle @{handle} doesn't match --account @{want}",
+                                    handle = account.user_name,
+                                    want = want
+                                )
+                            ));
+                        }
+                    }
+                    account
+                }
+                _ => {
+                    check_archive(&paths[0], args.account.as_deref())?;
+                    get_acc(&paths[0])?
+                }
+            };
+            // Archives with broken accounts can't be compared, so are all
+            // taken to be for the given account
+            let others = if account_id.is_some() {
+                &[][..]
+            } else {
+                &paths[1..]
+            };
+            for path in others {
+                let other = get_acc(path)?;
+                if other.id_str != account.id_str {
+                    return Err(anyhow!(
+                        "{}",
+                        tr!(
+                            "{path} is for @{archived}, not @{handle}, archives for different \
+                             accounts must be imported separately",
+                            path = path.display(),
+                            archived = other.user_name,
+                            handle = account.user_name
+                        )
+                    ));
+                }
+            }
+            let run = start_run(
+                conn,
+                "import",
+                &account.id_str,
+                None,
+                None,
+                clock.now().unix_timestamp(),
+            )?;
+            let mut stats = ImportStats::default();
+            let mut import = |conn: &mut SqliteConnection| -> Result<Vec<Merged>> {
+                paths
+                    .iter()
+                    .map(|path| {
+                        import_archive(conn, clock.as_ref(), path, &account, lenient, &mut stats)
+                    })
+                    .collect()
+            };
+
+            let merged = if fast {
+                let (merged, took) = import_fast(conn, &mut import)?;
+                writeln!(
+                    text,
+                    "{}",
+                    tr!(
+                        "Dropped indexes in {dropped}, imported in {imported}, rebuilt indexes in \
+                         {rebuilt}",
+                        dropped = format!("{:.2?}", took.dropped),
+                        imported = format!("{:.2?}", took.imported),
+                        rebuilt = format!("{:.2?}", took.rebuilt)
+                    )
+                )?;
+                merged
+            } else {
+                import(conn)?
+            };
+            if paths.len() > 1 {
+                for (path, merged) in paths.iter().zip(&merged) {
+                    writeln!(
+                        text,
+                        "{}: {}",
+                        path.display(),
+                        tr!(
+                            "{added} new tweets, {updated} with newer likes or retweets",
+                            added = merged.added,
+                            updated = merged.updated
+                        )
+                    )?;
+                }
+            }
+            writeln!(
+                text,
+                "{}",
+                tr!(
+                    "Imported {added} tweets. Total tweets {total}",
+                    added = merged.iter().map(|m| m.added).sum::<usize>(),
+                    total = count_tweets(conn)?
+                )
+            )?;
+            stats.peak_memory = util::peak_memory();
+            finish_run(conn, run, 0, clock.now().unix_timestamp())?;
+            record_import(conn, run, &stats)?;
+            let likes = paths
+                .iter()
+                .map(|path| import_likes(conn, path, &account))
+                .sum::<Result<usize>>()?;
+            if likes > 0 {
+                writeln!(
+                    text,
+                    "{}",
+                    tr!("Imported {likes} liked tweets", likes = likes)
+                )?;
+            }
+            let messages = paths
+                .iter()
+                .map(|path| import_dms(conn, path, &account))
+                .sum::<Result<usize>>()?;
+            if messages > 0 {
+                writeln!(
+                    text,
+                    "{}",
+                    tr!("Imported {messages} direct messages", messages = messages)
+                )?;
+            }
+            let account = account_by_id(conn, &account.id_str)?
+                .ok_or_else(|| anyhow!("BUG: Account {} was just imported", account.id_str))?;
+            let keys = keys_for(conn, &credentials, &account)?;
+            let checked = check_account(conn, &keys, api, &account, &pb, |r, _| {
+                pb.enable_steady_tick(std::time::Duration::from_secs(1));
+                rate_limited(r)
+            })?;
+            match checked {
+                Some(total) => writeln!(
+                    text,
+                    "{}",
+                    tr!(
+                        "Marked {total} total tweets as already deleted from twitter",
+                        total = total
+                    )
+                )?,
+                None => writeln!(text, "{}", tr!("All tweets have already been checked"))?,
+            }
+            if json {
+                let report = ImportReport {
+                    added: merged.iter().map(|m| m.added).sum(),
+                    updated: merged.iter().map(|m| m.updated).sum(),
+                    total: count_tweets(conn)?,
+                    likes,
+                    messages,
+                    already_deleted: checked,
+                };
+                print_json(&mut stdout, &report)?;
+            }
+        }
+        Cmd::Check {} => {
+            let account = find_account(conn, args.account.as_deref())?;
+            let keys = keys_for(conn, &credentials, &account)?;
+            let checked = check_account(conn, &keys, api, &account, &pb, |r, _| {
+                pb.enable_steady_tick(std::time::Duration::from_secs(1));
+                rate_limited(r)
+            })?;
+            match checked {
+                Some(total) => writeln!(
+                    stdout,
+                    "{}",
+                    tr!(
+                        "Marked {total} total tweets as already deleted from twitter",
+                        total = total
+                    )
+                )?,
+                None => writeln!(stdout, "{}", tr!("All tweets have already been checked"))?,
+            }
+        }
+        Cmd::ImportIds { path, mark } => {
+            let ids = util::read_ids(&path)?;
+            let marked = match mark {
+                Mark::Deleted => tr!(
+                    "Marked {marked} tweets as deleted, out of {count} tweet IDs in {path}",
+                    marked = deleted(conn, clock.as_ref(), ids.iter())?,
+                    count = ids.len(),
+                    path = path.display()
+                ),
+                Mark::Protected => tr!(
+                    "Marked {marked} tweets as protected, out of {count} tweet IDs in {path}",
+                    marked = protect(conn, clock.as_ref(), ids.iter(), None)?,
+                    count = ids.len(),
+                    path = path.display()
+                ),
+            };
+            writeln!(stdout, "{marked}")?;
+        }
+        Cmd::Delete {
+            filter,
+            diff_last_run,
+            dry_run,
+            order,
+            tag,
+            yes,
+            export_before_delete,
+            resume: _,
+        } => {
+            let filter = filter.resolve(conn)?;
+            let everything = filter.older_than == Some(0);
+            let filters = filter.canonical();
+            let account = find_account(conn, args.account.as_deref())?;
+            let mut filter = filter.build(clock.now().to_offset(utc_offset))?;
+            filter.account = Some(account.id_str.clone());
+            let to_process = filter.load(conn, order)?;
+
+            if diff_last_run {
+                let (run, previous) = last_matches(conn, "delete", tag.as_deref(), &filters)?
+                    .ok_or_else(|| {
+                        anyhow!("{}", tr!("No previous run with the same tag and filters"))
+                    })?;
+                let previous: HashSet<TweetId> = previous.into_iter().collect();
+                let new: Vec<&TweetId> = to_process
+                    .iter()
+                    .filter(|id| !previous.contains(*id))
+                    .collect();
+                if json {
+                    let report = DeleteReport {
+                        run: Some(run),
+                        dry_run: true,
+                        matched: to_process.len(),
+                        deleted: 0,
+                        ids: new.into_iter().cloned().collect(),
+                    };
+                    return print_json(&mut stdout, &report);
+                }
+                for id in &new {
+                    writeln!(stdout, "{id}")?;
+                }
+                writeln!(
+                    stdout,
+                    "{}",
+                    tr!(
+                        "{count} tweets newly matched since run #{run}, out of {total} total \
+                         matches",
+                        count = new.len(),
+                        run = run,
+                        total = to_process.len()
+                    )
+                )?;
+                return Ok(());
+            }
+            if json && (dry_run || to_process.is_empty()) {
+                let report = DeleteReport {
+                    run: None,
+                    dry_run,
+                    matched: to_process.len(),
+                    deleted: 0,
+                    ids: if dry_run { to_process } else { Vec::new() },
+                };
+                return print_json(&mut stdout, &report);
+            }
+            if to_process.is_empty() {
+                nothing_matches(conn, &account.id_str, &mut stdout)?;
+                return Ok(());
+            }
+            warn_engagement(conn, &filter, &to_process, &account, &mut stderr)?;
+            if dry_run {
+                preview(conn, &to_process, &mut stdout)?;
+                writeln!(
+                    stdout,
+                    "{}",
+                    tr!(
+                        "{count} tweets would be deleted from @{handle}",
+                        count = to_process.len(),
+                        handle = account.user_name
+                    )
+                )?;
+                return Ok(());
+            }
+            if !yes {
+                confirm_delete(conn, &to_process, &account, everything, &mut stderr)?;
+            }
+            if let Some(path) = export_before_delete {
+                let format = export::Format::from_path(&path)?;
+                let mut file = File::options()
+                    .write(true)
+                    .create_new(true)
+                    .open(&path)
+                    .map_err(|e| {
+                        anyhow!(
+                            "{}",
+                            tr!(
+                                "Couldn't export to {path}: {error}",
+                                path = path.display(),
+                                error = e
+                            )
+                        )
+                    })?;
+                let exported = export::export_ids(conn, &to_process, format, &mut file)?;
+                let text: &mut dyn Write = if json { &mut sink } else { &mut stdout };
+                writeln!(
+                    text,
+                    "{}",
+                    tr!(
+                        "Exported {exported} tweets to {path}",
+                        exported = exported,
+                        path = path.display()
+                    )
+                )?;
+            }
+
+            pb.set_length(to_process.len() as u64);
+            pb.set_message(tr!(
+                "Deleting tweets from @{handle}",
+                handle = account.user_name
+            ));
+
+            let keys = keys_for(conn, &credentials, &account)?;
+            let run = start_run(
+                conn,
+                "delete",
+                &account.id_str,
+                tag.as_deref(),
+                Some(filters.as_str()),
+                clock.now().unix_timestamp(),
+            )?;
+            record_matches(conn, run, &to_process)?;
+            let total = delete_ids(conn, &keys, api, run, &to_process, &pb, |r, _| {
+                pb.enable_steady_tick(std::time::Duration::from_secs(1));
+                rate_limited(r)
+            })?;
+            pb.finish();
+            finish_run(conn, run, total, clock.now().unix_timestamp())?;
+            if json {
+                let report = DeleteReport {
+                    run: Some(run),
+                    dry_run: false,
+                    matched: to_process.len(),
+                    deleted: total,
+                    ids: run_deleted(conn, run)?,
+                };
+                print_json(&mut stdout, &report)?;
+            } else {
+                writeln!(stdout, "{}", tr!("Deleted {total} tweets", total = total))?;
+            }
+        }
+        Cmd::Simulate { filter, order } => {
+            let account = find_account(conn, args.account.as_deref())?;
+            let simulated = simulate(
+                conn,
+                &db_path,
+                &filter,
+                &account.id_str,
+                clock.as_ref(),
+                utc_offset,
+                order,
+            )?;
+            let Simulated { run, changes: changed } = match simulated {
+                Some(simulated) => simulated,
+                None => {
+                    nothing_matches(conn, &account.id_str, &mut stdout)?;
+                    return Ok(());
+                }
+            };
+            for change in &changed {
+                if change.deleted {
+                    writeln!(stdout, "{}", tr!("{id} deleted", id = change.id_str))?;
+                } else {
+                    writeln!(
+                        stdout,
+                        "{}",
+                        tr!(
+                            "{id} failed, {failures} failures",
+                            id = change.id_str,
+                            failures = change.failures
+                        )
+                    )?;
+                }
+            }
+            writeln!(
+                stdout,
+                "{}",
+                tr!(
+                    "Simulated run #{run} would change {count} tweets, {deleted} deleted",
+                    run = run,
+                    count = changed.len(),
+                    deleted = changed.iter().filter(|c| c.deleted).count()
+                )
+            )?;
+        }
+        Cmd::Stage { stage: what } => {
+            let account = find_account(conn, args.account.as_deref())?;
+            let staged = stage(
+                conn,
+                &what.into(),
+                &account.id_str,
+                clock.now().to_offset(utc_offset),
+            )?;
+            if staged.is_empty() {
+                nothing_matches(conn, &account.id_str, &mut stdout)?;
+                return Ok(());
+            }
+            for Staged { run, batch, tweets } in staged {
+                writeln!(
+                    stdout,
+                    "{}",
+                    tr!(
+                        "Staged batch #{run} \"{batch}\" with {tweets} tweets",
+                        run = run,
+                        batch = batch,
+                        tweets = tweets
+                    )
+                )?;
+            }
+        }
+        Cmd::Review { filter, order, tag } => {
+            let account = find_account(conn, args.account.as_deref())?;
+            let filter = filter.resolve(conn)?;
+            let filters = filter.canonical();
+            let mut filter = filter.build(clock.now().to_offset(utc_offset))?;
+            filter.account = Some(account.id_str.clone());
+            let ids = filter.load(conn, order)?;
+            if ids.is_empty() {
+                nothing_matches(conn, &account.id_str, &mut stdout)?;
+                return Ok(());
+            }
+            let tweets = summarize(conn, &ids)?;
+            let decisions = match review::review(&tweets, utc_offset)? {
+                Some(decisions) => decisions,
+                None => {
+                    writeln!(stdout, "{}", tr!("Review cancelled, nothing was changed"))?;
+                    return Ok(());
+                }
+            };
+            let decided = |want| {
+                ids.iter()
+                    .zip(&decisions)
+                    .filter(move |(_, d)| **d == Some(want))
+                    .map(|(id, _)| id)
+            };
+
+            let kept = protect(
+                conn,
+                clock.as_ref(),
+                decided(Decision::Keep),
+                Some("Kept in review"),
+            )?;
+            writeln!(stdout, "{}", tr!("Protected {kept} tweets", kept = kept))?;
+            let queued: Vec<TweetId> = decided(Decision::Delete).cloned().collect();
+            if !queued.is_empty() {
+                let now = clock.now().unix_timestamp();
+                let run = stage_batch(
+                    conn,
+                    &account.id_str,
+                    tag.as_deref(),
+                    &filters,
+                    "review",
+                    &queued,
+                    now,
+                )?;
+                writeln!(
+                    stdout,
+                    "{}",
+                    tr!(
+                        "Staged batch #{run} \"review\" with {count} tweets, delete them with \
+                         `commit {run}`",
+                        run = run,
+                        count = queued.len()
+                    )
+                )?;
+            }
+        }
+        Cmd::Commit { run } => {
+            let account = find_account(conn, args.account.as_deref())?;
+            let keys = keys_for(conn, &credentials, &account)?;
+            let (batch, to_process) = unfinished(conn, run, &account.id_str)?;
+            if to_process.is_empty() {
+                finish_run(conn, run, 0, clock.now().unix_timestamp())?;
+                if json {
+                    let report = DeleteReport {
+                        run: Some(run),
+                        dry_run: false,
+                        matched: 0,
+                        deleted: 0,
+                        ids: Vec::new(),
+                    };
+                    return print_json(&mut stdout, &report);
+                }
+                writeln!(
+                    stdout,
+                    "{}",
+                    tr!(
+                        "Nothing left to delete in run #{run}, every tweet is already deleted \
+                         or protected",
+                        run = run
+                    )
+                )?;
+                return Ok(());
+            }
+            pb.set_length(to_process.len() as u64);
+            match batch.batch {
+                Some(name) => pb.set_message(tr!(
+                    "Deleting tweets from @{handle} in batch #{run} \"{batch}\"",
+                    handle = account.user_name,
+                    run = run,
+                    batch = name
+                )),
+                None => pb.set_message(tr!(
+                    "Resuming {command} run #{run} for @{handle}",
+                    command = batch.command,
+                    run = run,
+                    handle = account.user_name
+                )),
+            }
+            let total = delete_ids(conn, &keys, api, run, &to_process, &pb, |r, _| {
+                pb.enable_steady_tick(std::time::Duration::from_secs(1));
+                rate_limited(r)
+            })?;
+            pb.finish();
+            finish_run(conn, run, total, clock.now().unix_timestamp())?;
+            if json {
+                let report = DeleteReport {
+                    run: Some(run),
+                    dry_run: false,
+                    matched: to_process.len(),
+                    deleted: total,
+                    ids: run_deleted(conn, run)?,
+                };
+                print_json(&mut stdout, &report)?;
+            } else {
+                writeln!(stdout, "{}", tr!("Deleted {total} tweets", total = total))?;
+            }
+        }
+        Cmd::Watch {
+            filter,
+            every,
+            tag,
+            accept_policy,
+            metrics,
+        } => {
+            let filter = filter.resolve(conn)?;
+            let unattended = match filter.retention() {
+                Ok(_) => None,
+                Err(NotRetention::EveryAge) => Some(tr!(
+                    "`watch` deletes without asking, so won't delete tweets of every age with \
+                     `--older-than 0`"
+                )),
+                Err(NotRetention::NoAge) => Some(tr!(
+                    "`watch` deletes without asking, so needs `--older-than` to keep new tweets \
+                     for that many days"
+                )),
+                Err(NotRetention::FixedDate) => Some(tr!(
+                    "`watch` keeps deleting tweets as they get older, so needs `--older-than` \
+                     instead of `--before`"
+                )),
+            };
+            if let Some(e) = unattended {
+                return Err(anyhow!("{e}"));
+            }
+            let account = find_account(conn, args.account.as_deref())?;
+            let keys = keys_for(conn, &credentials, &account)?;
+            confirm_policy(
+                conn,
+                &filter,
+                &account,
+                clock.now().to_offset(utc_offset),
+                accept_policy.as_deref(),
+                &mut stderr,
+            )?;
+            // Runs unattended, so only report each pass
+            pb.set_draw_target(ProgressDrawTarget::hidden());
+            if let Some(addr) = metrics {
+                metrics::serve(addr, pb.clone())?;
+            }
+            systemd::ready();
+            loop {
+                systemd::ping();
+                let when = clock.now().to_offset(utc_offset).format(HUMAN_DATE)?;
+                pb.reset();
+                pb.set_length(0);
+                let mut started = None;
+                let pass = watch_pass(
+                    conn,
+                    &keys,
+                    api,
+                    &filter,
+                    &account.id_str,
+                    utc_offset,
+                    tag.as_deref(),
+                    |run, count| {
+                        started = Some(run);
+                        pb.set_length(count as u64);
+                        systemd::status(&tr!("Deleting, {count} remaining", count = count));
+                    },
+                    |r, _| {
+                        systemd::status(&tr!(
+                            "Rate limited, {count} remaining",
+                            count = pb.length().unwrap_or(0).saturating_sub(pb.position())
+                        ));
+                        rate_limited(r)?;
+                        // Waited out here, rather than by twitter, to keep the watchdog fed
+                        systemd::sleep(StdDuration::from_secs(r.wait(clock.as_ref())));
+                        Ok(())
+                    },
+                    |id, deletion, retweet| {
+                        // Long passes would otherwise look hung
+                        systemd::ping();
+                        show_deletion(&pb, id, deletion, retweet);
+                    },
+                );
+                if let Some(run) = started {
+                    suggest_resume(&pass, run);
+                }
+
+                match pass? {
+                    None => writeln!(stdout, "{when}: {}", tr!("Nothing to delete"))?,
+                    Some(pass) => {
+                        metrics::deleted(pass.deleted);
+                        metrics::failed(pass.failed);
+                        match pass.error {
+                            None => writeln!(
+                                stdout,
+                                "{when}: {}",
+                                tr!("Deleted {total} tweets", total = pass.deleted)
+                            )?,
+                            Some(e) => {
+                                metrics::pass_failed();
+                                warn!(
+                                    "{when}: {}",
+                                    tr!(
+                                        "Pass failed, trying again next pass: {error}",
+                                        error = format!("{e:#}")
+                                    )
+                                )
+                            }
+                        }
+                        reports.write();
+                    }
+                }
+                stdout.flush()?;
+                let sleep = StdDuration::from_secs(every.saturating_mul(60 * 60));
+                let until = (clock.now() + sleep).to_offset(utc_offset);
+                systemd::status(&tr!(
+                    "Sleeping until {time}",
+                    time = until.format(HUMAN_DATE)?
+                ));
+                systemd::sleep(sleep);
+            }
+        }
+        cmd @ (Cmd::DeleteLikes { .. } | Cmd::Unlike { .. }) => {
+            let (account, keys, older_than) = match cmd {
+                Cmd::DeleteLikes { path, older_than } => {
+                    check_archive(&path, args.account.as_deref())?;
+                    let archived = get_acc(&path)?;
+                    let account = account_by_id(conn, &archived.id_str)?.ok_or_else(|| {
+                        anyhow!("{}", tr!("Import your twitter archive first with `import`"))
+                    })?;
+                    let keys = keys_for(conn, &credentials, &account)?;
+                    import_likes(conn, &path, &archived)?;
+                    (account, keys, older_than)
+                }
+                Cmd::Unlike { older_than } => {
+                    let account = find_account(conn, args.account.as_deref())?;
+                    let keys = keys_for(conn, &credentials, &account)?;
+                    (account, keys, older_than)
+                }
+                _ => unreachable!("Only likes are removed here"),
+            };
+
+            let off = Boundary::Rolling.cutoff(clock.now(), older_than)?;
+            let likes = likes_before(conn, &account.id_str, off)?;
+            if likes.is_empty() {
+                writeln!(
+                    stdout,
+                    "{}",
+                    tr!(
+                        "No liked tweets left to unlike were created before {date}",
+                        date = off.format(DATE)?
+                    )
+                )?;
+                return Ok(());
+            }
+
+            pb.set_length(likes.len() as u64);
+            pb.set_message(tr!(
+                "Removing likes from @{handle}",
+                handle = account.user_name
+            ));
+            let unliked = remove_likes(
+                conn,
+                &client,
                 &keys,
-                to_process.iter().map(|f| f.as_str()),
-                |r, l| {
+                clock.as_ref(),
+                &account.id_str,
+                &likes,
+                |r, _| {
                     pb.enable_steady_tick(std::time::Duration::from_secs(1));
-                    rate_limited(r, l)
+                    rate_limited(r)
                 },
-                |res, id| {
-                    pb.disable_steady_tick();
-                    // Probably a retweet thats gone private... just ignore it
-                    // Sigh.
-                    // So the problem is that the twitter archive includes your RTs,
-                    // but *not* the `retweeted_status` object that identifies them as RTs!
-                    // And retweets can fail to be deleted!
-                    // In theory your own tweets should never
-                    // TODO: Pre-process them to mark as RTs.
-                    // We already call lookup anyway, the info should be there,
-                    // we just currently throw it away.
-                    if res.status() == StatusCode::FORBIDDEN {
-                        pb.inc(1);
-                        pb.set_prefix(format!("Failed to unretweet {id}"));
-                        return Ok(());
-                    }
-                    // Probably also a RT, this time thats been deleted
-                    // Sigh.
-                    if res.status() == StatusCode::NOT_FOUND {
-                        total += deleted(conn, [id].into_iter())?;
-                        pb.inc(1);
-                        pb.set_prefix(format!("Already deleted (re)tweet? {id}"));
-                        return Ok(());
-                    }
-                    res.error_for_status()?;
+                |id, already| show_unliked(&pb, id, already),
+            )?;
+            pb.finish();
+            writeln!(
+                stdout,
+                "{}",
+                tr!("Unliked {unliked} tweets", unliked = unliked)
+            )?;
+        }
+        Cmd::DeleteDms { older_than } => {
+            let account = find_account(conn, args.account.as_deref())?;
+            let keys = keys_for(conn, &credentials, &account)?;
+
+            let off = Boundary::Rolling.cutoff(clock.now(), older_than)?;
+            let messages = dms_before(conn, &account.id_str, off.unix_timestamp())?;
+            if messages.is_empty() {
+                writeln!(
+                    stdout,
+                    "{}",
+                    tr!(
+                        "No direct messages left to delete are older than {days} days",
+                        days = older_than
+                    )
+                )?;
+                return Ok(());
+            }
+
+            pb.set_length(messages.len() as u64);
+            pb.set_message(tr!(
+                "Deleting direct messages from @{handle}",
+                handle = account.user_name
+            ));
+            let gone = delete_messages(
+                conn,
+                &client,
+                &keys,
+                clock.as_ref(),
+                &account.id_str,
+                &messages,
+                |r, _| {
+                    pb.enable_steady_tick(std::time::Duration::from_secs(1));
+                    rate_limited(r)
+                },
+                |id, already| show_message_deleted(&pb, id, already),
+            )?;
+            pb.finish();
+            writeln!(
+                stdout,
+                "{}",
+                tr!("Deleted {gone} direct messages", gone = gone)
+            )?;
+        }
+        Cmd::BackupMedia {
+            path,
+            output,
+            filter,
+        } => {
+            let filter = filter.resolve(conn)?;
+            let account = find_account(conn, args.account.as_deref())?;
+            let archived = get_acc(&path)?;
+            if archived.id_str != account.id_str {
+                return Err(anyhow!(
+                    "{}",
+                    tr!(
+                        "{path} is for @{archived}, not @{handle}",
+                        path = path.display(),
+                        archived = archived.user_name,
+                        handle = account.user_name
+                    )
+                ));
+            }
+            let mut filter = filter.build(clock.now().to_offset(utc_offset))?;
+            filter.account = Some(account.id_str);
+            let ids = filter.load(conn, Order::Id)?;
+            let (files, tweets) = backup_media(conn, &path, &ids, &output, utc_offset)?;
+            writeln!(
+                stdout,
+                "{}",
+                tr!(
+                    "Copied {files} media files from {tweets} tweets to {path}",
+                    files = files,
+                    tweets = tweets,
+                    path = output.display()
+                )
+            )?;
+        }
+        Cmd::Nuke {
+            path,
+            dms,
+            farewell,
+            tag,
+        } => {
+            check_archive(&path, args.account.as_deref())?;
+            let archived = get_acc(&path)?;
+            let plan = plan_nuke(conn, clock.as_ref(), &path, &archived, dms)?;
+            writeln!(
+                stdout,
+                "{}",
+                tr!(
+                    "Imported {added} tweets. Total tweets {total}",
+                    added = plan.imported,
+                    total = count_tweets(conn)?
+                )
+            )?;
+            let account = &plan.account;
+            let keys = keys_for(conn, &credentials, account)?;
 
-                    total += deleted(conn, [id].into_iter())?;
+            writeln!(
+                stderr,
+                "{}",
+                tr!(
+                    "This will delete, from @{handle}:\n{tweets} tweets\n{likes} likes\n{messages} \
+                     direct messages",
+                    handle = account.user_name,
+                    tweets = plan.tweets.len(),
+                    likes = plan.likes.len(),
+                    messages = plan.messages.len()
+                )
+            )?;
+            if let Some(farewell) = &farewell {
+                writeln!(stderr, "\n{}\n{farewell}", tr!("And then post:"))?;
+            }
+            writeln!(stderr, "\n{}", tr!("This CAN NOT be undone."))?;
+            let phrase = format!("nuke @{}", account.user_name);
+            write!(
+                stderr,
+                "{}",
+                tr!("Type `{phrase}` to continue: ", phrase = phrase)
+            )?;
+            stderr.flush()?;
+            let mut input = String::new();
+            stdin().read_line(&mut input)?;
+            if input.trim() != phrase {
+                return Err(anyhow!(
+                    "{}",
+                    tr!("Confirmation did not match, nothing was deleted")
+                ));
+            }
 
-                    pb.inc(1);
-                    pb.set_prefix(format!("Deleted tweet {id}"));
+            let backup = backup(conn, &path, &backups_path, clock.now())?;
+            writeln!(
+                stdout,
+                "{}",
+                tr!("Backed up to {path}", path = backup.display())
+            )?;
 
-                    Ok(())
+            // Only while deleting tweets is there a run to resume
+            let mut resumable = None;
+            let nuked = nuke(
+                conn,
+                &client,
+                &keys,
+                api,
+                &plan,
+                tag.as_deref(),
+                farewell.as_deref(),
+                |r| {
+                    pb.enable_steady_tick(std::time::Duration::from_secs(1));
+                    rate_limited(r)
                 },
+                |progress| match progress {
+                    Nuking::Tweets { run } => {
+                        resumable = Some(run);
+                        pb.set_length(plan.tweets.len() as u64);
+                        pb.set_message(tr!(
+                            "Deleting tweets from @{handle}",
+                            handle = account.user_name
+                        ));
+                    }
+                    Nuking::Tweet(id, deletion, retweet) => {
+                        show_deletion(&pb, id, deletion, retweet)
+                    }
+                    Nuking::Likes => {
+                        resumable = None;
+                        pb.reset();
+                        pb.set_length(plan.likes.len() as u64);
+                        pb.set_message(tr!(
+                            "Removing likes from @{handle}",
+                            handle = account.user_name
+                        ));
+                    }
+                    Nuking::Unliked(id, already) => show_unliked(&pb, id, already),
+                    Nuking::Messages => {
+                        pb.reset();
+                        pb.set_length(plan.messages.len() as u64);
+                        pb.set_message(tr!(
+                            "Deleting direct messages from @{handle}",
+                            handle = account.user_name
+                        ));
+                    }
+                    Nuking::Message(id, already) => show_message_deleted(&pb, id, already),
+                },
+            );
+            if let Some(run) = resumable {
+                suggest_resume(&nuked, run);
+            }
+            let nuked = nuked?;
+            pb.finish();
+
+            writeln!(
+                stdout,
+                "{}",
+                tr!(
+                    "Deleted {total} tweets, {unliked} likes, and {gone} direct messages",
+                    total = nuked.tweets,
+                    unliked = nuked.likes,
+                    gone = nuked.messages
+                )
+            )?;
+            if let Some(id) = nuked.farewell {
+                writeln!(stdout, "{}", tr!("Posted farewell tweet {id}", id = id))?;
+            }
+        }
+        Cmd::Post { text } => {
+            let account = find_account(conn, args.account.as_deref())?;
+            let keys = keys_for(conn, &credentials, &account)?;
+            let posted = post_tweet(&client, &keys, clock.as_ref(), &text, |r, _: &Response| {
+                rate_limited(r)
+            })?;
+            writeln!(
+                stdout,
+                "{}",
+                tr!(
+                    "Posted tweet {id} as @{handle}",
+                    id = posted.id_str,
+                    handle = account.user_name
+                )
             )?;
+        }
+        Cmd::Score {
+            command,
+            url,
+            rescore,
+        } => {
+            let scorer = match (command, url) {
+                (Some(command), _) => Scorer::Command(command),
+                (_, Some(url)) => Scorer::Http(url),
+                (None, None) => Scorer::builtin()?,
+            };
+
+            let to_score = unscored(conn, rescore)?;
+
+            pb.set_length(to_score.len() as u64);
+            pb.set_message(tr!("Scoring tweets"));
+            for (id, text) in &to_score {
+                let score = scorer.score(&client, id, text)?;
+                set_score(conn, id, score)?;
+                pb.inc(1);
+            }
             pb.finish();
-            writeln!(stdout, "Deleted {total} tweets")?;
+            writeln!(
+                stdout,
+                "{}",
+                tr!("Scored {count} tweets", count = to_score.len())
+            )?;
+        }
+        Cmd::Export {
+            state,
+            filter_name,
+            format,
+            threads,
+            output,
+        } => {
+            let ids = match filter_name {
+                Some(name) => {
+                    let filter = FilterArgs::saved(conn, &name)?;
+                    let account = find_account(conn, args.account.as_deref())?;
+                    let mut filter = filter.build(clock.now().to_offset(utc_offset))?;
+                    filter.account = Some(account.id_str);
+                    Some(filter.load(conn, Order::Id)?)
+                }
+                None => None,
+            };
+            let mut export = |out: &mut dyn Write| match (&ids, state) {
+                (Some(ids), _) => export::export_ids(conn, ids, format, out),
+                (None, Some(state)) => export::export_state(conn, state, format, threads, out),
+                (None, None) => unreachable!("Clap requires a state or saved filters"),
+            };
+            match output {
+                Some(output) => {
+                    let mut file = File::create(&output)?;
+                    let exported = export(&mut file)?;
+                    writeln!(
+                        stdout,
+                        "{}",
+                        tr!(
+                            "Exported {exported} tweets to {path}",
+                            exported = exported,
+                            path = output.display()
+                        )
+                    )?;
+                }
+                None => {
+                    export(&mut stdout)?;
+                }
+            }
+        }
+        Cmd::Filters { cmd } => match cmd {
+            FiltersCmd::Save { name, filter } => {
+                let filter = filter.resolve(conn)?;
+                // Catch invalid expressions and wordlists now, not when used
+                filter.build(clock.now().to_offset(utc_offset))?;
+                save_filter(
+                    conn,
+                    &name,
+                    &filter.canonical(),
+                    clock.now().unix_timestamp(),
+                )?;
+                writeln!(stdout, "{}", tr!("Saved filters as `{name}`", name = name))?;
+            }
+            FiltersCmd::List {} => {
+                for (name, filters) in saved_filters(conn)? {
+                    writeln!(stdout, "{name}: {filters}")?;
+                }
+            }
+            FiltersCmd::Remove { name } => {
+                if !remove_filter(conn, &name)? {
+                    return Err(anyhow!(
+                        "{}",
+                        tr!("No filters are saved as `{name}`", name = name)
+                    ));
+                }
+                writeln!(
+                    stdout,
+                    "{}",
+                    tr!("Removed saved filters `{name}`", name = name)
+                )?;
+            }
+        },
+        Cmd::Protect { cmd } => match cmd {
+            ProtectCmd::Add { ids, note } => {
+                let marked = protect(conn, clock.as_ref(), ids.iter(), note.as_deref())?;
+                writeln!(stdout, "{}", tr!("Protected {kept} tweets", kept = marked))?;
+            }
+            ProtectCmd::Remove { ids } => {
+                let unmarked = unprotect(conn, clock.as_ref(), ids.iter())?;
+                writeln!(
+                    stdout,
+                    "{}",
+                    tr!(
+                        "Removed protection from {unmarked} tweets",
+                        unmarked = unmarked
+                    )
+                )?;
+            }
+            ProtectCmd::List {} => {
+                for (id, note) in protected(conn)? {
+                    match note {
+                        Some(note) => writeln!(stdout, "{id}: {note}")?,
+                        None => writeln!(stdout, "{id}")?,
+                    }
+                }
+            }
+            ProtectCmd::Apply { path, clear, note } => {
+                let ids = util::read_ids(&path)?;
+                if clear {
+                    let unmarked = unprotect(conn, clock.as_ref(), ids.iter())?;
+                    writeln!(
+                        stdout,
+                        "{}",
+                        tr!(
+                            "Removed protection from {unmarked} tweets, out of {count} tweet IDs \
+                             in {path}",
+                            unmarked = unmarked,
+                            count = ids.len(),
+                            path = path.display()
+                        )
+                    )?;
+                } else {
+                    let marked = protect(conn, clock.as_ref(), ids.iter(), note.as_deref())?;
+                    writeln!(
+                        stdout,
+                        "{}",
+                        tr!(
+                            "Protected {marked} tweets, out of {count} tweet IDs in {path}",
+                            marked = marked,
+                            count = ids.len(),
+                            path = path.display()
+                        )
+                    )?;
+                }
+            }
+            ProtectCmd::Export { output } => {
+                let protected: Vec<TweetId> =
+                    protected(conn)?.into_iter().map(|(id, _)| id).collect();
+                match output {
+                    Some(output) => {
+                        let mut file = File::create(&output)?;
+                        for id in &protected {
+                            writeln!(file, "{id}")?;
+                        }
+                        writeln!(
+                            stdout,
+                            "{}",
+                            tr!(
+                                "Exported {count} protected tweets to {path}",
+                                count = protected.len(),
+                                path = output.display()
+                            )
+                        )?;
+                    }
+                    None => {
+                        for id in &protected {
+                            writeln!(stdout, "{id}")?;
+                        }
+                    }
+                }
+            }
+        },
+        Cmd::Search { query, limit } => {
+            let found = search(conn, &query, limit.into())?;
+            if found.is_empty() {
+                writeln!(
+                    stdout,
+                    "{}",
+                    tr!("No tweets match `{query}`", query = query)
+                )?;
+                return Ok(());
+            }
+            preview(conn, &found, &mut stdout)?;
+            writeln!(
+                stdout,
+                "{}",
+                tr!(
+                    "{count} tweets match `{query}`",
+                    count = found.len(),
+                    query = query
+                )
+            )?;
+        }
+        #[cfg(unix)]
+        Cmd::Serve { socket } => {
+            serve::serve(
+                conn,
+                &credentials,
+                args.account.as_deref(),
+                api,
+                utc_offset,
+                &socket,
+            )?;
+        }
+        #[cfg(not(unix))]
+        Cmd::Serve { .. } => {
+            return Err(anyhow!("{}", tr!("`serve` is only supported on unix")));
+        }
+        Cmd::Show { id, history } => {
+            let id = util::extract_tweet_id(&id)
+                .ok_or_else(|| anyhow!("{}", tr!("Invalid tweet ID or URL {id}", id = id)))?;
+            let tweet = find_tweet(conn, &id)?
+                .ok_or_else(|| anyhow!("{}", tr!("Tweet {id} has not been imported", id = id)))?;
+            let created = OffsetDateTime::from_unix_timestamp(tweet.created_at)?
+                .to_offset(utc_offset)
+                .format(HUMAN_DATE)?;
+
+            writeln!(
+                stdout,
+                "{}",
+                tr!(
+                    "\
+Tweet {id} by account {account}
+Created: {created}
+Likes: {likes}
+Retweets: {retweets}
+Deleted: {deleted}
+Checked: {checked}
+Protected: {protected}
+Failed deletions: {failures}
+Permanently failed: {permanently_failed}",
+                    id = tweet.id_str,
+                    account = tweet.account_id,
+                    created = created,
+                    likes = tweet.likes,
+                    retweets = tweet.retweets,
+                    deleted = tweet.deleted,
+                    checked = tweet.checked,
+                    protected = tweet.protected,
+                    failures = tweet.failures,
+                    permanently_failed = tweet.permanently_failed
+                )
+            )?;
+            if let Some(note) = &tweet.note {
+                writeln!(stdout, "{}", tr!("Note: {note}", note = note))?;
+            }
+            if let Some(text) = tweet.display_text.as_ref().or(tweet.full_text.as_ref()) {
+                writeln!(stdout, "\n{text}")?;
+            }
+
+            if history {
+                writeln!(stdout, "\n{}", tr!("History:"))?;
+                for event in tweet_history(conn, &id)? {
+                    let at = OffsetDateTime::from_unix_timestamp(event.at)?
+                        .to_offset(utc_offset)
+                        .format(HUMAN_DATE)?;
+                    match event.detail {
+                        Some(detail) => writeln!(stdout, "{at}: {} ({detail})", event.kind)?,
+                        None => writeln!(stdout, "{at}: {}", event.kind)?,
+                    }
+                }
+            }
+        }
+        Cmd::Reports { run, tag } => {
+            let mut reports = report::list(&reports_path)?;
+            if let Some(tag) = &tag {
+                reports.retain(|(_, r)| r.tag.as_ref() == Some(tag));
+            }
+            match run {
+                Some(run) => {
+                    let (_, report) = reports
+                        .into_iter()
+                        .find(|(_, r)| r.run == run)
+                        .ok_or_else(|| anyhow!("{}", tr!("No report for run #{run}", run = run)))?;
+                    if json {
+                        print_json(&mut stdout, &report)?;
+                    } else {
+                        writeln!(stdout, "{}", serde_json::to_string_pretty(&report)?)?;
+                    }
+                }
+                None if json => print_json(
+                    &mut stdout,
+                    &reports.into_iter().map(|(_, r)| r).collect::<Vec<_>>(),
+                )?,
+                None => {
+                    for (path, report) in reports {
+                        let tag = report.tag.map(|t| format!(" \"{t}\"")).unwrap_or_default();
+                        let unfinished = if report.finished_at.is_none() {
+                            tr!(" (unfinished)")
+                        } else {
+                            String::new()
+                        };
+                        writeln!(
+                            stdout,
+                            "{}",
+                            tr!(
+                                "#{run} {command}{tag} at {started}{unfinished}: {matched} \
+                                 matched, {deleted} deleted, {not_deleted} not deleted, {failed} \
+                                 failed, {limits} rate limits, {path}",
+                                run = report.run,
+                                command = report.command,
+                                tag = tag,
+                                started = report.started_at,
+                                unfinished = unfinished,
+                                matched = report.matched,
+                                deleted = report.deleted,
+                                not_deleted = report.not_deleted,
+                                failed = report.failures.len(),
+                                limits = report.rate_limits.len(),
+                                path = path.display()
+                            )
+                        )?;
+                    }
+                }
+            }
+        }
+        Cmd::Doctor {} => {
+            let mut problems = 0;
+            for b in lookup_anomalies(conn)? {
+                problems += 1;
+                let why = if b.missing > 0 {
+                    tr!(
+                        "{count} tweets were left out of the response",
+                        count = b.missing
+                    )
+                } else if b.returned + b.gone != b.requested {
+                    tr!(
+                        "{count} tweets were answered for instead of {requested}",
+                        count = b.returned + b.gone,
+                        requested = b.requested
+                    )
+                } else {
+                    tr!(
+                        "every tweet was said to be deleted, which may be twitter failing lookups \
+                         rather than the tweets being deleted"
+                    )
+                };
+                writeln!(
+                    stdout,
+                    "{}",
+                    tr!(
+                        "Run #{run} lookup {batch}: Asked about {requested} tweets, {returned} \
+                         exist, {gone} deleted, {missing} missing, {why}",
+                        run = b.run_id,
+                        batch = b.batch,
+                        requested = b.requested,
+                        returned = b.returned,
+                        gone = b.gone,
+                        missing = b.missing,
+                        why = why
+                    )
+                )?;
+            }
+            for run in discrepancies(conn)? {
+                problems += 1;
+                writeln!(
+                    stdout,
+                    "{}",
+                    tr!(
+                        "Run #{run} {command}: Twitter deleted {count} more tweets than were \
+                         marked deleted in the database",
+                        run = run.id,
+                        command = run.command,
+                        count = run.discrepancy.unwrap_or_default()
+                    )
+                )?;
+            }
+            if problems == 0 {
+                writeln!(stdout, "{}", tr!("No problems found"))?;
+            }
+        }
+        Cmd::History { tag } => {
+            for run in runs(conn, tag.as_deref())? {
+                let started = OffsetDateTime::from_unix_timestamp(run.started_at)?
+                    .to_offset(utc_offset)
+                    .format(HUMAN_DATE)?;
+                let tag = run.tag.map(|t| format!(" \"{t}\"")).unwrap_or_default();
+                let batch = run
+                    .batch
+                    .map(|b| format!(" batch \"{b}\""))
+                    .unwrap_or_default();
+                if let Some(discrepancy) = run.discrepancy {
+                    writeln!(
+                        stdout,
+                        "{}",
+                        tr!(
+                            "WARNING: Run #{run} deleted {discrepancy} more tweets on twitter than \
+                             in the database",
+                            run = run.id,
+                            discrepancy = discrepancy
+                        )
+                    )?;
+                }
+                match run.finished_at {
+                    Some(_) if run.command == "import" => {
+                        let parse = StdDuration::from_millis(run.parse_ms.unwrap_or(0) as u64);
+                        let insert = StdDuration::from_millis(run.insert_ms.unwrap_or(0) as u64);
+                        let rows = run.imported_rows.unwrap_or(0);
+                        let per_sec = f64::from(rows) / (parse + insert).as_secs_f64().max(0.001);
+                        let memory = run
+                            .peak_memory
+                            .map(|m| tr!(", peak memory {mib} MiB", mib = m / (1024 * 1024)))
+                            .unwrap_or_default();
+                        writeln!(
+                            stdout,
+                            "#{} {}{tag} {}",
+                            run.id,
+                            run.command,
+                            tr!(
+                                "at {started}: Read {rows} tweets, parsed in {parse}, inserted in \
+                                 {insert}, {per_sec} tweets/sec{memory}",
+                                started = started,
+                                rows = rows,
+                                parse = format!("{parse:.2?}"),
+                                insert = format!("{insert:.2?}"),
+                                per_sec = format!("{per_sec:.0}"),
+                                memory = memory
+                            )
+                        )?
+                    }
+                    Some(_) if run.command == "check" => writeln!(
+                        stdout,
+                        "#{} {}{tag} {}",
+                        run.id,
+                        run.command,
+                        tr!(
+                            "at {started}: Marked {count} tweets as already deleted",
+                            started = started,
+                            count = run.deleted
+                        )
+                    )?,
+                    Some(_) => writeln!(
+                        stdout,
+                        "#{} {}{batch}{tag} {}",
+                        run.id,
+                        run.command,
+                        tr!(
+                            "at {started}: Deleted {count} tweets",
+                            started = started,
+                            count = run.deleted
+                        )
+                    )?,
+                    None if run.command == "stage" => writeln!(
+                        stdout,
+                        "#{} {}{batch}{tag} {}",
+                        run.id,
+                        run.command,
+                        tr!("at {started}: Not yet committed", started = started)
+                    )?,
+                    None => writeln!(
+                        stdout,
+                        "#{} {}{tag} {}",
+                        run.id,
+                        run.command,
+                        tr!("at {started}: Interrupted", started = started)
+                    )?,
+                }
+            }
+        }
+        Cmd::Auth {
+            cmd: Some(AuthCmd::Store {}),
+        } => {
+            let account = find_account(conn, args.account.as_deref())?;
+            let keys = keys_for(conn, &credentials, &account)?;
+            secrets::store(&account.id_str, &keys)?;
+            set_keyring(conn, &account.id_str, true)?;
+            writeln!(
+                stdout,
+                "{}",
+                tr!(
+                    "API keys for @{handle} are now stored in the OS keyring",
+                    handle = account.user_name
+                )
+            )?;
+            if let Some(path) = &account.keys_path {
+                writeln!(
+                    stdout,
+                    "{}",
+                    tr!("{path} is no longer used, and can be deleted", path = path)
+                )?;
+            }
+        }
+        Cmd::Auth {
+            cmd: Some(AuthCmd::Forget {}),
+        } => {
+            let account = find_account(conn, args.account.as_deref())?;
+            secrets::forget(&account.id_str)?;
+            set_keyring(conn, &account.id_str, false)?;
+            writeln!(
+                stdout,
+                "{}",
+                tr!(
+                    "Removed the API keys for @{handle} from the OS keyring, the default keys will \
+                     be used",
+                    handle = account.user_name
+                )
+            )?;
+        }
+        Cmd::Auth {
+            cmd: Some(AuthCmd::Test {}),
+        } => {
+            let account = find_account(conn, args.account.as_deref())?;
+            let keys = keys_for(conn, &credentials, &account)?;
+            let test = test_auth(&client, &keys, clock.as_ref())?;
+            let unknown = tr!("unknown");
+            writeln!(
+                stdout,
+                "{}",
+                tr!(
+                    "API keys for @{handle} have {level} access",
+                    handle = test.screen_name,
+                    level = test.access_level.as_deref().unwrap_or(&unknown)
+                )
+            )?;
+            for endpoint in &test.endpoints {
+                if endpoint.allowed() {
+                    writeln!(stdout, "  {}: {}", endpoint.name, tr!("allowed"))?;
+                } else {
+                    writeln!(
+                        stdout,
+                        "  {}: {}, {}{}",
+                        endpoint.name,
+                        tr!("NOT allowed"),
+                        endpoint.status,
+                        endpoint
+                            .error
+                            .as_deref()
+                            .map(|e| format!(": {e}"))
+                            .unwrap_or_default()
+                    )?;
+                }
+            }
+            if !test
+                .access_level
+                .as_deref()
+                .map_or(false, |l| l.contains("write"))
+            {
+                writeln!(
+                    stdout,
+                    "{}",
+                    tr!(
+                        "Deleting and posting need read-write access, change the app permissions \
+                         and authorize again with `auth`"
+                    )
+                )?;
+            }
+        }
+        Cmd::Auth { cmd: None } => {
+            let keys = default_keys(&credentials)?;
+            let token = request_token(&client, &keys, clock.as_ref())?;
+            writeln!(
+                stderr,
+                "{}\n{}",
+                tr!("Open this URL, authorize the app, and enter the PIN twitter shows"),
+                token.authorize_url()
+            )?;
+            write!(stderr, "{}", tr!("PIN: "))?;
+            stderr.flush()?;
+            let mut pin = String::new();
+            stdin().read_line(&mut pin)?;
+            let Authorized { keys, screen_name } =
+                access_token(&client, &keys, clock.as_ref(), &token, pin.trim())?;
+
+            let user_id = keys
+                .user_id()
+                .ok_or_else(|| anyhow!("{}", tr!("Twitter returned an invalid access token")))?;
+            let keys_path = save_keys(&config_path.join("keys"), user_id, &keys)?;
+            writeln!(
+                stdout,
+                "{}",
+                tr!(
+                    "Authorized @{handle}, keys saved to {path}",
+                    handle = screen_name,
+                    path = keys_path.display()
+                )
+            )?;
+
+            match find_account(conn, Some(&screen_name)) {
+                Ok(account) => {
+                    let path = keys_path.to_str().ok_or_else(|| {
+                        anyhow!(
+                            "{}",
+                            tr!(
+                                "Invalid UTF-8 in keys path {path}",
+                                path = keys_path.display()
+                            )
+                        )
+                    })?;
+                    set_keys(conn, &account.id_str, &keys.api_key, Some(path))?;
+                    writeln!(
+                        stdout,
+                        "{}",
+                        tr!(
+                            "Account @{handle} now uses these keys",
+                            handle = account.user_name
+                        )
+                    )?;
+                }
+                Err(_) => writeln!(
+                    stdout,
+                    "{}",
+                    tr!(
+                        "Once your archive is imported, use them with `accounts set-keys \
+                         @{handle} {path}`",
+                        handle = screen_name,
+                        path = keys_path.display()
+                    )
+                )?,
+            }
+        }
+        Cmd::ArchiveAccount { force, undo } => {
+            let account = find_account(conn, args.account.as_deref())?;
+            if undo {
+                if account.frozen_at.is_none() {
+                    return Err(anyhow!(
+                        "{}",
+                        tr!(
+                            "Account @{handle} is not archived",
+                            handle = account.user_name
+                        )
+                    ));
+                }
+                set_frozen(conn, &account.id_str, None)?;
+                writeln!(
+                    stdout,
+                    "{}",
+                    tr!(
+                        "Account @{handle} can be changed again",
+                        handle = account.user_name
+                    )
+                )?;
+                return Ok(());
+            }
+            if account.frozen_at.is_some() {
+                return Err(anyhow!(
+                    "{}",
+                    tr!(
+                        "Account @{handle} is already archived",
+                        handle = account.user_name
+                    )
+                ));
+            }
+
+            let left = left_to_delete(conn, &account.id_str)?;
+            if left > 0 && !force {
+                return Err(anyhow!(
+                    "{}",
+                    tr!(
+                        "Account @{handle} still has {left} tweets to delete, delete them first or \
+                         use `--force`",
+                        handle = account.user_name,
+                        left = left
+                    )
+                ));
+            }
+            set_frozen(conn, &account.id_str, Some(clock.now().unix_timestamp()))?;
+            writeln!(
+                stdout,
+                "{}",
+                tr!(
+                    "Archived @{handle}, its tweets, likes, and direct messages can no longer be \
+                     changed",
+                    handle = account.user_name
+                )
+            )?;
+        }
+        Cmd::Accounts { cmd } => match cmd {
+            AccountsCmd::List {} => {
+                for acc in accounts(conn)?.into_iter().filter(|a| a.id_str != "0") {
+                    writeln!(
+                        stdout,
+                        "@{} {} ({}): {}{}",
+                        acc.user_name,
+                        acc.display_name,
+                        acc.id_str,
+                        match &acc.keys_path {
+                            _ if acc.keyring => tr!("OS keyring"),
+                            Some(path) => path.clone(),
+                            None => tr!("Default keys"),
+                        },
+                        if acc.frozen_at.is_some() {
+                            tr!(", archived")
+                        } else {
+                            String::new()
+                        }
+                    )?;
+                }
+            }
+            AccountsCmd::SetKeys { handle, path } => {
+                let account = find_account(conn, Some(&handle))?;
+                let keys = load_keys(&path)?;
+                keys.check(&account)?;
+                let path = path.canonicalize()?;
+                let path = path.to_str().ok_or_else(|| {
+                    anyhow!(
+                        "{}",
+                        tr!("Invalid UTF-8 in keys path {path}", path = path.display())
+                    )
+                })?;
+                set_keys(conn, &account.id_str, &keys.api_key, Some(path))?;
+                writeln!(
+                    stdout,
+                    "{}",
+                    tr!(
+                        "Account @{handle} now uses the keys at {path}",
+                        handle = account.user_name,
+                        path = path
+                    )
+                )?;
+            }
+        },
+        Cmd::Stats { as_of: Some(as_of) } => {
+            let date = Date::parse(&as_of, DATE).map_err(|e| {
+                anyhow!(
+                    "{}",
+                    tr!(
+                        "Invalid date `{date}`, expected YYYY-MM-DD: {error}",
+                        date = as_of,
+                        error = e
+                    )
+                )
+            })?;
+            let at = date.midnight().assume_offset(utc_offset).unix_timestamp();
+
+            let accounts: Vec<MAccount> = match &args.account {
+                Some(handle) => vec![find_account(conn, Some(handle))?],
+                None => accounts(conn)?,
+            };
+            // The total is the same as the account
+            let total = args.account.is_none().then_some(None);
+            if json {
+                let stats = accounts
+                    .iter()
+                    .map(Some)
+                    .chain(total)
+                    .map(|acc| {
+                        Ok(AccountStats {
+                            handle: acc.map(|a| a.user_name.clone()),
+                            id: acc.map(|a| a.id_str.clone()),
+                            counts: count_as_of(conn, acc.map(|a| a.id_str.as_str()), at)?,
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                return print_json(&mut stdout, &stats);
+            }
+            writeln!(stdout, "{}\n", tr!("As of {date}", date = as_of))?;
+            for acc in accounts.iter().map(Some).chain(total) {
+                let counts = count_as_of(conn, acc.map(|a| a.id_str.as_str()), at)?;
+                match acc {
+                    Some(acc) => writeln!(
+                        stdout,
+                        "{}",
+                        tr!(
+                            "Account @{handle} {name} ({id})",
+                            handle = acc.user_name,
+                            name = acc.display_name,
+                            id = acc.id_str
+                        )
+                    )?,
+                    None => writeln!(stdout, "{}", tr!("Total"))?,
+                }
+                writeln!(
+                    stdout,
+                    "{}",
+                    tr!(
+                        "\
+Tweets: {existing}
+Deleted Tweets: {deleted}
+Deleted Tweets, unknown when: {unknown}
+---
+",
+                        existing = counts.existing,
+                        deleted = counts.deleted,
+                        unknown = counts.unknown
+                    )
+                )?;
+            }
         }
-        Args::Stats {} => {
-            let accounts: Vec<MAccount> = adb::dsl::accounts.get_results(conn)?;
+        Cmd::Stats { as_of: None } => {
+            let accounts: Vec<MAccount> = match &args.account {
+                Some(handle) => vec![find_account(conn, Some(handle))?],
+                None => accounts(conn)?,
+            };
+            if json {
+                let mut stats = accounts
+                    .iter()
+                    .map(|acc| {
+                        Ok(AccountStats {
+                            handle: Some(acc.user_name.clone()),
+                            id: Some(acc.id_str.clone()),
+                            counts: count_totals(conn, Some(&acc.id_str))?,
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                if args.account.is_none() {
+                    stats.push(AccountStats {
+                        handle: None,
+                        id: None,
+                        counts: count_totals(conn, None)?,
+                    });
+                }
+                return print_json(&mut stdout, &stats);
+            }
             let accounts = accounts.into_iter(); //.filter(|a| a.id_str != "0");
             for acc in accounts {
+                let totals = count_totals(conn, Some(&acc.id_str))?;
                 writeln!(
                     stdout,
-                    "\
-Account @{} {} ({})
+                    "{}",
+                    tr!(
+                        "\
+Account @{handle} {name} ({id})
 
-Imported Tweets: {}
-Deleted Tweets: {}
-Checked* Tweets: {}
+Imported Tweets: {imported}
+Deleted Tweets: {deleted}
+Checked* Tweets: {checked}
 ---
 ",
-                    &acc.user_name,
-                    &acc.display_name,
-                    &acc.id_str,
-                    tdb::dsl::tweets
-                        .filter(tdb::dsl::account_id.eq(&acc.id_str))
-                        .count()
-                        .get_result::<i64>(conn)?,
-                    tdb::dsl::tweets
-                        .filter(tdb::dsl::account_id.eq(&acc.id_str))
-                        .filter(tdb::dsl::deleted.eq(true))
-                        .count()
-                        .get_result::<i64>(conn)?,
-                    tdb::dsl::tweets
-                        .filter(tdb::dsl::account_id.eq(&acc.id_str))
-                        .filter(tdb::dsl::checked.eq(true))
-                        .count()
-                        .get_result::<i64>(conn)?,
+                        handle = acc.user_name,
+                        name = acc.display_name,
+                        id = acc.id_str,
+                        imported = totals.imported,
+                        deleted = totals.deleted,
+                        checked = totals.checked
+                    )
                 )?;
             }
+            if args.account.is_some() {
+                return Ok(());
+            }
 
+            let totals = count_totals(conn, None)?;
             writeln!(
                 stdout,
-                "\
-Total Imported Tweets: {}
-Deleted Tweets: {}
-Checked* Tweets: {}
+                "{}",
+                tr!(
+                    "\
+Total Imported Tweets: {imported}
+Deleted Tweets: {deleted}
+Checked* Tweets: {checked}
 
 *During Twitter Archive importing, tweets are checked for whether they've already
 been deleted or not. If this process was not interrupted, this is the same as the total tweets.
 ",
-                count_tweets(conn)?,
-                tdb::dsl::tweets
-                    .filter(tdb::dsl::deleted.eq(true))
-                    .count()
-                    .get_result::<i64>(conn)?,
-                tdb::dsl::tweets
-                    .filter(tdb::dsl::checked.eq(true))
-                    .count()
-                    .get_result::<i64>(conn)?,
+                    imported = totals.imported,
+                    deleted = totals.deleted,
+                    checked = totals.checked
+                )
             )?;
         }
-        Args::Update { path, to_ver } => {
+        Cmd::Completions { .. } => unreachable!("Completions are printed before running"),
+        Cmd::Db { cmd } => match cmd {
+            DbCmd::ExplainQuery { filter } => {
+                let mut filter = filter
+                    .resolve(conn)?
+                    .build(clock.now().to_offset(utc_offset))?;
+                // Match `delete` as closely as possible
+                filter.account = find_account(conn, args.account.as_deref())
+                    .ok()
+                    .map(|a| a.id_str);
+                let (query, plan) = explain_filter(conn, &filter)?;
+                writeln!(stdout, "{query}\n")?;
+                for step in plan {
+                    writeln!(stdout, "{step}")?;
+                }
+            }
+        },
+        Cmd::Update { path, to_ver } => {
             if to_ver == "v0.1.1" {
                 let account = get_acc(&path)?;
-
-                conn.transaction::<_, anyhow::Error, _>(|conn| {
-                    add_account(
-                        conn,
-                        &[MAccount {
-                            id_str: account.id_str.clone(),
-                            user_name: account.user_name,
-                            display_name: account.display_name,
-                        }],
-                    )?;
-
-                    diesel::update(tdb::dsl::tweets)
-                        .filter(tdb::dsl::account_id.eq("0"))
-                        .set(tdb::dsl::account_id.eq(account.id_str))
-                        .execute(conn)?;
-                    Ok(())
-                })?;
+                claim_unknown(conn, &account)?;
             }
         }
     };
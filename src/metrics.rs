@@ -0,0 +1,109 @@
+//! Prometheus metrics for `watch`, see `--metrics`
+//!
+//! Served as plain text over HTTP, so self-hosters can alert when passes
+//! fail, or stop deleting.
+//!
+//! - `twitter_delete_deletions_total`, tweets deleted
+//! - `twitter_delete_failures_total`, by `kind`, tweets twitter failed to
+//!   delete, counted each pass they fail, and passes that failed
+//! - `twitter_delete_rate_limit_seconds_total`, seconds spent waiting on
+//!   twitters rate limits
+//! - `twitter_delete_queue_depth`, tweets left to delete in the current pass
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::atomic::{AtomicU64, Ordering},
+    thread,
+};
+
+use anyhow::{anyhow, Result};
+use indicatif::ProgressBar;
+use tracing::{info, warn};
+
+static DELETIONS: AtomicU64 = AtomicU64::new(0);
+static TWEET_FAILURES: AtomicU64 = AtomicU64::new(0);
+static PASS_FAILURES: AtomicU64 = AtomicU64::new(0);
+static RATE_LIMIT_SECONDS: AtomicU64 = AtomicU64::new(0);
+
+/// Record that `count` tweets were deleted
+pub fn deleted(count: usize) {
+    DELETIONS.fetch_add(count as u64, Ordering::Relaxed);
+}
+
+/// Record that twitter failed to delete `count` tweets
+pub fn failed(count: usize) {
+    TWEET_FAILURES.fetch_add(count as u64, Ordering::Relaxed);
+}
+
+/// Record that a pass failed
+pub fn pass_failed() {
+    PASS_FAILURES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Record waiting `seconds` for a rate limit
+pub fn rate_limited(seconds: u64) {
+    RATE_LIMIT_SECONDS.fetch_add(seconds, Ordering::Relaxed);
+}
+
+/// Serve metrics on `addr` from a new thread, with the queue depth taken
+/// from the progress of `pass`
+///
+/// Only binding `addr` can fail, errors answering scrapes only warn.
+pub fn serve(addr: SocketAddr, pass: ProgressBar) -> Result<()> {
+    let listener =
+        TcpListener::bind(addr).map_err(|e| anyhow!("Couldn't serve metrics on {addr}: {e}"))?;
+    info!("Serving metrics on http://{addr}/metrics");
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            if let Err(e) = stream.map_err(Into::into).and_then(|s| scrape(s, &pass)) {
+                warn!("Couldn't serve metrics: {e}");
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Answer a single HTTP request on `stream`
+fn scrape(mut stream: TcpStream, pass: &ProgressBar) -> Result<()> {
+    let mut request = String::new();
+    BufReader::new(&stream).read_line(&mut request)?;
+    let path = request.split_whitespace().nth(1).unwrap_or_default();
+    if path != "/metrics" {
+        write!(
+            stream,
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+        )?;
+        return Ok(());
+    }
+
+    let queue = pass.length().unwrap_or(0).saturating_sub(pass.position());
+    let body = format!(
+        "\
+# HELP twitter_delete_deletions_total Tweets deleted
+# TYPE twitter_delete_deletions_total counter
+twitter_delete_deletions_total {}
+# HELP twitter_delete_failures_total Tweets twitter failed to delete, and passes that failed
+# TYPE twitter_delete_failures_total counter
+twitter_delete_failures_total{{kind=\"tweet\"}} {}
+twitter_delete_failures_total{{kind=\"pass\"}} {}
+# HELP twitter_delete_rate_limit_seconds_total Seconds spent waiting on rate limits
+# TYPE twitter_delete_rate_limit_seconds_total counter
+twitter_delete_rate_limit_seconds_total {}
+# HELP twitter_delete_queue_depth Tweets left to delete in the current pass
+# TYPE twitter_delete_queue_depth gauge
+twitter_delete_queue_depth {queue}
+",
+        DELETIONS.load(Ordering::Relaxed),
+        TWEET_FAILURES.load(Ordering::Relaxed),
+        PASS_FAILURES.load(Ordering::Relaxed),
+        RATE_LIMIT_SECONDS.load(Ordering::Relaxed),
+    );
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: \
+         {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()?;
+    Ok(())
+}
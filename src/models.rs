@@ -3,8 +3,9 @@ use diesel::prelude::*;
 use time::OffsetDateTime;
 
 use crate::{
-    schema::{accounts, tweets},
-    twitter::TWITTER_DATE,
+    archive::TWITTER_DATE,
+    id::TweetId,
+    schema::{accounts, dms, hashtags, likes, lookup_batches, mentions, tweets, urls},
 };
 
 #[derive(Queryable, Insertable, Clone, Associations)]
@@ -12,7 +13,7 @@ use crate::{
 #[diesel(belongs_to(Account))]
 pub struct Tweet {
     /// Tweet ID. Primary key, Unique.
-    pub id_str: String,
+    pub id_str: TweetId,
 
     /// Number of retweets
     pub retweets: i32,
@@ -33,11 +34,93 @@ pub struct Tweet {
     ///
     /// Corresponds to [`Account`]
     pub account_id: String,
+
+    /// Whether the tweet is protected from deletion
+    pub protected: bool,
+
+    /// Free-text note explaining why a tweet is protected
+    pub note: Option<String>,
+
+    /// Number of failed attempts to delete the tweet
+    pub failures: i32,
+
+    /// Tweet ID this tweet is a reply to
+    pub in_reply_to_status_id: Option<String>,
+
+    /// Account ID this tweet is a reply to
+    pub in_reply_to_user_id: Option<String>,
+
+    /// Account handle this tweet is a reply to, at time of archive
+    pub in_reply_to_screen_name: Option<String>,
+
+    /// Text of the tweet
+    pub full_text: Option<String>,
+
+    /// What the tweet consists of
+    ///
+    /// See [`ContentKind`](crate::analyze::ContentKind)
+    pub content_kind: Option<String>,
+
+    /// Score from `0.0` to `1.0` of how worth keeping the tweet is
+    ///
+    /// See [`Scorer`](crate::analyze::Scorer)
+    pub score: Option<f64>,
+
+    /// When the tweet was marked as deleted, UTC unix time
+    ///
+    /// [`None`] if the tweet is not deleted, or was deleted before this was
+    /// recorded
+    pub deleted_at: Option<i64>,
+
+    /// Text of the tweet as it would be displayed, with HTML entities decoded
+    /// and t.co links expanded
+    ///
+    /// See [`display_text`](crate::analyze::display_text)
+    pub display_text: Option<String>,
+
+    /// When the archive the likes and retweets came from was created, UTC
+    /// unix time
+    ///
+    /// Importing a newer archive updates them, see
+    /// [`add_tweets`](crate::db::add_tweets)
+    pub archived_at: Option<i64>,
+
+    /// Number of consecutive attempts to delete the tweet twitter refused
+    /// with 403 Forbidden
+    pub forbidden: i32,
+
+    /// Whether twitter refused to delete the tweet too many times in a row,
+    /// so it's no longer retried
+    ///
+    /// See [`PERMANENT_FAILURE_ATTEMPTS`](crate::db::PERMANENT_FAILURE_ATTEMPTS)
+    pub permanently_failed: bool,
+
+    /// Whether the tweet is a retweet
+    ///
+    /// Twitter archives don't record this, so it's [`None`] until the tweet
+    /// is checked with the lookup API, and for tweets checked before this was
+    /// recorded.
+    pub is_retweet: Option<bool>,
+
+    /// Tweet ID this is a retweet of, if known
+    pub retweet_of: Option<TweetId>,
+
+    /// Whether the tweet has attached photos, videos, or GIFs
+    ///
+    /// [`None`] for tweets imported before this was recorded.
+    pub has_media: Option<bool>,
+
+    /// ID of the first version of the tweet, if it was ever edited
+    ///
+    /// Every version of an edited tweet has the same one, including the
+    /// first. [`None`] for tweets that were never edited, and tweets imported
+    /// before this was recorded.
+    pub initial_id: Option<TweetId>,
 }
 
 impl Tweet {
     pub fn new(
-        id_str: String,
+        id_str: TweetId,
         retweets: i32,
         likes: i32,
         created_at: i64,
@@ -51,6 +134,24 @@ impl Tweet {
             deleted: false,
             checked: false,
             account_id,
+            protected: false,
+            note: None,
+            failures: 0,
+            in_reply_to_status_id: None,
+            in_reply_to_user_id: None,
+            in_reply_to_screen_name: None,
+            full_text: None,
+            content_kind: None,
+            score: None,
+            deleted_at: None,
+            display_text: None,
+            archived_at: None,
+            forbidden: 0,
+            permanently_failed: false,
+            is_retweet: None,
+            retweet_of: None,
+            has_media: None,
+            initial_id: None,
         }
     }
 }
@@ -71,6 +172,15 @@ impl std::fmt::Debug for Tweet {
         }
         f.field("checked", &self.checked)
             .field("account_id", &self.account_id)
+            .field("protected", &self.protected)
+            .field("note", &self.note)
+            .field("failures", &self.failures)
+            .field("permanently_failed", &self.permanently_failed)
+            .field("is_retweet", &self.is_retweet)
+            .field("has_media", &self.has_media)
+            .field("initial_id", &self.initial_id)
+            .field("in_reply_to_status_id", &self.in_reply_to_status_id)
+            .field("in_reply_to_screen_name", &self.in_reply_to_screen_name)
             .finish()
     }
 }
@@ -81,4 +191,259 @@ pub struct Account {
     pub id_str: String,
     pub user_name: String,
     pub display_name: String,
+
+    /// API key of the app last used with this account
+    ///
+    /// Used to catch using the wrong app by mistake
+    pub api_key: Option<String>,
+
+    /// Path to the API keys to use for this account, if not the default
+    pub keys_path: Option<String>,
+
+    /// Whether the API keys for this account are stored in the OS keyring
+    ///
+    /// See [`secrets`](crate::secrets)
+    pub keyring: bool,
+
+    /// When the account was archived with `archive-account`, after which its
+    /// tweets, likes, and messages can't change
+    pub frozen_at: Option<i64>,
+}
+
+/// An account mentioned in a tweet
+#[derive(Debug, Queryable, Insertable, Clone, Associations)]
+#[diesel(table_name = mentions)]
+#[diesel(belongs_to(Tweet))]
+pub struct Mention {
+    /// Tweet ID the mention is in
+    pub tweet_id: TweetId,
+
+    /// Account ID of the mentioned account
+    pub user_id: String,
+
+    /// Account handle of the mentioned account, at time of archive
+    pub screen_name: String,
+}
+
+/// A hashtag in a tweet
+#[derive(Debug, Queryable, Insertable, Clone, Associations)]
+#[diesel(table_name = hashtags)]
+#[diesel(belongs_to(Tweet))]
+pub struct Hashtag {
+    /// Tweet ID the hashtag is in
+    pub tweet_id: TweetId,
+
+    /// Hashtag, without the `#`
+    pub hashtag: String,
+}
+
+/// A t.co link in a tweet
+#[derive(Debug, Queryable, Insertable, Clone, Associations)]
+#[diesel(table_name = urls)]
+#[diesel(belongs_to(Tweet))]
+pub struct Url {
+    /// Tweet ID the link is in
+    pub tweet_id: TweetId,
+
+    /// Shortened t.co link, as it appears in the tweet text
+    pub url: String,
+
+    /// Where the link actually goes
+    pub expanded_url: String,
+}
+
+/// A tweet liked by an account
+#[derive(Debug, Queryable, Insertable, Clone, Associations)]
+#[diesel(table_name = likes)]
+#[diesel(belongs_to(Account))]
+pub struct Like {
+    /// ID of the liked tweet
+    pub tweet_id: TweetId,
+
+    /// Account ID that liked it
+    ///
+    /// Corresponds to [`Account`]
+    pub account_id: String,
+
+    /// Text of the liked tweet, at time of archive
+    pub full_text: Option<String>,
+
+    /// Whether the tweet has been unliked
+    pub unliked: bool,
+
+    /// When the tweet was unliked, UTC unix time
+    pub unliked_at: Option<i64>,
+}
+
+/// A direct message in an account's view of a conversation
+#[derive(Debug, Queryable, Insertable, Clone, Associations)]
+#[diesel(table_name = dms)]
+#[diesel(belongs_to(Account))]
+pub struct Dm {
+    /// Message ID
+    pub id: String,
+
+    /// Account ID whose view of the conversation it's in
+    ///
+    /// Corresponds to [`Account`]
+    pub account_id: String,
+
+    /// Conversation ID, shared by everyone in the conversation
+    pub conversation_id: String,
+
+    /// Account ID of who sent it
+    pub sender_id: Option<String>,
+
+    /// Text of the message, at time of archive
+    pub text: Option<String>,
+
+    /// When the message was sent, UTC unix time
+    pub created_at: i64,
+
+    /// Whether the message has been deleted
+    pub deleted: bool,
+
+    /// When the message was deleted, UTC unix time
+    pub deleted_at: Option<i64>,
+}
+
+/// One request made while checking tweets, recorded so changes in how twitter
+/// answers lookups can be noticed, see `doctor`
+#[derive(Debug, Queryable, Insertable, Clone, Associations)]
+#[diesel(table_name = lookup_batches)]
+#[diesel(belongs_to(Run))]
+pub struct LookupBatch {
+    /// Run ID of the `check` this was part of
+    pub run_id: i32,
+
+    /// Index of this request within the run, from 0
+    pub batch: i32,
+
+    /// Tweets looked up
+    pub requested: i32,
+
+    /// Tweets twitter returned, as still existing
+    pub returned: i32,
+
+    /// Tweets twitter returned as null, taken to be deleted
+    pub gone: i32,
+
+    /// Tweets twitter left out of the response entirely
+    pub missing: i32,
+}
+
+/// A single invocation of a command that modifies tweets
+#[derive(Debug, Queryable, Clone)]
+#[diesel(table_name = runs)]
+pub struct Run {
+    /// Run ID, increasing
+    pub id: i32,
+
+    /// Subcommand that was run, eg `delete`
+    pub command: String,
+
+    /// User supplied tag for this run
+    pub tag: Option<String>,
+
+    /// When the run started, UTC unix time
+    pub started_at: i64,
+
+    /// When the run finished, UTC unix time
+    ///
+    /// [`None`] if the run was interrupted
+    pub finished_at: Option<i64>,
+
+    /// Number of tweets deleted during this run
+    pub deleted: i32,
+
+    /// Canonical representation of the filters used for this run
+    pub filters: Option<String>,
+
+    /// Name of the batch, for runs staged with `stage`
+    ///
+    /// Staged runs are not finished until they are committed
+    pub batch: Option<String>,
+
+    /// How many more tweets twitter reported as deleted than were marked as
+    /// deleted in the database, if they differed
+    pub discrepancy: Option<i32>,
+
+    /// Milliseconds spent reading and parsing archives, for `import` runs
+    pub parse_ms: Option<i64>,
+
+    /// Milliseconds spent adding tweets to the database, for `import` runs
+    pub insert_ms: Option<i64>,
+
+    /// Tweets read from archives, for `import` runs
+    pub imported_rows: Option<i32>,
+
+    /// Peak memory use in bytes, for `import` runs, if it could be found
+    pub peak_memory: Option<i64>,
+
+    /// Account the run was for
+    ///
+    /// [`None`] for runs from before this was recorded
+    pub account_id: Option<String>,
+}
+
+/// Something that happened to a tweet, for auditing
+#[derive(Debug, Queryable, Clone)]
+#[diesel(table_name = events)]
+pub struct Event {
+    /// Event ID, increasing
+    pub id: i32,
+
+    /// Tweet ID this happened to
+    pub tweet_id: TweetId,
+
+    /// What happened
+    ///
+    /// See [`EventKind`]
+    pub kind: String,
+
+    /// When it happened, UTC unix time
+    pub at: i64,
+
+    /// Extra details, such as an HTTP status
+    pub detail: Option<String>,
+}
+
+/// Kinds of [`Event`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    /// Imported from a twitter archive
+    Imported,
+
+    /// Checked for existence on twitter
+    Checked,
+
+    /// Marked as deleted
+    Deleted,
+
+    /// Attempted to delete on twitter
+    DeleteAttempted,
+
+    /// Failed to delete on twitter
+    Failed,
+
+    /// Protected from deletion
+    Protected,
+
+    /// Protection removed
+    Unprotected,
+}
+
+impl EventKind {
+    /// Representation stored in the database
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Imported => "imported",
+            Self::Checked => "checked",
+            Self::Deleted => "deleted",
+            Self::DeleteAttempted => "delete attempted",
+            Self::Failed => "failed",
+            Self::Protected => "protected",
+            Self::Unprotected => "unprotected",
+        }
+    }
 }
@@ -0,0 +1,50 @@
+//! Desktop notifications, see `--notify`
+//!
+//! Only available when built with the `notify-rust` feature, otherwise
+//! [`supported`] returns an error.
+use anyhow::Result;
+#[cfg(feature = "notify-rust")]
+use tracing::warn;
+
+/// Whether notifications can be shown, an error explaining why not otherwise
+#[cfg(feature = "notify-rust")]
+pub fn supported() -> Result<()> {
+    Ok(())
+}
+
+/// Show a desktop notification
+///
+/// Failing to show it only warns, a notification shouldn't stop a run.
+#[cfg(feature = "notify-rust")]
+pub fn send(summary: &str, body: &str) {
+    let shown = notify_rust::Notification::new()
+        .appname("twitter_delete")
+        .summary(summary)
+        .body(body)
+        .show();
+    if let Err(e) = shown {
+        warn!("Couldn't show a desktop notification: {e}");
+    }
+}
+
+#[cfg(not(feature = "notify-rust"))]
+pub fn supported() -> Result<()> {
+    Err(anyhow::anyhow!(
+        "twitter_delete was built without desktop notifications, rebuild it with `--features \
+         notify-rust`"
+    ))
+}
+
+#[cfg(not(feature = "notify-rust"))]
+pub fn send(_summary: &str, _body: &str) {}
+
+/// Notify that command `name` finished, or failed with `result`s error
+pub fn finished<T>(name: &str, result: &Result<T>) {
+    match result {
+        Ok(_) => send(
+            &tr!("`{name}` finished", name = name),
+            &tr!("twitter_delete is done"),
+        ),
+        Err(e) => send(&tr!("`{name}` failed", name = name), &format!("{e:#}")),
+    }
+}
@@ -0,0 +1,63 @@
+//! Plain progress output, see `--plain-progress`
+//!
+//! The progress bar is redrawn in place with ANSI escapes, which screen
+//! readers and log files can't follow. Instead it can be hidden, and its state
+//! printed to stderr as a single line, at most once every [`INTERVAL`] and
+//! only when it changed.
+use std::{fmt::Write, thread, time::Duration};
+
+use indicatif::{HumanCount, ProgressBar, ProgressDrawTarget};
+
+/// How often to print the status line
+const INTERVAL: Duration = Duration::from_secs(10);
+
+/// Hide `pb` and report its progress in plain lines from a background thread
+/// instead
+pub fn report_plain(pb: &ProgressBar) {
+    pb.set_draw_target(ProgressDrawTarget::hidden());
+    let pb = pb.clone();
+    thread::spawn(move || {
+        let mut last = String::new();
+        loop {
+            thread::sleep(INTERVAL);
+            let line = status(&pb);
+            if !line.is_empty() && line != last {
+                eprintln!("{line}");
+                last = line;
+            }
+        }
+    });
+}
+
+/// One line describing `pb`, eg
+/// `Deleting tweets from @name: 1,200/12,431 — ETA 3h 12m`
+fn status(pb: &ProgressBar) -> String {
+    let mut line = pb.message();
+    if let Some(len) = pb.length().filter(|len| *len > 0) {
+        if !line.is_empty() {
+            line.push(':');
+        }
+        let _ = write!(line, " {}/{}", HumanCount(pb.position()), HumanCount(len));
+        if !pb.is_finished() {
+            let _ = write!(line, " — ETA {}", eta(pb.eta()));
+        }
+    }
+    let prefix = pb.prefix();
+    if !prefix.is_empty() {
+        let _ = write!(line, " ({prefix})");
+    }
+    line.trim().to_owned()
+}
+
+/// `eta` as hours and minutes, or minutes and seconds when under an hour
+fn eta(eta: Duration) -> String {
+    let secs = eta.as_secs();
+    let (h, m, s) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if h > 0 {
+        format!("{h}h {m}m")
+    } else if m > 0 {
+        format!("{m}m {s}s")
+    } else {
+        format!("{s}s")
+    }
+}
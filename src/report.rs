@@ -0,0 +1,321 @@
+//! Reports of each run, as JSON files in `~/.local/share/twitter_delete/reports`,
+//! see `reports`
+//!
+//! The run history in the database is the source of truth, reports are a
+//! portable copy of it to attach to bug reports, along with when twitter rate
+//! limited the run.
+use std::{
+    cell::Cell,
+    fs,
+    mem,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::{anyhow, Result};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use time::{
+    format_description::{well_known::Rfc3339, FormatItem},
+    macros::format_description,
+    OffsetDateTime,
+};
+use tracing::warn;
+
+use crate::{
+    clock::Clock,
+    db::{count_run_matches, remaining, run_failures},
+    id::TweetId,
+    models::Run,
+    schema::runs as rdb,
+};
+
+/// Timestamp reports are named by, so they sort by when the run started
+static FILE_TIME: &[FormatItem] = format_description!("[year][month][day]T[hour][minute][second]Z");
+
+/// Rate limits hit since reports were last written, see [`rate_limited`]
+static LIMITS: Mutex<Vec<Limit>> = Mutex::new(Vec::new());
+
+/// A rate limit twitter responded with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Limit {
+    /// When it was hit, UTC unix time
+    pub at: i64,
+
+    /// Seconds waited for it to reset
+    pub seconds: i64,
+}
+
+/// What happened during a run
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Report {
+    /// Version of twitter_delete that made the run
+    pub version: String,
+
+    /// Run ID, see `history`
+    pub run: i32,
+
+    /// Subcommand that was run, eg `delete`
+    pub command: String,
+    pub tag: Option<String>,
+
+    /// Name of the batch, for runs staged with `stage`
+    pub batch: Option<String>,
+
+    /// Filters used, as saved with `filters save`
+    pub filters: Option<Value>,
+
+    /// When the run started, RFC 3339
+    pub started_at: String,
+
+    /// When the run finished, RFC 3339, or [`None`] if it was interrupted or
+    /// failed
+    pub finished_at: Option<String>,
+
+    /// Seconds from starting to finishing, or to the report being written if
+    /// it didn't finish
+    pub seconds: i64,
+
+    /// Tweets matched by the run
+    pub matched: i64,
+
+    /// Tweets deleted, or found already deleted by `check`
+    pub deleted: i32,
+
+    /// Tweets matched but still not deleted, such as those twitter refused to
+    /// delete, or that were protected since
+    pub not_deleted: usize,
+
+    /// Tweets twitter failed to delete, that are still not deleted
+    #[serde(default)]
+    pub failures: Vec<Failure>,
+
+    /// How many more tweets twitter reported as deleted than were marked as
+    /// deleted in the database, if they differed
+    pub discrepancy: Option<i32>,
+
+    /// Rate limits hit during the run
+    pub rate_limits: Vec<Limit>,
+}
+
+/// A tweet twitter failed to delete
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Failure {
+    /// Tweet ID
+    pub id: TweetId,
+
+    /// Failed attempts to delete it, across every run
+    pub attempts: i32,
+
+    /// Why the last attempt failed, if recorded
+    pub error: Option<String>,
+}
+
+/// Record that a rate limit was hit `at`, UTC unix time, waiting `seconds`
+pub fn rate_limited(at: i64, seconds: i64) {
+    if let Ok(mut limits) = LIMITS.lock() {
+        limits.push(Limit { at, seconds });
+    }
+}
+
+/// Writes a report for every run started after it was created, once dropped,
+/// into `dir`
+///
+/// Dropped when the command ends, however it ends, so failed runs are
+/// reported too. Each run is only reported once.
+pub struct Reports<'a> {
+    db_path: PathBuf,
+    dir: PathBuf,
+
+    /// Time unfinished runs are reported as of
+    clock: &'a dyn Clock,
+
+    /// The last run already reported, or from before this command
+    after: Cell<i32>,
+}
+
+impl<'a> Reports<'a> {
+    /// Report runs made from now on, in the database at `db_path`, into `dir`,
+    /// as of the time of `clock`
+    pub fn new(
+        conn: &mut SqliteConnection,
+        db_path: &Path,
+        dir: PathBuf,
+        clock: &'a dyn Clock,
+    ) -> Result<Self> {
+        let after = rdb::dsl::runs
+            .select(diesel::dsl::max(rdb::dsl::id))
+            .first::<Option<i32>>(conn)?
+            .unwrap_or(0);
+        Ok(Self {
+            db_path: db_path.to_owned(),
+            dir,
+            clock,
+            after: Cell::new(after),
+        })
+    }
+
+    /// Write reports of the runs since they were last written, such as after
+    /// each pass of `watch`, which never ends
+    ///
+    /// Failing to write them only warns, they shouldn't stop a run.
+    pub fn write(&self) {
+        if let Err(e) = self.write_all() {
+            warn!("Couldn't write run reports: {e:#}");
+        }
+    }
+
+    fn write_all(&self) -> Result<()> {
+        // The command's own connection may still be borrowed
+        let conn = &mut crate::db::create_db(&self.db_path)?;
+        let runs: Vec<Run> = rdb::dsl::runs
+            .filter(rdb::dsl::id.gt(self.after.get()))
+            .order(rdb::dsl::id.asc())
+            .load(conn)?;
+        // Rate limits outside of these runs would never be reported
+        let limits = mem::take(
+            &mut *LIMITS
+                .lock()
+                .map_err(|_| anyhow!("BUG: Rate limits were poisoned"))?,
+        );
+        if runs.is_empty() {
+            return Ok(());
+        }
+        fs::create_dir_all(&self.dir)?;
+        let now = self.clock.now().unix_timestamp();
+        for run in runs {
+            let id = run.id;
+            let name = format!(
+                "{}-{}.json",
+                OffsetDateTime::from_unix_timestamp(run.started_at)?.format(FILE_TIME)?,
+                id
+            );
+            let report = report(conn, run, &limits, now)?;
+            fs::write(self.dir.join(name), serde_json::to_string_pretty(&report)?)?;
+            self.after.set(id);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Reports<'_> {
+    fn drop(&mut self) {
+        self.write();
+    }
+}
+
+/// Report on `run`, with the rate limits in `limits` hit during it, as of
+/// `now`, UTC unix time
+fn report(conn: &mut SqliteConnection, run: Run, limits: &[Limit], now: i64) -> Result<Report> {
+    let time = |t: i64| -> Result<String> {
+        Ok(OffsetDateTime::from_unix_timestamp(t)?.format(&Rfc3339)?)
+    };
+    let end = run.finished_at.unwrap_or(now);
+    let rate_limits = limits
+        .iter()
+        .filter(|l| (run.started_at..=end).contains(&l.at))
+        .cloned()
+        .collect();
+    Ok(Report {
+        version: env!("CARGO_PKG_VERSION").to_owned(),
+        run: run.id,
+        matched: count_run_matches(conn, run.id)?,
+        not_deleted: remaining(conn, run.id)?.len(),
+        failures: run_failures(conn, run.id)?
+            .into_iter()
+            .map(|(id, attempts, error)| Failure {
+                id,
+                attempts,
+                error,
+            })
+            .collect(),
+        command: run.command,
+        tag: run.tag,
+        batch: run.batch,
+        filters: run.filters.and_then(|f| serde_json::from_str(&f).ok()),
+        started_at: time(run.started_at)?,
+        finished_at: run.finished_at.map(time).transpose()?,
+        seconds: end - run.started_at,
+        deleted: run.deleted,
+        discrepancy: run.discrepancy,
+        rate_limits,
+    })
+}
+
+/// Every report in `dir`, oldest first, with its path
+pub fn list(dir: &Path) -> Result<Vec<(PathBuf, Report)>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        // No runs yet
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut paths = entries
+        .map(|e| Ok(e?.path()))
+        .collect::<Result<Vec<PathBuf>>>()?;
+    paths.retain(|p| p.extension().map_or(false, |e| e == "json"));
+    // Named by when they started
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| {
+            let report = serde_json::from_str(&fs::read_to_string(&path)?)
+                .map_err(|e| anyhow!("Invalid report {}: {e}", path.display()))?;
+            Ok((path, report))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use time::macros::datetime;
+
+    use super::*;
+    use crate::{
+        clock::FrozenClock,
+        db::{create_db, finish_run, start_run},
+    };
+
+    #[test]
+    fn reports_each_run_once() {
+        let base = env::temp_dir().join(format!("twitter_delete-reports-{}", process::id()));
+        let (db_path, dir) = (base.join("tweets.db"), base.join("reports"));
+        fs::create_dir_all(&base).unwrap();
+        let clock = FrozenClock(datetime!(2023-01-01 0:00 UTC));
+        let now = clock.now().unix_timestamp();
+        let conn = &mut create_db(&db_path).unwrap();
+        let reports = Reports::new(conn, &db_path, dir.clone(), &clock).unwrap();
+        let written = || {
+            let mut runs: Vec<(i32, usize)> = list(&dir)
+                .unwrap()
+                .into_iter()
+                .map(|(_, r)| (r.run, r.rate_limits.len()))
+                .collect();
+            runs.sort();
+            runs
+        };
+
+        let first = start_run(conn, "delete", "0", None, None, now).unwrap();
+        rate_limited(now, 60);
+        finish_run(conn, first, 0, now + 60).unwrap();
+        reports.write();
+        assert_eq!(written(), [(first, 1)]);
+
+        // Removed, so rewriting it would be noticed
+        fs::remove_dir_all(&dir).unwrap();
+        // Started at the same time, so it would include the rate limit too
+        let second = start_run(conn, "delete", "0", None, None, now).unwrap();
+        finish_run(conn, second, 0, now + 180).unwrap();
+        reports.write();
+        // The rate limit was reported with the first run, and not again
+        assert_eq!(written(), [(second, 0)]);
+
+        reports.write();
+        assert_eq!(written(), [(second, 0)]);
+        drop(reports);
+        fs::remove_dir_all(&base).unwrap();
+    }
+}
@@ -0,0 +1,162 @@
+//! Interactive review of the tweets matching some filters, before deleting
+//! them, see `review`
+//!
+//! Tweets are shown one at a time, with their text, when they were created,
+//! and their likes and retweets, and each can be marked to keep or delete.
+use std::io::{self, Stdout};
+
+use anyhow::Result;
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::{Backend, CrosstermBackend},
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, Paragraph, Wrap},
+    Frame,
+    Terminal,
+};
+use time::{OffsetDateTime, UtcOffset};
+use twitter_delete::Summary;
+
+use crate::HUMAN_DATE;
+
+/// What to do with a reviewed tweet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    /// Protect it, so it's never deleted
+    Keep,
+
+    /// Stage it for deletion
+    Delete,
+}
+
+/// The terminal, in raw mode on the alternate screen until dropped
+struct Tui(Terminal<CrosstermBackend<Stdout>>);
+
+impl Tui {
+    fn new() -> Result<Self> {
+        enable_raw_mode()?;
+        let mut out = io::stdout();
+        execute!(out, EnterAlternateScreen)?;
+        Ok(Self(Terminal::new(CrosstermBackend::new(out))?))
+    }
+}
+
+impl Drop for Tui {
+    fn drop(&mut self) {
+        // Nothing else can be done if the terminal can't be restored
+        let _ = disable_raw_mode();
+        let _ = execute!(self.0.backend_mut(), LeaveAlternateScreen);
+        let _ = self.0.show_cursor();
+    }
+}
+
+/// Review `tweets`, showing times in `offset`
+///
+/// Returns the [`Decision`] made for each of `tweets`, in order, [`None`]
+/// for those skipped, or [`None`] overall if the review was cancelled.
+pub fn review(tweets: &[Summary], offset: UtcOffset) -> Result<Option<Vec<Option<Decision>>>> {
+    let mut decisions = vec![None; tweets.len()];
+    let last = tweets.len().saturating_sub(1);
+    let mut at = 0;
+
+    let mut tui = Tui::new()?;
+    loop {
+        tui.0.draw(|f| draw(f, tweets, &decisions, at, offset))?;
+        let key = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+        match key.code {
+            KeyCode::Char('k') => {
+                decisions[at] = Some(Decision::Keep);
+                at = (at + 1).min(last);
+            }
+            KeyCode::Char('d') => {
+                decisions[at] = Some(Decision::Delete);
+                at = (at + 1).min(last);
+            }
+            KeyCode::Char('u') => decisions[at] = None,
+            KeyCode::Right | KeyCode::Down | KeyCode::Char('n' | ' ') => at = (at + 1).min(last),
+            KeyCode::Left | KeyCode::Up | KeyCode::Char('p') => at = at.saturating_sub(1),
+            KeyCode::Char('q') => return Ok(Some(decisions)),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(None),
+            KeyCode::Esc => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+/// Draw tweet `at` of `tweets`, with a summary of `decisions` so far
+fn draw<B: Backend>(
+    f: &mut Frame<B>,
+    tweets: &[Summary],
+    decisions: &[Option<Decision>],
+    at: usize,
+    offset: UtcOffset,
+) {
+    let tweet = &tweets[at];
+    let count = |want| decisions.iter().filter(|d| **d == Some(want)).count();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Min(3),
+            Constraint::Length(1),
+        ])
+        .split(f.size());
+
+    let status = Paragraph::new(tr!(
+        "Tweet {at} of {total}, {keep} to keep, {delete} to delete",
+        at = at + 1,
+        total = tweets.len(),
+        keep = count(Decision::Keep),
+        delete = count(Decision::Delete)
+    ));
+    f.render_widget(status, chunks[0]);
+
+    let created = OffsetDateTime::from_unix_timestamp(tweet.created_at)
+        .ok()
+        .and_then(|t| t.to_offset(offset).format(HUMAN_DATE).ok())
+        .unwrap_or_else(|| tweet.created_at.to_string());
+    let (marked, color) = match decisions[at] {
+        Some(Decision::Keep) => (tr!("KEEP"), Color::Green),
+        Some(Decision::Delete) => (tr!("DELETE"), Color::Red),
+        None => (tr!("undecided"), Color::Reset),
+    };
+    let title = Spans::from(vec![
+        Span::raw(format!(
+            " {} {created} {} ",
+            tweet.id,
+            tr!(
+                "{likes} likes {retweets} retweets",
+                likes = tweet.likes,
+                retweets = tweet.retweets
+            )
+        )),
+        Span::styled(
+            format!("{marked} "),
+            Style::default().fg(color).add_modifier(Modifier::BOLD),
+        ),
+    ]);
+    // Tweets imported before their text was recorded have none
+    let text = tweet
+        .text
+        .clone()
+        .unwrap_or_else(|| tr!("(text not imported, import the archive again to see it)"));
+    let body = Paragraph::new(text)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .wrap(Wrap { trim: false });
+    f.render_widget(body, chunks[1]);
+
+    let help = Paragraph::new(tr!(
+        "k keep, d delete, u undo, n/p next/previous, q finish and save, Esc cancel"
+    ))
+    .style(Style::default().add_modifier(Modifier::DIM));
+    f.render_widget(help, chunks[2]);
+}
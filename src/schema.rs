@@ -5,6 +5,104 @@ diesel::table! {
         id_str -> Text,
         user_name -> Text,
         display_name -> Text,
+        api_key -> Nullable<Text>,
+        keys_path -> Nullable<Text>,
+        keyring -> Bool,
+        frozen_at -> Nullable<BigInt>,
+    }
+}
+
+diesel::table! {
+    dms (id, account_id) {
+        id -> Text,
+        account_id -> Text,
+        conversation_id -> Text,
+        sender_id -> Nullable<Text>,
+        text -> Nullable<Text>,
+        created_at -> BigInt,
+        deleted -> Bool,
+        deleted_at -> Nullable<BigInt>,
+    }
+}
+
+diesel::table! {
+    events (id) {
+        id -> Integer,
+        tweet_id -> Text,
+        kind -> Text,
+        at -> BigInt,
+        detail -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    hashtags (tweet_id, hashtag) {
+        tweet_id -> Text,
+        hashtag -> Text,
+    }
+}
+
+diesel::table! {
+    likes (tweet_id, account_id) {
+        tweet_id -> Text,
+        account_id -> Text,
+        full_text -> Nullable<Text>,
+        unliked -> Bool,
+        unliked_at -> Nullable<BigInt>,
+    }
+}
+
+diesel::table! {
+    lookup_batches (run_id, batch) {
+        run_id -> Integer,
+        batch -> Integer,
+        requested -> Integer,
+        returned -> Integer,
+        gone -> Integer,
+        missing -> Integer,
+    }
+}
+
+diesel::table! {
+    mentions (tweet_id, user_id) {
+        tweet_id -> Text,
+        user_id -> Text,
+        screen_name -> Text,
+    }
+}
+
+diesel::table! {
+    runs (id) {
+        id -> Integer,
+        command -> Text,
+        tag -> Nullable<Text>,
+        started_at -> BigInt,
+        finished_at -> Nullable<BigInt>,
+        deleted -> Integer,
+        filters -> Nullable<Text>,
+        batch -> Nullable<Text>,
+        discrepancy -> Nullable<Integer>,
+        parse_ms -> Nullable<BigInt>,
+        insert_ms -> Nullable<BigInt>,
+        imported_rows -> Nullable<Integer>,
+        peak_memory -> Nullable<BigInt>,
+        account_id -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    run_tweets (run_id, tweet_id) {
+        run_id -> Integer,
+        tweet_id -> Text,
+        position -> Nullable<Integer>,
+    }
+}
+
+diesel::table! {
+    saved_filters (name) {
+        name -> Text,
+        filters -> Text,
+        saved_at -> BigInt,
     }
 }
 
@@ -17,9 +115,68 @@ diesel::table! {
         deleted -> Bool,
         checked -> Bool,
         account_id -> Text,
+        protected -> Bool,
+        note -> Nullable<Text>,
+        failures -> Integer,
+        in_reply_to_status_id -> Nullable<Text>,
+        in_reply_to_user_id -> Nullable<Text>,
+        in_reply_to_screen_name -> Nullable<Text>,
+        full_text -> Nullable<Text>,
+        content_kind -> Nullable<Text>,
+        score -> Nullable<Double>,
+        deleted_at -> Nullable<BigInt>,
+        display_text -> Nullable<Text>,
+        archived_at -> Nullable<BigInt>,
+        forbidden -> Integer,
+        permanently_failed -> Bool,
+        is_retweet -> Nullable<Bool>,
+        retweet_of -> Nullable<Text>,
+        has_media -> Nullable<Bool>,
+        initial_id -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    urls (tweet_id, url) {
+        tweet_id -> Text,
+        url -> Text,
+        expanded_url -> Text,
+    }
+}
+
+diesel::table! {
+    watch_policies (account_id) {
+        account_id -> Text,
+        filters -> Text,
+        accepted_at -> BigInt,
     }
 }
 
+diesel::joinable!(dms -> accounts (account_id));
+diesel::joinable!(events -> tweets (tweet_id));
+diesel::joinable!(hashtags -> tweets (tweet_id));
+diesel::joinable!(likes -> accounts (account_id));
+diesel::joinable!(lookup_batches -> runs (run_id));
+diesel::joinable!(mentions -> tweets (tweet_id));
+diesel::joinable!(run_tweets -> runs (run_id));
+diesel::joinable!(run_tweets -> tweets (tweet_id));
+diesel::joinable!(runs -> accounts (account_id));
 diesel::joinable!(tweets -> accounts (account_id));
+diesel::joinable!(urls -> tweets (tweet_id));
+diesel::joinable!(watch_policies -> accounts (account_id));
 
-diesel::allow_tables_to_appear_in_same_query!(accounts, tweets,);
+diesel::allow_tables_to_appear_in_same_query!(
+    accounts,
+    dms,
+    events,
+    hashtags,
+    likes,
+    lookup_batches,
+    mentions,
+    run_tweets,
+    runs,
+    saved_filters,
+    tweets,
+    urls,
+    watch_policies,
+);
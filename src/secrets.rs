@@ -0,0 +1,64 @@
+//! Stores API keys in the OS keyring
+//!
+//! Such as the Secret Service on Linux, Keychain on macOS, or Credential
+//! Manager on Windows.
+//!
+//! Only available when built with the `keyring` feature, otherwise every
+//! function returns an error.
+use anyhow::Result;
+
+use crate::Access;
+
+/// Service name keys are stored under, per account ID
+#[cfg(feature = "keyring")]
+const SERVICE: &str = "twitter_delete";
+
+/// Store `keys` for account `account` in the keyring, replacing any already
+/// stored
+#[cfg(feature = "keyring")]
+pub fn store(account: &str, keys: &Access) -> Result<()> {
+    let entry = keyring::Entry::new(SERVICE, account)?;
+    entry.set_password(&serde_json::to_string(keys)?)?;
+    Ok(())
+}
+
+/// Load the keys for account `account` from the keyring
+#[cfg(feature = "keyring")]
+pub fn load(account: &str) -> Result<Access> {
+    let entry = keyring::Entry::new(SERVICE, account)?;
+    Ok(serde_json::from_str(&entry.get_password()?)?)
+}
+
+/// Remove the keys for account `account` from the keyring
+///
+/// Succeeds if there were no keys stored
+#[cfg(feature = "keyring")]
+pub fn forget(account: &str) -> Result<()> {
+    let entry = keyring::Entry::new(SERVICE, account)?;
+    match entry.delete_password() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(not(feature = "keyring"))]
+fn unsupported() -> anyhow::Error {
+    anyhow::anyhow!(
+        "twitter_delete was built without keyring support, rebuild it with `--features keyring`"
+    )
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn store(_account: &str, _keys: &Access) -> Result<()> {
+    Err(unsupported())
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn load(_account: &str) -> Result<Access> {
+    Err(unsupported())
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn forget(_account: &str) -> Result<()> {
+    Err(unsupported())
+}
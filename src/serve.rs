@@ -0,0 +1,494 @@
+//! Serves core operations over a local socket, so GUI wrappers and editor
+//! plugins can drive twitter_delete without scraping its output
+//!
+//! Speaks JSON-RPC 2.0, one message per line, to one client at a time.
+//! Params are JSON objects, and methods that operate on an account accept an
+//! optional `account` handle, defaulting to `--account`.
+//!
+//! - `stats`, counts of imported, deleted, and checked tweets, per account and
+//!   in total
+//! - `list`, tweets matching `filter`, in `order`
+//! - `stage`, stage tweets like the `stage` subcommand, see [`StageArgs`]
+//! - `commit`, delete the tweets in staged batch `run`
+//! - `subscribe`, send `progress` notifications on this connection during
+//!   `commit`
+//!
+//! Filters use the same names as the command line, in snake case, eg
+//! `{"older_than": 30, "unless_likes": 2}`, or `{"filter_name": "old"}` for
+//! filters saved with `filters save`.
+use std::{
+    fs::{self, Permissions},
+    io::{BufRead, BufReader, Write},
+    os::unix::{
+        fs::PermissionsExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+    thread::{scope, sleep},
+    time::Duration as StdDuration,
+};
+
+use anyhow::{anyhow, Result};
+use diesel::prelude::*;
+use indicatif::ProgressBar;
+use reqwest::Response as AsyncResponse;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer};
+use serde_json::{from_str, from_value, json, to_value, Map, Value};
+use time::{OffsetDateTime, UtcOffset};
+use tracing::{info, warn};
+use twitter_delete::{
+    db::{count_totals, finish_run},
+    execute_deletion,
+    filter::{FilterArgs, Order},
+    find_account,
+    keys_for,
+    models::Account as MAccount,
+    schema::accounts as adb,
+    stage,
+    summarize,
+    twitter::RateLimit,
+    unfinished,
+    ApiOptions,
+    Credentials,
+    Deletion,
+    StageArgs,
+};
+
+/// Error codes defined by JSON-RPC
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Error code for the operation itself failing
+const FAILED: i64 = -32000;
+
+/// A JSON-RPC request
+#[derive(Debug, Deserialize)]
+struct Request {
+    /// Absent for notifications, which are never answered
+    ///
+    /// An ID of `null` is still a request.
+    #[serde(default, deserialize_with = "present")]
+    id: Option<Value>,
+
+    method: String,
+
+    #[serde(default)]
+    params: Value,
+}
+
+/// Deserialize any value as present, even `null`, so only missing values
+/// are [`None`]
+fn present<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Value>, D::Error> {
+    Value::deserialize(d).map(Some)
+}
+
+/// A JSON-RPC error
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl From<anyhow::Error> for RpcError {
+    fn from(e: anyhow::Error) -> Self {
+        Self {
+            code: FAILED,
+            message: e.to_string(),
+        }
+    }
+}
+
+/// Params for `list`
+#[derive(Debug, Deserialize)]
+struct ListParams {
+    filter: FilterArgs,
+
+    #[serde(default)]
+    order: Order,
+
+    #[serde(default)]
+    account: Option<String>,
+}
+
+/// Params for `stage`
+#[derive(Debug, Deserialize)]
+struct StageParams {
+    #[serde(flatten)]
+    stage: StageArgs,
+
+    #[serde(default)]
+    account: Option<String>,
+}
+
+/// Params for `commit`
+#[derive(Debug, Deserialize)]
+struct CommitParams {
+    run: i32,
+
+    #[serde(default)]
+    account: Option<String>,
+}
+
+/// State shared by every connection
+struct Server<'a> {
+    conn: &'a mut SqliteConnection,
+    credentials: &'a Credentials,
+
+    /// Account handle to use when a request doesn't say, from `--account`
+    account: Option<&'a str>,
+
+    /// From `--api`, `--concurrency`, and `--now`
+    api: ApiOptions<'a>,
+
+    /// Offset of the local time zone, or `--tz`
+    offset: UtcOffset,
+}
+
+/// Serve on a new unix socket at `socket`, until killed
+///
+/// Requests are handled like the equivalent subcommand, with `credentials`
+/// as the default API keys, `account` as the default account, `api` for
+/// deleting, and its clock as the current time in `offset`.
+pub fn serve(
+    conn: &mut SqliteConnection,
+    credentials: &Credentials,
+    account: Option<&str>,
+    api: ApiOptions,
+    offset: UtcOffset,
+    socket: &Path,
+) -> Result<()> {
+    // Left behind by a previous server that was killed
+    if socket.exists() {
+        if UnixStream::connect(socket).is_ok() {
+            return Err(anyhow!("Already serving on {}", socket.display()));
+        }
+        fs::remove_file(socket)?;
+    }
+    let listener = UnixListener::bind(socket)
+        .map_err(|e| anyhow!("Couldn't create socket {}: {e}", socket.display()))?;
+    // Anyone who can connect can delete tweets
+    fs::set_permissions(socket, Permissions::from_mode(0o600))?;
+    info!("Serving on {}", socket.display());
+
+    let mut server = Server {
+        conn,
+        credentials,
+        account,
+        api,
+        offset,
+    };
+    for stream in listener.incoming() {
+        if let Err(e) = server.connection(stream?) {
+            warn!("Connection closed: {e}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Send `msg` as a single line
+fn send(mut out: &UnixStream, msg: &Value) -> Result<()> {
+    writeln!(out, "{msg}")?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Parse `params`, where missing params are the same as empty ones
+fn params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    let params = match params {
+        Value::Null => Value::Object(Map::new()),
+        params => params,
+    };
+    from_value(params).map_err(|e| RpcError {
+        code: INVALID_PARAMS,
+        message: e.to_string(),
+    })
+}
+
+impl Server<'_> {
+    /// Answer every request on `stream` until the client disconnects
+    fn connection(&mut self, stream: UnixStream) -> Result<()> {
+        let out = stream.try_clone()?;
+        let mut subscribed = false;
+        for line in BufReader::new(stream).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (id, res) = match from_str::<Request>(&line) {
+                Ok(req) => (req.id.clone(), self.call(req, &mut subscribed, &out)),
+                // Always answered, as the ID can't be known
+                Err(e) => (
+                    Some(Value::Null),
+                    Err(RpcError {
+                        code: PARSE_ERROR,
+                        message: e.to_string(),
+                    }),
+                ),
+            };
+            // Notifications are never answered, even when they fail
+            let id = match id {
+                Some(id) => id,
+                None => continue,
+            };
+            let msg = match res {
+                Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                Err(e) => json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": { "code": e.code, "message": e.message },
+                }),
+            };
+            send(&out, &msg)?;
+        }
+        Ok(())
+    }
+
+    /// Handle `req`, sending any progress notifications to `out`
+    fn call(
+        &mut self,
+        req: Request,
+        subscribed: &mut bool,
+        out: &UnixStream,
+    ) -> Result<Value, RpcError> {
+        match req.method.as_str() {
+            "stats" => Ok(self.stats()?),
+            "list" => Ok(self.list(params(req.params)?)?),
+            "stage" => Ok(self.stage(params(req.params)?)?),
+            "commit" => {
+                let progress = subscribed.then_some(out);
+                Ok(self.commit(params(req.params)?, progress)?)
+            }
+            "subscribe" => {
+                *subscribed = true;
+                Ok(Value::Bool(true))
+            }
+            method => Err(RpcError {
+                code: METHOD_NOT_FOUND,
+                message: format!("No method `{method}`"),
+            }),
+        }
+    }
+
+    /// The current time, in the local time zone
+    fn now(&self) -> OffsetDateTime {
+        self.api.clock.now().to_offset(self.offset)
+    }
+
+    /// Find the account with handle `handle`, or the default account
+    fn account(&mut self, handle: Option<&str>) -> Result<MAccount> {
+        find_account(self.conn, handle.or(self.account))
+    }
+
+    fn stats(&mut self) -> Result<Value> {
+        let accounts: Vec<MAccount> = adb::dsl::accounts.get_results(self.conn)?;
+        let mut per_account = Vec::with_capacity(accounts.len());
+        for acc in accounts {
+            let totals = count_totals(self.conn, Some(&acc.id_str))?;
+            per_account.push(json!({
+                "id": acc.id_str,
+                "handle": acc.user_name,
+                "display_name": acc.display_name,
+                "imported": totals.imported,
+                "deleted": totals.deleted,
+                "checked": totals.checked,
+            }));
+        }
+        let totals = count_totals(self.conn, None)?;
+        Ok(json!({
+            "accounts": per_account,
+            "imported": totals.imported,
+            "deleted": totals.deleted,
+            "checked": totals.checked,
+        }))
+    }
+
+    fn list(&mut self, params: ListParams) -> Result<Value> {
+        let account = self.account(params.account.as_deref())?;
+        let mut filter = params.filter.resolve(self.conn)?.build(self.now())?;
+        filter.account = Some(account.id_str);
+        let ids = filter.load(self.conn, params.order)?;
+        Ok(to_value(summarize(self.conn, &ids)?)?)
+    }
+
+    fn stage(&mut self, params: StageParams) -> Result<Value> {
+        let account = self.account(params.account.as_deref())?;
+        let staged = stage(self.conn, &params.stage, &account.id_str, self.now())?;
+        Ok(to_value(staged)?)
+    }
+
+    /// Commit a staged batch, sending `progress` notifications to `progress`
+    /// every second, if given
+    fn commit(&mut self, params: CommitParams, progress: Option<&UnixStream>) -> Result<Value> {
+        let run = params.run;
+        let account = self.account(params.account.as_deref())?;
+        let keys = keys_for(self.conn, self.credentials, &account)?;
+        let (_, to_process) = unfinished(self.conn, run, &account.id_str)?;
+
+        let pb = ProgressBar::hidden();
+        pb.set_length(to_process.len() as u64);
+        let on_limit = |limit, _: &AsyncResponse| {
+            pb.set_prefix(match limit {
+                RateLimit::Until(secs) => format!("Rate limited, waiting until {secs}"),
+                RateLimit::Unknown => "Rate limited, waiting 15 minutes".to_owned(),
+            });
+            Ok(())
+        };
+
+        let done = AtomicBool::new(false);
+        let total = scope(|s| {
+            if let Some(out) = progress {
+                s.spawn(|| {
+                    while !done.load(Ordering::Relaxed) {
+                        let msg = json!({
+                            "jsonrpc": "2.0",
+                            "method": "progress",
+                            "params": {
+                                "run": run,
+                                "done": pb.position(),
+                                "total": to_process.len(),
+                                "status": pb.prefix(),
+                            },
+                        });
+                        // The client going away is noticed after committing
+                        if send(out, &msg).is_err() {
+                            break;
+                        }
+                        sleep(StdDuration::from_secs(1));
+                    }
+                });
+            }
+            let total = execute_deletion(
+                self.conn,
+                &keys,
+                self.api,
+                run,
+                &to_process,
+                on_limit,
+                |id, deletion, retweet| {
+                    pb.inc(1);
+                    match deletion {
+                        Deletion::Forbidden(true) => {
+                            pb.set_prefix(format!("Giving up on unretweeting {id}"))
+                        }
+                        Deletion::Forbidden(false) => {
+                            pb.set_prefix(format!("Failed to unretweet {id}"))
+                        }
+                        Deletion::Gone(_) => pb.set_prefix(format!("Already deleted {id}")),
+                        Deletion::Deleted(_) if retweet => {
+                            pb.set_prefix(format!("Unretweeted {id}"))
+                        }
+                        Deletion::Deleted(_) => pb.set_prefix(format!("Deleted {id}")),
+                        Deletion::Error => (),
+                    }
+                },
+            );
+            done.store(true, Ordering::Relaxed);
+            total
+        })?;
+
+        finish_run(self.conn, run, total, self.api.clock.now().unix_timestamp())?;
+        Ok(json!({ "deleted": total }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::Shutdown, path::PathBuf};
+
+    use time::macros::datetime;
+    use twitter_delete::{
+        clock::FrozenClock,
+        db::create_db,
+        twitter::{create_concurrent, Api},
+    };
+
+    use super::*;
+
+    /// Answers to the lines of `requests`, sent on one connection
+    fn answers(requests: &str) -> Vec<Value> {
+        let conn = &mut create_db(Path::new(":memory:")).unwrap();
+        let credentials = Credentials {
+            path: PathBuf::new(),
+            configured: None,
+        };
+        let concurrent = create_concurrent(1).unwrap();
+        let clock = FrozenClock(datetime!(2023-01-01 0:00 UTC));
+        let mut server = Server {
+            conn,
+            credentials: &credentials,
+            account: None,
+            api: ApiOptions {
+                api: Api::V1,
+                concurrent: &concurrent,
+                clock: &clock,
+            },
+            offset: UtcOffset::UTC,
+        };
+        let (mut client, stream) = UnixStream::pair().unwrap();
+        client.write_all(requests.as_bytes()).unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+        server.connection(stream).unwrap();
+        BufReader::new(client)
+            .lines()
+            .map(|line| from_str(&line.unwrap()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn null_id_is_a_request() {
+        let req: Request =
+            from_str(r#"{"jsonrpc": "2.0", "id": null, "method": "stats"}"#).unwrap();
+        assert_eq!(req.id, Some(Value::Null));
+        let req: Request = from_str(r#"{"jsonrpc": "2.0", "method": "stats"}"#).unwrap();
+        assert_eq!(req.id, None);
+    }
+
+    #[test]
+    fn null_params_are_empty() {
+        let parsed: CommitParams = params(json!({ "run": 1 })).unwrap();
+        assert_eq!(parsed.run, 1);
+        assert_eq!(parsed.account, None);
+        // Parsed as `{}`, not rejected for being null
+        let e = params::<CommitParams>(Value::Null).unwrap_err();
+        assert_eq!(e.code, INVALID_PARAMS);
+        assert!(e.message.contains("missing field `run`"), "{}", e.message);
+    }
+
+    #[test]
+    fn answers_requests_but_not_notifications() {
+        let answers = answers(concat!(
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "nope"}"#,
+            "\n",
+            "not json\n",
+            "\n",
+            r#"{"jsonrpc": "2.0", "method": "nope"}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "method": "subscribe"}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": null, "method": "subscribe"}"#,
+            "\n",
+        ));
+        assert_eq!(
+            answers,
+            [
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "error": { "code": METHOD_NOT_FOUND, "message": "No method `nope`" },
+                }),
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": null,
+                    "error": {
+                        "code": PARSE_ERROR,
+                        "message": "expected ident at line 1 column 2",
+                    },
+                }),
+                json!({ "jsonrpc": "2.0", "id": null, "result": true }),
+            ]
+        );
+    }
+}
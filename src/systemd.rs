@@ -0,0 +1,102 @@
+//! Tells systemd how `watch` is doing, when run as a `Type=notify` service
+//!
+//! Messages go to the socket in `$NOTIFY_SOCKET`, so this does nothing when
+//! not run by systemd, or on platforms other than unix.
+//!
+//! With `WatchdogSec=` set, the watchdog is pinged as `watch` makes
+//! progress, and while it waits, so a pass that hangs gets restarted.
+#[cfg(unix)]
+use std::env;
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+use std::{
+    thread,
+    time::{Duration as StdDuration, Instant},
+};
+
+#[cfg(unix)]
+use tracing::debug;
+
+/// Tell systemd `watch` has started
+pub fn ready() {
+    send("READY=1");
+    ping();
+}
+
+/// Show `status` in `systemctl status`
+pub fn status(status: &str) {
+    send(&format!("STATUS={status}"));
+}
+
+/// Tell the watchdog we're still making progress, if systemd asked for one
+pub fn ping() {
+    if watchdog().is_some() {
+        send("WATCHDOG=1");
+    }
+}
+
+/// Sleep for `duration`, in slices short enough to keep pinging the
+/// watchdog
+pub fn sleep(duration: StdDuration) {
+    let Some(slice) = watchdog() else {
+        thread::sleep(duration);
+        return;
+    };
+    let end = Instant::now() + duration;
+    loop {
+        ping();
+        let left = end.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            break;
+        }
+        thread::sleep(left.min(slice));
+    }
+}
+
+/// Send `state` to systemd, if it's listening
+///
+/// Failing to send only logs, systemd not hearing from us shouldn't stop a
+/// run.
+#[cfg(unix)]
+fn send(state: &str) {
+    let Some(path) = env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+    let sent = UnixDatagram::unbound().and_then(|sock| {
+        // Abstract sockets start with `@`
+        #[cfg(target_os = "linux")]
+        if let Some(name) = path.to_str().and_then(|p| p.strip_prefix('@')) {
+            use std::os::{linux::net::SocketAddrExt, unix::net::SocketAddr};
+            let addr = SocketAddr::from_abstract_name(name)?;
+            return sock.send_to_addr(state.as_bytes(), &addr);
+        }
+        sock.send_to(state.as_bytes(), &path)
+    });
+    if let Err(e) = sent {
+        debug!("Couldn't notify systemd: {e}");
+    }
+}
+
+#[cfg(not(unix))]
+fn send(_state: &str) {}
+
+/// How often to ping the watchdog, if systemd asked for it
+///
+/// A quarter of `WATCHDOG_USEC`, comfortably inside the half systemd
+/// recommends.
+#[cfg(unix)]
+fn watchdog() -> Option<StdDuration> {
+    let usec = env::var("WATCHDOG_USEC").ok()?.parse::<u64>().ok()?;
+    // Meant for another process
+    if let Ok(pid) = env::var("WATCHDOG_PID") {
+        if pid != std::process::id().to_string() {
+            return None;
+        }
+    }
+    Some(StdDuration::from_micros(usec / 4).max(StdDuration::from_millis(1)))
+}
+
+#[cfg(not(unix))]
+fn watchdog() -> Option<StdDuration> {
+    None
+}
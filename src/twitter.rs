@@ -1,34 +1,45 @@
 //! Handles stuff related to interacting with the twitter API
 use std::{
+    cell::{Cell, RefCell},
     collections::HashMap,
-    fs,
+    fmt,
+    future::Future,
     iter::once,
-    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
     thread::sleep,
     time::Duration as StdDuration,
 };
 
 use anyhow::{anyhow, Result};
 use base64::{engine::general_purpose::STANDARD, Engine as _};
+use clap::ValueEnum;
+use futures::stream::{self, StreamExt};
 use hmac::{Hmac, Mac};
 use rand::{
     distributions::{Alphanumeric, DistString},
     thread_rng,
+    Rng,
 };
 use req::{
-    blocking::{Client, RequestBuilder, Response},
-    header::AUTHORIZATION,
+    blocking::{Client, ClientBuilder, RequestBuilder, Response},
+    header::{HeaderMap, AUTHORIZATION},
+    Client as AsyncClient,
+    ClientBuilder as AsyncClientBuilder,
     Method,
+    RequestBuilder as AsyncRequestBuilder,
+    Response as AsyncResponse,
     StatusCode,
 };
 use reqwest as req;
-use serde::Deserialize;
-use serde_json::from_str;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{from_str, Value};
 use sha1::Sha1;
-use time::{format_description::FormatItem, macros::format_description, OffsetDateTime};
-use urlencoding::encode;
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use tokio::runtime::Runtime;
+use tracing::debug;
+use urlencoding::{decode, encode};
 
-use crate::Access;
+use crate::{archive::TWITTER_DATE, clock::Clock, id::TweetId, Access};
 
 type HmacSha1 = Hmac<Sha1>;
 
@@ -46,20 +57,108 @@ pub const TWEET_DESTROY_URL_FMT: &str = "https://api.twitter.com/1.1/statuses/de
 
 /// Unretweet a tweet
 ///
-/// Ends in `{id}.json`
+/// Ends in `{id}.json`, the ID of the retweeted tweet
 ///
 /// <https://developer.twitter.com/en/docs/twitter-api/v1/tweets/post-and-engage/api-reference/post-statuses-unretweet-id>
-pub const _TWEET_RETWEET_URL_FMT: &str = "https://api.twitter.com/1.1/statuses/unretweet";
+pub const TWEET_UNRETWEET_URL_FMT: &str = "https://api.twitter.com/1.1/statuses/unretweet";
+
+/// Lookup 100 tweet IDs at a time, with API v2
+///
+/// <https://developer.twitter.com/en/docs/twitter-api/tweets/lookup/api-reference/get-tweets>
+pub const TWEET_LOOKUP_V2_URL: &str = "https://api.twitter.com/2/tweets";
+
+/// Delete a tweet, with API v2
+///
+/// Ends in `{id}`
+///
+/// <https://developer.twitter.com/en/docs/twitter-api/tweets/manage-tweets/api-reference/delete-tweets-id>
+pub const TWEET_DELETE_V2_URL_FMT: &str = "https://api.twitter.com/2/tweets";
+
+/// Unretweet a tweet, with API v2
+///
+/// Ends in `{user id}/retweets/{id}`, the ID of the retweeted tweet
+///
+/// <https://developer.twitter.com/en/docs/twitter-api/tweets/retweets/api-reference/delete-users-id-retweets-tweet_id>
+pub const TWEET_UNRETWEET_V2_URL_FMT: &str = "https://api.twitter.com/2/users";
+
+/// Post a tweet
+///
+/// <https://developer.twitter.com/en/docs/twitter-api/v1/tweets/post-and-engage/api-reference/post-statuses-update>
+pub const TWEET_UPDATE_URL: &str = "https://api.twitter.com/1.1/statuses/update.json";
+
+/// Unlike a tweet
+///
+/// <https://developer.twitter.com/en/docs/twitter-api/v1/tweets/post-and-engage/api-reference/post-favorites-destroy>
+pub const FAVORITE_DESTROY_URL: &str = "https://api.twitter.com/1.1/favorites/destroy.json";
+
+/// Delete a direct message
+///
+/// <https://developer.twitter.com/en/docs/twitter-api/v1/direct-messages/sending-and-receiving/api-reference/delete-message-event>
+pub const DM_DESTROY_URL: &str = "https://api.twitter.com/1.1/direct_messages/events/destroy.json";
 
 /// Get information on a specific tweet
 ///
 /// <https://developer.twitter.com/en/docs/twitter-api/v1/tweets/post-and-engage/api-reference/get-statuses-show-id>
 pub const _TWEET_SHOW_URL: &str = "https://api.twitter.com/1.1/statuses/show.json";
 
-/// The format of twitters `created_at` dates
-pub static TWITTER_DATE: &[FormatItem] = format_description!(
-    "[weekday repr:short case_sensitive:false] [month repr:short] [day] [hour]:[minute]:[second] +0000 [year]"
-);
+/// Get the account API keys are for
+///
+/// <https://developer.twitter.com/en/docs/twitter-api/v1/accounts-and-users/manage-account-settings/api-reference/get-account-verify_credentials>
+pub const VERIFY_CREDENTIALS_URL: &str =
+    "https://api.twitter.com/1.1/account/verify_credentials.json";
+
+/// A tweet, like, and direct message ID that doesn't exist, so
+/// [`test_auth`] can't delete anything
+const NONEXISTENT_ID: &str = "1";
+
+/// Get a request token to start authorizing an account
+///
+/// <https://developer.twitter.com/en/docs/authentication/api-reference/request_token>
+pub const REQUEST_TOKEN_URL: &str = "https://api.twitter.com/oauth/request_token";
+
+/// Page where the user authorizes a request token, and is shown a PIN
+///
+/// <https://developer.twitter.com/en/docs/authentication/api-reference/authorize>
+pub const AUTHORIZE_URL: &str = "https://api.twitter.com/oauth/authorize";
+
+/// Exchange an authorized request token for an access token
+///
+/// <https://developer.twitter.com/en/docs/authentication/api-reference/access_token>
+pub const ACCESS_TOKEN_URL: &str = "https://api.twitter.com/oauth/access_token";
+
+/// Version of the twitter API to look up and delete tweets with
+///
+/// Everything else always uses v1.1
+#[derive(ValueEnum, Debug, Clone, Copy, Default)]
+pub enum Api {
+    /// v1.1
+    #[default]
+    V1,
+
+    /// v2, for when v1.1 endpoints are restricted
+    V2,
+}
+
+/// A response to a request sent concurrently with others, see [`bulk`]
+///
+/// The body has already been read, so responses can be handled in order while
+/// later requests are still in flight.
+#[derive(Debug)]
+pub struct Reply {
+    status: StatusCode,
+    body: String,
+}
+
+impl Reply {
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Parse the body as JSON
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T> {
+        Ok(from_str(&self.body)?)
+    }
+}
 
 /// Indicates the rate limit response from the server
 #[derive(Debug, Clone, Copy)]
@@ -78,17 +177,61 @@ pub enum RateLimit {
     Unknown,
 }
 
+/// Twitter rejected the API keys, such as when the access token was revoked
+///
+/// Returned by every API call, see [`rate_limit`]
+#[derive(Debug, Clone, Copy)]
+pub struct Unauthorized;
+
+impl fmt::Display for Unauthorized {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Twitter rejected the API keys with 401 Unauthorized, the access token may have been \
+             revoked. Re-authorize the account with `auth`, or update your keys with `accounts \
+             set-keys`"
+        )
+    }
+}
+
+impl std::error::Error for Unauthorized {}
+
+impl RateLimit {
+    /// How many seconds to wait for the rate limit to reset, from the time of
+    /// `clock`
+    ///
+    /// Defaults to 15 minutes, the length of a rate limit window, when twitter
+    /// didn't say.
+    pub fn wait(self, clock: &dyn Clock) -> u64 {
+        match self {
+            Self::Until(secs) => (secs as i64)
+                .checked_sub(clock.now().unix_timestamp())
+                .map_or(60 * 15, |secs| secs.max(0) as u64),
+            Self::Unknown => 60 * 15,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub struct LookupResp {
-    pub id: HashMap<String, Option<LookupTweet>>,
+    pub id: HashMap<TweetId, Option<LookupTweet>>,
+
+    /// IDs that were looked up but left out of [`LookupResp::id`] entirely,
+    /// so aren't known to exist or not
+    #[serde(skip)]
+    pub missing: Vec<TweetId>,
+
+    /// How many IDs were looked up
+    #[serde(skip)]
+    pub requested: usize,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub struct LookupTweet {
     /// Tweet ID
-    pub id_str: String,
+    pub id_str: TweetId,
 
     /// Number of retweets
     pub retweet_count: u64,
@@ -102,71 +245,236 @@ pub struct LookupTweet {
     ///
     /// See [`TWITTER_DATE`]
     pub created_at: String,
+
+    /// The retweeted tweet, only present for retweets
+    #[serde(default)]
+    pub retweeted_status: Option<Retweeted>,
+
+    /// ID of every version of the tweet, oldest first, if it was edited
+    ///
+    /// Only known from API v2, empty otherwise.
+    #[serde(default)]
+    pub edit_history_tweet_ids: Vec<TweetId>,
 }
 
+/// A tweet that was retweeted, see [`LookupTweet::retweeted_status`]
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "snake_case")]
-pub struct DeleteResp {
+pub struct Retweeted {
     /// Tweet ID
-    pub id_str: String,
+    pub id_str: TweetId,
 }
 
-/// Twitter tweet object. Internal, useless.
+/// Response from looking up tweets with API v2
+///
+/// Tweets that don't exist, or can't be seen, are in `errors` instead of
+/// `data`
 #[derive(Debug, Deserialize)]
-struct TweetObj {
-    tweet: Tweet,
+struct V2LookupResp {
+    #[serde(default)]
+    data: Vec<V2Tweet>,
+
+    #[serde(default)]
+    errors: Vec<V2Problem>,
 }
 
-/// A Tweet in the twitter archive.
-///
-/// NOTE: This is ***different*** than what would be returned by
-/// the twitter API.
+/// A tweet from API v2
 #[derive(Debug, Deserialize)]
-pub struct Tweet {
+struct V2Tweet {
     /// Tweet ID
-    ///
-    /// Currently 19 characters long, a 64-bit number.
-    pub id_str: String,
+    id: TweetId,
 
-    /// Number of retweets
-    #[serde(rename = "retweet_count")]
-    pub retweets: String,
+    /// Time of tweet, in RFC 3339 format
+    created_at: String,
 
-    /// Number of likes
-    #[serde(rename = "favorite_count")]
-    pub likes: String,
+    public_metrics: V2Metrics,
 
-    /// Time of tweet
+    /// Tweets this retweets, quotes, or replies to
+    #[serde(default)]
+    referenced_tweets: Vec<V2Reference>,
+
+    /// ID of every version of the tweet, oldest first
     ///
-    /// See [`TWITTER_DATE`]
-    pub created_at: String,
+    /// Tweets that were never edited only list themselves
+    #[serde(default)]
+    edit_history_tweet_ids: Vec<TweetId>,
 }
 
-/// Twitter account object. Internal, useless.
+/// Engagement with a [`V2Tweet`]
 #[derive(Debug, Deserialize)]
-struct AccountObj {
-    account: Account,
+struct V2Metrics {
+    retweet_count: u64,
+    like_count: u64,
 }
 
-/// A Account in the twitter archive.
-///
-/// NOTE: This is ***different*** than what would be returned by
-/// the twitter API.
+/// A tweet referenced by a [`V2Tweet`]
 #[derive(Debug, Deserialize)]
-pub struct Account {
+struct V2Reference {
+    /// `retweeted`, `quoted`, or `replied_to`
+    #[serde(rename = "type")]
+    kind: String,
+
     /// Tweet ID
-    ///
-    /// Currently 19 characters long, a 64-bit number.
-    #[serde(rename = "accountId")]
-    pub id_str: String,
+    id: TweetId,
+}
+
+/// A partial error in an API v2 response
+#[derive(Debug, Deserialize)]
+struct V2Problem {
+    /// ID of the tweet this is about, if any
+    #[serde(default)]
+    resource_id: Option<String>,
 
-    /// Account username at time of archive
-    #[serde(rename = "username")]
-    pub user_name: String,
+    #[serde(default)]
+    title: String,
 
-    /// Account display name at time of archive
-    #[serde(rename = "accountDisplayName")]
-    pub display_name: String,
+    #[serde(default)]
+    detail: Option<String>,
+}
+
+impl V2LookupResp {
+    /// Convert to the v1.1 [`LookupResp`] for `ids`, which has every ID
+    /// looked up, and [`None`] for tweets that don't exist
+    fn into_v1(self, ids: &[&TweetId]) -> Result<LookupResp> {
+        if let Some(e) = self.errors.iter().find(|e| e.resource_id.is_none()) {
+            return Err(anyhow!(
+                "Twitter failed to look up tweets: {}",
+                e.detail.as_deref().unwrap_or(&e.title)
+            ));
+        }
+        let mut id: HashMap<TweetId, Option<LookupTweet>> =
+            ids.iter().map(|&i| (i.clone(), None)).collect();
+        for tweet in self.data {
+            let created_at = OffsetDateTime::parse(&tweet.created_at, &Rfc3339)?;
+            let retweeted_status = tweet
+                .referenced_tweets
+                .into_iter()
+                .find(|r| r.kind == "retweeted")
+                .map(|r| Retweeted { id_str: r.id });
+            let mut edit_history_tweet_ids = tweet.edit_history_tweet_ids;
+            if edit_history_tweet_ids.len() < 2 {
+                edit_history_tweet_ids.clear();
+            }
+            id.insert(
+                tweet.id.clone(),
+                Some(LookupTweet {
+                    id_str: tweet.id,
+                    retweet_count: tweet.public_metrics.retweet_count,
+                    like_count: tweet.public_metrics.like_count,
+                    created_at: created_at.format(TWITTER_DATE)?,
+                    retweeted_status,
+                    edit_history_tweet_ids,
+                }),
+            );
+        }
+        Ok(LookupResp {
+            id,
+            missing: Vec::new(),
+            requested: 0,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct PostResp {
+    /// Tweet ID
+    pub id_str: TweetId,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DeleteResp {
+    /// Tweet ID
+    pub id_str: TweetId,
+}
+
+/// How long idle connections are kept, longer than the 15 minute rate limit
+/// windows so they're reused after waiting one out
+const POOL_IDLE_TIMEOUT: StdDuration = StdDuration::from_secs(60 * 16);
+
+/// How often idle connections are probed to keep them alive
+const TCP_KEEPALIVE: StdDuration = StdDuration::from_secs(60);
+
+/// Idle connections kept per host, at least
+const POOL_MAX_IDLE: usize = 4;
+
+/// Configure `$builder`, either a blocking or async client builder, with the
+/// connection settings shared by both, keeping at least `$idle` idle
+/// connections
+macro_rules! pooled {
+    ($builder:expr, $idle:expr) => {
+        $builder
+            .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+            .pool_max_idle_per_host($idle.max(POOL_MAX_IDLE))
+            .tcp_keepalive(TCP_KEEPALIVE)
+            .tcp_nodelay(true)
+    };
+}
+
+/// Create the HTTP client used for every request, for the entire run
+///
+/// Long runs make thousands of requests one after another, mostly to the
+/// same host, so connections are kept alive and reused between them,
+/// including while waiting out rate limits.
+/// HTTP/2 is used when the server supports it.
+pub fn create_client() -> Result<Client> {
+    let client = pooled!(ClientBuilder::new(), POOL_MAX_IDLE).build()?;
+    Ok(client)
+}
+
+/// Whether requests are paced, see [`set_gentle`]
+static GENTLE: AtomicBool = AtomicBool::new(false);
+
+/// Least time between requests when gentle, well below any rate limit
+const GENTLE_DELAY: StdDuration = StdDuration::from_secs(3);
+
+/// Most random extra time between requests when gentle, in milliseconds
+const GENTLE_JITTER_MS: u64 = 2000;
+
+/// Pace every request from now on, waiting [`GENTLE_DELAY`] plus some random
+/// jitter before each, see `--gentle`
+pub fn set_gentle(gentle: bool) {
+    GENTLE.store(gentle, Ordering::Relaxed);
+}
+
+/// How long to wait before the next request, nothing unless gentle
+fn gentle_delay() -> StdDuration {
+    if !GENTLE.load(Ordering::Relaxed) {
+        return StdDuration::ZERO;
+    }
+    GENTLE_DELAY + StdDuration::from_millis(thread_rng().gen_range(0..GENTLE_JITTER_MS))
+}
+
+/// The HTTP client and runtime used for sending requests concurrently, see
+/// [`create_concurrent`]
+pub struct Concurrent {
+    client: AsyncClient,
+    runtime: Runtime,
+
+    /// How many requests to have in flight at once
+    ///
+    /// Rate limits are still respected, see [`Budget`].
+    concurrency: usize,
+}
+
+/// Create the client and runtime used for concurrent requests, for the entire
+/// run, with up to `concurrency` requests in flight at once, see [`bulk`]
+///
+/// The client shares its connection settings with [`create_client`], with a
+/// connection for each request in flight kept alive and reused by every
+/// lookup and delete.
+pub fn create_concurrent(concurrency: usize) -> Result<Concurrent> {
+    let concurrency = concurrency.max(1);
+    let client = pooled!(AsyncClientBuilder::new(), concurrency).build()?;
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    Ok(Concurrent {
+        client,
+        runtime,
+        concurrency,
+    })
 }
 
 /// Create twitter authentication headers
@@ -174,26 +482,38 @@ pub struct Account {
 /// Params is not percent encoded
 fn create_auth(
     keys: &Access,
+    clock: &dyn Clock,
+    base_url: &str,
+    method: Method,
+    params: &[(String, String)],
+) -> String {
+    let nonce = Alphanumeric.sample_string(&mut thread_rng(), 32);
+    auth_with_nonce(keys, clock, &nonce, base_url, method, params)
+}
+
+/// [`create_auth`], with a known `nonce`
+fn auth_with_nonce(
+    keys: &Access,
+    clock: &dyn Clock,
+    nonce: &str,
     base_url: &str,
     method: Method,
     params: &[(String, String)],
 ) -> String {
-    let mut rng = thread_rng();
     let auth = &[
         //
         ("oauth_consumer_key", &keys.api_key),
-        ("oauth_nonce", &Alphanumeric.sample_string(&mut rng, 32)),
+        ("oauth_nonce", &nonce.to_owned()),
         ("oauth_signature_method", &"HMAC-SHA1".to_string()),
-        (
-            "oauth_timestamp",
-            &OffsetDateTime::now_utc().unix_timestamp().to_string(),
-        ),
+        ("oauth_timestamp", &clock.now().unix_timestamp().to_string()),
         ("oauth_token", &keys.access),
         ("oauth_version", &"1.0".to_string()),
     ];
     // Percent encoded auth values
+    // There's no access token yet while authorizing, see [`request_token`]
     let mut auth: Vec<_> = auth
         .iter()
+        .filter(|(k, v)| *k != "oauth_token" || !v.is_empty())
         .map(|(k, v)| (encode(k).into_owned(), encode(v).into_owned()))
         .collect();
     auth.sort_by(|a, b| a.0.cmp(&b.0));
@@ -264,59 +584,51 @@ fn create_auth(
 
 /// Handles rate limiting with the Twitter API
 ///
-/// Sends request `req`, and if a rate limit error is returned,
+/// Sends the request built by `req`, and if a rate limit error is returned,
 /// waits either until the time specified by twitter, or 15 minutes,
-/// and then repeats the request.
+/// as of `clock`, and then builds and sends it again.
+///
+/// `req` has to sign the request each time it's called, as twitter rejects a
+/// reused nonce or a stale timestamp with 401 Unauthorized.
 ///
 /// Before waiting, calls `on_limit`. If this returns an error, it is returned.
+/// Only whatever is left of the wait once it returns is waited, so `on_limit`
+/// may wait out the limit itself.
 ///
 /// Ignores transient HTTP 500 errors. `on_limit` is **NOT** called.
 ///
-/// Client errors are passed through
-fn rate_limit<F: FnMut(RateLimit, &Response) -> Result<()>>(
-    req: &RequestBuilder,
-    on_limit: F,
-) -> Result<Response> {
+/// Returns [`Unauthorized`] if twitter rejects the API keys, as nothing
+/// else will succeed either.
+///
+/// Other client errors are passed through
+fn rate_limit<Req, F>(req: Req, clock: &dyn Clock, on_limit: F) -> Result<Response>
+where
+    Req: Fn() -> RequestBuilder,
+    F: FnMut(RateLimit, &Response) -> Result<()>,
+{
     let mut on_limit = on_limit;
 
     let res = loop {
-        let req = req
-            .try_clone()
-            .expect("BUG: Failed to clone RequestBuilder");
-
-        let res = req.send()?;
+        sleep(gentle_delay());
+        let res = req().send()?;
+        debug!(url = %res.url(), status = res.status().as_u16(), "Twitter responded");
         if res.status().is_success() {
             break res;
         } else if res.status() == StatusCode::TOO_MANY_REQUESTS {
-            if let Some(r) = res
-                .headers()
-                .get("x-rate-limit-reset")
-                .map(|f| f.to_str())
-                .transpose()?
-            {
-                let secs: u64 = r.parse()?;
-                on_limit(RateLimit::Until(secs), &res)?;
-
-                // Default to 15 minutes
-                let secs = (secs as i64)
-                    .checked_sub(OffsetDateTime::now_utc().unix_timestamp())
-                    .unwrap_or(60 * 15);
-                sleep(StdDuration::from_secs(secs as u64));
-            } else {
-                on_limit(RateLimit::Unknown, &res)?;
-
-                // Try waiting 15 minutes if there was no reset
-                // header
-                sleep(StdDuration::from_secs(60 * 15));
-            }
+            let limit = limit_of(res.headers())?;
+            let until = clock.now().unix_timestamp() + limit.wait(clock) as i64;
+            on_limit(limit, &res)?;
+            let left = until - clock.now().unix_timestamp();
+            sleep(StdDuration::from_secs(left.max(0) as u64));
         } else if res.status().is_server_error() {
-            // // Wait a minute and retry on transient server errors
-            // eprintln!(
-            //     "Encountered transient HTTP error {}, waiting one minute\nData: {}",
-            //     res.status(),
-            //     res.text()?
-            // );
+            // Wait a minute and retry on transient server errors
+            debug!(
+                status = res.status().as_u16(),
+                "Transient HTTP error, waiting one minute"
+            );
             sleep(StdDuration::from_secs(60));
+        } else if res.status() == StatusCode::UNAUTHORIZED {
+            return Err(Unauthorized.into());
         } else if res.status().is_client_error() {
             break res;
         }
@@ -325,146 +637,604 @@ fn rate_limit<F: FnMut(RateLimit, &Response) -> Result<()>>(
     Ok(res)
 }
 
-/// Remove the prefix in twitter archive files
-fn remove_tweet_prefix(data: &str) -> &str {
-    // Twitter puts this nonsense in front of the tweet files
-    // Assume there are less than 99 parts.
-    // This will work for both single and double digits
-    // The full line is  `window.YTD.tweets.part4 = [`
-    const PREFIX: &str = "window.YTD.tweets.part99 ";
-    &data[PREFIX.len()..]
+/// The rate limit twitter responded with, from the `headers` of a
+/// `429 Too Many Requests` response
+fn limit_of(headers: &HeaderMap) -> Result<RateLimit> {
+    // API v2 also limits each user per day, which resets long after
+    // the usual 15 minute window
+    let daily = headers
+        .get("x-user-limit-24hour-remaining")
+        .and_then(|r| r.to_str().ok())
+        == Some("0");
+    let reset = if daily {
+        "x-user-limit-24hour-reset"
+    } else {
+        "x-rate-limit-reset"
+    };
+    match headers.get(reset).map(|f| f.to_str()).transpose()? {
+        Some(r) => Ok(RateLimit::Until(r.parse()?)),
+        None => Ok(RateLimit::Unknown),
+    }
+}
+
+/// What's left of the rate limit window twitter reported in `headers`, as
+/// how many requests are left and when it resets, UTC unix time
+///
+/// API v2s daily per-user limit is used instead once it has fewer left.
+fn window_of(headers: &HeaderMap) -> Option<(u64, i64)> {
+    let header = |name| -> Option<u64> { headers.get(name)?.to_str().ok()?.parse().ok() };
+    let read = |remaining, reset| Some((header(remaining)?, header(reset)? as i64));
+    let window = read("x-rate-limit-remaining", "x-rate-limit-reset");
+    let daily = read("x-user-limit-24hour-remaining", "x-user-limit-24hour-reset");
+    match (window, daily) {
+        (Some(w), Some(d)) if d.0 < w.0 => Some(d),
+        (w, d) => w.or(d),
+    }
 }
 
-/// Remove the prefix in twitter archive files
-fn remove_account_prefix(data: &str) -> &str {
-    // Twitter puts this nonsense in front of the account.js file
-    // Assume there is one account
-    // The full line is  `window.YTD.account.part0 = [`
-    const PREFIX: &str = "window.YTD.account.part0 =";
-    &data[PREFIX.len()..]
+/// The rate limit budget shared by requests sent concurrently, see
+/// [`rate_limit_async`]
+///
+/// Twitter says how many requests are left in the window with every
+/// response, so once those are all in flight, the rest wait for the window
+/// to reset instead of being sent only to be rate limited.
+#[derive(Debug, Default)]
+struct Budget {
+    /// Every request waits until then, UTC unix time
+    paused: Cell<i64>,
+
+    /// How many more requests can be sent in the window, if known
+    left: Cell<Option<u64>>,
+
+    /// When the window resets, UTC unix time
+    reset: Cell<i64>,
+
+    /// Requests sent that haven't been answered yet
+    in_flight: Cell<u64>,
 }
 
-/// Get the account ID for this twitter archive
-pub fn get_account(path: &Path) -> Result<Account> {
-    let path = path.join("data").join("account.js");
-    let data = fs::read_to_string(path)?;
-    let data = remove_account_prefix(&data);
+impl Budget {
+    /// How many seconds to wait before sending a request, as of `now`
+    fn wait(&self, now: i64) -> i64 {
+        let until = match self.left.get() {
+            Some(0) => self.paused.get().max(self.reset.get()),
+            _ => self.paused.get(),
+        };
+        if until <= now && self.left.get() == Some(0) {
+            // The window reset, and how much is left is learned again from
+            // the next response
+            self.left.set(None);
+        }
+        until - now
+    }
+
+    /// A request was sent
+    fn sent(&self) {
+        self.in_flight.set(self.in_flight.get() + 1);
+        self.left.set(self.left.get().map(|l| l.saturating_sub(1)));
+    }
 
-    let acc: Vec<AccountObj> = from_str(data)?;
-    let acc = acc
-        .into_iter()
-        .next()
-        .ok_or_else(|| anyhow!("Couldn't get twitter account information"))?;
+    /// A request was answered, with `window` left, see [`window_of`]
+    ///
+    /// Returns when requests are paused until, if this used up the window.
+    fn answered(&self, window: Option<(u64, i64)>) -> Option<i64> {
+        self.in_flight.set(self.in_flight.get().saturating_sub(1));
+        let (remaining, reset) = window?;
+        // From before the window reset
+        if reset < self.reset.get() {
+            return None;
+        }
+        let left = remaining.saturating_sub(self.in_flight.get());
+        self.left.set(Some(left));
+        self.reset.set(reset);
+        if left > 0 || self.paused.get() >= reset {
+            return None;
+        }
+        self.paused.set(reset);
+        Some(reset)
+    }
 
-    Ok(acc.account)
+    /// Pause every request until `until`, UTC unix time
+    fn pause(&self, until: i64) {
+        self.paused.set(self.paused.get().max(until));
+    }
 }
 
-/// Collect tweets from the twitter archive. Returns ALL found tweets.
-///
-/// `path` is the path to the archive, and tweets are expected to exist at
-/// `data/tweets.js` and `data/tweets-partN.js`.
+/// Like [`rate_limit`], for requests sent concurrently, see [`bulk`]
 ///
-/// There is a limit of 99 `tweets-partN.js` files
-pub fn collect_tweets(path: &Path) -> Result<Vec<Tweet>> {
-    let mut files = Vec::with_capacity(99);
-    let path = path.join("data");
-    for file in path.read_dir()? {
-        let file = file?;
-        let ty = file.file_type()?;
-        if !ty.is_file() {
-            continue;
+/// A rate limit on any request pauses every request until it resets, instead
+/// of each of them discovering it separately. Requests also wait once
+/// `budget` says those in flight will use up the window, and `on_limit` is
+/// called for that as if it had been hit.
+async fn rate_limit_async<Req, F>(
+    req: Req,
+    clock: &dyn Clock,
+    on_limit: &RefCell<F>,
+    budget: &Budget,
+) -> Result<Reply>
+where
+    Req: Fn() -> AsyncRequestBuilder,
+    F: FnMut(RateLimit, &AsyncResponse) -> Result<()>,
+{
+    loop {
+        let wait = budget.wait(clock.now().unix_timestamp());
+        if wait > 0 {
+            tokio::time::sleep(StdDuration::from_secs(wait as u64)).await;
         }
-        let name = file.file_name();
-        let name = name
-            .to_str()
-            .ok_or_else(|| anyhow!("Invalid UTF-8 in filename {:?}", file.file_name()))?;
-        if !name.starts_with("tweets") {
+        tokio::time::sleep(gentle_delay()).await;
+        budget.sent();
+        let res = req().send().await;
+        let window = res.as_ref().ok().and_then(|r| window_of(r.headers()));
+        let used_up = budget.answered(window);
+        let res = res?;
+
+        let status = res.status();
+        debug!(url = %res.url(), status = status.as_u16(), "Twitter responded");
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            let limit = limit_of(res.headers())?;
+            let until = clock.now().unix_timestamp() + limit.wait(clock) as i64;
+            (*on_limit.borrow_mut())(limit, &res)?;
+            budget.pause(until);
             continue;
         }
-        if files.len() > 99 {
-            return Err(anyhow!("Too many tweet files, can not handle more than 99"));
+        if let Some(until) = used_up {
+            debug!(until, "Rate limit window used up, pausing");
+            (*on_limit.borrow_mut())(RateLimit::Until(until as u64), &res)?;
+        }
+        if status.is_server_error() {
+            debug!(
+                status = status.as_u16(),
+                "Transient HTTP error, waiting one minute"
+            );
+            tokio::time::sleep(StdDuration::from_secs(60)).await;
+        } else if status == StatusCode::UNAUTHORIZED {
+            return Err(Unauthorized.into());
+        } else {
+            return Ok(Reply {
+                status,
+                body: res.text().await?,
+            });
         }
-        files.push(file.path());
     }
+}
 
-    let mut out = Vec::new();
-    for path in files {
-        let data = fs::read_to_string(path)?;
-        let data = remove_tweet_prefix(&data);
+/// Run `requests` on `concurrent`, with up to its concurrency in flight at
+/// once, handling each result in order with `on_done`
+///
+/// Stops at the first error, abandoning any requests still in flight,
+/// which may or may not have taken effect.
+fn bulk<T, Fut, OnDone>(
+    concurrent: &Concurrent,
+    requests: impl Iterator<Item = Fut>,
+    on_done: OnDone,
+) -> Result<()>
+where
+    Fut: Future<Output = Result<T>>,
+    OnDone: FnMut(T) -> Result<()>,
+{
+    let mut on_done = on_done;
+    concurrent.runtime.block_on(async {
+        let mut done = stream::iter(requests).buffered(concurrent.concurrency);
+        while let Some(res) = done.next().await {
+            on_done(res?)?;
+        }
+        Ok(())
+    })
+}
 
-        let data: Vec<TweetObj> = from_str(data)?;
-        out.extend(data.into_iter().map(|t| t.tweet));
+/// `res`, or if it was unsuccessful an error with twitters explanation, from
+/// either the v1.1 or v2 error format
+pub fn error_for_status(res: Reply) -> Result<Reply> {
+    let status = res.status();
+    if status.is_success() {
+        return Ok(res);
     }
-
-    Ok(out)
+    let body: Value = from_str(&res.body).unwrap_or_default();
+    // v2 problems have a `detail`, v1.1 has a list of `errors`
+    let why = body["detail"]
+        .as_str()
+        .or_else(|| body.pointer("/errors/0/message").and_then(Value::as_str));
+    Err(match why {
+        Some(why) => anyhow!("Twitter responded with {status}: {why}"),
+        None => anyhow!("Twitter responded with {status}"),
+    })
 }
 
-/// Lookup `tweets` on twitter.
+/// Lookup `tweets` on twitter, with `api`, sending requests on `concurrent`.
 ///
 /// `tweets` is a list of tweet IDs to lookup
 ///
 /// Note that this twitter API can only look up tweets in batches of up to 100,
-/// so this will call `on_chunk` for each successfully processed chunk.
+/// so this will call `on_chunk` for each successfully processed chunk, in
+/// order. Responses from API v2 are converted to the v1.1 format.
 ///
 /// Calls `on_limit` whenever a rate limit is hit.
 pub fn lookup_tweets<'a, OnLimit, OnChunk>(
-    client: &Client,
     keys: &Access,
-    tweets: impl Iterator<Item = &'a str>,
+    clock: &dyn Clock,
+    api: Api,
+    concurrent: &Concurrent,
+    tweets: impl Iterator<Item = &'a TweetId>,
     on_limit: OnLimit,
     on_chunk: OnChunk,
 ) -> Result<()>
 where
-    OnLimit: FnMut(RateLimit, &Response) -> Result<()>,
-    OnChunk: FnMut(Response) -> Result<()>,
+    OnLimit: FnMut(RateLimit, &AsyncResponse) -> Result<()>,
+    OnChunk: FnMut(LookupResp) -> Result<()>,
 {
-    let mut on_limit = on_limit;
+    let client = &concurrent.client;
+    let on_limit = RefCell::new(on_limit);
+    let budget = Budget::default();
     let mut on_chunk = on_chunk;
     let mut tweets = tweets;
-    let tweets = tweets.by_ref();
 
-    loop {
-        let ids = tweets.take(100).collect::<Vec<&str>>().join(",");
-        if ids.is_empty() {
-            break;
+    let chunks = std::iter::from_fn(|| {
+        let chunk: Vec<&TweetId> = tweets.by_ref().take(100).collect();
+        (!chunk.is_empty()).then_some(chunk)
+    });
+    let requests =
+        chunks.map(|chunk| {
+            let ids = chunk
+                .iter()
+                .map(|i| i.as_str())
+                .collect::<Vec<_>>()
+                .join(",");
+            let req = move || match api {
+                Api::V1 => {
+                    let params = &[
+                        //
+                        ("id", ids.as_str()),
+                        ("map", "true"),
+                    ];
+                    client
+                        .post(TWEET_LOOKUP_URL)
+                        .header(
+                            AUTHORIZATION,
+                            create_auth(
+                                keys,
+                                clock,
+                                TWEET_LOOKUP_URL,
+                                Method::POST,
+                                &params.map(|f| (f.0.to_owned(), f.1.to_owned())),
+                            ),
+                        )
+                        .form(params)
+                }
+                Api::V2 => {
+                    let params = [
+                        ("ids".to_owned(), ids.clone()),
+                        (
+                            "tweet.fields".to_owned(),
+                            "created_at,public_metrics,referenced_tweets,edit_history_tweet_ids"
+                                .to_owned(),
+                        ),
+                    ];
+                    client
+                        .get(TWEET_LOOKUP_V2_URL)
+                        .header(
+                            AUTHORIZATION,
+                            create_auth(keys, clock, TWEET_LOOKUP_V2_URL, Method::GET, &params),
+                        )
+                        .query(&params)
+                }
+            };
+            let (on_limit, budget) = (&on_limit, &budget);
+            async move {
+                let res = rate_limit_async(req, clock, on_limit, budget).await?;
+                Ok::<_, anyhow::Error>((chunk, res))
+            }
+        });
+
+    bulk(concurrent, requests, |(chunk, res)| {
+        let res = error_for_status(res)?;
+        let mut res: LookupResp = match api {
+            Api::V1 => res.json()?,
+            Api::V2 => res.json::<V2LookupResp>()?.into_v1(&chunk)?,
+        };
+        // Deleted tweets are mapped to null, but the occasional API quirk is
+        // missing entirely
+        res.missing = chunk
+            .iter()
+            .filter(|i| !res.id.contains_key(i))
+            .map(|&i| i.clone())
+            .collect();
+        res.requested = chunk.len();
+        on_chunk(res)
+    })
+}
+
+/// How a paginated endpoint identifies pages, see [`paginate`]
+#[derive(Debug, Clone, Copy)]
+pub enum Cursor {
+    /// v1.1 endpoints, such as `followers/ids`
+    ///
+    /// Uses the `cursor` parameter, starting at `-1`, and `next_cursor_str`
+    /// in the response, ending at `0`
+    V1,
+
+    /// v2 endpoints
+    ///
+    /// Uses the `pagination_token` parameter, absent on the first page,
+    /// and `meta.next_token` in the response, ending when missing
+    V2,
+}
+
+impl Cursor {
+    /// Cursor for the first page
+    fn first(self) -> String {
+        match self {
+            Cursor::V1 => "-1".to_owned(),
+            Cursor::V2 => String::new(),
         }
-        let params = &[
-            //
-            ("id", ids.as_str()),
-            ("map", "true"),
-        ];
+    }
 
-        let req = client
-            .post(TWEET_LOOKUP_URL)
-            .header(
-                AUTHORIZATION,
-                create_auth(
-                    keys,
-                    TWEET_LOOKUP_URL,
-                    Method::POST,
-                    &params.map(|f| (f.0.to_owned(), f.1.to_owned())),
-                ),
-            )
-            .form(params);
-        let res = rate_limit(&req, &mut on_limit)?;
-        on_chunk(res)?;
+    /// The request parameter selecting the page at `cursor`, if any
+    fn param(self, cursor: String) -> Option<(String, String)> {
+        match self {
+            Cursor::V1 => Some(("cursor".to_owned(), cursor)),
+            Cursor::V2 if !cursor.is_empty() => Some(("pagination_token".to_owned(), cursor)),
+            Cursor::V2 => None,
+        }
     }
 
-    Ok(())
+    /// Cursor for the page after `page`, or [`None`] if it was the last
+    fn next(self, page: &Value) -> Option<String> {
+        match self {
+            Cursor::V1 => page["next_cursor_str"].as_str().filter(|c| *c != "0"),
+            Cursor::V2 => page.pointer("/meta/next_token").and_then(|c| c.as_str()),
+        }
+        .map(str::to_owned)
+    }
 }
 
-/// Delete `tweets` on twitter.
+/// Iterator over the pages of a paginated endpoint, see [`paginate`]
+pub struct Pages<'a, OnLimit> {
+    client: &'a Client,
+    keys: &'a Access,
+    clock: &'a dyn Clock,
+    url: &'a str,
+    params: Vec<(String, String)>,
+    cursor: Cursor,
+
+    /// Cursor for the next page, or [`None`] if there are no more pages
+    next: Option<String>,
+    on_limit: OnLimit,
+}
+
+impl<OnLimit> Pages<'_, OnLimit>
+where
+    OnLimit: FnMut(RateLimit, &Response) -> Result<()>,
+{
+    /// Fetch the page at `cursor`, and find the cursor for the next page
+    fn fetch(&mut self, cursor: String) -> Result<Value> {
+        let mut params = self.params.clone();
+        params.extend(self.cursor.param(cursor));
+
+        let req = || {
+            self.client
+                .get(self.url)
+                .header(
+                    AUTHORIZATION,
+                    create_auth(self.keys, self.clock, self.url, Method::GET, &params),
+                )
+                .query(&params)
+        };
+        let res = rate_limit(req, self.clock, &mut self.on_limit)?;
+        let res = Reply {
+            status: res.status(),
+            body: res.text()?,
+        };
+        let page: Value = error_for_status(res)?.json()?;
+
+        self.next = self.cursor.next(&page);
+
+        Ok(page)
+    }
+}
+
+impl<OnLimit> Iterator for Pages<'_, OnLimit>
+where
+    OnLimit: FnMut(RateLimit, &Response) -> Result<()>,
+{
+    type Item = Result<Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let cursor = self.next.take()?;
+        Some(self.fetch(cursor))
+    }
+}
+
+/// Paginate through the cursor based endpoint `url`, with `params`.
+///
+/// Returns an iterator of each page, as JSON, which ends after the last page
+/// or an error.
 ///
-/// `tweets` is a list of tweet IDs to delete
+/// Calls `on_limit` whenever a rate limit is hit.
+///
+/// This is the foundation for endpoints listing followers, following,
+/// and bookmarks.
+pub fn paginate<'a, OnLimit>(
+    client: &'a Client,
+    keys: &'a Access,
+    clock: &'a dyn Clock,
+    url: &'a str,
+    params: &[(&str, &str)],
+    cursor: Cursor,
+    on_limit: OnLimit,
+) -> Pages<'a, OnLimit>
+where
+    OnLimit: FnMut(RateLimit, &Response) -> Result<()>,
+{
+    Pages {
+        client,
+        keys,
+        clock,
+        url,
+        params: params
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        cursor,
+        next: Some(cursor.first()),
+        on_limit,
+    }
+}
+
+/// Delete `tweets` on twitter, with `api`, sending requests on `concurrent`.
+///
+/// `tweets` is a list of tweet IDs to delete, with the tweet ID each
+/// retweeted, if known. Retweets are unretweeted instead.
 ///
 /// Note that this twitter API can only delete tweets one at a time,
-/// so this will call `on_del` for each successfully processed tweet.
+/// so this will call `on_del` for each successfully processed tweet, in
+/// order. API v2 responds with the same status codes as v1.1.
 ///
 /// Calls `on_limit` whenever a rate limit is hit.
 pub fn delete_tweets<'a, OnLimit, OnDelete>(
+    keys: &Access,
+    clock: &dyn Clock,
+    api: Api,
+    concurrent: &Concurrent,
+    tweets: impl Iterator<Item = (&'a TweetId, Option<&'a TweetId>)>,
+    on_limit: OnLimit,
+    on_delete: OnDelete,
+) -> Result<()>
+where
+    OnLimit: FnMut(RateLimit, &AsyncResponse) -> Result<()>,
+    OnDelete: FnMut(Reply, &TweetId) -> Result<()>,
+{
+    let client = &concurrent.client;
+    let on_limit = RefCell::new(on_limit);
+    let budget = Budget::default();
+    let mut on_delete = on_delete;
+
+    // Unretweeting with API v2 is done as the user
+    let user = match (api, keys.user_id()) {
+        (_, Some(user)) => user,
+        (Api::V2, None) => {
+            return Err(anyhow!(
+                "API v2 needs an access token starting with your account ID to unretweet, run \
+                 `auth` to get one"
+            ))
+        }
+        (Api::V1, None) => "",
+    };
+
+    let requests =
+        tweets.map(|(tweet, retweet_of)| {
+            let req = move || match api {
+                Api::V1 => {
+                    let (url, params) = match retweet_of {
+                        Some(original) => (
+                            format!("{TWEET_UNRETWEET_URL_FMT}/{original}.json"),
+                            [("id", original.as_str())],
+                        ),
+                        None => (
+                            format!("{TWEET_DESTROY_URL_FMT}/{tweet}.json"),
+                            [("id", tweet.as_str())],
+                        ),
+                    };
+                    let params = &params;
+
+                    client
+                        .post(&url)
+                        .header(
+                            AUTHORIZATION,
+                            create_auth(
+                                keys,
+                                clock,
+                                &url,
+                                Method::POST,
+                                &params.map(|f| (f.0.to_owned(), f.1.to_owned())),
+                            ),
+                        )
+                        .form(params)
+                }
+                Api::V2 => {
+                    let url = match retweet_of {
+                        Some(original) => {
+                            format!("{TWEET_UNRETWEET_V2_URL_FMT}/{user}/retweets/{original}")
+                        }
+                        None => format!("{TWEET_DELETE_V2_URL_FMT}/{tweet}"),
+                    };
+                    client.delete(&url).header(
+                        AUTHORIZATION,
+                        create_auth(keys, clock, &url, Method::DELETE, &[]),
+                    )
+                }
+            };
+            let (on_limit, budget) = (&on_limit, &budget);
+            async move {
+                let res = rate_limit_async(req, clock, on_limit, budget).await?;
+                Ok::<_, anyhow::Error>((tweet, res))
+            }
+        });
+
+    bulk(concurrent, requests, |(tweet, res)| on_delete(res, tweet))
+}
+
+/// Unlike `tweets` on twitter.
+///
+/// `tweets` is a list of tweet IDs to unlike
+///
+/// Note that this twitter API can only unlike tweets one at a time,
+/// so this will call `on_unlike` for each successfully processed tweet.
+///
+/// Calls `on_limit` whenever a rate limit is hit.
+pub fn unlike_tweets<'a, OnLimit, OnUnlike>(
+    client: &Client,
+    keys: &Access,
+    clock: &dyn Clock,
+    tweets: impl Iterator<Item = &'a TweetId>,
+    on_limit: OnLimit,
+    on_unlike: OnUnlike,
+) -> Result<()>
+where
+    OnLimit: FnMut(RateLimit, &Response) -> Result<()>,
+    OnUnlike: FnMut(Response, &TweetId) -> Result<()>,
+{
+    let mut on_limit = on_limit;
+    let mut on_unlike = on_unlike;
+
+    for tweet in tweets {
+        let params = &[("id", tweet.as_str())];
+
+        let req = || {
+            client
+                .post(FAVORITE_DESTROY_URL)
+                .header(
+                    AUTHORIZATION,
+                    create_auth(
+                        keys,
+                        clock,
+                        FAVORITE_DESTROY_URL,
+                        Method::POST,
+                        &params.map(|f| (f.0.to_owned(), f.1.to_owned())),
+                    ),
+                )
+                .form(params)
+        };
+        let res = rate_limit(req, clock, &mut on_limit)?;
+        on_unlike(res, tweet)?;
+    }
+
+    Ok(())
+}
+
+/// Delete direct `messages` on twitter.
+///
+/// `messages` is a list of direct message IDs to delete
+///
+/// Note that this only deletes them from your view of the conversation,
+/// and can only delete them one at a time,
+/// so this will call `on_delete` for each successfully processed message.
+///
+/// Calls `on_limit` whenever a rate limit is hit.
+pub fn delete_dms<'a, OnLimit, OnDelete>(
     client: &Client,
     keys: &Access,
-    tweets: impl Iterator<Item = &'a str>,
+    clock: &dyn Clock,
+    messages: impl Iterator<Item = &'a str>,
     on_limit: OnLimit,
     on_delete: OnDelete,
 ) -> Result<()>
@@ -474,28 +1244,528 @@ where
 {
     let mut on_limit = on_limit;
     let mut on_delete = on_delete;
-    let mut tweets = tweets;
-    let tweets = tweets.by_ref();
 
-    for tweet in tweets {
-        let url = format!("{TWEET_DESTROY_URL_FMT}/{tweet}.json");
-        let params = &[("id", tweet)];
+    for message in messages {
+        let params = &[("id", message)];
 
-        let req = client
-            .post(&url)
+        let req = || {
+            client
+                .delete(DM_DESTROY_URL)
+                .header(
+                    AUTHORIZATION,
+                    create_auth(
+                        keys,
+                        clock,
+                        DM_DESTROY_URL,
+                        Method::DELETE,
+                        &params.map(|f| (f.0.to_owned(), f.1.to_owned())),
+                    ),
+                )
+                .query(params)
+        };
+        let res = rate_limit(req, clock, &mut on_limit)?;
+        on_delete(res, message)?;
+    }
+
+    Ok(())
+}
+
+/// Post a tweet with the text `text` on twitter.
+///
+/// Calls `on_limit` whenever a rate limit is hit.
+pub fn post_tweet<OnLimit>(
+    client: &Client,
+    keys: &Access,
+    clock: &dyn Clock,
+    text: &str,
+    on_limit: OnLimit,
+) -> Result<PostResp>
+where
+    OnLimit: FnMut(RateLimit, &Response) -> Result<()>,
+{
+    let params = &[("status", text)];
+
+    let req = || {
+        client
+            .post(TWEET_UPDATE_URL)
             .header(
                 AUTHORIZATION,
                 create_auth(
                     keys,
-                    &url,
+                    clock,
+                    TWEET_UPDATE_URL,
                     Method::POST,
                     &params.map(|f| (f.0.to_owned(), f.1.to_owned())),
                 ),
             )
-            .form(params);
-        let res = rate_limit(&req, &mut on_limit)?;
-        on_delete(res, tweet)?;
+            .form(params)
+    };
+    let res = rate_limit(req, clock, on_limit)?;
+    Ok(res.error_for_status()?.json()?)
+}
+
+/// A temporary token for authorizing an account, see [`request_token`]
+#[derive(Debug)]
+pub struct RequestToken {
+    token: String,
+    secret: String,
+}
+
+impl RequestToken {
+    /// Page where the user authorizes the app, and is shown a PIN
+    pub fn authorize_url(&self) -> String {
+        format!("{AUTHORIZE_URL}?oauth_token={}", encode(&self.token))
+    }
+}
+
+/// An account authorized with [`access_token`]
+#[derive(Debug)]
+pub struct Authorized {
+    /// API keys for the account
+    pub keys: Access,
+
+    /// Account handle
+    pub screen_name: String,
+}
+
+/// Parse the form encoded `data` returned by the OAuth endpoints
+fn parse_form(data: &str) -> Result<HashMap<String, String>> {
+    data.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(k, v)| Ok((decode(k)?.into_owned(), decode(v)?.into_owned())))
+        .collect()
+}
+
+/// Start authorizing an account with the app `keys`, using the out-of-band
+/// PIN flow
+///
+/// Only the API key and secret are used.
+///
+/// See [`RequestToken::authorize_url`] and [`access_token`]
+pub fn request_token(client: &Client, keys: &Access, clock: &dyn Clock) -> Result<RequestToken> {
+    let app = Access {
+        api_key: keys.api_key.clone(),
+        api_secret: keys.api_secret.clone(),
+        access: String::new(),
+        access_secret: String::new(),
+    };
+    let params = &[("oauth_callback", "oob")];
+
+    let res = client
+        .post(REQUEST_TOKEN_URL)
+        .header(
+            AUTHORIZATION,
+            create_auth(
+                &app,
+                clock,
+                REQUEST_TOKEN_URL,
+                Method::POST,
+                &params.map(|f| (f.0.to_owned(), f.1.to_owned())),
+            ),
+        )
+        .query(params)
+        .send()?;
+    let mut form = parse_form(&res.error_for_status()?.text()?)?;
+    let mut take = |k: &str| {
+        form.remove(k)
+            .ok_or_else(|| anyhow!("Twitter request token response missing {k}"))
+    };
+
+    Ok(RequestToken {
+        token: take("oauth_token")?,
+        secret: take("oauth_token_secret")?,
+    })
+}
+
+/// Finish authorizing an account with the app `keys`, using the `pin` shown
+/// after authorizing `token`
+///
+/// Returns API keys for the account, using the same app.
+pub fn access_token(
+    client: &Client,
+    keys: &Access,
+    clock: &dyn Clock,
+    token: &RequestToken,
+    pin: &str,
+) -> Result<Authorized> {
+    let request = Access {
+        api_key: keys.api_key.clone(),
+        api_secret: keys.api_secret.clone(),
+        access: token.token.clone(),
+        access_secret: token.secret.clone(),
+    };
+    let params = &[("oauth_verifier", pin)];
+
+    let res = client
+        .post(ACCESS_TOKEN_URL)
+        .header(
+            AUTHORIZATION,
+            create_auth(
+                &request,
+                clock,
+                ACCESS_TOKEN_URL,
+                Method::POST,
+                &params.map(|f| (f.0.to_owned(), f.1.to_owned())),
+            ),
+        )
+        .query(params)
+        .send()?;
+    if res.status() == StatusCode::UNAUTHORIZED {
+        return Err(anyhow!("Twitter rejected the PIN, try authorizing again"));
+    }
+    let mut form = parse_form(&res.error_for_status()?.text()?)?;
+    let mut take = |k: &str| {
+        form.remove(k)
+            .ok_or_else(|| anyhow!("Twitter access token response missing {k}"))
+    };
+
+    Ok(Authorized {
+        keys: Access {
+            api_key: request.api_key,
+            api_secret: request.api_secret,
+            access: take("oauth_token")?,
+            access_secret: take("oauth_token_secret")?,
+        },
+        screen_name: take("screen_name")?,
+    })
+}
+
+/// An endpoint category tested by [`test_auth`]
+#[derive(Debug)]
+pub struct EndpointTest {
+    /// What the endpoint is used for, eg `delete tweets`
+    pub name: &'static str,
+
+    /// Status twitter returned
+    pub status: StatusCode,
+
+    /// Error message twitter returned, if any
+    pub error: Option<String>,
+}
+
+impl EndpointTest {
+    /// Whether the keys are allowed to use the endpoint
+    ///
+    /// Deleting an ID that doesn't exist is only a 404 once permissions have
+    /// been checked.
+    pub fn allowed(&self) -> bool {
+        self.status.is_success() || self.status == StatusCode::NOT_FOUND
+    }
+}
+
+/// What API keys are allowed to do, see [`test_auth`]
+#[derive(Debug)]
+pub struct AuthTest {
+    /// Account handle the keys are for
+    pub screen_name: String,
+
+    /// Access level twitter reports for the keys, eg `read-write`
+    pub access_level: Option<String>,
+
+    /// Every endpoint category tested
+    pub endpoints: Vec<EndpointTest>,
+}
+
+/// Test what `keys` are allowed to do, by sending a harmless signed request
+/// to every category of endpoint used
+///
+/// Deleting is tested on an ID that doesn't exist, so nothing is deleted.
+/// Posting can't be tested harmlessly, the access level covers it instead.
+///
+/// Rate limits are reported, not waited on.
+pub fn test_auth(client: &Client, keys: &Access, clock: &dyn Clock) -> Result<AuthTest> {
+    let send = |method: Method, url: &str, params: &[(&str, &str)]| -> Result<Response> {
+        let auth = create_auth(
+            keys,
+            clock,
+            url,
+            method.clone(),
+            &params
+                .iter()
+                .map(|f| (f.0.to_owned(), f.1.to_owned()))
+                .collect::<Vec<_>>(),
+        );
+        let req = client
+            .request(method.clone(), url)
+            .header(AUTHORIZATION, auth);
+        let req = if method == Method::POST {
+            req.form(params)
+        } else {
+            req.query(params)
+        };
+        Ok(req.send()?)
+    };
+
+    let res = send(
+        Method::GET,
+        VERIFY_CREDENTIALS_URL,
+        &[("skip_status", "true")],
+    )?;
+    if res.status() == StatusCode::UNAUTHORIZED {
+        return Err(Unauthorized.into());
+    }
+    let access_level = res
+        .headers()
+        .get("x-access-level")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_owned);
+    let user: Value = res.error_for_status()?.json()?;
+    let screen_name = user["screen_name"]
+        .as_str()
+        .ok_or_else(|| anyhow!("Twitter returned an invalid account"))?
+        .to_owned();
+
+    let destroy = format!("{TWEET_DESTROY_URL_FMT}/{NONEXISTENT_ID}.json");
+    let tests = [
+        ("look up tweets", Method::POST, TWEET_LOOKUP_URL),
+        ("delete tweets", Method::POST, destroy.as_str()),
+        ("unlike tweets", Method::POST, FAVORITE_DESTROY_URL),
+        ("delete direct messages", Method::DELETE, DM_DESTROY_URL),
+    ];
+    let mut endpoints = Vec::new();
+    for (name, method, url) in tests {
+        let res = send(method, url, &[("id", NONEXISTENT_ID)])?;
+        let status = res.status();
+        let error = if status.is_success() {
+            None
+        } else {
+            let body: Value = res.json().unwrap_or_default();
+            body["errors"][0]["message"].as_str().map(str::to_owned)
+        };
+        endpoints.push(EndpointTest {
+            name,
+            status,
+            error,
+        });
+    }
+
+    Ok(AuthTest {
+        screen_name,
+        access_level,
+        endpoints,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use time::{macros::datetime, Duration};
+
+    use super::*;
+    use crate::clock::FrozenClock;
+
+    /// The example from twitters "Creating a signature" documentation
+    fn example() -> (Access, Vec<(String, String)>) {
+        let keys = Access {
+            api_key: "xvz1evFS4wEEPTGEFPHBog".into(),
+            api_secret: "kAcSOqF21Fu85e7zjz7ZN2U4ZRhfV3WpwPAoE3Z7kBw".into(),
+            access: "370773112-GmHxMAgYyLbNEtIKZeRNFsMKPR9EyMZeS9weJAEb".into(),
+            access_secret: "LswwdoUaIvS8ltyTt5jkRh4J50vUPVVHtR2YPi5kE".into(),
+        };
+        let params = vec![
+            ("include_entities".into(), "true".into()),
+            (
+                "status".into(),
+                "Hello Ladies + Gentlemen, a signed OAuth request!".into(),
+            ),
+        ];
+        (keys, params)
+    }
+
+    #[test]
+    fn signs_with_clock() {
+        let (keys, params) = example();
+        let clock = FrozenClock(datetime!(2011-10-14 20:09:18 UTC));
+        let auth = auth_with_nonce(
+            &keys,
+            &clock,
+            "kYjzVBB8Y0ZFabxSWbWovY3uYSQ2pTgmZeNu2VS4cg",
+            "https://api.twitter.com/1.1/statuses/update.json",
+            Method::POST,
+            &params,
+        );
+        assert!(auth.contains(r#"oauth_timestamp="1318622958""#), "{auth}");
+        assert!(
+            auth.contains(r#"oauth_signature="hCtSmYh%2BiHYCEqBWrE7C7hYmtUk%3D""#),
+            "{auth}"
+        );
+    }
+
+    #[test]
+    fn timestamp_from_clock() {
+        let (keys, params) = example();
+        let clock = FrozenClock(datetime!(2023-01-01 0:00 UTC));
+        let auth = create_auth(&keys, &clock, TWEET_LOOKUP_URL, Method::POST, &params);
+        assert!(auth.contains(r#"oauth_timestamp="1672531200""#), "{auth}");
+    }
+
+    /// A [`FrozenClock`] that can be moved while a request is retried
+    impl Clock for Cell<FrozenClock> {
+        fn now(&self) -> OffsetDateTime {
+            self.get().0
+        }
+    }
+
+    #[test]
+    fn signs_each_attempt() {
+        let (keys, _) = example();
+        let start = datetime!(2023-01-01 0:00 UTC);
+        let clock = Cell::new(FrozenClock(start));
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", server.local_addr().unwrap());
+        // Rate limited until the time of the first attempt, then fine
+        let replies = [
+            "429 Too Many Requests\r\nx-rate-limit-reset: 1672531200",
+            "200 OK",
+        ];
+        let served = thread::spawn(move || {
+            replies
+                .iter()
+                .map(|reply| {
+                    let (mut stream, _) = server.accept().unwrap();
+                    let auth = BufReader::new(&stream)
+                        .lines()
+                        .map(Result::unwrap)
+                        .take_while(|l| !l.is_empty())
+                        .find(|l| l.to_ascii_lowercase().starts_with("authorization:"));
+                    write!(
+                        stream,
+                        "HTTP/1.1 {reply}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    )
+                    .unwrap();
+                    auth.unwrap()
+                })
+                .collect::<Vec<_>>()
+        });
+
+        let client = create_client().unwrap();
+        let req = || {
+            client
+                .get(&url)
+                .header(AUTHORIZATION, create_auth(&keys, &clock, &url, Method::GET, &[]))
+        };
+        let res = rate_limit(req, &clock, |_, _| {
+            // As if the limit had been waited out
+            clock.set(FrozenClock(start + Duration::minutes(15)));
+            Ok(())
+        })
+        .unwrap();
+        assert!(res.status().is_success());
+
+        let auths = served.join().unwrap();
+        assert_ne!(auths[0], auths[1]);
+        assert!(auths[0].contains(r#"oauth_timestamp="1672531200""#), "{}", auths[0]);
+        assert!(auths[1].contains(r#"oauth_timestamp="1672532100""#), "{}", auths[1]);
+        let nonce = |auth: &str| auth.split("oauth_nonce=").nth(1).unwrap()[..34].to_owned();
+        assert_ne!(nonce(&auths[0]), nonce(&auths[1]));
+    }
+
+    #[test]
+    fn budget_waits_for_window() {
+        let budget = Budget::default();
+        assert!(budget.wait(100) <= 0);
+        budget.sent();
+        budget.sent();
+        // One of the two left is already in flight
+        assert_eq!(budget.answered(Some((2, 1000))), None);
+        budget.sent();
+        assert_eq!(budget.wait(100), 900);
+        // Only reported once per window
+        assert_eq!(budget.answered(Some((1, 1000))), Some(1000));
+        assert_eq!(budget.answered(Some((0, 1000))), None);
+        assert_eq!(budget.wait(1000), 0);
+        // Unknown again once the window reset
+        assert!(budget.wait(1001) < 0);
+        assert_eq!(budget.answered(Some((0, 500))), None);
+        assert!(budget.wait(1001) < 0);
+    }
+
+    #[test]
+    fn window_prefers_daily_limit() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(window_of(&headers), None);
+        headers.insert("x-rate-limit-remaining", "5".parse().unwrap());
+        headers.insert("x-rate-limit-reset", "1000".parse().unwrap());
+        assert_eq!(window_of(&headers), Some((5, 1000)));
+        headers.insert("x-user-limit-24hour-remaining", "2".parse().unwrap());
+        headers.insert("x-user-limit-24hour-reset", "80000".parse().unwrap());
+        assert_eq!(window_of(&headers), Some((2, 80000)));
+        headers.insert("x-user-limit-24hour-remaining", "50".parse().unwrap());
+        assert_eq!(window_of(&headers), Some((5, 1000)));
+    }
+
+    #[test]
+    fn v1_cursor_ends_at_zero() {
+        let cursor = Cursor::V1;
+        assert_eq!(
+            cursor.param(cursor.first()),
+            Some(("cursor".into(), "-1".into()))
+        );
+        let page = serde_json::json!({"ids": [1], "next_cursor_str": "1374004777531007833"});
+        assert_eq!(cursor.next(&page), Some("1374004777531007833".into()));
+        let page = serde_json::json!({"ids": [2], "next_cursor_str": "0"});
+        assert_eq!(cursor.next(&page), None);
+        assert_eq!(cursor.next(&serde_json::json!({"ids": []})), None);
+    }
+
+    #[test]
+    fn v2_cursor_ends_without_next_token() {
+        let cursor = Cursor::V2;
+        assert_eq!(cursor.param(cursor.first()), None);
+        assert_eq!(
+            cursor.param("7140dibdnow9c7btw3w29grvxfcgvpb9n9coehpk7xz5i".into()),
+            Some((
+                "pagination_token".into(),
+                "7140dibdnow9c7btw3w29grvxfcgvpb9n9coehpk7xz5i".into()
+            ))
+        );
+        let page = serde_json::json!({
+            "data": [{"id": "1"}],
+            "meta": {
+                "result_count": 1,
+                "next_token": "7140dibdnow9c7btw3w29grvxfcgvpb9n9coehpk7xz5i"
+            }
+        });
+        assert_eq!(
+            cursor.next(&page),
+            Some("7140dibdnow9c7btw3w29grvxfcgvpb9n9coehpk7xz5i".into())
+        );
+        let page = serde_json::json!({
+            "data": [{"id": "2"}],
+            "meta": {"result_count": 1, "previous_token": "77qp8"}
+        });
+        assert_eq!(cursor.next(&page), None);
+    }
+
+    #[test]
+    fn v2_lookup_keeps_edit_history() {
+        let data = r#"{"data": [
+            {"id": "3", "created_at": "2022-03-04T12:00:00.000Z",
+             "public_metrics": {"retweet_count": 0, "like_count": 0},
+             "edit_history_tweet_ids": ["1", "3"]},
+            {"id": "4", "created_at": "2022-03-04T12:00:00.000Z",
+             "public_metrics": {"retweet_count": 0, "like_count": 0},
+             "edit_history_tweet_ids": ["4"]}
+        ]}"#;
+        let ids: Vec<TweetId> = ["3", "4"].iter().map(|i| i.parse().unwrap()).collect();
+        let res: V2LookupResp = serde_json::from_str(data).unwrap();
+        let res = res.into_v1(&ids.iter().collect::<Vec<_>>()).unwrap();
+        let history = |id: &TweetId| -> Vec<String> {
+            res.id[id]
+                .as_ref()
+                .unwrap()
+                .edit_history_tweet_ids
+                .iter()
+                .map(ToString::to_string)
+                .collect()
+        };
+        assert_eq!(history(&ids[0]), ["1", "3"]);
+        assert!(history(&ids[1]).is_empty());
     }
 
-    Ok(())
 }
@@ -1,8 +1,20 @@
 //! Misc utilities
 
-use std::fmt::Display;
+use std::{fmt::Display, fs, iter::once, path::Path};
 
-use time::Duration;
+use anyhow::{anyhow, Result};
+use time::{Duration, OffsetDateTime, UtcOffset};
+use time_tz::{timezones, Offset, TimeZone};
+
+use crate::id::TweetId;
+
+/// The offset of the IANA time zone `tz`, eg `Europe/London`, from UTC at `at`
+pub fn tz_offset(tz: &str, at: OffsetDateTime) -> Result<UtcOffset> {
+    let tz = timezones::get_by_name(tz).ok_or_else(|| {
+        anyhow!("Unknown time zone `{tz}`, expected an IANA name such as `Europe/London`")
+    })?;
+    Ok(tz.get_offset_utc(&at).to_utc())
+}
 
 /// Get a human readable representation of the [`Duration`] `dur`, as whole
 /// units
@@ -37,3 +49,198 @@ pub fn human_dur(dur: Duration) -> impl Display {
         format!("{dur}")
     }
 }
+
+/// Peak memory use of this process in bytes, if it can be found
+///
+/// # Implementation Details
+///
+/// Only known on Linux, from `VmHWM` in `/proc/self/status`
+pub fn peak_memory() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let kb = status
+        .lines()
+        .find_map(|l| l.strip_prefix("VmHWM:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim();
+    kb.parse::<u64>().ok().map(|kb| kb * 1024)
+}
+
+/// Extract a tweet ID from `field`, which may be a bare ID or a tweet URL
+///
+/// For example, `123456` and `https://twitter.com/user/status/123456`
+/// both return `123456`.
+pub fn extract_tweet_id(field: &str) -> Option<TweetId> {
+    let field = field.trim().trim_matches('"');
+    let id = match field.rfind("/status/") {
+        Some(i) => field[i + "/status/".len()..].split(['/', '?']).next()?,
+        None => field,
+    };
+    id.parse().ok()
+}
+
+/// Column names holding tweet IDs, see [`read_ids`]
+const ID_COLUMNS: &[&str] = &["id", "id_str", "tweet_id"];
+
+/// Column names holding tweet URLs, see [`read_ids`]
+const URL_COLUMNS: &[&str] = &["url", "tweet_url", "status_url", "link", "permalink"];
+
+/// Read tweet IDs from a plain text or CSV file at `path`
+///
+/// Plain text files have a bare tweet ID or a tweet URL on each line.
+///
+/// CSV files must have a header naming the column to use, one of
+/// [`ID_COLUMNS`], or else one of [`URL_COLUMNS`], as other columns may hold
+/// numbers that look like tweet IDs, such as an index, counts, or user IDs.
+/// It's an error if there is no header, or more than one such column.
+///
+/// The returned IDs are sorted and deduplicated
+pub fn read_ids(path: &Path) -> Result<Vec<TweetId>> {
+    let data = fs::read_to_string(path)?;
+    let mut ids = ids_from(&data).map_err(|e| anyhow!("{e} in {}", path.display()))?;
+    ids.sort();
+    ids.dedup();
+    Ok(ids)
+}
+
+/// Tweet IDs in `data`, see [`read_ids`]
+fn ids_from(data: &str) -> Result<Vec<TweetId>> {
+    let records = csv_records(data);
+    let mut records = records.iter().filter(|r| r.iter().any(|f| !f.trim().is_empty()));
+    let Some(header) = records.next() else {
+        return Ok(Vec::new());
+    };
+
+    if header.len() == 1 {
+        let ids = once(header)
+            .chain(records)
+            .filter_map(|r| r.first())
+            .filter_map(|f| extract_tweet_id(f))
+            .collect();
+        return Ok(ids);
+    }
+
+    if header.iter().any(|f| extract_tweet_id(f).is_some()) {
+        return Err(anyhow!(
+            "CSV has no header, name the column of tweet IDs `id`, `id_str`, or `tweet_id`"
+        ));
+    }
+    let named = |names: &[&str]| -> Vec<usize> {
+        let names = names.iter();
+        header
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| names.clone().any(|n| f.trim().eq_ignore_ascii_case(n)))
+            .map(|(i, _)| i)
+            .collect()
+    };
+    let column = match (&named(ID_COLUMNS)[..], &named(URL_COLUMNS)[..]) {
+        ([column], _) | ([], [column]) => *column,
+        ([], []) => {
+            return Err(anyhow!(
+                "CSV has no column of tweet IDs, expected one named `id`, `id_str`, `tweet_id`, \
+                 or `url`"
+            ))
+        }
+        ([], columns) | (columns, _) => {
+            let names: Vec<&str> = columns.iter().map(|&i| header[i].trim()).collect();
+            return Err(anyhow!(
+                "CSV has more than one column of tweet IDs, `{}`, keep only one",
+                names.join("`, `")
+            ));
+        }
+    };
+
+    records
+        .filter_map(|r| r.get(column))
+        .filter(|f| !f.trim().is_empty())
+        .map(|f| extract_tweet_id(f).ok_or_else(|| anyhow!("Invalid tweet ID or URL `{f}`")))
+        .collect()
+}
+
+/// Split CSV `data` into records of fields
+///
+/// Quoted fields may contain commas, newlines, and quotes escaped as `""`,
+/// such as tweet text.
+fn csv_records(data: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = data.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            '\r' if !quoted => (),
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(data: &str) -> Result<Vec<String>> {
+        Ok(ids_from(data)?.iter().map(ToString::to_string).collect())
+    }
+
+    #[test]
+    fn plain_ids_and_urls() {
+        let data = "1630000000000000001\n\
+                    https://twitter.com/user/status/1630000000000000002?s=20\n\n";
+        assert_eq!(
+            ids(data).unwrap(),
+            ["1630000000000000001", "1630000000000000002"]
+        );
+        assert_eq!(
+            ids("tweet_id\n1630000000000000001\n").unwrap(),
+            ["1630000000000000001"]
+        );
+    }
+
+    #[test]
+    fn pandas_index_is_not_an_id() {
+        let data = ",id,text,favorite_count\n\
+                    0,1630000000000000001,\"hello, world\",5\n\
+                    1,1630000000000000002,\"two\nlines \"\"quoted\"\"\",12\n";
+        assert_eq!(
+            ids(data).unwrap(),
+            ["1630000000000000001", "1630000000000000002"]
+        );
+    }
+
+    #[test]
+    fn counts_and_users_are_not_ids() {
+        let data = "user_id,retweets,tweet_url\r\n\
+                    783214,3,https://twitter.com/user/status/1630000000000000001\r\n";
+        assert_eq!(ids(data).unwrap(), ["1630000000000000001"]);
+    }
+
+    #[test]
+    fn csv_needs_one_named_column() {
+        let no_header = "0,1630000000000000001\n1,1630000000000000002\n";
+        assert!(ids(no_header).unwrap_err().to_string().contains("no header"));
+        let unnamed = "index,tweet\n0,1630000000000000001\n";
+        assert!(ids(unnamed).unwrap_err().to_string().contains("no column"));
+        let ambiguous = "id,tweet_id\n1,1630000000000000001\n";
+        assert!(ids(ambiguous)
+            .unwrap_err()
+            .to_string()
+            .contains("`id`, `tweet_id`"));
+    }
+}